@@ -6,10 +6,16 @@ use amethyst::{
 use nom::{
     bytes::complete::take,
     combinator::all_consuming,
-    error::ParseError,
     multi::count,
     number::complete::{le_u8, le_u16, le_u32},
-    Finish, IResult, Parser,
+    Finish, IResult,
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    table::{self, sort_rows, Error as TableError},
+    write_column, DatLayout, StockLayouts,
 };
 
 #[derive(Debug)]
@@ -19,9 +25,17 @@ pub struct Flingy {
     acceleration: u16,
     halt_distance: u32,
     turn_radius: u8,
+    unknown: u8,
     move_control: u8,
 }
 
+impl Flingy {
+    /// Sprite of the flingy in sprites.dat.
+    pub fn sprite(&self) -> u16 {
+        self.sprite
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct FlingyPointer(u32);
 
@@ -29,9 +43,102 @@ impl FlingyPointer {
     pub fn new(p: u32) -> FlingyPointer {
         FlingyPointer(p)
     }
+
+    pub fn value(&self) -> u32 {
+        self.0
+    }
+}
+
+/// A [`Flingy`] as a flat row of named columns.
+///
+/// Columns are named after the fields of [`Flingy`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FlingyRow {
+    pub id: u16,
+    pub sprite: u16,
+    pub top_speed: u32,
+    pub acceleration: u16,
+    pub halt_distance: u32,
+    pub turn_radius: u8,
+    pub unknown: u8,
+    pub move_control: u8,
+}
+
+impl FlingyRow {
+    fn new(id: u16, flingy: &Flingy) -> FlingyRow {
+        FlingyRow {
+            id,
+            sprite: flingy.sprite,
+            top_speed: flingy.top_speed,
+            acceleration: flingy.acceleration,
+            halt_distance: flingy.halt_distance,
+            turn_radius: flingy.turn_radius,
+            unknown: flingy.unknown,
+            move_control: flingy.move_control,
+        }
+    }
+
+    fn into_flingy(self) -> Flingy {
+        Flingy {
+            sprite: self.sprite,
+            top_speed: self.top_speed,
+            acceleration: self.acceleration,
+            halt_distance: self.halt_distance,
+            turn_radius: self.turn_radius,
+            unknown: self.unknown,
+            move_control: self.move_control,
+        }
+    }
 }
 
-pub struct FlingyDat(Vec<Flingy>);
+pub struct FlingyDat {
+    layout: DatLayout,
+    flingies: Vec<Flingy>,
+}
+
+impl FlingyDat {
+    /// Layout of the file the entries were read from.
+    pub fn layout(&self) -> DatLayout {
+        self.layout
+    }
+
+    /// Picks the layout of flingy.dat from the length of the file.
+    pub fn detect_layout(len: usize) -> Option<DatLayout> {
+        DatLayout::detect(len, FILE_SIZES, ENTRY_SIZE)
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Flingy> {
+        self.flingies.get(id)
+    }
+
+    /// Every entry as a row keyed by its flingy id.
+    pub fn rows(&self) -> Vec<FlingyRow> {
+        self.flingies
+            .iter()
+            .enumerate()
+            .map(|(i, flingy)| FlingyRow::new(i as u16, flingy))
+            .collect()
+    }
+
+    /// Builds flingy.dat from rows in any order. Every entry must have
+    /// exactly one row and more rows than the stock limit make an
+    /// extended file.
+    pub fn from_rows(rows: Vec<FlingyRow>) -> table::Result<FlingyDat> {
+        let layout =
+            DatLayout::for_count(rows.len(), ENTRY_COUNTS).ok_or(TableError::RowCount {
+                expected: BLOCK_SIZE,
+                actual: rows.len(),
+            })?;
+
+        sort_rows(rows, layout.entry_count(ENTRY_COUNTS), |row| {
+            row.id as usize
+        })
+        .map(|rows| FlingyDat {
+            layout,
+            flingies: rows.into_iter().map(FlingyRow::into_flingy).collect(),
+        })
+    }
+}
 
 pub struct FlingyDatAsset(Option<FlingyDat>);
 
@@ -66,46 +173,71 @@ impl Format<FlingyDatAsset> for FlingyDatFormat {
             )
         })?;
 
-
         Ok(FlingyDatAsset(Some(flingy_dat)))
     }
 }
 
 const BLOCK_SIZE: usize = 209;
 
-pub fn count_total<I, O, E, F>(f: F) -> impl FnMut(I) -> IResult<I, Vec<O>, E>
-where
-    I: Clone + PartialEq,
-    F: Parser<I, O, E>,
-    E: ParseError<I>,
-{
-    count(f, BLOCK_SIZE)
+/// Size of a single entry when every column exists for it.
+const ENTRY_SIZE: usize = 15;
+
+const ENTRY_COUNTS: &StockLayouts = &[(DatLayout::BroodWar, BLOCK_SIZE)];
+const FILE_SIZES: &StockLayouts = &[(DatLayout::BroodWar, 3135)];
+
+/// Parses flingy.dat in the layout that matches the length of the file.
+pub fn parse_flingy_dat(b: &[u8]) -> IResult<&[u8], FlingyDat> {
+    let layout = FlingyDat::detect_layout(b.len()).unwrap_or(DatLayout::BroodWar);
+
+    parse_flingy_dat_with_layout(b, layout)
 }
 
-fn parse_flingy_dat(b: &[u8]) -> IResult<&[u8], FlingyDat> {
-    let (remaining, sprite_col) = count_total(le_u16)(b)?;
-    let (remaining, top_speed_col) = count_total(le_u32)(remaining)?;
-    let (remaining, acceleration_col) = count_total(le_u16)(remaining)?;
-    let (remaining, halt_distance_col) = count_total(le_u32)(remaining)?;
-    let (remaining, turn_radius_col) = count_total(le_u8)(remaining)?;
+/// Parses flingy.dat in the given layout.
+pub fn parse_flingy_dat_with_layout(b: &[u8], layout: DatLayout) -> IResult<&[u8], FlingyDat> {
+    let entry_count = layout.entry_count(ENTRY_COUNTS);
+
+    let (remaining, sprite_col) = count(le_u16, entry_count)(b)?;
+    let (remaining, top_speed_col) = count(le_u32, entry_count)(remaining)?;
+    let (remaining, acceleration_col) = count(le_u16, entry_count)(remaining)?;
+    let (remaining, halt_distance_col) = count(le_u32, entry_count)(remaining)?;
+    let (remaining, turn_radius_col) = count(le_u8, entry_count)(remaining)?;
 
     // unknown block
-    let (remaining, _) = count_total(le_u8)(remaining)?;
+    let (remaining, unknown_col) = count(le_u8, entry_count)(remaining)?;
 
-    let (remaining, move_control_col) = count_total(le_u8)(remaining)?;
+    let (remaining, move_control_col) = count(le_u8, entry_count)(remaining)?;
 
     all_consuming(take(0u8))(remaining)?;
 
-    let flingies = (0..BLOCK_SIZE)
+    let flingies = (0..entry_count)
         .map(|i| Flingy {
             sprite: sprite_col[i],
             top_speed: top_speed_col[i],
             acceleration: acceleration_col[i],
             halt_distance: halt_distance_col[i],
             turn_radius: turn_radius_col[i],
+            unknown: unknown_col[i],
             move_control: move_control_col[i],
         })
         .collect::<Vec<_>>();
 
-    Ok((remaining, FlingyDat(flingies)))
+    Ok((remaining, FlingyDat { layout, flingies }))
+}
+
+/// Writes flingy.dat in the same column-major layout that
+/// [`parse_flingy_dat`] reads.
+pub fn write_flingy_dat(flingy_dat: &FlingyDat) -> Vec<u8> {
+    let flingies = &flingy_dat.flingies;
+
+    let mut b = vec![];
+
+    write_column(&mut b, flingies, |f| f.sprite.to_le_bytes());
+    write_column(&mut b, flingies, |f| f.top_speed.to_le_bytes());
+    write_column(&mut b, flingies, |f| f.acceleration.to_le_bytes());
+    write_column(&mut b, flingies, |f| f.halt_distance.to_le_bytes());
+    write_column(&mut b, flingies, |f| f.turn_radius.to_le_bytes());
+    write_column(&mut b, flingies, |f| f.unknown.to_le_bytes());
+    write_column(&mut b, flingies, |f| f.move_control.to_le_bytes());
+
+    b
 }
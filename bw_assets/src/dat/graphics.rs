@@ -0,0 +1,157 @@
+//! Graphics of Units
+//!
+//! A unit isn't drawn directly from units.dat. Its `graphics` column points
+//! to a flingy in flingy.dat, the flingy to a sprite in sprites.dat, the
+//! sprite to an image in images.dat and the image to the path of its GRP file
+//! in `arr\images.tbl`.
+
+use super::{DrawFunction, FlingyDat, ImagesDat, SpritesDat, UnitsDat};
+use crate::tbl::Tbl;
+use bw_core::UnitId;
+use snafu::{OptionExt, Snafu};
+
+#[derive(Debug, Snafu, PartialEq, Eq)]
+pub enum Error {
+    #[snafu(display("unit {:?} is missing from units.dat", unit_id))]
+    MissingUnit { unit_id: UnitId },
+
+    #[snafu(display("flingy {} is missing from flingy.dat", flingy))]
+    MissingFlingy { flingy: u8 },
+
+    #[snafu(display("sprite {} is missing from sprites.dat", sprite))]
+    MissingSprite { sprite: u16 },
+
+    #[snafu(display("image {} is missing from images.dat", image))]
+    MissingImage { image: u16 },
+
+    #[snafu(display("GRP {} of image {} is missing from images.tbl", grp_file, image))]
+    MissingGrp { image: u16, grp_file: u32 },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// The image a unit is drawn with.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnitImage {
+    pub image: u16,
+
+    /// Path of the GRP file in the game archives, e.g.
+    /// `unit\terran\marine.grp`.
+    pub grp_path: String,
+
+    /// `None` for draw functions with no known meaning.
+    pub draw_function: Option<DrawFunction>,
+}
+
+impl UnitImage {
+    /// Whether colours 8-15 are drawn in the colours of the owner.
+    pub fn has_player_colors(&self) -> bool {
+        self.draw_function == Some(DrawFunction::PlayerColor)
+    }
+}
+
+/// The tables that lead from a unit to its image.
+#[derive(Clone, Copy)]
+pub struct UnitGraphics<'a> {
+    pub units_dat: &'a UnitsDat,
+    pub flingy_dat: &'a FlingyDat,
+    pub sprites_dat: &'a SpritesDat,
+    pub images_dat: &'a ImagesDat,
+    pub images_tbl: &'a Tbl,
+}
+
+impl UnitGraphics<'_> {
+    /// Follows the graphics of a unit down to its image.
+    pub fn image(&self, unit_id: UnitId) -> Result<UnitImage> {
+        let flingy = self
+            .units_dat
+            .get(unit_id as usize)
+            .context(MissingUnit { unit_id })?
+            .graphics();
+        let sprite = self
+            .flingy_dat
+            .get(flingy as usize)
+            .context(MissingFlingy { flingy })?
+            .sprite();
+        let image = self
+            .sprites_dat
+            .get(sprite as usize)
+            .context(MissingSprite { sprite })?
+            .image_file();
+        let image_entry = self
+            .images_dat
+            .get(image as usize)
+            .context(MissingImage { image })?;
+        let grp_path = image_entry.grp_path(self.images_tbl).context(MissingGrp {
+            image,
+            grp_file: image_entry.grp_file(),
+        })?;
+
+        Ok(UnitImage {
+            image,
+            grp_path,
+            draw_function: image_entry.draw_function(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dat::{parse_flingy_dat, parse_images_dat, parse_sprites_dat, parse_unit_dat},
+        tbl::parse_tbl,
+    };
+    use byteorder::{LittleEndian, WriteBytesExt};
+    use nom::Finish;
+    use spectral::prelude::*;
+
+    /// A string table with a single string, `terran\marine.grp`.
+    fn images_tbl() -> Tbl {
+        let mut b = Vec::new();
+        b.write_u16::<LittleEndian>(1).unwrap();
+        b.write_u16::<LittleEndian>(4).unwrap();
+        b.extend(b"terran\\marine.grp\0");
+
+        parse_tbl(&b).finish().unwrap().1
+    }
+
+    #[test]
+    fn it_follows_a_unit_to_its_image() {
+        let zeroes = |len: usize| vec![0; len];
+        let mut units = parse_unit_dat(&zeroes(19876)).finish().unwrap().1.rows();
+        units[UnitId::TerranMarine as usize].graphics = 2;
+        let mut flingies = parse_flingy_dat(&zeroes(3135)).finish().unwrap().1.rows();
+        flingies[2].sprite = 5;
+        let mut sprites = parse_sprites_dat(&zeroes(3229)).finish().unwrap().1.rows();
+        sprites[5].image_file = 7;
+        let mut images = parse_images_dat(&zeroes(37962)).finish().unwrap().1.rows();
+        images[7].grp_file = 1;
+        images[7].draw_function = 14;
+
+        let units_dat = UnitsDat::from_rows(units).unwrap();
+        let flingy_dat = FlingyDat::from_rows(flingies).unwrap();
+        let sprites_dat = SpritesDat::from_rows(sprites).unwrap();
+        let images_dat = ImagesDat::from_rows(images).unwrap();
+        let images_tbl = images_tbl();
+        let graphics = UnitGraphics {
+            units_dat: &units_dat,
+            flingy_dat: &flingy_dat,
+            sprites_dat: &sprites_dat,
+            images_dat: &images_dat,
+            images_tbl: &images_tbl,
+        };
+
+        let image = graphics.image(UnitId::TerranMarine).unwrap();
+        assert_that(&image.image).is_equal_to(7);
+        assert_that(&image.grp_path.as_str()).is_equal_to("unit\\terran\\marine.grp");
+        assert_that(&image.has_player_colors()).is_true();
+
+        // The image of the Terran Ghost is image 0, whose GRP label 0 is not
+        // in the string table.
+        assert_that(&graphics.image(UnitId::TerranGhost)).is_equal_to(Err(Error::MissingGrp {
+            image: 0,
+            grp_file: 0,
+        }));
+    }
+}
@@ -0,0 +1,375 @@
+use amethyst::{
+    assets::Format,
+    assets::{Asset, Handle},
+    ecs::DenseVecStorage,
+};
+use nom::{
+    bytes::complete::take,
+    combinator::all_consuming,
+    multi::count,
+    number::complete::{le_u8, le_u32},
+    Finish, IResult,
+};
+use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+use super::{
+    table::{self, sort_rows, Error as TableError},
+    write_column, DatLayout, StockLayouts,
+};
+use crate::tbl::Tbl;
+
+/// How the frames of an image are drawn.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, FromPrimitive)]
+pub enum DrawFunction {
+    Normal = 0,
+    NormalNoHallucination = 1,
+
+    /// Cloaking, cloaked and decloaking as seen by players that can't detect
+    /// the unit.
+    NonVisionCloaking = 2,
+    NonVisionCloaked = 3,
+    NonVisionDecloaking = 4,
+    Cloaking = 5,
+    Cloaked = 6,
+    Decloaking = 7,
+    EmpShockwave = 8,
+
+    /// Colours are remapped through the table of the `remapping` column,
+    /// e.g. the flames of burning buildings.
+    Remapping = 9,
+
+    /// Pixels darken what is below them instead of having a colour.
+    Shadow = 10,
+    HpBar = 11,
+    WarpTexture = 12,
+    SelectionCircle = 13,
+
+    /// Colours 8-15 are replaced with the colours of the owner.
+    PlayerColor = 14,
+    UpdateRect = 15,
+    Hallucination = 16,
+    WarpFlash = 17,
+}
+
+#[derive(Debug)]
+pub struct Image {
+    grp_file: u32,
+    graphic_turns: u8,
+    clickable: u8,
+    use_full_iscript: u8,
+    draw_if_cloaked: u8,
+    draw_function: u8,
+    remapping: u8,
+    iscript_id: u32,
+    shield_overlay: u32,
+    attack_overlay: u32,
+    damage_overlay: u32,
+    special_overlay: u32,
+    landing_dust_overlay: u32,
+    lift_off_overlay: u32,
+}
+
+impl Image {
+    /// Label of the GRP path in `arr\images.tbl`.
+    pub fn grp_file(&self) -> u32 {
+        self.grp_file
+    }
+
+    /// Path of the GRP file in the game archives, e.g.
+    /// `unit\terran\marine.grp`.
+    pub fn grp_path(&self, images_tbl: &Tbl) -> Option<String> {
+        u16::try_from(self.grp_file)
+            .ok()
+            .and_then(|label| images_tbl.label(label))
+            .map(|entry| format!("unit\\{}", entry.text()))
+    }
+
+    /// Whether the frames face 17 directions that are mirrored to make 32.
+    pub fn has_graphic_turns(&self) -> bool {
+        self.graphic_turns != 0
+    }
+
+    /// `None` for draw functions with no known meaning.
+    pub fn draw_function(&self) -> Option<DrawFunction> {
+        DrawFunction::from_u8(self.draw_function)
+    }
+
+    /// Remapping table of the [`DrawFunction::Remapping`] draw function.
+    pub fn remapping(&self) -> u8 {
+        self.remapping
+    }
+
+    pub fn iscript_id(&self) -> u32 {
+        self.iscript_id
+    }
+}
+
+/// An [`Image`] as a flat row of named columns.
+///
+/// Columns are named after the fields of [`Image`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ImageRow {
+    pub id: u16,
+    pub grp_file: u32,
+    pub graphic_turns: u8,
+    pub clickable: u8,
+    pub use_full_iscript: u8,
+    pub draw_if_cloaked: u8,
+    pub draw_function: u8,
+    pub remapping: u8,
+    pub iscript_id: u32,
+    pub shield_overlay: u32,
+    pub attack_overlay: u32,
+    pub damage_overlay: u32,
+    pub special_overlay: u32,
+    pub landing_dust_overlay: u32,
+    pub lift_off_overlay: u32,
+}
+
+impl ImageRow {
+    fn new(id: u16, image: &Image) -> ImageRow {
+        ImageRow {
+            id,
+            grp_file: image.grp_file,
+            graphic_turns: image.graphic_turns,
+            clickable: image.clickable,
+            use_full_iscript: image.use_full_iscript,
+            draw_if_cloaked: image.draw_if_cloaked,
+            draw_function: image.draw_function,
+            remapping: image.remapping,
+            iscript_id: image.iscript_id,
+            shield_overlay: image.shield_overlay,
+            attack_overlay: image.attack_overlay,
+            damage_overlay: image.damage_overlay,
+            special_overlay: image.special_overlay,
+            landing_dust_overlay: image.landing_dust_overlay,
+            lift_off_overlay: image.lift_off_overlay,
+        }
+    }
+
+    fn into_image(self) -> Image {
+        Image {
+            grp_file: self.grp_file,
+            graphic_turns: self.graphic_turns,
+            clickable: self.clickable,
+            use_full_iscript: self.use_full_iscript,
+            draw_if_cloaked: self.draw_if_cloaked,
+            draw_function: self.draw_function,
+            remapping: self.remapping,
+            iscript_id: self.iscript_id,
+            shield_overlay: self.shield_overlay,
+            attack_overlay: self.attack_overlay,
+            damage_overlay: self.damage_overlay,
+            special_overlay: self.special_overlay,
+            landing_dust_overlay: self.landing_dust_overlay,
+            lift_off_overlay: self.lift_off_overlay,
+        }
+    }
+}
+
+pub struct ImagesDat {
+    layout: DatLayout,
+    images: Vec<Image>,
+}
+
+impl ImagesDat {
+    /// Layout of the file the entries were read from.
+    pub fn layout(&self) -> DatLayout {
+        self.layout
+    }
+
+    /// Picks the layout of images.dat from the length of the file.
+    pub fn detect_layout(len: usize) -> Option<DatLayout> {
+        DatLayout::detect(len, FILE_SIZES, ENTRY_SIZE)
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Image> {
+        self.images.get(id)
+    }
+
+    /// Every entry as a row keyed by its image id.
+    pub fn rows(&self) -> Vec<ImageRow> {
+        self.images
+            .iter()
+            .enumerate()
+            .map(|(i, image)| ImageRow::new(i as u16, image))
+            .collect()
+    }
+
+    /// Builds images.dat from rows in any order. Every entry must have
+    /// exactly one row and more rows than the stock limit make an
+    /// extended file.
+    pub fn from_rows(rows: Vec<ImageRow>) -> table::Result<ImagesDat> {
+        let layout =
+            DatLayout::for_count(rows.len(), ENTRY_COUNTS).ok_or(TableError::RowCount {
+                expected: BLOCK_SIZE,
+                actual: rows.len(),
+            })?;
+
+        sort_rows(rows, layout.entry_count(ENTRY_COUNTS), |row| {
+            row.id as usize
+        })
+        .map(|rows| ImagesDat {
+            layout,
+            images: rows.into_iter().map(ImageRow::into_image).collect(),
+        })
+    }
+}
+
+pub struct ImagesDatAsset(Option<ImagesDat>);
+
+impl ImagesDatAsset {
+    pub fn take(&mut self) -> Option<ImagesDat> {
+        self.0.take()
+    }
+}
+
+pub type ImagesDatHandle = Handle<ImagesDatAsset>;
+
+impl Asset for ImagesDatAsset {
+    const NAME: &'static str = "bw_assets::dat::ImagesDatAsset";
+    type Data = Self;
+    type HandleStorage = DenseVecStorage<ImagesDatHandle>;
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ImagesDatFormat;
+
+impl Format<ImagesDatAsset> for ImagesDatFormat {
+    fn name(&self) -> &'static str {
+        "ImagesDatFormat"
+    }
+
+    fn import_simple(&self, b: Vec<u8>) -> amethyst::Result<ImagesDatAsset> {
+        let (_, images_dat) = parse_images_dat(&b).finish().map_err(|err| {
+            amethyst::error::format_err!(
+                "failed to load images.dat asset: {} at position {}",
+                err.code.description(),
+                b.len() - err.input.len()
+            )
+        })?;
+
+        Ok(ImagesDatAsset(Some(images_dat)))
+    }
+}
+
+const BLOCK_SIZE: usize = 999;
+
+/// Size of a single entry when every column exists for it.
+const ENTRY_SIZE: usize = 38;
+
+const ENTRY_COUNTS: &StockLayouts = &[(DatLayout::BroodWar, BLOCK_SIZE)];
+const FILE_SIZES: &StockLayouts = &[(DatLayout::BroodWar, 37962)];
+
+/// Parses images.dat in the layout that matches the length of the file.
+pub fn parse_images_dat(b: &[u8]) -> IResult<&[u8], ImagesDat> {
+    let layout = ImagesDat::detect_layout(b.len()).unwrap_or(DatLayout::BroodWar);
+
+    parse_images_dat_with_layout(b, layout)
+}
+
+/// Parses images.dat in the given layout.
+pub fn parse_images_dat_with_layout(b: &[u8], layout: DatLayout) -> IResult<&[u8], ImagesDat> {
+    let entry_count = layout.entry_count(ENTRY_COUNTS);
+
+    let (remaining, grp_file_col) = count(le_u32, entry_count)(b)?;
+    let (remaining, graphic_turns_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, clickable_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, use_full_iscript_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, draw_if_cloaked_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, draw_function_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, remapping_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, iscript_id_col) = count(le_u32, entry_count)(remaining)?;
+    let (remaining, shield_overlay_col) = count(le_u32, entry_count)(remaining)?;
+    let (remaining, attack_overlay_col) = count(le_u32, entry_count)(remaining)?;
+    let (remaining, damage_overlay_col) = count(le_u32, entry_count)(remaining)?;
+    let (remaining, special_overlay_col) = count(le_u32, entry_count)(remaining)?;
+    let (remaining, landing_dust_overlay_col) = count(le_u32, entry_count)(remaining)?;
+    let (remaining, lift_off_overlay_col) = count(le_u32, entry_count)(remaining)?;
+
+    all_consuming(take(0u8))(remaining)?;
+
+    let images = (0..entry_count)
+        .map(|i| Image {
+            grp_file: grp_file_col[i],
+            graphic_turns: graphic_turns_col[i],
+            clickable: clickable_col[i],
+            use_full_iscript: use_full_iscript_col[i],
+            draw_if_cloaked: draw_if_cloaked_col[i],
+            draw_function: draw_function_col[i],
+            remapping: remapping_col[i],
+            iscript_id: iscript_id_col[i],
+            shield_overlay: shield_overlay_col[i],
+            attack_overlay: attack_overlay_col[i],
+            damage_overlay: damage_overlay_col[i],
+            special_overlay: special_overlay_col[i],
+            landing_dust_overlay: landing_dust_overlay_col[i],
+            lift_off_overlay: lift_off_overlay_col[i],
+        })
+        .collect::<Vec<_>>();
+
+    Ok((remaining, ImagesDat { layout, images }))
+}
+
+/// Writes images.dat in the same column-major layout that
+/// [`parse_images_dat`] reads.
+pub fn write_images_dat(images_dat: &ImagesDat) -> Vec<u8> {
+    let images = &images_dat.images;
+
+    let mut b = vec![];
+
+    write_column(&mut b, images, |i| i.grp_file.to_le_bytes());
+    write_column(&mut b, images, |i| i.graphic_turns.to_le_bytes());
+    write_column(&mut b, images, |i| i.clickable.to_le_bytes());
+    write_column(&mut b, images, |i| i.use_full_iscript.to_le_bytes());
+    write_column(&mut b, images, |i| i.draw_if_cloaked.to_le_bytes());
+    write_column(&mut b, images, |i| i.draw_function.to_le_bytes());
+    write_column(&mut b, images, |i| i.remapping.to_le_bytes());
+    write_column(&mut b, images, |i| i.iscript_id.to_le_bytes());
+    write_column(&mut b, images, |i| i.shield_overlay.to_le_bytes());
+    write_column(&mut b, images, |i| i.attack_overlay.to_le_bytes());
+    write_column(&mut b, images, |i| i.damage_overlay.to_le_bytes());
+    write_column(&mut b, images, |i| i.special_overlay.to_le_bytes());
+    write_column(&mut b, images, |i| i.landing_dust_overlay.to_le_bytes());
+    write_column(&mut b, images, |i| i.lift_off_overlay.to_le_bytes());
+
+    b
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn it_reads_draw_functions_by_their_images_dat_value() {
+        let draw_functions = [
+            (0, DrawFunction::Normal),
+            (1, DrawFunction::NormalNoHallucination),
+            (2, DrawFunction::NonVisionCloaking),
+            (3, DrawFunction::NonVisionCloaked),
+            (4, DrawFunction::NonVisionDecloaking),
+            (5, DrawFunction::Cloaking),
+            (6, DrawFunction::Cloaked),
+            (7, DrawFunction::Decloaking),
+            (8, DrawFunction::EmpShockwave),
+            (9, DrawFunction::Remapping),
+            (10, DrawFunction::Shadow),
+            (11, DrawFunction::HpBar),
+            (12, DrawFunction::WarpTexture),
+            (13, DrawFunction::SelectionCircle),
+            (14, DrawFunction::PlayerColor),
+            (15, DrawFunction::UpdateRect),
+            (16, DrawFunction::Hallucination),
+            (17, DrawFunction::WarpFlash),
+        ];
+
+        for &(value, draw_function) in draw_functions.iter() {
+            assert_that(&DrawFunction::from_u8(value)).is_equal_to(Some(draw_function));
+        }
+        assert_that(&DrawFunction::from_u8(18)).is_none();
+    }
+}
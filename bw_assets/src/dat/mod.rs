@@ -1,15 +1,270 @@
+use boolinator::Boolinator;
+
 mod flingy;
+mod graphics;
+mod images;
 mod sprites;
+mod table;
 mod tech_data;
 mod unit;
 mod upgrades;
 mod weapons;
 
-pub use flingy::{Flingy, FlingyDat, FlingyDatAsset, FlingyDatFormat, FlingyDatHandle};
-pub use sprites::{Sprite, SpritesDat, SpritesDatAsset, SpritesDatFormat, SpritesDatHandle};
+pub use flingy::{
+    parse_flingy_dat, parse_flingy_dat_with_layout, write_flingy_dat, Flingy, FlingyDat,
+    FlingyDatAsset, FlingyDatFormat, FlingyDatHandle, FlingyRow,
+};
+pub use graphics::{Error as GraphicsError, UnitGraphics, UnitImage};
+pub use images::{
+    parse_images_dat, parse_images_dat_with_layout, write_images_dat, DrawFunction, Image,
+    ImageRow, ImagesDat, ImagesDatAsset, ImagesDatFormat, ImagesDatHandle,
+};
+pub use sprites::{
+    parse_sprites_dat, parse_sprites_dat_with_layout, write_sprites_dat, Sprite, SpriteRow,
+    SpritesDat, SpritesDatAsset, SpritesDatFormat, SpritesDatHandle,
+};
+pub use table::{read_rows, write_rows, Error as TableError, TableFormat};
 pub use tech_data::{
-    TechData, TechDataDat, TechDataDatAsset, TechDataDatFormat, TechDataDatHandle,
+    parse_tech_dat_dat, parse_tech_dat_dat_with_layout, write_tech_data_dat, TechData, TechDataDat,
+    TechDataDatAsset, TechDataDatFormat, TechDataDatHandle, TechDataRow,
 };
-pub use unit::{Unit, UnitPointer, UnitsDat, UnitsDatAsset, UnitsDatFormat, UnitsDatHandle};
-pub use upgrades::{Upgrade, UpgradesDat, UpgradesDatAsset, UpgradesDatFormat, UpgradesDatHandle};
-pub use weapons::{Weapon, WeaponsDat, WeaponsDatAsset, WeaponsDatFormat, WeaponsDatHandle};
+pub use unit::{
+    parse_unit_dat, parse_unit_dat_with_layout, write_unit_dat, Unit, UnitPointer, UnitRow,
+    UnitRowId, UnitsDat, UnitsDatAsset, UnitsDatFormat, UnitsDatHandle,
+};
+pub use upgrades::{
+    parse_upgrades_dat, parse_upgrades_dat_with_layout, write_upgrades_dat, Upgrade, UpgradeRow,
+    UpgradesDat, UpgradesDatAsset, UpgradesDatFormat, UpgradesDatHandle,
+};
+pub use weapons::{
+    parse_weapons_dat, parse_weapons_dat_with_layout, write_weapons_dat, Weapon, WeaponRow,
+    WeaponsDat, WeaponsDatAsset, WeaponsDatFormat, WeaponsDatHandle,
+};
+
+/// Record layout of a DAT file.
+///
+/// The game hardcodes how many entries every DAT file has. Files from
+/// different versions of the game and from mods that raise the limits only
+/// differ in the number of entries and in which columns exist for which
+/// entries, so every layout is parsed into the same types.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DatLayout {
+    /// Files of the original StarCraft before Brood War. Only techdata.dat
+    /// and upgrades.dat differ from Brood War: they have fewer entries and no
+    /// Brood War column.
+    StarCraft,
+
+    /// Files of StarCraft: Brood War.
+    BroodWar,
+
+    /// Files of mods that extend the limits of the game with the number of
+    /// entries. Columns that only exist for part of the entries in the stock
+    /// files exist for every entry.
+    Extended(usize),
+}
+
+/// Entry counts or file sizes of a DAT file in the stock layouts. The first
+/// layout is used for the layouts that are missing.
+type StockLayouts = [(DatLayout, usize)];
+
+impl DatLayout {
+    /// Picks the layout of a file from its length.
+    ///
+    /// `entry_size` is the size of a single entry when every column exists
+    /// for it.
+    fn detect(len: usize, sizes: &StockLayouts, entry_size: usize) -> Option<DatLayout> {
+        sizes
+            .iter()
+            .find(|(_, size)| *size == len)
+            .map(|(layout, _)| *layout)
+            .or_else(|| {
+                let count = len / entry_size;
+                (count > 0 && count * entry_size == len).as_some(DatLayout::Extended(count))
+            })
+    }
+
+    /// Picks the layout for a number of entries. Counts above the stock limit
+    /// are extended.
+    fn for_count(count: usize, counts: &StockLayouts) -> Option<DatLayout> {
+        counts
+            .iter()
+            .find(|(_, n)| *n == count)
+            .map(|(layout, _)| *layout)
+            .or_else(|| {
+                let limit = counts.iter().map(|(_, n)| *n).max().unwrap_or(0);
+                (count > limit).as_some(DatLayout::Extended(count))
+            })
+    }
+
+    fn entry_count(self, counts: &StockLayouts) -> usize {
+        match self {
+            DatLayout::Extended(count) => count,
+            layout => counts
+                .iter()
+                .find(|(l, _)| *l == layout)
+                .or_else(|| counts.first())
+                .map_or(0, |(_, n)| *n),
+        }
+    }
+}
+
+/// Writes a single column of a DAT file.
+///
+/// DAT files are column-major: a property is written for every entry
+/// before the next property starts.
+fn write_column<T, B, F>(b: &mut Vec<u8>, entries: &[T], f: F)
+where
+    B: AsRef<[u8]>,
+    F: Fn(&T) -> B,
+{
+    entries
+        .iter()
+        .for_each(|entry| b.extend_from_slice(f(entry).as_ref()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::random_dat;
+    use nom::Finish;
+    use spectral::prelude::*;
+
+    const UNITS_DAT_SIZE: usize = 19876;
+    const WEAPONS_DAT_SIZE: usize = 5460;
+    const FLINGY_DAT_SIZE: usize = 3135;
+    const SPRITES_DAT_SIZE: usize = 3229;
+    const IMAGES_DAT_SIZE: usize = 37962;
+    const TECH_DATA_DAT_SIZE: usize = 836;
+    const UPGRADES_DAT_SIZE: usize = 1281;
+
+    macro_rules! test_round_trip {
+        ($b:expr, $parse:expr, $write:expr) => {{
+            let b: Vec<u8> = $b;
+            let (_, dat) = $parse(&b).finish().expect("failed to parse dat");
+
+            assert_that(&$write(&dat)).is_equal_to(b);
+        }};
+    }
+
+    #[test]
+    fn it_writes_what_it_parses() {
+        test_round_trip!(random_dat(UNITS_DAT_SIZE), parse_unit_dat, write_unit_dat);
+        test_round_trip!(
+            random_dat(WEAPONS_DAT_SIZE),
+            parse_weapons_dat,
+            write_weapons_dat
+        );
+        test_round_trip!(
+            random_dat(FLINGY_DAT_SIZE),
+            parse_flingy_dat,
+            write_flingy_dat
+        );
+        test_round_trip!(
+            random_dat(SPRITES_DAT_SIZE),
+            parse_sprites_dat,
+            write_sprites_dat
+        );
+        test_round_trip!(
+            random_dat(IMAGES_DAT_SIZE),
+            parse_images_dat,
+            write_images_dat
+        );
+        test_round_trip!(
+            random_dat(TECH_DATA_DAT_SIZE),
+            parse_tech_dat_dat,
+            write_tech_data_dat
+        );
+        test_round_trip!(
+            random_dat(UPGRADES_DAT_SIZE),
+            parse_upgrades_dat,
+            write_upgrades_dat
+        );
+    }
+
+    #[test]
+    fn it_detects_the_layout_from_the_file_length() {
+        assert_that(&UnitsDat::detect_layout(UNITS_DAT_SIZE))
+            .is_equal_to(Some(DatLayout::BroodWar));
+        assert_that(&TechDataDat::detect_layout(432)).is_equal_to(Some(DatLayout::StarCraft));
+        assert_that(&UpgradesDat::detect_layout(920)).is_equal_to(Some(DatLayout::StarCraft));
+        assert_that(&UnitsDat::detect_layout(300 * 96)).is_equal_to(Some(DatLayout::Extended(300)));
+        assert_that(&WeaponsDat::detect_layout(WEAPONS_DAT_SIZE + 1)).is_none();
+    }
+
+    #[test]
+    fn it_writes_what_it_parses_in_every_layout() {
+        test_round_trip!(random_dat(432), parse_tech_dat_dat, write_tech_data_dat);
+        test_round_trip!(random_dat(920), parse_upgrades_dat, write_upgrades_dat);
+        test_round_trip!(random_dat(300 * 96), parse_unit_dat, write_unit_dat);
+        test_round_trip!(random_dat(400 * 42), parse_weapons_dat, write_weapons_dat);
+        test_round_trip!(random_dat(300 * 15), parse_flingy_dat, write_flingy_dat);
+        test_round_trip!(random_dat(600 * 7), parse_sprites_dat, write_sprites_dat);
+        test_round_trip!(random_dat(1200 * 38), parse_images_dat, write_images_dat);
+    }
+
+    #[test]
+    fn it_parses_extended_dats() {
+        let b = random_dat(300 * 96);
+        let (_, units_dat) = parse_unit_dat(&b).finish().unwrap();
+        let rows = units_dat.rows();
+
+        assert_that(&units_dat.layout()).is_equal_to(DatLayout::Extended(300));
+        assert_that(&rows.len()).is_equal_to(300);
+        assert_that(&rows[0].id.unit_id()).is_equal_to(Some(bw_core::UnitId::TerranMarine));
+        assert_that(&rows[299].id.unit_id()).is_none();
+
+        // Every unit of an extended file has the columns of units and of
+        // buildings.
+        assert_that(&rows[0].infestation).is_some();
+        assert_that(&rows[299].ready_sound).is_some();
+
+        let units_dat = UnitsDat::from_rows(rows).unwrap();
+        assert_that(&units_dat.layout()).is_equal_to(DatLayout::Extended(300));
+        assert_that(&write_unit_dat(&units_dat)).is_equal_to(&b);
+    }
+
+    #[test]
+    fn it_parses_an_explicit_layout() {
+        let b = random_dat(TECH_DATA_DAT_SIZE);
+        let (_, tech_data_dat) = parse_tech_dat_dat_with_layout(&b, DatLayout::Extended(44))
+            .finish()
+            .unwrap();
+
+        assert_that(&tech_data_dat.layout()).is_equal_to(DatLayout::Extended(44));
+        assert_that(&parse_tech_dat_dat_with_layout(&b, DatLayout::StarCraft).is_err()).is_true();
+    }
+
+    #[test]
+    #[ignore = "needs stock MPQs"]
+    fn it_writes_the_stock_dats_byte_for_byte() {
+        use crate::test_utils::stock_mpq_source;
+        use amethyst::assets::Source;
+
+        let mpq_source = stock_mpq_source();
+        let load = |file_name: &str| mpq_source.load(file_name).unwrap();
+
+        test_round_trip!(load("arr\\units.dat"), parse_unit_dat, write_unit_dat);
+        test_round_trip!(
+            load("arr\\weapons.dat"),
+            parse_weapons_dat,
+            write_weapons_dat
+        );
+        test_round_trip!(load("arr\\flingy.dat"), parse_flingy_dat, write_flingy_dat);
+        test_round_trip!(
+            load("arr\\sprites.dat"),
+            parse_sprites_dat,
+            write_sprites_dat
+        );
+        test_round_trip!(load("arr\\images.dat"), parse_images_dat, write_images_dat);
+        test_round_trip!(
+            load("arr\\techdata.dat"),
+            parse_tech_dat_dat,
+            write_tech_data_dat
+        );
+        test_round_trip!(
+            load("arr\\upgrades.dat"),
+            parse_upgrades_dat,
+            write_upgrades_dat
+        );
+    }
+}
@@ -7,22 +7,138 @@ use boolinator::Boolinator;
 use nom::{
     bytes::complete::take,
     combinator::all_consuming,
-    error::ParseError,
     multi::count,
     number::complete::{le_u8, le_u16},
-    Finish, IResult, Parser,
+    Finish, IResult,
+};
+
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+use super::{
+    table::{self, sort_rows, Error as TableError},
+    write_column, DatLayout, StockLayouts,
 };
 
 #[derive(Debug)]
 pub struct Sprite {
     image_file: u16,
     health_bar: Option<u8>,
+    unknown: u8,
     is_visible: u8,
     selection_circle_image: Option<u8>,
     selection_circle_offset: Option<u8>,
 }
 
-pub struct SpritesDat(Vec<Sprite>);
+impl Sprite {
+    /// Image of the sprite in images.dat.
+    pub fn image_file(&self) -> u16 {
+        self.image_file
+    }
+}
+
+/// A [`Sprite`] as a flat row of named columns.
+///
+/// Columns are named after the fields of [`Sprite`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpriteRow {
+    pub id: u16,
+    pub image_file: u16,
+    pub health_bar: Option<u8>,
+    pub unknown: u8,
+    pub is_visible: u8,
+    pub selection_circle_image: Option<u8>,
+    pub selection_circle_offset: Option<u8>,
+}
+
+impl SpriteRow {
+    fn new(id: u16, sprite: &Sprite) -> SpriteRow {
+        SpriteRow {
+            id,
+            image_file: sprite.image_file,
+            health_bar: sprite.health_bar,
+            unknown: sprite.unknown,
+            is_visible: sprite.is_visible,
+            selection_circle_image: sprite.selection_circle_image,
+            selection_circle_offset: sprite.selection_circle_offset,
+        }
+    }
+
+    fn into_sprite(self) -> Sprite {
+        Sprite {
+            image_file: self.image_file,
+            health_bar: self.health_bar,
+            unknown: self.unknown,
+            is_visible: self.is_visible,
+            selection_circle_image: self.selection_circle_image,
+            selection_circle_offset: self.selection_circle_offset,
+        }
+    }
+}
+
+pub struct SpritesDat {
+    layout: DatLayout,
+    sprites: Vec<Sprite>,
+}
+
+impl SpritesDat {
+    /// Layout of the file the entries were read from.
+    pub fn layout(&self) -> DatLayout {
+        self.layout
+    }
+
+    /// Picks the layout of sprites.dat from the length of the file.
+    pub fn detect_layout(len: usize) -> Option<DatLayout> {
+        DatLayout::detect(len, FILE_SIZES, ENTRY_SIZE)
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Sprite> {
+        self.sprites.get(id)
+    }
+
+    /// Every entry as a row keyed by its sprite id.
+    pub fn rows(&self) -> Vec<SpriteRow> {
+        self.sprites
+            .iter()
+            .enumerate()
+            .map(|(i, sprite)| SpriteRow::new(i as u16, sprite))
+            .collect()
+    }
+
+    /// Builds sprites.dat from rows in any order. Every entry must have
+    /// exactly one row and more rows than the stock limit make an
+    /// extended file.
+    pub fn from_rows(rows: Vec<SpriteRow>) -> table::Result<SpritesDat> {
+        let layout =
+            DatLayout::for_count(rows.len(), ENTRY_COUNTS).ok_or(TableError::RowCount {
+                expected: BLOCK_SIZE,
+                actual: rows.len(),
+            })?;
+
+        let selectable = selectable(layout);
+
+        let sprites = sort_rows(rows, layout.entry_count(ENTRY_COUNTS), |row| {
+            row.id as usize
+        })?
+        .into_iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let mut sprite = row.into_sprite();
+
+            // Only selectable sprites have these columns.
+            if !selectable.contains(&i) {
+                sprite.health_bar = None;
+                sprite.selection_circle_image = None;
+                sprite.selection_circle_offset = None;
+            }
+
+            sprite
+        })
+        .collect();
+
+        Ok(SpritesDat { layout, sprites })
+    }
+}
 
 pub struct SpritesDatAsset(Option<SpritesDat>);
 
@@ -65,62 +181,90 @@ const SELECTABLE_COUNT: usize = 387;
 const NON_SELECTABLE_COUNT: usize = 130;
 const BLOCK_SIZE: usize = 517;
 
-pub fn count_total<I, O, E, F>(f: F) -> impl FnMut(I) -> IResult<I, Vec<O>, E>
-where
-    I: Clone + PartialEq,
-    F: Parser<I, O, E>,
-    E: ParseError<I>,
-{
-    count(f, BLOCK_SIZE)
+/// Size of a single entry when every column exists for it.
+const ENTRY_SIZE: usize = 7;
+
+const ENTRY_COUNTS: &StockLayouts = &[(DatLayout::BroodWar, BLOCK_SIZE)];
+const FILE_SIZES: &StockLayouts = &[(DatLayout::BroodWar, 3229)];
+
+/// Sprites that have the columns of selectable sprites.
+fn selectable(layout: DatLayout) -> Range<usize> {
+    match layout {
+        DatLayout::Extended(count) => 0..count,
+        _ => NON_SELECTABLE_COUNT..NON_SELECTABLE_COUNT + SELECTABLE_COUNT,
+    }
 }
 
-pub fn count_selectable_block<I, O, E, F>(f: F) -> impl FnMut(I) -> IResult<I, Vec<O>, E>
-where
-    I: Clone + PartialEq,
-    F: Parser<I, O, E>,
-    E: ParseError<I>,
-{
-    count(f, SELECTABLE_COUNT)
+/// Parses sprites.dat in the layout that matches the length of the file.
+pub fn parse_sprites_dat(b: &[u8]) -> IResult<&[u8], SpritesDat> {
+    let layout = SpritesDat::detect_layout(b.len()).unwrap_or(DatLayout::BroodWar);
+
+    parse_sprites_dat_with_layout(b, layout)
 }
 
-fn parse_sprites_dat(b: &[u8]) -> IResult<&[u8], SpritesDat> {
-    let (remaining, image_file_col) = count_total(le_u16)(b)?;
-    let (remaining, health_bar_col) = count_selectable_block(le_u8)(remaining)?;
+/// Parses sprites.dat in the given layout.
+pub fn parse_sprites_dat_with_layout(b: &[u8], layout: DatLayout) -> IResult<&[u8], SpritesDat> {
+    let entry_count = layout.entry_count(ENTRY_COUNTS);
+    let selectable = selectable(layout);
+
+    let (remaining, image_file_col) = count(le_u16, entry_count)(b)?;
+    let (remaining, health_bar_col) = count(le_u8, selectable.len())(remaining)?;
 
-    let (remaining, _) = count_total(le_u8)(remaining)?;
+    // unknown block
+    let (remaining, unknown_col) = count(le_u8, entry_count)(remaining)?;
 
-    let (remaining, is_visible_col) = count_total(le_u8)(remaining)?;
-    let (remaining, selection_circle_image_col) = count_selectable_block(le_u8)(remaining)?;
-    let (remaining, selection_circle_offset_col) = count_selectable_block(le_u8)(remaining)?;
+    let (remaining, is_visible_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, selection_circle_image_col) = count(le_u8, selectable.len())(remaining)?;
+    let (remaining, selection_circle_offset_col) = count(le_u8, selectable.len())(remaining)?;
 
     all_consuming(take(0u8))(remaining)?;
 
-    let sprites = (0..BLOCK_SIZE)
+    let sprites = (0..entry_count)
         .map(|i| Sprite {
             image_file: image_file_col[i],
-            health_bar: (i >= NON_SELECTABLE_COUNT && i < NON_SELECTABLE_COUNT + SELECTABLE_COUNT)
-                .and_option_from(|| {
-                    health_bar_col
-                        .get(i - NON_SELECTABLE_COUNT)
-                        .map(ToOwned::to_owned)
-                }),
+            health_bar: selectable.contains(&i).and_option_from(|| {
+                health_bar_col
+                    .get(i - selectable.start)
+                    .map(ToOwned::to_owned)
+            }),
+            unknown: unknown_col[i],
             is_visible: is_visible_col[i],
-            selection_circle_image: (i >= NON_SELECTABLE_COUNT
-                && i < NON_SELECTABLE_COUNT + SELECTABLE_COUNT)
-                .and_option_from(|| {
-                    selection_circle_image_col
-                        .get(i - NON_SELECTABLE_COUNT)
-                        .map(ToOwned::to_owned)
-                }),
-            selection_circle_offset: (i >= NON_SELECTABLE_COUNT
-                && i < NON_SELECTABLE_COUNT + SELECTABLE_COUNT)
-                .and_option_from(|| {
-                    selection_circle_offset_col
-                        .get(i - NON_SELECTABLE_COUNT)
-                        .map(ToOwned::to_owned)
-                }),
+            selection_circle_image: selectable.contains(&i).and_option_from(|| {
+                selection_circle_image_col
+                    .get(i - selectable.start)
+                    .map(ToOwned::to_owned)
+            }),
+            selection_circle_offset: selectable.contains(&i).and_option_from(|| {
+                selection_circle_offset_col
+                    .get(i - selectable.start)
+                    .map(ToOwned::to_owned)
+            }),
         })
         .collect::<Vec<_>>();
 
-    Ok((remaining, SpritesDat(sprites)))
+    Ok((remaining, SpritesDat { layout, sprites }))
+}
+
+/// Writes sprites.dat in the same column-major layout that
+/// [`parse_sprites_dat`] reads.
+pub fn write_sprites_dat(sprites_dat: &SpritesDat) -> Vec<u8> {
+    let sprites = &sprites_dat.sprites;
+    let selectable = &sprites[selectable(sprites_dat.layout)];
+
+    let mut b = vec![];
+
+    write_column(&mut b, sprites, |s| s.image_file.to_le_bytes());
+    write_column(&mut b, selectable, |s| {
+        s.health_bar.unwrap_or(0).to_le_bytes()
+    });
+    write_column(&mut b, sprites, |s| s.unknown.to_le_bytes());
+    write_column(&mut b, sprites, |s| s.is_visible.to_le_bytes());
+    write_column(&mut b, selectable, |s| {
+        s.selection_circle_image.unwrap_or(0).to_le_bytes()
+    });
+    write_column(&mut b, selectable, |s| {
+        s.selection_circle_offset.unwrap_or(0).to_le_bytes()
+    });
+
+    b
 }
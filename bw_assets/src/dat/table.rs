@@ -0,0 +1,255 @@
+//! Tabular Views of DAT Files
+//!
+//! Every DAT file can be converted into rows with one named column per
+//! property so the data can be viewed and edited in a spreadsheet, diffed as
+//! text, or stored next to a mod. Rows can be written to and read from CSV,
+//! JSON, or RON and turned back into the original DAT file.
+//!
+//! Column names are the field names of the DAT entry. Nested properties are
+//! flattened with the name of the field as a prefix, i.e. `dimensions_left`,
+//! and bit flags are written as their names joined by `|`.
+
+use serde::{de::DeserializeOwned, Serialize};
+use snafu::{ResultExt, Snafu};
+use std::{
+    fmt,
+    io::{Read, Write},
+    path::Path,
+    str::FromStr,
+};
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("csv error: {}", source))]
+    Csv { source: csv::Error },
+
+    #[snafu(display("json error: {}", source))]
+    Json { source: serde_json::Error },
+
+    #[snafu(display("ron error: {}", source))]
+    Ron { source: ron::Error },
+
+    #[snafu(display("unknown table format {}", format))]
+    UnknownFormat { format: String },
+
+    #[snafu(display("unknown flag {}", flag))]
+    UnknownFlag { flag: String },
+
+    #[snafu(display("expected {} rows but found {}", expected, actual))]
+    RowCount { expected: usize, actual: usize },
+
+    #[snafu(display("row {} is missing", id))]
+    MissingRow { id: usize },
+
+    #[snafu(display("value {} of column {} is out of range", value, column))]
+    OutOfRange { column: String, value: String },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Text format that DAT rows are written in.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TableFormat {
+    Csv,
+    Json,
+    Ron,
+}
+
+impl TableFormat {
+    /// Guesses the format from the extension of a file.
+    pub fn from_path(path: &Path) -> Option<TableFormat> {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(|ext| ext.parse().ok())
+    }
+}
+
+impl FromStr for TableFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(TableFormat::Csv),
+            "json" => Ok(TableFormat::Json),
+            "ron" => Ok(TableFormat::Ron),
+            _ => UnknownFormat { format: s }.fail(),
+        }
+    }
+}
+
+impl fmt::Display for TableFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TableFormat::Csv => write!(f, "csv"),
+            TableFormat::Json => write!(f, "json"),
+            TableFormat::Ron => write!(f, "ron"),
+        }
+    }
+}
+
+/// Writes rows in the given format.
+pub fn write_rows<R: Serialize, W: Write>(w: W, rows: &[R], format: TableFormat) -> Result<()> {
+    match format {
+        TableFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(w);
+            for row in rows {
+                writer.serialize(row).context(Csv {})?;
+            }
+            writer.flush().map_err(csv::Error::from).context(Csv {})
+        }
+        TableFormat::Json => serde_json::to_writer_pretty(w, rows).context(Json {}),
+        TableFormat::Ron => {
+            ron::ser::to_writer_pretty(w, &rows, ron::ser::PrettyConfig::new()).context(Ron {})
+        }
+    }
+}
+
+/// Reads rows that were written by [`write_rows`].
+pub fn read_rows<R: DeserializeOwned, Rd: Read>(r: Rd, format: TableFormat) -> Result<Vec<R>> {
+    match format {
+        TableFormat::Csv => csv::Reader::from_reader(r)
+            .deserialize()
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .context(Csv {}),
+        TableFormat::Json => serde_json::from_reader(r).context(Json {}),
+        TableFormat::Ron => ron::de::from_reader(r).context(Ron {}),
+    }
+}
+
+/// Orders rows by their id and checks that every entry of the DAT file is
+/// present exactly once.
+pub(super) fn sort_rows<R, F>(mut rows: Vec<R>, expected: usize, id: F) -> Result<Vec<R>>
+where
+    F: Fn(&R) -> usize,
+{
+    if rows.len() != expected {
+        return RowCount {
+            expected,
+            actual: rows.len(),
+        }
+        .fail();
+    }
+
+    rows.sort_by_key(|row| id(row));
+
+    match rows.iter().enumerate().find(|(i, row)| id(row) != *i) {
+        Some((i, _)) => MissingRow { id: i }.fail(),
+        None => Ok(rows),
+    }
+}
+
+/// Narrows a column value to the width it has in the DAT file.
+pub(super) fn narrow<T, U>(column: &str, value: T) -> Result<U>
+where
+    T: Copy + fmt::Display,
+    U: std::convert::TryFrom<T>,
+{
+    U::try_from(value).map_err(|_| Error::OutOfRange {
+        column: column.to_string(),
+        value: value.to_string(),
+    })
+}
+
+/// Writes the set bits of `value` as their names joined by `|`.
+///
+/// Bits without a name are written as `bit<n>`.
+pub(super) fn encode_flags(value: u32, names: &[&str]) -> String {
+    (0..32)
+        .filter(|bit| value & (1 << bit) != 0)
+        .map(|bit| match names.get(bit) {
+            Some(name) => name.to_string(),
+            None => format!("bit{}", bit),
+        })
+        .collect::<Vec<_>>()
+        .join("|")
+}
+
+/// Reads flags that were written by [`encode_flags`].
+pub(super) fn decode_flags(s: &str, names: &[&str]) -> Result<u32> {
+    s.split('|')
+        .map(str::trim)
+        .filter(|flag| !flag.is_empty())
+        .try_fold(0u32, |value, flag| {
+            let bit = names.iter().position(|name| *name == flag).or_else(|| {
+                Some(flag)
+                    .filter(|flag| flag.starts_with("bit"))
+                    .and_then(|flag| flag["bit".len()..].parse().ok())
+                    .filter(|&n| n < 32)
+            });
+
+            match bit {
+                Some(bit) => Ok(value | (1 << bit)),
+                None => UnknownFlag { flag }.fail(),
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dat::{
+        parse_unit_dat, parse_weapons_dat, write_unit_dat, write_weapons_dat, UnitRow, UnitsDat,
+        WeaponRow, WeaponsDat,
+    };
+    use crate::test_utils::random_dat;
+    use nom::Finish;
+    use spectral::prelude::*;
+
+    const NAMES: &[&str] = &["air", "ground", "mechanical"];
+
+    #[test]
+    fn it_encodes_flags_by_name() {
+        assert_that(&encode_flags(0b101, NAMES)).is_equal_to("air|mechanical".to_string());
+        assert_that(&encode_flags(1 << 9, NAMES)).is_equal_to("bit9".to_string());
+        assert_that(&decode_flags("air | bit9", NAMES))
+            .is_ok()
+            .is_equal_to(1 | 1 << 9);
+        assert_that(&decode_flags("", NAMES)).is_ok().is_equal_to(0);
+        assert_that(&decode_flags("flying", NAMES).is_err()).is_true();
+    }
+
+    #[test]
+    fn it_round_trips_units_through_every_format() {
+        let b = random_dat(19876);
+        let (_, units_dat) = parse_unit_dat(&b).finish().unwrap();
+
+        for &format in &[TableFormat::Csv, TableFormat::Json, TableFormat::Ron] {
+            let mut text = vec![];
+            write_rows(&mut text, &units_dat.rows(), format).unwrap();
+
+            let rows: Vec<UnitRow> = read_rows(text.as_slice(), format).unwrap();
+            let units_dat = UnitsDat::from_rows(rows).unwrap();
+
+            assert_that(&write_unit_dat(&units_dat)).is_equal_to(&b);
+        }
+    }
+
+    #[test]
+    fn it_round_trips_weapons_through_csv() {
+        let b = random_dat(5460);
+        let (_, weapons_dat) = parse_weapons_dat(&b).finish().unwrap();
+
+        let mut text = vec![];
+        write_rows(&mut text, &weapons_dat.rows(), TableFormat::Csv).unwrap();
+
+        let header = String::from_utf8_lossy(&text)
+            .lines()
+            .next()
+            .unwrap()
+            .to_string();
+        assert_that(&header).starts_with("id,label,graphics,unused,target_flags");
+
+        let rows: Vec<WeaponRow> = read_rows(text.as_slice(), TableFormat::Csv).unwrap();
+        let weapons_dat = WeaponsDat::from_rows(rows).unwrap();
+
+        assert_that(&write_weapons_dat(&weapons_dat)).is_equal_to(&b);
+    }
+
+    #[test]
+    fn it_rejects_missing_rows() {
+        let rows = vec![0usize, 2, 3];
+
+        assert_that(&sort_rows(rows.clone(), 4, |&id| id).is_err()).is_true();
+        assert_that(&sort_rows(rows, 3, |&id| id).is_err()).is_true();
+    }
+}
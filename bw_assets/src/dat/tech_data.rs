@@ -6,10 +6,16 @@ use amethyst::{
 use nom::{
     bytes::complete::take,
     combinator::all_consuming,
-    error::ParseError,
     multi::count,
     number::complete::{le_u8, le_u16, le_u32},
-    Finish, IResult, Parser,
+    Finish, IResult,
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    table::{self, sort_rows, Error as TableError},
+    write_column, DatLayout, StockLayouts,
 };
 
 #[derive(Debug)]
@@ -18,13 +24,136 @@ pub struct TechData {
     vespene_cost: u16,
     research_time: u16,
     energy_cost: u16,
+    unknown_1: u32,
     icon: u16,
     label: u16,
     race: u8,
+    unknown_2: u8,
     broodwar: u8,
 }
 
-pub struct TechDataDat(Vec<TechData>);
+impl TechData {
+    /// Name of the tech.
+    ///
+    /// Index to stat_txt.tbl
+    pub fn label(&self) -> u16 {
+        self.label
+    }
+
+    pub fn mineral_cost(&self) -> u16 {
+        self.mineral_cost
+    }
+
+    pub fn vespene_cost(&self) -> u16 {
+        self.vespene_cost
+    }
+
+    /// Time it takes to research the tech, in frames at the Fastest speed.
+    pub fn research_time(&self) -> u16 {
+        self.research_time
+    }
+}
+
+/// A [`TechData`] as a flat row of named columns.
+///
+/// Columns are named after the fields of [`TechData`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TechDataRow {
+    pub id: u16,
+    pub mineral_cost: u16,
+    pub vespene_cost: u16,
+    pub research_time: u16,
+    pub energy_cost: u16,
+    pub unknown_1: u32,
+    pub icon: u16,
+    pub label: u16,
+    pub race: u8,
+    pub unknown_2: u8,
+    pub broodwar: u8,
+}
+
+impl TechDataRow {
+    fn new(id: u16, tech_data: &TechData) -> TechDataRow {
+        TechDataRow {
+            id,
+            mineral_cost: tech_data.mineral_cost,
+            vespene_cost: tech_data.vespene_cost,
+            research_time: tech_data.research_time,
+            energy_cost: tech_data.energy_cost,
+            unknown_1: tech_data.unknown_1,
+            icon: tech_data.icon,
+            label: tech_data.label,
+            race: tech_data.race,
+            unknown_2: tech_data.unknown_2,
+            broodwar: tech_data.broodwar,
+        }
+    }
+
+    fn into_tech_data(self) -> TechData {
+        TechData {
+            mineral_cost: self.mineral_cost,
+            vespene_cost: self.vespene_cost,
+            research_time: self.research_time,
+            energy_cost: self.energy_cost,
+            unknown_1: self.unknown_1,
+            icon: self.icon,
+            label: self.label,
+            race: self.race,
+            unknown_2: self.unknown_2,
+            broodwar: self.broodwar,
+        }
+    }
+}
+
+pub struct TechDataDat {
+    layout: DatLayout,
+    tech_data: Vec<TechData>,
+}
+
+impl TechDataDat {
+    /// Layout of the file the entries were read from.
+    pub fn layout(&self) -> DatLayout {
+        self.layout
+    }
+
+    /// Picks the layout of techdata.dat from the length of the file.
+    pub fn detect_layout(len: usize) -> Option<DatLayout> {
+        DatLayout::detect(len, FILE_SIZES, ENTRY_SIZE)
+    }
+
+    /// Entry with the given tech id.
+    pub fn get(&self, id: usize) -> Option<&TechData> {
+        self.tech_data.get(id)
+    }
+
+    /// Every entry as a row keyed by its tech id.
+    pub fn rows(&self) -> Vec<TechDataRow> {
+        self.tech_data
+            .iter()
+            .enumerate()
+            .map(|(i, tech_data)| TechDataRow::new(i as u16, tech_data))
+            .collect()
+    }
+
+    /// Builds techdata.dat from rows in any order. Every entry must have
+    /// exactly one row and more rows than the stock limit make an
+    /// extended file.
+    pub fn from_rows(rows: Vec<TechDataRow>) -> table::Result<TechDataDat> {
+        let layout =
+            DatLayout::for_count(rows.len(), ENTRY_COUNTS).ok_or(TableError::RowCount {
+                expected: BLOCK_SIZE,
+                actual: rows.len(),
+            })?;
+
+        sort_rows(rows, layout.entry_count(ENTRY_COUNTS), |row| {
+            row.id as usize
+        })
+        .map(|rows| TechDataDat {
+            layout,
+            tech_data: rows.into_iter().map(TechDataRow::into_tech_data).collect(),
+        })
+    }
+}
 
 pub struct TechDataDatAsset(Option<TechDataDat>);
 
@@ -64,49 +193,89 @@ impl Format<TechDataDatAsset> for TechDataDatFormat {
 }
 
 const BLOCK_SIZE: usize = 44;
+const STAR_CRAFT_BLOCK_SIZE: usize = 24;
 
-pub fn count_total<I, O, E, F>(f: F) -> impl FnMut(I) -> IResult<I, Vec<O>, E>
-where
-    I: Clone + PartialEq,
-    F: Parser<I, O, E>,
-    E: ParseError<I>,
-{
-    count(f, BLOCK_SIZE)
+/// Size of a single entry when every column exists for it.
+const ENTRY_SIZE: usize = 19;
+
+const ENTRY_COUNTS: &StockLayouts = &[
+    (DatLayout::BroodWar, BLOCK_SIZE),
+    (DatLayout::StarCraft, STAR_CRAFT_BLOCK_SIZE),
+];
+const FILE_SIZES: &StockLayouts = &[(DatLayout::BroodWar, 836), (DatLayout::StarCraft, 432)];
+
+/// Parses techdata.dat in the layout that matches the length of the file.
+pub fn parse_tech_dat_dat(b: &[u8]) -> IResult<&[u8], TechDataDat> {
+    let layout = TechDataDat::detect_layout(b.len()).unwrap_or(DatLayout::BroodWar);
+
+    parse_tech_dat_dat_with_layout(b, layout)
 }
 
-fn parse_tech_dat_dat(b: &[u8]) -> IResult<&[u8], TechDataDat> {
-    let (remaining, mineral_cost_col) = count_total(le_u16)(b)?;
-    let (remaining, vespene_cost_col) = count_total(le_u16)(remaining)?;
-    let (remaining, research_time_col) = count_total(le_u16)(remaining)?;
-    let (remaining, energy_cost_col) = count_total(le_u16)(remaining)?;
+/// Parses techdata.dat in the given layout.
+pub fn parse_tech_dat_dat_with_layout(b: &[u8], layout: DatLayout) -> IResult<&[u8], TechDataDat> {
+    let entry_count = layout.entry_count(ENTRY_COUNTS);
+
+    let (remaining, mineral_cost_col) = count(le_u16, entry_count)(b)?;
+    let (remaining, vespene_cost_col) = count(le_u16, entry_count)(remaining)?;
+    let (remaining, research_time_col) = count(le_u16, entry_count)(remaining)?;
+    let (remaining, energy_cost_col) = count(le_u16, entry_count)(remaining)?;
 
     // unknown block
-    let (remaining, _) = count_total(le_u32)(remaining)?;
+    let (remaining, unknown_1_col) = count(le_u32, entry_count)(remaining)?;
 
-    let (remaining, icon_cost_col) = count_total(le_u16)(remaining)?;
-    let (remaining, label_cost_col) = count_total(le_u16)(remaining)?;
-    let (remaining, race_cost_col) = count_total(le_u8)(remaining)?;
+    let (remaining, icon_cost_col) = count(le_u16, entry_count)(remaining)?;
+    let (remaining, label_cost_col) = count(le_u16, entry_count)(remaining)?;
+    let (remaining, race_cost_col) = count(le_u8, entry_count)(remaining)?;
 
     // unknown block
-    let (remaining, _) = count_total(le_u8)(remaining)?;
+    let (remaining, unknown_2_col) = count(le_u8, entry_count)(remaining)?;
 
-    // unknown block
-    let (remaining, broodwar_col) = count_total(le_u8)(remaining)?;
+    // unknown block, missing before Brood War
+    let (remaining, broodwar_col) = if layout == DatLayout::StarCraft {
+        (remaining, vec![0; entry_count])
+    } else {
+        count(le_u8, entry_count)(remaining)?
+    };
 
     all_consuming(take(0u8))(remaining)?;
 
-    let tech_data = (0..BLOCK_SIZE)
+    let tech_data = (0..entry_count)
         .map(|i| TechData {
             mineral_cost: mineral_cost_col[i],
             vespene_cost: vespene_cost_col[i],
             research_time: research_time_col[i],
             energy_cost: energy_cost_col[i],
+            unknown_1: unknown_1_col[i],
             icon: icon_cost_col[i],
             label: label_cost_col[i],
             race: race_cost_col[i],
+            unknown_2: unknown_2_col[i],
             broodwar: broodwar_col[i],
         })
         .collect::<Vec<_>>();
 
-    Ok((remaining, TechDataDat(tech_data)))
+    Ok((remaining, TechDataDat { layout, tech_data }))
+}
+
+/// Writes techdata.dat in the same column-major layout that
+/// [`parse_tech_dat_dat`] reads.
+pub fn write_tech_data_dat(tech_data_dat: &TechDataDat) -> Vec<u8> {
+    let tech_data = &tech_data_dat.tech_data;
+
+    let mut b = vec![];
+
+    write_column(&mut b, tech_data, |t| t.mineral_cost.to_le_bytes());
+    write_column(&mut b, tech_data, |t| t.vespene_cost.to_le_bytes());
+    write_column(&mut b, tech_data, |t| t.research_time.to_le_bytes());
+    write_column(&mut b, tech_data, |t| t.energy_cost.to_le_bytes());
+    write_column(&mut b, tech_data, |t| t.unknown_1.to_le_bytes());
+    write_column(&mut b, tech_data, |t| t.icon.to_le_bytes());
+    write_column(&mut b, tech_data, |t| t.label.to_le_bytes());
+    write_column(&mut b, tech_data, |t| t.race.to_le_bytes());
+    write_column(&mut b, tech_data, |t| t.unknown_2.to_le_bytes());
+    if tech_data_dat.layout != DatLayout::StarCraft {
+        write_column(&mut b, tech_data, |t| t.broodwar.to_le_bytes());
+    }
+
+    b
 }
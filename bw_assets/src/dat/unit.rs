@@ -7,14 +7,26 @@ use boolinator::Boolinator;
 use nom::{
     bytes::complete::take,
     combinator::{all_consuming, map},
-    error::ParseError,
     multi::count,
     number::complete::{le_u8, le_u16, le_u32},
     sequence::tuple,
-    Finish, IResult, InputIter, InputTake, Parser,
+    Finish, IResult,
 };
+use num_traits::FromPrimitive;
+use serde::{
+    de::{self, Unexpected, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::{convert::TryFrom, fmt, ops::Range};
 use struple::Struple;
 
+use bw_core::UnitId;
+
+use super::{
+    table::{self, decode_flags, encode_flags, narrow, sort_rows, Error as TableError},
+    write_column, DatLayout, StockLayouts,
+};
+
 #[derive(Clone, Debug, Struple)]
 pub struct StarEditPlacementBox {
     width: u16,
@@ -42,6 +54,9 @@ pub struct Unit {
     /// Main subunit to the unit. Various turrets mostly.
     sub_unit_1: UnitPointer,
 
+    /// Secondary subunit. Unused by the game.
+    sub_unit_2: UnitPointer,
+
     /// Unit to transform into after Infestation. Exists only for units of
     /// ID 106-201 (buildings).
     infestation: Option<UnitPointer>,
@@ -61,7 +76,10 @@ pub struct Unit {
     /// Enables Shields for the unit. Works for any unit, not only Protoss.
     /// Terran and zerg buildings with shields do NOT acquire full shield
     /// capacity during construction.
-    are_shields_enabled: bool,
+    ///
+    /// Any value other than 0 enables them. The value is kept as it is so
+    /// that it is written back unchanged.
+    are_shields_enabled: u8,
 
     /// Amount of Shield Points the unit has. Shields are reduced before the
     /// unit's Hit Points are affected.
@@ -69,7 +87,9 @@ pub struct Unit {
 
     /// Unit Hit Points (HP) or "life" amount. Values over "9999" will be
     /// accepted in-game, but they will not be displayed.
-    hit_points: f32,
+    ///
+    /// Stored as 24.8 fixed point like the game does.
+    hit_points: u32,
 
     /// The elevation level at which the unit moves. It can be used to make
     /// units moves like flyers, but still be attacked by ground weapons and
@@ -77,6 +97,9 @@ pub struct Unit {
     /// Higher values puts the unit higher over terrain and other units.
     elevation_level: u8,
 
+    /// Unknown block that follows the elevation level.
+    unknown_1: u8,
+
     /// Controls ground units movement: units with lower Rank will stop and
     /// wait to allow units with higher Rank to continue movement. Has no
     /// effects on air units. Also the order this unit is displayed in its
@@ -251,6 +274,9 @@ pub struct Unit {
     /// (at Fastest speed). A value of 0 will crash the game.
     build_time: u16,
 
+    /// Unknown block that follows the build time.
+    unknown_2: u16,
+
     star_edit_group_flags: u8,
 
     /// Amount of Supply/Psi/Control the unit adds to the total pool. Halves
@@ -286,12 +312,558 @@ pub struct Unit {
     unit_map_string: u16,
 
     /// Makes the unit available only while playing BroodWar expansion set.
-    is_broodwar_only: bool,
+    ///
+    /// Any value other than 0 is set. The value is kept as it is so that it
+    /// is written back unchanged.
+    is_broodwar_only: u8,
 
     star_edit_availability_flags: u16,
 }
 
-pub struct UnitsDat(Vec<Unit>);
+impl Unit {
+    /// Flingy of the unit in flingy.dat, which leads to the sprite it is
+    /// drawn with.
+    pub fn graphics(&self) -> u8 {
+        self.graphics
+    }
+
+    /// Unit Hit Points (HP) or "life" amount.
+    pub fn hit_points(&self) -> f32 {
+        self.hit_points as f32 / 256.0
+    }
+
+    /// Hit points as 24.8 fixed point like the game uses them.
+    pub fn raw_hit_points(&self) -> u32 {
+        self.hit_points
+    }
+
+    /// Main subunit of the unit, e.g. the turret of a Siege Tank.
+    ///
+    /// Pointer to units.dat
+    pub fn sub_unit_1(&self) -> u16 {
+        self.sub_unit_1.0
+    }
+
+    /// Amount of shield points when shields are enabled.
+    pub fn shields(&self) -> Option<u16> {
+        (self.are_shields_enabled != 0).as_some(self.shield_amount)
+    }
+
+    /// Weapon used against ground units.
+    ///
+    /// Pointer to weapons.dat
+    pub fn ground_weapon(&self) -> u8 {
+        self.ground_weapon
+    }
+
+    /// Weapon used against air units.
+    ///
+    /// Pointer to weapons.dat
+    pub fn air_weapon(&self) -> u8 {
+        self.air_weapon
+    }
+
+    /// Whether the unit is attacked by air weapons.
+    pub fn is_flyer(&self) -> bool {
+        self.special_ability_flags & FLYER != 0
+    }
+
+    /// Whether the building can only be placed on creep.
+    pub fn requires_creep(&self) -> bool {
+        self.special_ability_flags & REQUIRES_CREEP != 0
+    }
+
+    /// Width and height of the footprint of a building, in pixels.
+    pub fn placement_box(&self) -> (u16, u16) {
+        (
+            self.star_edit_placement_box.width,
+            self.star_edit_placement_box.height,
+        )
+    }
+
+    /// Upgrade that improves the armor of the unit.
+    ///
+    /// Pointer to upgrades.dat
+    pub fn armour_upgrade(&self) -> u8 {
+        self.armour_upgrade
+    }
+
+    /// Unit size: independent, small, medium or large.
+    pub fn unit_size(&self) -> u8 {
+        self.unit_size
+    }
+
+    /// Armor without upgrades.
+    pub fn armour(&self) -> u8 {
+        self.armour
+    }
+
+    pub fn mineral_cost(&self) -> u16 {
+        self.mineral_cost
+    }
+
+    pub fn vespene_cost(&self) -> u16 {
+        self.vespense_cost
+    }
+
+    /// Time it takes to train or build the unit, in frames at the Fastest
+    /// speed.
+    pub fn build_time(&self) -> u16 {
+        self.build_time
+    }
+
+    /// Supply that the unit provides, in halves.
+    pub fn supply_provided(&self) -> u8 {
+        self.supply_provided
+    }
+
+    /// Supply that the unit takes up, in halves.
+    pub fn supply_required(&self) -> u8 {
+        self.supply_required
+    }
+}
+
+/// Bit of [`Unit::special_ability_flags`] for flying units.
+const FLYER: u32 = 1 << 2;
+
+/// Bit of [`Unit::special_ability_flags`] for buildings placed on creep.
+const REQUIRES_CREEP: u32 = 1 << 17;
+
+/// Names of the bits in [`Unit::special_ability_flags`].
+const SPECIAL_ABILITY_FLAGS: &[&str] = &[
+    "building",
+    "addon",
+    "flyer",
+    "resource_miner",
+    "subunit",
+    "flying_building",
+    "hero",
+    "regenerates_hp",
+    "animated_idle",
+    "cloakable",
+    "two_units_in_one_egg",
+    "single_entity",
+    "resource_depot",
+    "resource_container",
+    "robotic",
+    "detector",
+    "organic",
+    "requires_creep",
+    "unused",
+    "requires_psi",
+    "burrowable",
+    "spellcaster",
+    "permanent_cloak",
+    "pickup_item",
+    "ignore_supply_check",
+    "use_medium_overlays",
+    "use_large_overlays",
+    "battle_reactions",
+    "full_auto_attack",
+    "invincible",
+    "mechanical",
+    "produces_units",
+];
+
+/// Names of the bits in [`Unit::star_edit_group_flags`].
+const STAR_EDIT_GROUP_FLAGS: &[&str] = &[
+    "zerg",
+    "terran",
+    "protoss",
+    "men",
+    "building",
+    "factory",
+    "independent",
+    "neutral",
+];
+
+/// Names of the bits in [`Unit::star_edit_availability_flags`].
+const STAR_EDIT_AVAILABILITY_FLAGS: &[&str] = &[
+    "non_neutral",
+    "unit_listing",
+    "mission_briefing",
+    "player_settings",
+    "all_races",
+    "set_doodad_state",
+    "non_location_triggers",
+    "unit_hero_settings",
+    "location_triggers",
+    "brood_war_only",
+];
+
+/// Id of a row of units.dat.
+///
+/// Stock units are written by the name of their [`UnitId`] and the units that
+/// extended files add by their number.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct UnitRowId(pub u16);
+
+impl UnitRowId {
+    /// The stock unit of the row, if any.
+    pub fn unit_id(self) -> Option<UnitId> {
+        UnitId::from_u16(self.0)
+    }
+}
+
+impl Serialize for UnitRowId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // Names are written as strings rather than as enum variants so that
+        // self-describing formats like RON can read them back next to numbers.
+        match self.unit_id() {
+            Some(unit_id) => serializer.collect_str(&unit_id),
+            None => serializer.serialize_u16(self.0),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for UnitRowId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(UnitRowIdVisitor)
+    }
+}
+
+struct UnitRowIdVisitor;
+
+impl<'de> Visitor<'de> for UnitRowIdVisitor {
+    type Value = UnitRowId;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a unit name or a unit id")
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<UnitRowId, E> {
+        u16::try_from(v)
+            .map(UnitRowId)
+            .map_err(|_| E::invalid_value(Unexpected::Unsigned(v), &self))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<UnitRowId, E> {
+        u16::try_from(v)
+            .map(UnitRowId)
+            .map_err(|_| E::invalid_value(Unexpected::Signed(v), &self))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<UnitRowId, E> {
+        if let Ok(id) = v.parse() {
+            return Ok(UnitRowId(id));
+        }
+
+        v.parse::<UnitId>()
+            .map(|unit_id| UnitRowId(unit_id as u16))
+            .map_err(|_| E::invalid_value(Unexpected::Str(v), &self))
+    }
+}
+
+/// A [`Unit`] as a flat row of named columns.
+///
+/// Columns are named after the fields of [`Unit`]. Hit points are decoded to
+/// their displayed value and flags are written as their names joined by `|`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UnitRow {
+    pub id: UnitRowId,
+    pub graphics: u8,
+    pub sub_unit_1: u16,
+    pub sub_unit_2: u16,
+    pub infestation: Option<u16>,
+    pub construction_animation: u32,
+    pub unit_direction: u8,
+    pub are_shields_enabled: u8,
+    pub shield_amount: u16,
+    pub hit_points: f64,
+    pub elevation_level: u8,
+    pub unknown_1: u8,
+    pub sub_label: u8,
+    pub comp_ai_idle: u8,
+    pub human_ai_idle: u8,
+    pub return_to_idle: u8,
+    pub attack_unit: u8,
+    pub attack_move: u8,
+    pub ground_weapon: u8,
+    pub max_ground_hits: u8,
+    pub air_weapon: u8,
+    pub max_air_hits: u8,
+    pub ai_internal: u8,
+    pub special_ability_flags: String,
+    pub target_acquisition_range: u8,
+    pub sight_range: u8,
+    pub armour_upgrade: u8,
+    pub unit_size: u8,
+    pub armour: u8,
+    pub right_click_action: u8,
+    pub ready_sound: Option<u16>,
+    pub what_sound_start: u16,
+    pub what_sound_end: u16,
+    pub annoyed_sound_start: Option<u16>,
+    pub annoyed_sound_end: Option<u16>,
+    pub yes_sound_start: Option<u16>,
+    pub yes_sound_end: Option<u16>,
+    pub star_edit_placement_box_width: u16,
+    pub star_edit_placement_box_height: u16,
+    pub addon_horizontal: Option<u16>,
+    pub addon_vertical: Option<u16>,
+    pub dimensions_left: u16,
+    pub dimensions_up: u16,
+    pub dimensions_right: u16,
+    pub dimensions_down: u16,
+    pub portrait: u16,
+    pub mineral_cost: u16,
+    pub vespense_cost: u16,
+    pub build_time: u16,
+    pub unknown_2: u16,
+    pub star_edit_group_flags: String,
+    pub supply_provided: u8,
+    pub supply_required: u8,
+    pub space_required: u8,
+    pub space_provided: u8,
+    pub build_score: u16,
+    pub destroy_score: u16,
+    pub unit_map_string: u16,
+    pub is_broodwar_only: u8,
+    pub star_edit_availability_flags: String,
+}
+
+impl UnitRow {
+    fn new(id: UnitRowId, unit: &Unit) -> UnitRow {
+        UnitRow {
+            id,
+            graphics: unit.graphics,
+            sub_unit_1: unit.sub_unit_1.0,
+            sub_unit_2: unit.sub_unit_2.0,
+            infestation: unit.infestation.as_ref().map(|p| p.0),
+            construction_animation: unit.construction_animation,
+            unit_direction: unit.unit_direction,
+            are_shields_enabled: unit.are_shields_enabled,
+            shield_amount: unit.shield_amount,
+            hit_points: unit.hit_points as f64 / 256.0,
+            elevation_level: unit.elevation_level,
+            unknown_1: unit.unknown_1,
+            sub_label: unit.sub_label,
+            comp_ai_idle: unit.comp_ai_idle,
+            human_ai_idle: unit.human_ai_idle,
+            return_to_idle: unit.return_to_idle,
+            attack_unit: unit.attack_unit,
+            attack_move: unit.attack_move,
+            ground_weapon: unit.ground_weapon,
+            max_ground_hits: unit.max_ground_hits,
+            air_weapon: unit.air_weapon,
+            max_air_hits: unit.max_air_hits,
+            ai_internal: unit.ai_internal,
+            special_ability_flags: encode_flags(unit.special_ability_flags, SPECIAL_ABILITY_FLAGS),
+            target_acquisition_range: unit.target_acquisition_range,
+            sight_range: unit.sight_range,
+            armour_upgrade: unit.armour_upgrade,
+            unit_size: unit.unit_size,
+            armour: unit.armour,
+            right_click_action: unit.right_click_action,
+            ready_sound: unit.ready_sound,
+            what_sound_start: unit.what_sound_start,
+            what_sound_end: unit.what_sound_end,
+            annoyed_sound_start: unit.annoyed_sound_start,
+            annoyed_sound_end: unit.annoyed_sound_end,
+            yes_sound_start: unit.yes_sound_start,
+            yes_sound_end: unit.yes_sound_end,
+            star_edit_placement_box_width: unit.star_edit_placement_box.width,
+            star_edit_placement_box_height: unit.star_edit_placement_box.height,
+            addon_horizontal: unit.addon_horizontal,
+            addon_vertical: unit.addon_vertical,
+            dimensions_left: unit.dimensions.left,
+            dimensions_up: unit.dimensions.up,
+            dimensions_right: unit.dimensions.right,
+            dimensions_down: unit.dimensions.down,
+            portrait: unit.portrait,
+            mineral_cost: unit.mineral_cost,
+            vespense_cost: unit.vespense_cost,
+            build_time: unit.build_time,
+            unknown_2: unit.unknown_2,
+            star_edit_group_flags: encode_flags(
+                unit.star_edit_group_flags as u32,
+                STAR_EDIT_GROUP_FLAGS,
+            ),
+            supply_provided: unit.supply_provided,
+            supply_required: unit.supply_required,
+            space_required: unit.space_required,
+            space_provided: unit.space_provided,
+            build_score: unit.build_score,
+            destroy_score: unit.destroy_score,
+            unit_map_string: unit.unit_map_string,
+            is_broodwar_only: unit.is_broodwar_only,
+            star_edit_availability_flags: encode_flags(
+                unit.star_edit_availability_flags as u32,
+                STAR_EDIT_AVAILABILITY_FLAGS,
+            ),
+        }
+    }
+
+    fn into_unit(self) -> table::Result<Unit> {
+        let hit_points = (self.hit_points * 256.0).round();
+        if !(0.0..=u32::MAX as f64).contains(&hit_points) {
+            return Err(table::Error::OutOfRange {
+                column: "hit_points".to_string(),
+                value: self.hit_points.to_string(),
+            });
+        }
+
+        Ok(Unit {
+            graphics: self.graphics,
+            sub_unit_1: UnitPointer(self.sub_unit_1),
+            sub_unit_2: UnitPointer(self.sub_unit_2),
+            infestation: self.infestation.map(UnitPointer),
+            construction_animation: self.construction_animation,
+            unit_direction: self.unit_direction,
+            are_shields_enabled: self.are_shields_enabled,
+            shield_amount: self.shield_amount,
+            hit_points: hit_points as u32,
+            elevation_level: self.elevation_level,
+            unknown_1: self.unknown_1,
+            sub_label: self.sub_label,
+            comp_ai_idle: self.comp_ai_idle,
+            human_ai_idle: self.human_ai_idle,
+            return_to_idle: self.return_to_idle,
+            attack_unit: self.attack_unit,
+            attack_move: self.attack_move,
+            ground_weapon: self.ground_weapon,
+            max_ground_hits: self.max_ground_hits,
+            air_weapon: self.air_weapon,
+            max_air_hits: self.max_air_hits,
+            ai_internal: self.ai_internal,
+            special_ability_flags: decode_flags(
+                &self.special_ability_flags,
+                SPECIAL_ABILITY_FLAGS,
+            )?,
+            target_acquisition_range: self.target_acquisition_range,
+            sight_range: self.sight_range,
+            armour_upgrade: self.armour_upgrade,
+            unit_size: self.unit_size,
+            armour: self.armour,
+            right_click_action: self.right_click_action,
+            ready_sound: self.ready_sound,
+            what_sound_start: self.what_sound_start,
+            what_sound_end: self.what_sound_end,
+            annoyed_sound_start: self.annoyed_sound_start,
+            annoyed_sound_end: self.annoyed_sound_end,
+            yes_sound_start: self.yes_sound_start,
+            yes_sound_end: self.yes_sound_end,
+            star_edit_placement_box: StarEditPlacementBox {
+                width: self.star_edit_placement_box_width,
+                height: self.star_edit_placement_box_height,
+            },
+            addon_horizontal: self.addon_horizontal,
+            addon_vertical: self.addon_vertical,
+            dimensions: Dimensions {
+                left: self.dimensions_left,
+                up: self.dimensions_up,
+                right: self.dimensions_right,
+                down: self.dimensions_down,
+            },
+            portrait: self.portrait,
+            mineral_cost: self.mineral_cost,
+            vespense_cost: self.vespense_cost,
+            build_time: self.build_time,
+            unknown_2: self.unknown_2,
+            star_edit_group_flags: narrow(
+                "star_edit_group_flags",
+                decode_flags(&self.star_edit_group_flags, STAR_EDIT_GROUP_FLAGS)?,
+            )?,
+            supply_provided: self.supply_provided,
+            supply_required: self.supply_required,
+            space_required: self.space_required,
+            space_provided: self.space_provided,
+            build_score: self.build_score,
+            destroy_score: self.destroy_score,
+            unit_map_string: self.unit_map_string,
+            is_broodwar_only: self.is_broodwar_only,
+            star_edit_availability_flags: narrow(
+                "star_edit_availability_flags",
+                decode_flags(
+                    &self.star_edit_availability_flags,
+                    STAR_EDIT_AVAILABILITY_FLAGS,
+                )?,
+            )?,
+        })
+    }
+}
+
+pub struct UnitsDat {
+    layout: DatLayout,
+    units: Vec<Unit>,
+}
+
+impl UnitsDat {
+    /// Layout of the file the units were read from.
+    pub fn layout(&self) -> DatLayout {
+        self.layout
+    }
+
+    /// Picks the layout of units.dat from the length of the file.
+    pub fn detect_layout(len: usize) -> Option<DatLayout> {
+        DatLayout::detect(len, FILE_SIZES, ENTRY_SIZE)
+    }
+
+    /// Unit with the given unit id.
+    pub fn get(&self, id: usize) -> Option<&Unit> {
+        self.units.get(id)
+    }
+
+    /// Number of units, which is also the id that fields referring to a
+    /// unit use for none.
+    pub fn len(&self) -> usize {
+        self.units.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.units.is_empty()
+    }
+
+    /// Every unit as a row keyed by its [`UnitId`], or by its number for the
+    /// units that extended files add.
+    pub fn rows(&self) -> Vec<UnitRow> {
+        self.units
+            .iter()
+            .enumerate()
+            .map(|(i, unit)| UnitRow::new(UnitRowId(i as u16), unit))
+            .collect()
+    }
+
+    /// Builds units.dat from rows in any order. Every unit must have exactly
+    /// one row and more rows than the stock limit make an extended file.
+    pub fn from_rows(rows: Vec<UnitRow>) -> table::Result<UnitsDat> {
+        let layout =
+            DatLayout::for_count(rows.len(), ENTRY_COUNTS).ok_or(TableError::RowCount {
+                expected: BLOCK_SIZE,
+                actual: rows.len(),
+            })?;
+        let (units_only, buildings) = (units_only(layout), buildings(layout));
+
+        let units = sort_rows(rows, layout.entry_count(ENTRY_COUNTS), |row| {
+            row.id.0 as usize
+        })?
+        .into_iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let mut unit = row.into_unit()?;
+
+            // Columns that only exist for part of the units are dropped
+            // for the others so they are not written out.
+            if !buildings.contains(&i) {
+                unit.infestation = None;
+                unit.addon_horizontal = None;
+                unit.addon_vertical = None;
+            }
+            if !units_only.contains(&i) {
+                unit.ready_sound = None;
+                unit.annoyed_sound_start = None;
+                unit.annoyed_sound_end = None;
+                unit.yes_sound_start = None;
+                unit.yes_sound_end = None;
+            }
+
+            Ok(unit)
+        })
+        .collect::<table::Result<Vec<_>>>()?;
+
+        Ok(UnitsDat { layout, units })
+    }
+}
 
 pub struct UnitsDatAsset(Option<UnitsDat>);
 
@@ -334,214 +906,479 @@ const UNIT_COUNT: usize = 106;
 const BUILDING_COUNT: usize = 96;
 const BLOCK_SIZE: usize = 228;
 
-fn parse_u8_boolean(b: &[u8]) -> IResult<&[u8], bool> {
-    map(le_u8, |x| x != 0)(b)
-}
+/// Size of a single entry when every column exists for it.
+const ENTRY_SIZE: usize = 96;
+
+const ENTRY_COUNTS: &StockLayouts = &[(DatLayout::BroodWar, BLOCK_SIZE)];
+const FILE_SIZES: &StockLayouts = &[(DatLayout::BroodWar, 19876)];
 
-fn take_block(b: &[u8], size: u8) -> IResult<&[u8], ()> {
-    map(count_total(take(size)), |_| ())(b)
+/// Units that have the columns of units that are not buildings, e.g. their
+/// sounds.
+fn units_only(layout: DatLayout) -> Range<usize> {
+    match layout {
+        DatLayout::Extended(count) => 0..count,
+        _ => 0..UNIT_COUNT,
+    }
 }
 
-fn take_u8_block(b: &[u8]) -> IResult<&[u8], ()> {
-    take_block(b, 1u8)
+/// Units that have the columns of buildings, e.g. their addon position.
+fn buildings(layout: DatLayout) -> Range<usize> {
+    match layout {
+        DatLayout::Extended(count) => 0..count,
+        _ => UNIT_COUNT..UNIT_COUNT + BUILDING_COUNT,
+    }
 }
 
-fn take_u16_block(b: &[u8]) -> IResult<&[u8], ()> {
-    take_block(b, 2u8)
+fn parse_unit_pointer(b: &[u8]) -> IResult<&[u8], UnitPointer> {
+    map(le_u16, UnitPointer)(b)
 }
 
-fn u32_to_f32(x: u32, decimal_places: u32) -> f32 {
-    let whole = (x >> decimal_places) as f32;
-    let decimals = (x & ((1 << decimal_places) - 1)) as f32 / 10f32.powf(decimal_places as f32);
+/// Parses units.dat in the layout that matches the length of the file.
+pub fn parse_unit_dat(b: &[u8]) -> IResult<&[u8], UnitsDat> {
+    let layout = UnitsDat::detect_layout(b.len()).unwrap_or(DatLayout::BroodWar);
 
-    whole + decimals
+    parse_unit_dat_with_layout(b, layout)
 }
 
-fn count_building_block<I, O, E, F>(f: F) -> impl FnMut(I) -> IResult<I, Vec<O>, E>
-where
-    I: Clone + PartialEq + InputIter + InputTake,
-    F: Parser<I, O, E> + Clone,
-    E: ParseError<I>,
-{
-    count(f, BUILDING_COUNT)
-}
+/// Parses units.dat in the given layout.
+pub fn parse_unit_dat_with_layout(b: &[u8], layout: DatLayout) -> IResult<&[u8], UnitsDat> {
+    let entry_count = layout.entry_count(ENTRY_COUNTS);
+    let (units_only, buildings) = (units_only(layout), buildings(layout));
 
-fn count_unit_block<I, O, E, F>(f: F) -> impl FnMut(I) -> IResult<I, Vec<O>, E>
-where
-    I: Clone + PartialEq + InputIter + InputTake,
-    F: Parser<I, O, E>,
-    E: ParseError<I>,
-{
-    count(f, UNIT_COUNT)
-}
+    let (remaining, graphic_col) = count(le_u8, entry_count)(b)?;
+
+    let (remaining, sub_unit_1_col) = count(parse_unit_pointer, entry_count)(remaining)?;
+
+    let (remaining, sub_unit_2_col) = count(parse_unit_pointer, entry_count)(remaining)?;
+
+    let (remaining, infestation_col) = count(parse_unit_pointer, buildings.len())(remaining)?;
+    let (remaining, construction_animation_col) = count(le_u32, entry_count)(remaining)?;
+    let (remaining, unit_direction_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, are_shields_enabled_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, shield_amount_col) = count(le_u16, entry_count)(remaining)?;
+    let (remaining, hit_points_col) = count(le_u32, entry_count)(remaining)?;
+    let (remaining, elevation_level_col) = count(le_u8, entry_count)(remaining)?;
+
+    // unknown block
+    let (remaining, unknown_1_col) = count(le_u8, entry_count)(remaining)?;
+
+    let (remaining, sub_label_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, comp_ai_idle_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, human_ai_idle_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, return_to_idle_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, attack_unit_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, attack_move_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, ground_weapon_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, max_ground_hits_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, air_weapon_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, max_air_hits_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, ai_internal_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, special_ability_flags_col) = count(le_u32, entry_count)(remaining)?;
+    let (remaining, target_acquisition_range_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, sight_range_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, armour_upgrade_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, unit_size_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, armour_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, right_click_action_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, ready_sound_col) = count(le_u16, units_only.len())(remaining)?;
+    let (remaining, what_sound_start_col) = count(le_u16, entry_count)(remaining)?;
+    let (remaining, what_sound_end_col) = count(le_u16, entry_count)(remaining)?;
+    let (remaining, annoyed_sound_start_col) = count(le_u16, units_only.len())(remaining)?;
+    let (remaining, annoyed_sound_end_col) = count(le_u16, units_only.len())(remaining)?;
+    let (remaining, yes_sound_start_col) = count(le_u16, units_only.len())(remaining)?;
+    let (remaining, yes_sound_end_col) = count(le_u16, units_only.len())(remaining)?;
+
+    let (remaining, star_edit_placement_col) = count(
+        map(tuple((le_u16, le_u16)), StarEditPlacementBox::from_tuple),
+        entry_count,
+    )(remaining)?;
+
+    let (remaining, addon_horizontal_col) = count(le_u16, buildings.len())(remaining)?;
+    let (remaining, addon_vertical_col) = count(le_u16, buildings.len())(remaining)?;
+
+    let (remaining, dimensions_col) = count(
+        map(
+            tuple((le_u16, le_u16, le_u16, le_u16)),
+            Dimensions::from_tuple,
+        ),
+        entry_count,
+    )(remaining)?;
+
+    let (remaining, portrait_col) = count(le_u16, entry_count)(remaining)?;
+    let (remaining, mineral_cost_col) = count(le_u16, entry_count)(remaining)?;
+    let (remaining, vespense_cost_col) = count(le_u16, entry_count)(remaining)?;
+    let (remaining, build_time_col) = count(le_u16, entry_count)(remaining)?;
+
+    // The u16 after build time is unknown and useless
+    let (remaining, unknown_2_col) = count(le_u16, entry_count)(remaining)?;
+
+    let (remaining, star_edit_group_flags_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, supply_provided_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, supply_required_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, space_required_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, space_provided_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, build_score_col) = count(le_u16, entry_count)(remaining)?;
+    let (remaining, destroy_score_col) = count(le_u16, entry_count)(remaining)?;
+    let (remaining, unit_map_string_col) = count(le_u16, entry_count)(remaining)?;
+    let (remaining, is_broodwar_only_col) = count(le_u8, entry_count)(remaining)?;
 
-pub fn count_total<I, O, E, F>(f: F) -> impl FnMut(I) -> IResult<I, Vec<O>, E>
-where
-    I: Clone + PartialEq,
-    F: Parser<I, O, E>,
-    E: ParseError<I>,
-{
-    count(f, BLOCK_SIZE)
+    let (remaining, star_edit_availability_flags_col) = count(le_u16, entry_count)(remaining)?;
+
+    all_consuming(take(0u8))(remaining)?;
+
+    let units = (0..entry_count)
+        .map(|i| Unit {
+            graphics: graphic_col[i],
+            sub_unit_1: sub_unit_1_col[i].clone(),
+            sub_unit_2: sub_unit_2_col[i].clone(),
+            infestation: buildings.contains(&i).and_option_from(|| {
+                infestation_col
+                    .get(i - buildings.start)
+                    .map(ToOwned::to_owned)
+            }),
+            construction_animation: construction_animation_col[i],
+            unit_direction: unit_direction_col[i],
+            are_shields_enabled: are_shields_enabled_col[i],
+            shield_amount: shield_amount_col[i],
+            hit_points: hit_points_col[i],
+            elevation_level: elevation_level_col[i],
+            unknown_1: unknown_1_col[i],
+            sub_label: sub_label_col[i],
+            comp_ai_idle: comp_ai_idle_col[i],
+            human_ai_idle: human_ai_idle_col[i],
+            return_to_idle: return_to_idle_col[i],
+            attack_unit: attack_unit_col[i],
+            attack_move: attack_move_col[i],
+            ground_weapon: ground_weapon_col[i],
+            max_ground_hits: max_ground_hits_col[i],
+            air_weapon: air_weapon_col[i],
+            max_air_hits: max_air_hits_col[i],
+            ai_internal: ai_internal_col[i],
+            special_ability_flags: special_ability_flags_col[i],
+            target_acquisition_range: target_acquisition_range_col[i],
+            sight_range: sight_range_col[i],
+            armour_upgrade: armour_upgrade_col[i],
+            unit_size: unit_size_col[i],
+            armour: armour_col[i],
+            right_click_action: right_click_action_col[i],
+            ready_sound: units_only.contains(&i).and_option_from(|| {
+                ready_sound_col
+                    .get(i - units_only.start)
+                    .map(ToOwned::to_owned)
+            }),
+            what_sound_start: what_sound_start_col[i],
+            what_sound_end: what_sound_end_col[i],
+            annoyed_sound_start: units_only.contains(&i).and_option_from(|| {
+                annoyed_sound_start_col
+                    .get(i - units_only.start)
+                    .map(ToOwned::to_owned)
+            }),
+            annoyed_sound_end: units_only.contains(&i).and_option_from(|| {
+                annoyed_sound_end_col
+                    .get(i - units_only.start)
+                    .map(ToOwned::to_owned)
+            }),
+            yes_sound_start: units_only.contains(&i).and_option_from(|| {
+                yes_sound_start_col
+                    .get(i - units_only.start)
+                    .map(ToOwned::to_owned)
+            }),
+            yes_sound_end: units_only.contains(&i).and_option_from(|| {
+                yes_sound_end_col
+                    .get(i - units_only.start)
+                    .map(ToOwned::to_owned)
+            }),
+            star_edit_placement_box: star_edit_placement_col[i].clone(),
+            addon_horizontal: buildings.contains(&i).and_option_from(|| {
+                addon_horizontal_col
+                    .get(i - buildings.start)
+                    .map(ToOwned::to_owned)
+            }),
+            addon_vertical: buildings.contains(&i).and_option_from(|| {
+                addon_vertical_col
+                    .get(i - buildings.start)
+                    .map(ToOwned::to_owned)
+            }),
+            dimensions: dimensions_col[i].clone(),
+            portrait: portrait_col[i],
+            mineral_cost: mineral_cost_col[i],
+            vespense_cost: vespense_cost_col[i],
+            build_time: build_time_col[i],
+            unknown_2: unknown_2_col[i],
+            star_edit_group_flags: star_edit_group_flags_col[i],
+            supply_provided: supply_provided_col[i],
+            supply_required: supply_required_col[i],
+            space_required: space_required_col[i],
+            space_provided: space_provided_col[i],
+            build_score: build_score_col[i],
+            destroy_score: destroy_score_col[i],
+            unit_map_string: unit_map_string_col[i],
+            is_broodwar_only: is_broodwar_only_col[i],
+            star_edit_availability_flags: star_edit_availability_flags_col[i],
+        })
+        .collect::<Vec<_>>();
+
+    Ok((remaining, UnitsDat { layout, units }))
 }
 
-fn parse_unit_pointer(b: &[u8]) -> IResult<&[u8], UnitPointer> {
-    map(le_u16, UnitPointer)(b)
+/// Writes units.dat in the same column-major layout that [`parse_unit_dat`]
+/// reads.
+pub fn write_unit_dat(units_dat: &UnitsDat) -> Vec<u8> {
+    let units = &units_dat.units;
+    let buildings = &units[buildings(units_dat.layout)];
+    let units_only = &units[units_only(units_dat.layout)];
+
+    let mut b = vec![];
+
+    write_column(&mut b, units, |u| u.graphics.to_le_bytes());
+    write_column(&mut b, units, |u| u.sub_unit_1.0.to_le_bytes());
+    write_column(&mut b, units, |u| u.sub_unit_2.0.to_le_bytes());
+    write_column(&mut b, buildings, |u| {
+        u.infestation.as_ref().map_or(0, |p| p.0).to_le_bytes()
+    });
+    write_column(&mut b, units, |u| u.construction_animation.to_le_bytes());
+    write_column(&mut b, units, |u| u.unit_direction.to_le_bytes());
+    write_column(&mut b, units, |u| u.are_shields_enabled.to_le_bytes());
+    write_column(&mut b, units, |u| u.shield_amount.to_le_bytes());
+    write_column(&mut b, units, |u| u.hit_points.to_le_bytes());
+    write_column(&mut b, units, |u| u.elevation_level.to_le_bytes());
+    write_column(&mut b, units, |u| u.unknown_1.to_le_bytes());
+    write_column(&mut b, units, |u| u.sub_label.to_le_bytes());
+    write_column(&mut b, units, |u| u.comp_ai_idle.to_le_bytes());
+    write_column(&mut b, units, |u| u.human_ai_idle.to_le_bytes());
+    write_column(&mut b, units, |u| u.return_to_idle.to_le_bytes());
+    write_column(&mut b, units, |u| u.attack_unit.to_le_bytes());
+    write_column(&mut b, units, |u| u.attack_move.to_le_bytes());
+    write_column(&mut b, units, |u| u.ground_weapon.to_le_bytes());
+    write_column(&mut b, units, |u| u.max_ground_hits.to_le_bytes());
+    write_column(&mut b, units, |u| u.air_weapon.to_le_bytes());
+    write_column(&mut b, units, |u| u.max_air_hits.to_le_bytes());
+    write_column(&mut b, units, |u| u.ai_internal.to_le_bytes());
+    write_column(&mut b, units, |u| u.special_ability_flags.to_le_bytes());
+    write_column(&mut b, units, |u| u.target_acquisition_range.to_le_bytes());
+    write_column(&mut b, units, |u| u.sight_range.to_le_bytes());
+    write_column(&mut b, units, |u| u.armour_upgrade.to_le_bytes());
+    write_column(&mut b, units, |u| u.unit_size.to_le_bytes());
+    write_column(&mut b, units, |u| u.armour.to_le_bytes());
+    write_column(&mut b, units, |u| u.right_click_action.to_le_bytes());
+    write_column(&mut b, units_only, |u| {
+        u.ready_sound.unwrap_or(0).to_le_bytes()
+    });
+    write_column(&mut b, units, |u| u.what_sound_start.to_le_bytes());
+    write_column(&mut b, units, |u| u.what_sound_end.to_le_bytes());
+    write_column(&mut b, units_only, |u| {
+        u.annoyed_sound_start.unwrap_or(0).to_le_bytes()
+    });
+    write_column(&mut b, units_only, |u| {
+        u.annoyed_sound_end.unwrap_or(0).to_le_bytes()
+    });
+    write_column(&mut b, units_only, |u| {
+        u.yes_sound_start.unwrap_or(0).to_le_bytes()
+    });
+    write_column(&mut b, units_only, |u| {
+        u.yes_sound_end.unwrap_or(0).to_le_bytes()
+    });
+    write_column(&mut b, units, |u| {
+        let StarEditPlacementBox { width, height } = &u.star_edit_placement_box;
+        [width.to_le_bytes(), height.to_le_bytes()].concat()
+    });
+    write_column(&mut b, buildings, |u| {
+        u.addon_horizontal.unwrap_or(0).to_le_bytes()
+    });
+    write_column(&mut b, buildings, |u| {
+        u.addon_vertical.unwrap_or(0).to_le_bytes()
+    });
+    write_column(&mut b, units, |u| {
+        let Dimensions {
+            left,
+            up,
+            right,
+            down,
+        } = &u.dimensions;
+        [
+            left.to_le_bytes(),
+            up.to_le_bytes(),
+            right.to_le_bytes(),
+            down.to_le_bytes(),
+        ]
+        .concat()
+    });
+    write_column(&mut b, units, |u| u.portrait.to_le_bytes());
+    write_column(&mut b, units, |u| u.mineral_cost.to_le_bytes());
+    write_column(&mut b, units, |u| u.vespense_cost.to_le_bytes());
+    write_column(&mut b, units, |u| u.build_time.to_le_bytes());
+    write_column(&mut b, units, |u| u.unknown_2.to_le_bytes());
+    write_column(&mut b, units, |u| u.star_edit_group_flags.to_le_bytes());
+    write_column(&mut b, units, |u| u.supply_provided.to_le_bytes());
+    write_column(&mut b, units, |u| u.supply_required.to_le_bytes());
+    write_column(&mut b, units, |u| u.space_required.to_le_bytes());
+    write_column(&mut b, units, |u| u.space_provided.to_le_bytes());
+    write_column(&mut b, units, |u| u.build_score.to_le_bytes());
+    write_column(&mut b, units, |u| u.destroy_score.to_le_bytes());
+    write_column(&mut b, units, |u| u.unit_map_string.to_le_bytes());
+    write_column(&mut b, units, |u| u.is_broodwar_only.to_le_bytes());
+    write_column(&mut b, units, |u| {
+        u.star_edit_availability_flags.to_le_bytes()
+    });
+
+    b
 }
 
-fn parse_unit_dat(b: &[u8]) -> IResult<&[u8], UnitsDat> {
-    let (remaining, graphic_col) = count_total(le_u8)(b)?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::stock_mpq_source;
+    use amethyst::assets::Source;
+    use bw_core::Race;
+    use spectral::prelude::*;
 
-    let (remaining, sub_unit_1_col) = count_total(parse_unit_pointer)(remaining)?;
+    fn has_flag(flags: u32, names: &[&str], name: &str) -> bool {
+        let bit = names.iter().position(|n| *n == name).expect("flag exists");
 
-    // The next u16 is sub_unit_2 which is unused by the game.
-    let (remaining, _) = take_u16_block(remaining)?;
+        flags & (1 << bit) != 0
+    }
 
-    let (remaining, infestation_col) = count_building_block(parse_unit_pointer)(remaining)?;
-    let (remaining, construction_animation_col) = count_total(le_u32)(remaining)?;
-    let (remaining, unit_direction_col) = count_total(le_u8)(remaining)?;
-    let (remaining, are_shields_enabled_col) = count_total(parse_u8_boolean)(remaining)?;
-    let (remaining, shield_amount_col) = count_total(le_u16)(remaining)?;
-    let (remaining, hit_points_col) = count_total(map(le_u32, |x| u32_to_f32(x, 8)))(remaining)?;
-    let (remaining, elevation_level_col) = count_total(le_u8)(remaining)?;
+    #[test]
+    fn it_keeps_every_value_of_boolean_columns() {
+        let b = vec![0; 19876];
+        let mut rows = parse_unit_dat(&b).finish().unwrap().1.rows();
+        rows[0].are_shields_enabled = 2;
+        rows[1].is_broodwar_only = 0xFF;
+        let units_dat = UnitsDat::from_rows(rows.clone()).unwrap();
 
-    // unknown block
-    let (remaining, _) = take_u8_block(remaining)?;
-
-    let (remaining, sub_label_col) = count_total(le_u8)(remaining)?;
-    let (remaining, comp_ai_idle_col) = count_total(le_u8)(remaining)?;
-    let (remaining, human_ai_idle_col) = count_total(le_u8)(remaining)?;
-    let (remaining, return_to_idle_col) = count_total(le_u8)(remaining)?;
-    let (remaining, attack_unit_col) = count_total(le_u8)(remaining)?;
-    let (remaining, attack_move_col) = count_total(le_u8)(remaining)?;
-    let (remaining, ground_weapon_col) = count_total(le_u8)(remaining)?;
-    let (remaining, max_ground_hits_col) = count_total(le_u8)(remaining)?;
-    let (remaining, air_weapon_col) = count_total(le_u8)(remaining)?;
-    let (remaining, max_air_hits_col) = count_total(le_u8)(remaining)?;
-    let (remaining, ai_internal_col) = count_total(le_u8)(remaining)?;
-    let (remaining, special_ability_flags_col) = count_total(le_u32)(remaining)?;
-    let (remaining, target_acquisition_range_col) = count_total(le_u8)(remaining)?;
-    let (remaining, sight_range_col) = count_total(le_u8)(remaining)?;
-    let (remaining, armour_upgrade_col) = count_total(le_u8)(remaining)?;
-    let (remaining, unit_size_col) = count_total(le_u8)(remaining)?;
-    let (remaining, armour_col) = count_total(le_u8)(remaining)?;
-    let (remaining, right_click_action_col) = count_total(le_u8)(remaining)?;
-    let (remaining, ready_sound_col) = count_unit_block(le_u16)(remaining)?;
-    let (remaining, what_sound_start_col) = count_total(le_u16)(remaining)?;
-    let (remaining, what_sound_end_col) = count_total(le_u16)(remaining)?;
-    let (remaining, annoyed_sound_start_col) = count_unit_block(le_u16)(remaining)?;
-    let (remaining, annoyed_sound_end_col) = count_unit_block(le_u16)(remaining)?;
-    let (remaining, yes_sound_start_col) = count_unit_block(le_u16)(remaining)?;
-    let (remaining, yes_sound_end_col) = count_unit_block(le_u16)(remaining)?;
-
-
-    let (remaining, star_edit_placement_col) = count_total(map(
-        tuple((le_u16, le_u16)),
-        StarEditPlacementBox::from_tuple,
-    ))(remaining)?;
-
-    let (remaining, addon_horizontal_col) = count_building_block(le_u16)(remaining)?;
-    let (remaining, addon_vertical_col) = count_building_block(le_u16)(remaining)?;
-
-    let (remaining, dimensions_col) = count_total(map(
-        tuple((le_u16, le_u16, le_u16, le_u16)),
-        Dimensions::from_tuple,
-    ))(remaining)?;
-
-    let (remaining, portrait_col) = count_total(le_u16)(remaining)?;
-    let (remaining, mineral_cost_col) = count_total(le_u16)(remaining)?;
-    let (remaining, vespense_cost_col) = count_total(le_u16)(remaining)?;
-    let (remaining, build_time_col) = count_total(le_u16)(remaining)?;
+        let b = write_unit_dat(&units_dat);
+        let (_, units_dat) = parse_unit_dat(&b).finish().unwrap();
 
-    // The u16 after build time is unknown and useless
-    let (remaining, _) = take_u16_block(remaining)?;
+        assert_that(&units_dat.rows()).is_equal_to(&rows);
+        assert_that(&units_dat.get(0).unwrap().shields()).is_equal_to(Some(0));
+        assert_that(&units_dat.get(1).unwrap().shields()).is_none();
+    }
 
-    let (remaining, star_edit_group_flags_col) = count_total(le_u8)(remaining)?;
-    let (remaining, supply_provided_col) = count_total(le_u8)(remaining)?;
-    let (remaining, supply_required_col) = count_total(le_u8)(remaining)?;
-    let (remaining, space_required_col) = count_total(le_u8)(remaining)?;
-    let (remaining, space_provided_col) = count_total(le_u8)(remaining)?;
-    let (remaining, build_score_col) = count_total(le_u16)(remaining)?;
-    let (remaining, destroy_score_col) = count_total(le_u16)(remaining)?;
-    let (remaining, unit_map_string_col) = count_total(le_u16)(remaining)?;
-    let (remaining, is_broodwar_only_col) = count_total(parse_u8_boolean)(remaining)?;
+    /// Columns of the stock units.dat that units are classified by, as
+    /// `bw-dat export` writes them. Only the flags in [`FIXTURE_SPECIAL_FLAGS`]
+    /// and [`FIXTURE_GROUP_FLAGS`] are kept.
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct FlagRow {
+        id: UnitId,
+        special_ability_flags: String,
+        star_edit_group_flags: String,
+        sub_unit_1: u16,
+    }
 
-    let (remaining, star_edit_availability_flags_col) = count_total(le_u16)(remaining)?;
+    const FIXTURE_SPECIAL_FLAGS: &[&str] = &[
+        "building",
+        "addon",
+        "resource_miner",
+        "subunit",
+        "hero",
+        "resource_container",
+    ];
+
+    const FIXTURE_GROUP_FLAGS: &[&str] = &["zerg", "terran", "protoss"];
+
+    fn flag_fixture() -> Vec<FlagRow> {
+        csv::Reader::from_reader(&include_bytes!("fixtures/units_flags.csv")[..])
+            .deserialize()
+            .collect::<std::result::Result<_, _>>()
+            .unwrap()
+    }
 
-    all_consuming(take(0u8))(remaining)?;
+    /// Flags of `flags` that are also in `kept`, in the order of `names`.
+    fn keep_flags(flags: &str, names: &[&str], kept: &[&str]) -> String {
+        flags
+            .split('|')
+            .filter(|flag| names.contains(flag) && kept.contains(flag))
+            .collect::<Vec<_>>()
+            .join("|")
+    }
+
+    #[test]
+    fn it_classifies_unit_ids_like_the_units_dat_flags() {
+        let rows = flag_fixture();
+        assert_that(&rows).has_length(UnitId::all().count());
+
+        for (row, unit_id) in rows.iter().zip(UnitId::all()) {
+            assert_that(&row.id).is_equal_to(unit_id);
+
+            let special = |name| {
+                let flags = decode_flags(&row.special_ability_flags, SPECIAL_ABILITY_FLAGS);
+                has_flag(flags.unwrap(), SPECIAL_ABILITY_FLAGS, name)
+            };
+            let group = |name| {
+                let flags = decode_flags(&row.star_edit_group_flags, STAR_EDIT_GROUP_FLAGS);
+                has_flag(flags.unwrap(), STAR_EDIT_GROUP_FLAGS, name)
+            };
+
+            asserting(&format!("{} is a building", unit_id))
+                .that(&unit_id.is_building())
+                .is_equal_to(special("building"));
+            asserting(&format!("{} is an add-on", unit_id))
+                .that(&unit_id.is_addon())
+                .is_equal_to(special("addon"));
+            asserting(&format!("{} is a turret", unit_id))
+                .that(&unit_id.is_turret())
+                .is_equal_to(special("subunit"));
+            asserting(&format!("{} is a resource", unit_id))
+                .that(&unit_id.is_resource())
+                .is_equal_to(special("resource_container"));
+            asserting(&format!("{} is a worker", unit_id))
+                .that(&unit_id.is_worker())
+                .is_equal_to(special("resource_miner"));
+
+            // The Dark Templar hero lacks the flag, and turrets only carry
+            // it on some heroes.
+            if unit_id != UnitId::HeroDarkTemplar && !unit_id.is_turret() {
+                asserting(&format!("{} is a hero", unit_id))
+                    .that(&unit_id.is_hero())
+                    .is_equal_to(special("hero"));
+            }
+
+            if let Some(race) = unit_id.race() {
+                let group_name = match race {
+                    Race::Terran => "terran",
+                    Race::Zerg => "zerg",
+                    Race::Protoss => "protoss",
+                };
+                asserting(&format!("{} is {:?}", unit_id, race))
+                    .that(&group(group_name))
+                    .is_true();
+            }
+
+            if let Some(parent) = unit_id.turret_parent() {
+                asserting(&format!("{} is the turret of {}", unit_id, parent))
+                    .that(&rows[parent as usize].sub_unit_1)
+                    .is_equal_to(unit_id as u16);
+            }
+        }
+    }
 
-    let units =
-        (0..BLOCK_SIZE)
-            .map(|i| Unit {
-                graphics: graphic_col[i],
-                sub_unit_1: sub_unit_1_col[i].clone(),
-                infestation: (i >= UNIT_COUNT && i < UNIT_COUNT + BUILDING_COUNT)
-                    .and_option_from(|| infestation_col.get(i - UNIT_COUNT).map(ToOwned::to_owned)),
-                construction_animation: construction_animation_col[i],
-                unit_direction: unit_direction_col[i],
-                are_shields_enabled: are_shields_enabled_col[i],
-                shield_amount: shield_amount_col[i],
-                hit_points: hit_points_col[i],
-                elevation_level: elevation_level_col[i],
-                sub_label: sub_label_col[i],
-                comp_ai_idle: comp_ai_idle_col[i],
-                human_ai_idle: human_ai_idle_col[i],
-                return_to_idle: return_to_idle_col[i],
-                attack_unit: attack_unit_col[i],
-                attack_move: attack_move_col[i],
-                ground_weapon: ground_weapon_col[i],
-                max_ground_hits: max_ground_hits_col[i],
-                air_weapon: air_weapon_col[i],
-                max_air_hits: max_air_hits_col[i],
-                ai_internal: ai_internal_col[i],
-                special_ability_flags: special_ability_flags_col[i],
-                target_acquisition_range: target_acquisition_range_col[i],
-                sight_range: sight_range_col[i],
-                armour_upgrade: armour_upgrade_col[i],
-                unit_size: unit_size_col[i],
-                armour: armour_col[i],
-                right_click_action: right_click_action_col[i],
-                ready_sound: (i < UNIT_COUNT)
-                    .and_option_from(|| ready_sound_col.get(i).map(ToOwned::to_owned)),
-                what_sound_start: what_sound_start_col[i],
-                what_sound_end: what_sound_end_col[i],
-                annoyed_sound_start: (i < UNIT_COUNT)
-                    .and_option_from(|| annoyed_sound_start_col.get(i).map(ToOwned::to_owned)),
-                annoyed_sound_end: (i < UNIT_COUNT)
-                    .and_option_from(|| annoyed_sound_end_col.get(i).map(ToOwned::to_owned)),
-                yes_sound_start: (i < UNIT_COUNT)
-                    .and_option_from(|| yes_sound_start_col.get(i).map(ToOwned::to_owned)),
-                yes_sound_end: (i < UNIT_COUNT)
-                    .and_option_from(|| yes_sound_end_col.get(i).map(ToOwned::to_owned)),
-                star_edit_placement_box: star_edit_placement_col[i].clone(),
-                addon_horizontal: (i >= UNIT_COUNT && i < UNIT_COUNT + BUILDING_COUNT)
-                    .and_option_from(|| {
-                        addon_horizontal_col
-                            .get(i - UNIT_COUNT)
-                            .map(ToOwned::to_owned)
-                    }),
-                addon_vertical: (i >= UNIT_COUNT && i < UNIT_COUNT + BUILDING_COUNT)
-                    .and_option_from(|| {
-                        addon_vertical_col
-                            .get(i - UNIT_COUNT)
-                            .map(ToOwned::to_owned)
-                    }),
-                dimensions: dimensions_col[i].clone(),
-                portrait: portrait_col[i],
-                mineral_cost: mineral_cost_col[i],
-                vespense_cost: vespense_cost_col[i],
-                build_time: build_time_col[i],
-                star_edit_group_flags: star_edit_group_flags_col[i],
-                supply_provided: supply_provided_col[i],
-                supply_required: supply_required_col[i],
-                space_required: space_required_col[i],
-                space_provided: space_provided_col[i],
-                build_score: build_score_col[i],
-                destroy_score: destroy_score_col[i],
-                unit_map_string: unit_map_string_col[i],
-                is_broodwar_only: is_broodwar_only_col[i],
-                star_edit_availability_flags: star_edit_availability_flags_col[i],
+    #[test]
+    #[ignore = "needs stock MPQs"]
+    fn it_keeps_the_flag_fixture_in_line_with_the_stock_units_dat() {
+        let mpq_source = stock_mpq_source();
+        let b = mpq_source.load("arr\\units.dat").unwrap();
+        let (_, units_dat) = parse_unit_dat(&b).finish().unwrap();
+
+        let stock = units_dat
+            .rows()
+            .into_iter()
+            .take(UnitId::all().count())
+            .map(|row| FlagRow {
+                id: UnitId::from_u16(row.id.0).unwrap(),
+                special_ability_flags: keep_flags(
+                    &row.special_ability_flags,
+                    SPECIAL_ABILITY_FLAGS,
+                    FIXTURE_SPECIAL_FLAGS,
+                ),
+                star_edit_group_flags: keep_flags(
+                    &row.star_edit_group_flags,
+                    STAR_EDIT_GROUP_FLAGS,
+                    FIXTURE_GROUP_FLAGS,
+                ),
+                sub_unit_1: row.sub_unit_1,
             })
             .collect::<Vec<_>>();
 
-    Ok((remaining, UnitsDat(units)))
+        assert_that(&stock).is_equal_to(flag_fixture());
+    }
 }
@@ -6,10 +6,16 @@ use amethyst::{
 use nom::{
     bytes::complete::take,
     combinator::all_consuming,
-    error::ParseError,
     multi::count,
     number::complete::{le_u8, le_u16},
-    Finish, IResult, Parser,
+    Finish, IResult,
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    table::{self, sort_rows, Error as TableError},
+    write_column, DatLayout, StockLayouts,
 };
 
 #[derive(Debug)]
@@ -20,6 +26,7 @@ pub struct Upgrade {
     vespene_factor: u16,
     time_cost: u16,
     time_factor: u16,
+    unknown: u16,
     icon: u16,
     label: u16,
     race: u8,
@@ -27,7 +34,159 @@ pub struct Upgrade {
     brood_war_specific: u8,
 }
 
-pub struct UpgradesDat(Vec<Upgrade>);
+impl Upgrade {
+    /// Name of the upgrade.
+    ///
+    /// Index to stat_txt.tbl
+    pub fn label(&self) -> u16 {
+        self.label
+    }
+
+    /// Minerals it takes to research the first level.
+    pub fn mineral_cost(&self) -> u16 {
+        self.mineral_cost
+    }
+
+    /// Minerals that every level after the first costs on top of the
+    /// previous one.
+    pub fn mineral_factor(&self) -> u16 {
+        self.mineral_factor
+    }
+
+    /// Vespene gas it takes to research the first level.
+    pub fn vespene_cost(&self) -> u16 {
+        self.vespene_cost
+    }
+
+    /// Vespene gas that every level after the first costs on top of the
+    /// previous one.
+    pub fn vespene_factor(&self) -> u16 {
+        self.vespene_factor
+    }
+
+    /// Frames it takes to research the first level at the Fastest speed.
+    pub fn time_cost(&self) -> u16 {
+        self.time_cost
+    }
+
+    /// Frames that every level after the first takes on top of the previous
+    /// one.
+    pub fn time_factor(&self) -> u16 {
+        self.time_factor
+    }
+
+    /// Number of levels that can be researched.
+    pub fn max_repeats(&self) -> u8 {
+        self.max_repeats
+    }
+}
+
+/// A [`Upgrade`] as a flat row of named columns.
+///
+/// Columns are named after the fields of [`Upgrade`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UpgradeRow {
+    pub id: u16,
+    pub mineral_cost: u16,
+    pub mineral_factor: u16,
+    pub vespene_cost: u16,
+    pub vespene_factor: u16,
+    pub time_cost: u16,
+    pub time_factor: u16,
+    pub unknown: u16,
+    pub icon: u16,
+    pub label: u16,
+    pub race: u8,
+    pub max_repeats: u8,
+    pub brood_war_specific: u8,
+}
+
+impl UpgradeRow {
+    fn new(id: u16, upgrade: &Upgrade) -> UpgradeRow {
+        UpgradeRow {
+            id,
+            mineral_cost: upgrade.mineral_cost,
+            mineral_factor: upgrade.mineral_factor,
+            vespene_cost: upgrade.vespene_cost,
+            vespene_factor: upgrade.vespene_factor,
+            time_cost: upgrade.time_cost,
+            time_factor: upgrade.time_factor,
+            unknown: upgrade.unknown,
+            icon: upgrade.icon,
+            label: upgrade.label,
+            race: upgrade.race,
+            max_repeats: upgrade.max_repeats,
+            brood_war_specific: upgrade.brood_war_specific,
+        }
+    }
+
+    fn into_upgrade(self) -> Upgrade {
+        Upgrade {
+            mineral_cost: self.mineral_cost,
+            mineral_factor: self.mineral_factor,
+            vespene_cost: self.vespene_cost,
+            vespene_factor: self.vespene_factor,
+            time_cost: self.time_cost,
+            time_factor: self.time_factor,
+            unknown: self.unknown,
+            icon: self.icon,
+            label: self.label,
+            race: self.race,
+            max_repeats: self.max_repeats,
+            brood_war_specific: self.brood_war_specific,
+        }
+    }
+}
+
+pub struct UpgradesDat {
+    layout: DatLayout,
+    upgrades: Vec<Upgrade>,
+}
+
+impl UpgradesDat {
+    /// Layout of the file the entries were read from.
+    pub fn layout(&self) -> DatLayout {
+        self.layout
+    }
+
+    /// Picks the layout of upgrades.dat from the length of the file.
+    pub fn detect_layout(len: usize) -> Option<DatLayout> {
+        DatLayout::detect(len, FILE_SIZES, ENTRY_SIZE)
+    }
+
+    /// Entry with the given upgrade id.
+    pub fn get(&self, id: usize) -> Option<&Upgrade> {
+        self.upgrades.get(id)
+    }
+
+    /// Every entry as a row keyed by its upgrade id.
+    pub fn rows(&self) -> Vec<UpgradeRow> {
+        self.upgrades
+            .iter()
+            .enumerate()
+            .map(|(i, upgrade)| UpgradeRow::new(i as u16, upgrade))
+            .collect()
+    }
+
+    /// Builds upgrades.dat from rows in any order. Every entry must have
+    /// exactly one row and more rows than the stock limit make an
+    /// extended file.
+    pub fn from_rows(rows: Vec<UpgradeRow>) -> table::Result<UpgradesDat> {
+        let layout =
+            DatLayout::for_count(rows.len(), ENTRY_COUNTS).ok_or(TableError::RowCount {
+                expected: BLOCK_SIZE,
+                actual: rows.len(),
+            })?;
+
+        sort_rows(rows, layout.entry_count(ENTRY_COUNTS), |row| {
+            row.id as usize
+        })
+        .map(|rows| UpgradesDat {
+            layout,
+            upgrades: rows.into_iter().map(UpgradeRow::into_upgrade).collect(),
+        })
+    }
+}
 
 pub struct UpgradesDatAsset(Option<UpgradesDat>);
 
@@ -67,36 +226,53 @@ impl Format<UpgradesDatAsset> for UpgradesDatFormat {
 }
 
 const BLOCK_SIZE: usize = 61;
+const STAR_CRAFT_BLOCK_SIZE: usize = 46;
+
+/// Size of a single entry when every column exists for it.
+const ENTRY_SIZE: usize = 21;
+
+const ENTRY_COUNTS: &StockLayouts = &[
+    (DatLayout::BroodWar, BLOCK_SIZE),
+    (DatLayout::StarCraft, STAR_CRAFT_BLOCK_SIZE),
+];
+const FILE_SIZES: &StockLayouts = &[(DatLayout::BroodWar, 1281), (DatLayout::StarCraft, 920)];
 
-pub fn count_total<I, O, E, F>(f: F) -> impl FnMut(I) -> IResult<I, Vec<O>, E>
-where
-    I: Clone + PartialEq,
-    F: Parser<I, O, E>,
-    E: ParseError<I>,
-{
-    count(f, BLOCK_SIZE)
+/// Parses upgrades.dat in the layout that matches the length of the file.
+pub fn parse_upgrades_dat(b: &[u8]) -> IResult<&[u8], UpgradesDat> {
+    let layout = UpgradesDat::detect_layout(b.len()).unwrap_or(DatLayout::BroodWar);
+
+    parse_upgrades_dat_with_layout(b, layout)
 }
 
-fn parse_upgrades_dat(b: &[u8]) -> IResult<&[u8], UpgradesDat> {
-    let (remaining, mineral_cost_col) = count_total(le_u16)(b)?;
-    let (remaining, mineral_factor_col) = count_total(le_u16)(remaining)?;
-    let (remaining, vespene_cost_col) = count_total(le_u16)(remaining)?;
-    let (remaining, vespene_factor_col) = count_total(le_u16)(remaining)?;
-    let (remaining, time_cost_col) = count_total(le_u16)(remaining)?;
-    let (remaining, time_factor_col) = count_total(le_u16)(remaining)?;
+/// Parses upgrades.dat in the given layout.
+pub fn parse_upgrades_dat_with_layout(b: &[u8], layout: DatLayout) -> IResult<&[u8], UpgradesDat> {
+    let entry_count = layout.entry_count(ENTRY_COUNTS);
+
+    let (remaining, mineral_cost_col) = count(le_u16, entry_count)(b)?;
+    let (remaining, mineral_factor_col) = count(le_u16, entry_count)(remaining)?;
+    let (remaining, vespene_cost_col) = count(le_u16, entry_count)(remaining)?;
+    let (remaining, vespene_factor_col) = count(le_u16, entry_count)(remaining)?;
+    let (remaining, time_cost_col) = count(le_u16, entry_count)(remaining)?;
+    let (remaining, time_factor_col) = count(le_u16, entry_count)(remaining)?;
 
     // unknown block
-    let (remaining, _) = count_total(le_u16)(remaining)?;
+    let (remaining, unknown_col) = count(le_u16, entry_count)(remaining)?;
+
+    let (remaining, icon_col) = count(le_u16, entry_count)(remaining)?;
+    let (remaining, label_col) = count(le_u16, entry_count)(remaining)?;
+    let (remaining, race_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, max_repeats_col) = count(le_u8, entry_count)(remaining)?;
 
-    let (remaining, icon_col) = count_total(le_u16)(remaining)?;
-    let (remaining, label_col) = count_total(le_u16)(remaining)?;
-    let (remaining, race_col) = count_total(le_u8)(remaining)?;
-    let (remaining, max_repeats_col) = count_total(le_u8)(remaining)?;
-    let (remaining, brood_war_specific_col) = count_total(le_u8)(remaining)?;
+    // missing before Brood War
+    let (remaining, brood_war_specific_col) = if layout == DatLayout::StarCraft {
+        (remaining, vec![0; entry_count])
+    } else {
+        count(le_u8, entry_count)(remaining)?
+    };
 
     all_consuming(take(0u8))(remaining)?;
 
-    let upgrades = (0..BLOCK_SIZE)
+    let upgrades = (0..entry_count)
         .map(|i| Upgrade {
             mineral_cost: mineral_cost_col[i],
             mineral_factor: mineral_factor_col[i],
@@ -104,6 +280,7 @@ fn parse_upgrades_dat(b: &[u8]) -> IResult<&[u8], UpgradesDat> {
             vespene_factor: vespene_factor_col[i],
             time_cost: time_cost_col[i],
             time_factor: time_factor_col[i],
+            unknown: unknown_col[i],
             icon: icon_col[i],
             label: label_col[i],
             race: race_col[i],
@@ -112,5 +289,30 @@ fn parse_upgrades_dat(b: &[u8]) -> IResult<&[u8], UpgradesDat> {
         })
         .collect::<Vec<_>>();
 
-    Ok((remaining, UpgradesDat(upgrades)))
+    Ok((remaining, UpgradesDat { layout, upgrades }))
+}
+
+/// Writes upgrades.dat in the same column-major layout that
+/// [`parse_upgrades_dat`] reads.
+pub fn write_upgrades_dat(upgrades_dat: &UpgradesDat) -> Vec<u8> {
+    let upgrades = &upgrades_dat.upgrades;
+
+    let mut b = vec![];
+
+    write_column(&mut b, upgrades, |u| u.mineral_cost.to_le_bytes());
+    write_column(&mut b, upgrades, |u| u.mineral_factor.to_le_bytes());
+    write_column(&mut b, upgrades, |u| u.vespene_cost.to_le_bytes());
+    write_column(&mut b, upgrades, |u| u.vespene_factor.to_le_bytes());
+    write_column(&mut b, upgrades, |u| u.time_cost.to_le_bytes());
+    write_column(&mut b, upgrades, |u| u.time_factor.to_le_bytes());
+    write_column(&mut b, upgrades, |u| u.unknown.to_le_bytes());
+    write_column(&mut b, upgrades, |u| u.icon.to_le_bytes());
+    write_column(&mut b, upgrades, |u| u.label.to_le_bytes());
+    write_column(&mut b, upgrades, |u| u.race.to_le_bytes());
+    write_column(&mut b, upgrades, |u| u.max_repeats.to_le_bytes());
+    if upgrades_dat.layout != DatLayout::StarCraft {
+        write_column(&mut b, upgrades, |u| u.brood_war_specific.to_le_bytes());
+    }
+
+    b
 }
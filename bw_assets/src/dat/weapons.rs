@@ -7,18 +7,24 @@ use nom::{
     bytes::complete::take,
     combinator::all_consuming,
     combinator::map,
-    error::ParseError,
     multi::count,
     number::complete::{le_u8, le_u16, le_u32},
-    Finish, IResult, Parser,
+    Finish, IResult,
 };
 
-use super::flingy::FlingyPointer;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    flingy::FlingyPointer,
+    table::{self, decode_flags, encode_flags, narrow, sort_rows, Error as TableError},
+    write_column, DatLayout, StockLayouts,
+};
 
 #[derive(Debug)]
 pub struct Weapon {
     label: u16,
     graphics: FlingyPointer,
+    unused: u8,
     target_flags: u16,
     minimum_range: u32,
     maximum_range: u32,
@@ -42,7 +48,235 @@ pub struct Weapon {
     icon: u16,
 }
 
-pub struct WeaponsDat(Vec<Weapon>);
+impl Weapon {
+    /// Name of the weapon.
+    ///
+    /// Index to stat_txt.tbl
+    pub fn label(&self) -> u16 {
+        self.label
+    }
+
+    /// Message shown when the weapon can't target a unit.
+    ///
+    /// Index to stat_txt.tbl
+    pub fn target_error_message(&self) -> u16 {
+        self.target_error_message
+    }
+
+    /// Upgrade that adds [`Weapon::damage_bonus`] to the damage per level.
+    ///
+    /// Pointer to upgrades.dat
+    pub fn damage_upgrade(&self) -> u8 {
+        self.damage_upgrade
+    }
+
+    /// Damage type of the weapon: independent, explosive, concussive, normal
+    /// or ignore armor.
+    pub fn weapon_type(&self) -> u8 {
+        self.weapon_type
+    }
+
+    /// Splash radii in pixels. Units inside of them take 100%, 50% and 25% of
+    /// the damage.
+    pub fn splash_radii(&self) -> (u16, u16, u16) {
+        (
+            self.inner_splash_radius,
+            self.medium_splash_radius,
+            self.outer_splash_radius,
+        )
+    }
+
+    /// Damage of a single hit without upgrades.
+    pub fn damage_amount(&self) -> u16 {
+        self.damage_amount
+    }
+
+    /// Damage added to every hit per upgrade level.
+    pub fn damage_bonus(&self) -> u16 {
+        self.damage_bonus
+    }
+
+    /// Frames between two attacks.
+    pub fn weapon_cooldown(&self) -> u8 {
+        self.weapon_cooldown
+    }
+
+    /// Number of hits of every attack, e.g. 2 for Zealots.
+    pub fn damage_factor(&self) -> u8 {
+        self.damage_factor
+    }
+}
+
+/// Names of the bits in [`Weapon::target_flags`].
+const TARGET_FLAGS: &[&str] = &[
+    "air",
+    "ground",
+    "mechanical",
+    "organic",
+    "non_building",
+    "non_robotic",
+    "terrain",
+    "organic_or_mechanical",
+    "own",
+];
+
+/// A [`Weapon`] as a flat row of named columns.
+///
+/// Columns are named after the fields of [`Weapon`] and the target flags are
+/// written as their names joined by `|`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WeaponRow {
+    pub id: u16,
+    pub label: u16,
+    pub graphics: u32,
+    pub unused: u8,
+    pub target_flags: String,
+    pub minimum_range: u32,
+    pub maximum_range: u32,
+    pub damage_upgrade: u8,
+    pub weapon_type: u8,
+    pub weapon_behavior: u8,
+    pub remove_after: u8,
+    pub weapon_effect: u8,
+    pub inner_splash_radius: u16,
+    pub medium_splash_radius: u16,
+    pub outer_splash_radius: u16,
+    pub damage_amount: u16,
+    pub damage_bonus: u16,
+    pub weapon_cooldown: u8,
+    pub damage_factor: u8,
+    pub attack_angle: u8,
+    pub launch_spin: u8,
+    pub forward_offset: u8,
+    pub upward_offset: u8,
+    pub target_error_message: u16,
+    pub icon: u16,
+}
+
+impl WeaponRow {
+    fn new(id: u16, weapon: &Weapon) -> WeaponRow {
+        WeaponRow {
+            id,
+            label: weapon.label,
+            graphics: weapon.graphics.value(),
+            unused: weapon.unused,
+            target_flags: encode_flags(weapon.target_flags as u32, TARGET_FLAGS),
+            minimum_range: weapon.minimum_range,
+            maximum_range: weapon.maximum_range,
+            damage_upgrade: weapon.damage_upgrade,
+            weapon_type: weapon.weapon_type,
+            weapon_behavior: weapon.weapon_behavior,
+            remove_after: weapon.remove_after,
+            weapon_effect: weapon.weapon_effect,
+            inner_splash_radius: weapon.inner_splash_radius,
+            medium_splash_radius: weapon.medium_splash_radius,
+            outer_splash_radius: weapon.outer_splash_radius,
+            damage_amount: weapon.damage_amount,
+            damage_bonus: weapon.damage_bonus,
+            weapon_cooldown: weapon.weapon_cooldown,
+            damage_factor: weapon.damage_factor,
+            attack_angle: weapon.attack_angle,
+            launch_spin: weapon.launch_spin,
+            forward_offset: weapon.forward_offset,
+            upward_offset: weapon.upward_offset,
+            target_error_message: weapon.target_error_message,
+            icon: weapon.icon,
+        }
+    }
+
+    fn into_weapon(self) -> table::Result<Weapon> {
+        Ok(Weapon {
+            label: self.label,
+            graphics: FlingyPointer::new(self.graphics),
+            unused: self.unused,
+            target_flags: narrow(
+                "target_flags",
+                decode_flags(&self.target_flags, TARGET_FLAGS)?,
+            )?,
+            minimum_range: self.minimum_range,
+            maximum_range: self.maximum_range,
+            damage_upgrade: self.damage_upgrade,
+            weapon_type: self.weapon_type,
+            weapon_behavior: self.weapon_behavior,
+            remove_after: self.remove_after,
+            weapon_effect: self.weapon_effect,
+            inner_splash_radius: self.inner_splash_radius,
+            medium_splash_radius: self.medium_splash_radius,
+            outer_splash_radius: self.outer_splash_radius,
+            damage_amount: self.damage_amount,
+            damage_bonus: self.damage_bonus,
+            weapon_cooldown: self.weapon_cooldown,
+            damage_factor: self.damage_factor,
+            attack_angle: self.attack_angle,
+            launch_spin: self.launch_spin,
+            forward_offset: self.forward_offset,
+            upward_offset: self.upward_offset,
+            target_error_message: self.target_error_message,
+            icon: self.icon,
+        })
+    }
+}
+
+pub struct WeaponsDat {
+    layout: DatLayout,
+    weapons: Vec<Weapon>,
+}
+
+impl WeaponsDat {
+    /// Layout of the file the entries were read from.
+    pub fn layout(&self) -> DatLayout {
+        self.layout
+    }
+
+    /// Picks the layout of weapons.dat from the length of the file.
+    pub fn detect_layout(len: usize) -> Option<DatLayout> {
+        DatLayout::detect(len, FILE_SIZES, ENTRY_SIZE)
+    }
+
+    /// Weapon with the given weapon id.
+    pub fn get(&self, id: usize) -> Option<&Weapon> {
+        self.weapons.get(id)
+    }
+
+    /// Number of weapons, which is also the id that fields referring to a
+    /// weapon use for none.
+    pub fn len(&self) -> usize {
+        self.weapons.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.weapons.is_empty()
+    }
+
+    /// Every weapon as a row keyed by its weapon id.
+    pub fn rows(&self) -> Vec<WeaponRow> {
+        self.weapons
+            .iter()
+            .enumerate()
+            .map(|(i, weapon)| WeaponRow::new(i as u16, weapon))
+            .collect()
+    }
+
+    /// Builds weapons.dat from rows in any order. Every weapon must have
+    /// exactly one row and more rows than the stock limit make an
+    /// extended file.
+    pub fn from_rows(rows: Vec<WeaponRow>) -> table::Result<WeaponsDat> {
+        let layout =
+            DatLayout::for_count(rows.len(), ENTRY_COUNTS).ok_or(TableError::RowCount {
+                expected: BLOCK_SIZE,
+                actual: rows.len(),
+            })?;
+
+        let weapons = sort_rows(rows, layout.entry_count(ENTRY_COUNTS), |row| {
+            row.id as usize
+        })?
+        .into_iter()
+        .map(WeaponRow::into_weapon)
+        .collect::<table::Result<Vec<_>>>()?;
+
+        Ok(WeaponsDat { layout, weapons })
+    }
+}
 
 pub struct WeaponsDatAsset(Option<WeaponsDat>);
 
@@ -83,53 +317,62 @@ impl Format<WeaponsDatAsset> for WeaponsDatFormat {
 
 const BLOCK_SIZE: usize = 130;
 
-pub fn count_total<I, O, E, F>(f: F) -> impl FnMut(I) -> IResult<I, Vec<O>, E>
-where
-    I: Clone + PartialEq,
-    F: Parser<I, O, E>,
-    E: ParseError<I>,
-{
-    count(f, BLOCK_SIZE)
+/// Size of a single entry when every column exists for it.
+const ENTRY_SIZE: usize = 42;
+
+const ENTRY_COUNTS: &StockLayouts = &[(DatLayout::BroodWar, BLOCK_SIZE)];
+const FILE_SIZES: &StockLayouts = &[(DatLayout::BroodWar, 5460)];
+
+/// Parses weapons.dat in the layout that matches the length of the file.
+pub fn parse_weapons_dat(b: &[u8]) -> IResult<&[u8], WeaponsDat> {
+    let layout = WeaponsDat::detect_layout(b.len()).unwrap_or(DatLayout::BroodWar);
+
+    parse_weapons_dat_with_layout(b, layout)
 }
 
-fn parse_weapons_dat(b: &[u8]) -> IResult<&[u8], WeaponsDat> {
-    let (remaining, label_col) = count_total(le_u16)(b)?;
-    let (remaining, flingy_pointer_col) = count_total(map(le_u32, FlingyPointer::new))(remaining)?;
+/// Parses weapons.dat in the given layout.
+pub fn parse_weapons_dat_with_layout(b: &[u8], layout: DatLayout) -> IResult<&[u8], WeaponsDat> {
+    let entry_count = layout.entry_count(ENTRY_COUNTS);
+
+    let (remaining, label_col) = count(le_u16, entry_count)(b)?;
+    let (remaining, flingy_pointer_col) =
+        count(map(le_u32, FlingyPointer::new), entry_count)(remaining)?;
 
     // unused block
-    let (remaining, _) = count_total(le_u8)(remaining)?;
-
-    let (remaining, target_flags_col) = count_total(le_u16)(remaining)?;
-    let (remaining, minimum_range_col) = count_total(le_u32)(remaining)?;
-    let (remaining, maximum_range_col) = count_total(le_u32)(remaining)?;
-    let (remaining, damage_upgrade_col) = count_total(le_u8)(remaining)?;
-    let (remaining, weapon_type_col) = count_total(le_u8)(remaining)?;
-    let (remaining, weapon_behavior_col) = count_total(le_u8)(remaining)?;
-    let (remaining, remove_after_col) = count_total(le_u8)(remaining)?;
-    let (remaining, weapon_effect_col) = count_total(le_u8)(remaining)?;
-
-    let (remaining, inner_splash_radius_col) = count_total(le_u16)(remaining)?;
-    let (remaining, medium_splash_radius_col) = count_total(le_u16)(remaining)?;
-    let (remaining, outer_splash_radius_col) = count_total(le_u16)(remaining)?;
-
-    let (remaining, damage_amount_col) = count_total(le_u16)(remaining)?;
-    let (remaining, damage_bonus_col) = count_total(le_u16)(remaining)?;
-    let (remaining, weapon_cooldown_col) = count_total(le_u8)(remaining)?;
-    let (remaining, damage_factor_col) = count_total(le_u8)(remaining)?;
-    let (remaining, attack_angle_col) = count_total(le_u8)(remaining)?;
-    let (remaining, launch_spin_col) = count_total(le_u8)(remaining)?;
-    let (remaining, forward_offset_col) = count_total(le_u8)(remaining)?;
-    let (remaining, upward_offset_col) = count_total(le_u8)(remaining)?;
-
-    let (remaining, target_error_message_col) = count_total(le_u16)(remaining)?;
-    let (remaining, icon_col) = count_total(le_u16)(remaining)?;
+    let (remaining, unused_col) = count(le_u8, entry_count)(remaining)?;
+
+    let (remaining, target_flags_col) = count(le_u16, entry_count)(remaining)?;
+    let (remaining, minimum_range_col) = count(le_u32, entry_count)(remaining)?;
+    let (remaining, maximum_range_col) = count(le_u32, entry_count)(remaining)?;
+    let (remaining, damage_upgrade_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, weapon_type_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, weapon_behavior_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, remove_after_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, weapon_effect_col) = count(le_u8, entry_count)(remaining)?;
+
+    let (remaining, inner_splash_radius_col) = count(le_u16, entry_count)(remaining)?;
+    let (remaining, medium_splash_radius_col) = count(le_u16, entry_count)(remaining)?;
+    let (remaining, outer_splash_radius_col) = count(le_u16, entry_count)(remaining)?;
+
+    let (remaining, damage_amount_col) = count(le_u16, entry_count)(remaining)?;
+    let (remaining, damage_bonus_col) = count(le_u16, entry_count)(remaining)?;
+    let (remaining, weapon_cooldown_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, damage_factor_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, attack_angle_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, launch_spin_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, forward_offset_col) = count(le_u8, entry_count)(remaining)?;
+    let (remaining, upward_offset_col) = count(le_u8, entry_count)(remaining)?;
+
+    let (remaining, target_error_message_col) = count(le_u16, entry_count)(remaining)?;
+    let (remaining, icon_col) = count(le_u16, entry_count)(remaining)?;
 
     all_consuming(take(0u8))(remaining)?;
 
-    let weapons = (0..BLOCK_SIZE)
+    let weapons = (0..entry_count)
         .map(|i| Weapon {
             label: label_col[i],
             graphics: flingy_pointer_col[i].clone(),
+            unused: unused_col[i],
             target_flags: target_flags_col[i],
             minimum_range: minimum_range_col[i],
             maximum_range: maximum_range_col[i],
@@ -154,5 +397,40 @@ fn parse_weapons_dat(b: &[u8]) -> IResult<&[u8], WeaponsDat> {
         })
         .collect::<Vec<_>>();
 
-    Ok((remaining, WeaponsDat(weapons)))
+    Ok((remaining, WeaponsDat { layout, weapons }))
+}
+
+/// Writes weapons.dat in the same column-major layout that
+/// [`parse_weapons_dat`] reads.
+pub fn write_weapons_dat(weapons_dat: &WeaponsDat) -> Vec<u8> {
+    let weapons = &weapons_dat.weapons;
+
+    let mut b = vec![];
+
+    write_column(&mut b, weapons, |w| w.label.to_le_bytes());
+    write_column(&mut b, weapons, |w| w.graphics.value().to_le_bytes());
+    write_column(&mut b, weapons, |w| w.unused.to_le_bytes());
+    write_column(&mut b, weapons, |w| w.target_flags.to_le_bytes());
+    write_column(&mut b, weapons, |w| w.minimum_range.to_le_bytes());
+    write_column(&mut b, weapons, |w| w.maximum_range.to_le_bytes());
+    write_column(&mut b, weapons, |w| w.damage_upgrade.to_le_bytes());
+    write_column(&mut b, weapons, |w| w.weapon_type.to_le_bytes());
+    write_column(&mut b, weapons, |w| w.weapon_behavior.to_le_bytes());
+    write_column(&mut b, weapons, |w| w.remove_after.to_le_bytes());
+    write_column(&mut b, weapons, |w| w.weapon_effect.to_le_bytes());
+    write_column(&mut b, weapons, |w| w.inner_splash_radius.to_le_bytes());
+    write_column(&mut b, weapons, |w| w.medium_splash_radius.to_le_bytes());
+    write_column(&mut b, weapons, |w| w.outer_splash_radius.to_le_bytes());
+    write_column(&mut b, weapons, |w| w.damage_amount.to_le_bytes());
+    write_column(&mut b, weapons, |w| w.damage_bonus.to_le_bytes());
+    write_column(&mut b, weapons, |w| w.weapon_cooldown.to_le_bytes());
+    write_column(&mut b, weapons, |w| w.damage_factor.to_le_bytes());
+    write_column(&mut b, weapons, |w| w.attack_angle.to_le_bytes());
+    write_column(&mut b, weapons, |w| w.launch_spin.to_le_bytes());
+    write_column(&mut b, weapons, |w| w.forward_offset.to_le_bytes());
+    write_column(&mut b, weapons, |w| w.upward_offset.to_le_bytes());
+    write_column(&mut b, weapons, |w| w.target_error_message.to_le_bytes());
+    write_column(&mut b, weapons, |w| w.icon.to_le_bytes());
+
+    b
 }
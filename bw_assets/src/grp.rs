@@ -0,0 +1,397 @@
+//! Types and Parsers for the GRP Sprite Format
+//!
+//! GRP files such as `unit\terran\marine.grp` contain the frames that units,
+//! buildings and effects are drawn with. Images in `arr\images.dat` reference
+//! their GRP file by a label in `arr\images.tbl`.
+//!
+//! A GRP file starts with the number of frames and the size of the box that
+//! every frame is drawn in. A header for each frame follows with the bounding
+//! box of its opaque pixels inside of that box and the offset of its data.
+//! The data of a frame starts with an offset for each row, relative to the
+//! start of the frame data, and the rows are run-length encoded:
+//!
+//! * `0x80 | n` skips `n` transparent pixels.
+//! * `0x40 | n` repeats the next palette index `n` times.
+//! * `n` copies the next `n` palette indices.
+//!
+//! see: http://www.staredit.net/wiki/index.php?title=GRP
+
+use amethyst::{
+    assets::{Asset, Format, Handle},
+    ecs::DenseVecStorage,
+};
+use nom::{
+    bytes::complete::take,
+    combinator::map,
+    multi::count,
+    number::complete::{le_u8, le_u16, le_u32},
+    sequence::{preceded, tuple},
+    Finish, IResult,
+};
+
+/// Palette index of transparent pixels.
+pub const TRANSPARENT: u8 = 0;
+
+/// Alpha of the pixels of shadows.
+const SHADOW_ALPHA: u8 = 0x80;
+
+/// How the palette indices of a frame are turned into colours.
+#[derive(Debug, Clone, Copy)]
+pub enum DrawMode<'a> {
+    /// Palette indices are drawn with their colour.
+    Normal,
+
+    /// Palette indices are replaced with the index in the table first, e.g.
+    /// to draw player colours or the flames of burning buildings.
+    Remap(&'a [u8; 256]),
+
+    /// Opaque pixels darken what is below them, e.g. the shadows of units.
+    Shadow,
+}
+
+/// A single frame of a GRP file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrpFrame {
+    x: u8,
+    y: u8,
+    width: u8,
+    height: u8,
+    pixels: Vec<u8>,
+}
+
+impl GrpFrame {
+    /// Left of the bounding box inside of the box of the GRP.
+    pub fn x(&self) -> u8 {
+        self.x
+    }
+
+    /// Top of the bounding box inside of the box of the GRP.
+    pub fn y(&self) -> u8 {
+        self.y
+    }
+
+    pub fn width(&self) -> u8 {
+        self.width
+    }
+
+    pub fn height(&self) -> u8 {
+        self.height
+    }
+
+    /// Palette index of every pixel of the bounding box row by row.
+    /// Transparent pixels are [`TRANSPARENT`].
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Palette index at a position inside of the bounding box.
+    pub fn pixel(&self, x: u8, y: u8) -> Option<u8> {
+        if x < self.width && y < self.height {
+            self.pixels
+                .get(y as usize * self.width as usize + x as usize)
+                .copied()
+        } else {
+            None
+        }
+    }
+
+    /// Colours of the bounding box as RGBA with 8 bits per channel, row by
+    /// row. Transparent pixels are transparent black.
+    pub fn to_rgba(&self, palette: &[[u8; 3]], mode: DrawMode<'_>) -> Vec<u8> {
+        let mut rgba = Vec::with_capacity(self.pixels.len() * 4);
+
+        for &index in &self.pixels {
+            let pixel = match (index, mode) {
+                (TRANSPARENT, _) => [0, 0, 0, 0],
+                (_, DrawMode::Shadow) => [0, 0, 0, SHADOW_ALPHA],
+                (index, DrawMode::Normal) => opaque(palette, index),
+                (index, DrawMode::Remap(table)) => opaque(palette, table[index as usize]),
+            };
+            rgba.extend_from_slice(&pixel);
+        }
+
+        rgba
+    }
+}
+
+fn opaque(palette: &[[u8; 3]], index: u8) -> [u8; 4] {
+    let [r, g, b] = palette.get(index as usize).copied().unwrap_or_default();
+
+    [r, g, b, 0xFF]
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grp {
+    width: u16,
+    height: u16,
+    frames: Vec<GrpFrame>,
+}
+
+impl Grp {
+    /// Width of the box that every frame is drawn in.
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    /// Height of the box that every frame is drawn in.
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn frame(&self, index: usize) -> Option<&GrpFrame> {
+        self.frames.get(index)
+    }
+
+    pub fn frames(&self) -> std::slice::Iter<'_, GrpFrame> {
+        self.frames.iter()
+    }
+}
+
+struct FrameHeader {
+    x: u8,
+    y: u8,
+    width: u8,
+    height: u8,
+    offset: u32,
+}
+
+fn parse_frame_header(b: &[u8]) -> IResult<&[u8], FrameHeader> {
+    map(
+        tuple((le_u8, le_u8, le_u8, le_u8, le_u32)),
+        |(x, y, width, height, offset)| FrameHeader {
+            x,
+            y,
+            width,
+            height,
+            offset,
+        },
+    )(b)
+}
+
+/// Decodes a single row. Runs that go past the end of the row are cut off.
+fn parse_row(width: usize) -> impl Fn(&[u8]) -> IResult<&[u8], Vec<u8>> {
+    move |b| {
+        let mut row = Vec::with_capacity(width);
+        let mut remaining = b;
+
+        while row.len() < width {
+            let (r, code) = le_u8(remaining)?;
+            let n = match code {
+                c if c & 0x80 != 0 => {
+                    let n = (c & 0x7F) as usize;
+                    row.resize(row.len() + n, TRANSPARENT);
+                    remaining = r;
+                    n
+                }
+                c if c & 0x40 != 0 => {
+                    let (r, index) = le_u8(r)?;
+                    let n = (c & 0x3F) as usize;
+                    row.resize(row.len() + n, index);
+                    remaining = r;
+                    n
+                }
+                c => {
+                    let (r, indices) = take(c)(r)?;
+                    row.extend_from_slice(indices);
+                    remaining = r;
+                    c as usize
+                }
+            };
+
+            // A run of length 0 would never finish the row.
+            if n == 0 {
+                break;
+            }
+        }
+        row.resize(width, TRANSPARENT);
+
+        Ok((remaining, row))
+    }
+}
+
+fn parse_frame<'a>(b: &'a [u8], header: &FrameHeader) -> IResult<&'a [u8], GrpFrame> {
+    let width = header.width as usize;
+    let (data, _) = take(header.offset)(b)?;
+    let (_, row_offsets) = count(le_u16, header.height as usize)(data)?;
+
+    let mut pixels = Vec::with_capacity(width * header.height as usize);
+    for row_offset in row_offsets {
+        let (_, row) = preceded(take(row_offset), parse_row(width))(data)?;
+        pixels.extend(row);
+    }
+
+    Ok((
+        b,
+        GrpFrame {
+            x: header.x,
+            y: header.y,
+            width: header.width,
+            height: header.height,
+            pixels,
+        },
+    ))
+}
+
+pub fn parse_grp(b: &[u8]) -> IResult<&[u8], Grp> {
+    let (remaining, (frame_count, width, height)) = tuple((le_u16, le_u16, le_u16))(b)?;
+    let (_, headers) = count(parse_frame_header, frame_count as usize)(remaining)?;
+
+    let frames = headers
+        .iter()
+        .map(|header| parse_frame(b, header).map(|(_, frame)| frame))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let (remaining, _) = take(b.len())(b)?;
+
+    Ok((
+        remaining,
+        Grp {
+            width,
+            height,
+            frames,
+        },
+    ))
+}
+
+pub struct GrpAsset(Option<Grp>);
+
+impl GrpAsset {
+    pub fn take(&mut self) -> Option<Grp> {
+        self.0.take()
+    }
+}
+
+pub type GrpHandle = Handle<GrpAsset>;
+
+impl Asset for GrpAsset {
+    const NAME: &'static str = "bw_assets::grp::GrpAsset";
+    type Data = Self;
+    type HandleStorage = DenseVecStorage<GrpHandle>;
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GrpFormat;
+
+impl Format<GrpAsset> for GrpFormat {
+    fn name(&self) -> &'static str {
+        "GrpFormat"
+    }
+
+    fn import_simple(&self, b: Vec<u8>) -> amethyst::Result<GrpAsset> {
+        let (_, grp) = parse_grp(&b).finish().map_err(|err| {
+            amethyst::error::format_err!(
+                "failed to load grp asset: {} at position {}",
+                err.code.description(),
+                b.len() - err.input.len()
+            )
+        })?;
+
+        Ok(GrpAsset(Some(grp)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use byteorder::{LittleEndian, WriteBytesExt};
+    use spectral::prelude::*;
+
+    /// A GRP with a single frame made of the encoded rows.
+    fn create_grp(width: u8, rows: &[&[u8]]) -> Vec<u8> {
+        let frame_offset = 6 + 8;
+
+        let mut b: Vec<u8> = vec![];
+        b.write_u16::<LittleEndian>(1).unwrap();
+        b.write_u16::<LittleEndian>(32).unwrap();
+        b.write_u16::<LittleEndian>(32).unwrap();
+        b.extend(&[4, 8, width, rows.len() as u8]);
+        b.write_u32::<LittleEndian>(frame_offset).unwrap();
+
+        let mut row_offset = rows.len() * 2;
+        for row in rows {
+            b.write_u16::<LittleEndian>(row_offset as u16).unwrap();
+            row_offset += row.len();
+        }
+        for row in rows {
+            b.extend(*row);
+        }
+
+        b
+    }
+
+    #[test]
+    fn it_decodes_run_length_encoded_rows() {
+        let b = create_grp(4, &[&[0x81, 0x43, 7], &[0x02, 1, 2, 0x82]]);
+
+        let (_, grp) = parse_grp(&b).unwrap();
+        let frame = grp.frame(0).unwrap();
+
+        assert_that(&grp.len()).is_equal_to(1);
+        assert_that(&(grp.width(), grp.height())).is_equal_to((32, 32));
+        assert_that(&(frame.x(), frame.y())).is_equal_to((4, 8));
+        assert_that(&(frame.width(), frame.height())).is_equal_to((4, 2));
+        assert_that(&frame.pixels()).is_equal_to(&[0, 7, 7, 7, 1, 2, 0, 0][..]);
+        assert_that(&frame.pixel(1, 1)).is_equal_to(Some(2));
+        assert_that(&frame.pixel(4, 0)).is_none();
+    }
+
+    #[test]
+    fn it_draws_frames_in_every_mode() {
+        let b = create_grp(2, &[&[0x81, 0x01, 1]]);
+        let (_, grp) = parse_grp(&b).unwrap();
+        let frame = grp.frame(0).unwrap();
+
+        let mut palette = [[0; 3]; 256];
+        palette[1] = [10, 20, 30];
+        palette[2] = [40, 50, 60];
+        let mut table = [0; 256];
+        table[1] = 2;
+
+        assert_that(&frame.to_rgba(&palette, DrawMode::Normal))
+            .is_equal_to(vec![0, 0, 0, 0, 10, 20, 30, 0xFF]);
+        assert_that(&frame.to_rgba(&palette, DrawMode::Remap(&table)))
+            .is_equal_to(vec![0, 0, 0, 0, 40, 50, 60, 0xFF]);
+        let shadow = vec![0, 0, 0, 0, 0, 0, 0, SHADOW_ALPHA];
+        assert_that(&frame.to_rgba(&palette, DrawMode::Shadow)).is_equal_to(shadow);
+    }
+
+    #[test]
+    fn it_rejects_frames_past_the_end() {
+        let mut b = create_grp(2, &[&[0x82]]);
+        b.truncate(14);
+
+        assert_that(&parse_grp(&b).is_err()).is_true();
+    }
+
+    #[test]
+    #[ignore = "needs stock MPQs"]
+    fn it_decodes_the_grp_of_an_image() {
+        use crate::{dat::parse_images_dat, tbl::parse_tbl, test_utils::stock_mpq_source};
+        use amethyst::assets::Source;
+
+        let mpq_source = stock_mpq_source();
+        let load = |file_name: &str| mpq_source.load(file_name).unwrap();
+
+        let images_dat = load("arr\\images.dat");
+        let (_, images_dat) = parse_images_dat(&images_dat).unwrap();
+        let images_tbl = load("arr\\images.tbl");
+        let (_, images_tbl) = parse_tbl(&images_tbl).unwrap();
+
+        let path = images_dat
+            .get(0)
+            .and_then(|image| image.grp_path(&images_tbl))
+            .unwrap();
+        let (_, grp) = parse_grp(&load(&path)).unwrap();
+
+        assert_that(&path.ends_with(".grp")).is_true();
+        assert_that(&grp.is_empty()).is_false();
+    }
+}
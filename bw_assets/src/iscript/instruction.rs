@@ -0,0 +1,367 @@
+use nom::{
+    combinator::map,
+    multi::count,
+    number::complete::{le_i8, le_u8, le_u16},
+    sequence::tuple,
+    IResult,
+};
+
+/// Position of an instruction as a byte offset into iscript.bin.
+pub type Offset = u16;
+
+/// Which layer an overlay or sprite is created in, relative to the image
+/// that creates it.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Layer {
+    Above,
+    Below,
+}
+
+/// A single instruction of iscript.bin.
+///
+/// Instructions are named after the opcodes of the community assembler
+/// where that name is descriptive, e.g. `imgol` is [`Instruction::ImageOverlay`].
+/// Positions are relative to the image, in pixels.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Instruction {
+    /// `playfram`: shows a frame set of the GRP. The direction of the image
+    /// picks the frame of the set.
+    PlayFrame(u16),
+
+    /// `playframtile`: shows a frame set plus the index of the tileset.
+    PlayFrameTile(u16),
+
+    /// `sethorpos`
+    SetHorizontalPosition(i8),
+
+    /// `setvertpos`
+    SetVerticalPosition(i8),
+
+    /// `setpos`
+    SetPosition(i8, i8),
+
+    /// `wait`: continues after the number of frames.
+    Wait(u8),
+
+    /// `waitrand`: continues after a random number of frames in the range.
+    WaitRandom(u8, u8),
+
+    /// `goto`
+    Goto(Offset),
+
+    /// `imgol`, `imgul`, `imgoluselo` and `imguluselo`: creates an overlay
+    /// image. With `use_lo` the position is read from the LO* file of the
+    /// image instead.
+    ImageOverlay {
+        image: u16,
+        x: i8,
+        y: i8,
+        layer: Layer,
+        use_lo: bool,
+    },
+
+    /// `imgolorig`: creates an overlay at the origin of the image.
+    ImageOverlayOrigin(u16),
+
+    /// `switchul`: replaces the underlay of the image.
+    SwitchUnderlay(u16),
+
+    /// `imgulnextid`: creates an underlay of the image that follows this
+    /// one in images.dat.
+    ImageUnderlayNextId(i8, i8),
+
+    /// `sprol`, `highsprol`, `lowsprul`, `sprul`, `spruluselo`, `sproluselo`
+    /// and `grdsprol`: creates a sprite.
+    SpriteOverlay {
+        sprite: u16,
+        x: i8,
+        y: i8,
+        layer: Layer,
+        use_lo: bool,
+    },
+
+    /// `uflunstable`: creates a flingy that moves around randomly.
+    UnstableFlingy(u16),
+
+    /// `end`: removes the image.
+    End,
+
+    /// `setflipstate`
+    SetFlipState(bool),
+
+    /// `playsnd`
+    PlaySound(u16),
+
+    /// `playsndrand`: plays one of the sounds at random.
+    PlaySoundRandom(Vec<u16>),
+
+    /// `playsndbtwn`: plays a random sound id in the range.
+    PlaySoundBetween(u16, u16),
+
+    /// `domissiledmg`: deals the damage of the weapon of the parent.
+    DoMissileDamage,
+
+    /// `attackmelee`: deals melee damage and plays one of the sounds at
+    /// random.
+    AttackMelee(Vec<u16>),
+
+    /// `followmaingraphic`
+    FollowMainGraphic,
+
+    /// `randcondjmp`: jumps if a random byte is below the value.
+    RandomJump(u8, Offset),
+
+    /// `turnccwise`, `turncwise`, `turn1cwise`: turns by the number of
+    /// directions, counterclockwise when negative. Directions wrap around
+    /// after 256, so half a turn either way is -128.
+    Turn(i8),
+
+    /// `turnrand`: turns by the number of directions in a random direction.
+    TurnRandom(u8),
+
+    /// `setspawnframe`
+    SetSpawnFrame(u8),
+
+    /// `sigorder`
+    SignalOrder(u8),
+
+    /// `attackwith`: attacks with the ground (1) or air (2) weapon.
+    AttackWith(u8),
+
+    /// `attack`: attacks with the weapon for the target.
+    Attack,
+
+    /// `castspell`
+    CastSpell,
+
+    /// `useweapon`: attacks with a weapon id.
+    UseWeapon(u8),
+
+    /// `move`: moves forward by the speed.
+    Move(u8),
+
+    /// `gotorepeatattk`
+    GotoRepeatAttack,
+
+    /// `engframe`
+    EngineFrame(u8),
+
+    /// `engset`
+    EngineSet(u8),
+
+    /// `nobrkcodestart`: orders can't interrupt the script until
+    /// `nobrkcodeend`.
+    NoBreakCodeStart,
+
+    /// `nobrkcodeend`
+    NoBreakCodeEnd,
+
+    /// `ignorerest`: stops the script until the image is given another
+    /// animation.
+    IgnoreRest,
+
+    /// `attkshiftproj`
+    AttackShiftProjectile(u8),
+
+    /// `tmprmgraphicstart`
+    TemporaryRemoveGraphicStart,
+
+    /// `tmprmgraphicend`
+    TemporaryRemoveGraphicEnd,
+
+    /// `setfldirect`
+    SetFlingyDirection(u8),
+
+    /// `call`: jumps to a subroutine that `return` leaves.
+    Call(Offset),
+
+    /// `return`
+    Return,
+
+    /// `setflspeed`
+    SetFlingySpeed(u16),
+
+    /// `creategasoverlays`
+    CreateGasOverlays(u8),
+
+    /// `pwrupcondjmp`: jumps if the unit carries a power-up.
+    PowerUpJump(Offset),
+
+    /// `trgtrangecondjmp`: jumps if the target is within the distance.
+    TargetRangeJump(u16, Offset),
+
+    /// `trgtarccondjmp`: jumps if the target is within the arc around the
+    /// angle.
+    TargetArcJump(u16, u16, Offset),
+
+    /// `curdirectcondjmp`: jumps if the image faces within the arc around
+    /// the angle.
+    CurrentDirectionJump(u16, u16, Offset),
+
+    /// `liftoffcondjmp`: jumps if the building is lifted off.
+    LiftOffJump(Offset),
+
+    /// `warpoverlay`
+    WarpOverlay(u16),
+
+    /// `orderdone`
+    OrderDone(u8),
+
+    /// `dogrddamage`
+    DoGroundDamage,
+
+    /// Opcodes without a known meaning that have no arguments.
+    Unknown(u8),
+}
+
+impl Instruction {
+    /// Whether execution never continues with the next instruction.
+    pub fn is_terminator(&self) -> bool {
+        matches!(
+            self,
+            Instruction::Goto(_) | Instruction::End | Instruction::Return | Instruction::IgnoreRest
+        )
+    }
+
+    /// Offsets that the instruction may jump to.
+    pub fn jump_targets(&self) -> Vec<Offset> {
+        match *self {
+            Instruction::Goto(target)
+            | Instruction::Call(target)
+            | Instruction::RandomJump(_, target)
+            | Instruction::PowerUpJump(target)
+            | Instruction::TargetRangeJump(_, target)
+            | Instruction::TargetArcJump(_, _, target)
+            | Instruction::CurrentDirectionJump(_, _, target)
+            | Instruction::LiftOffJump(target) => vec![target],
+            _ => vec![],
+        }
+    }
+}
+
+fn image_overlay(layer: Layer, use_lo: bool) -> impl Fn(&[u8]) -> IResult<&[u8], Instruction> {
+    move |b| {
+        map(tuple((le_u16, le_i8, le_i8)), |(image, x, y)| {
+            Instruction::ImageOverlay {
+                image,
+                x,
+                y,
+                layer,
+                use_lo,
+            }
+        })(b)
+    }
+}
+
+fn sprite_overlay(layer: Layer, use_lo: bool) -> impl Fn(&[u8]) -> IResult<&[u8], Instruction> {
+    move |b| {
+        map(tuple((le_u16, le_i8, le_i8)), |(sprite, x, y)| {
+            Instruction::SpriteOverlay {
+                sprite,
+                x,
+                y,
+                layer,
+                use_lo,
+            }
+        })(b)
+    }
+}
+
+fn sounds(b: &[u8]) -> IResult<&[u8], Vec<u16>> {
+    let (remaining, n) = le_u8(b)?;
+
+    count(le_u16, n as usize)(remaining)
+}
+
+/// Parses the instruction at the start of `b`.
+pub fn parse_instruction(b: &[u8]) -> IResult<&[u8], Instruction> {
+    use Instruction::*;
+
+    let (b, opcode) = le_u8(b)?;
+
+    match opcode {
+        0x00 => map(le_u16, PlayFrame)(b),
+        0x01 => map(le_u16, PlayFrameTile)(b),
+        0x02 => map(le_i8, SetHorizontalPosition)(b),
+        0x03 => map(le_i8, SetVerticalPosition)(b),
+        0x04 => map(tuple((le_i8, le_i8)), |(x, y)| SetPosition(x, y))(b),
+        0x05 => map(le_u8, Wait)(b),
+        0x06 => map(tuple((le_u8, le_u8)), |(min, max)| WaitRandom(min, max))(b),
+        0x07 => map(le_u16, Goto)(b),
+        0x08 => image_overlay(Layer::Above, false)(b),
+        0x09 => image_overlay(Layer::Below, false)(b),
+        0x0A => map(le_u16, ImageOverlayOrigin)(b),
+        0x0B => map(le_u16, SwitchUnderlay)(b),
+        0x0D => image_overlay(Layer::Above, true)(b),
+        0x0E => image_overlay(Layer::Below, true)(b),
+        0x0F | 0x10 | 0x42 => sprite_overlay(Layer::Above, false)(b),
+        0x11 | 0x14 => sprite_overlay(Layer::Below, false)(b),
+        0x12 => map(le_u16, UnstableFlingy)(b),
+        0x13 => sprite_overlay(Layer::Below, true)(b),
+        0x15 => map(tuple((le_u16, le_u8)), |(sprite, _)| SpriteOverlay {
+            sprite,
+            x: 0,
+            y: 0,
+            layer: Layer::Above,
+            use_lo: true,
+        })(b),
+        0x16 => Ok((b, End)),
+        0x17 => map(le_u8, |state| SetFlipState(state != 0))(b),
+        0x18 => map(le_u16, PlaySound)(b),
+        0x19 => map(sounds, PlaySoundRandom)(b),
+        0x1A => map(tuple((le_u16, le_u16)), |(min, max)| {
+            PlaySoundBetween(min, max)
+        })(b),
+        0x1B => Ok((b, DoMissileDamage)),
+        0x1C => map(sounds, AttackMelee)(b),
+        0x1D => Ok((b, FollowMainGraphic)),
+        0x1E => map(tuple((le_u8, le_u16)), |(chance, target)| {
+            RandomJump(chance, target)
+        })(b),
+        0x1F => map(le_u8, |n| Turn((n as i8).wrapping_neg()))(b),
+        0x20 => map(le_u8, |n| Turn(n as i8))(b),
+        0x21 => Ok((b, Turn(1))),
+        0x22 => map(le_u8, TurnRandom)(b),
+        0x23 => map(le_u8, SetSpawnFrame)(b),
+        0x24 => map(le_u8, SignalOrder)(b),
+        0x25 => map(le_u8, AttackWith)(b),
+        0x26 => Ok((b, Attack)),
+        0x27 => Ok((b, CastSpell)),
+        0x28 => map(le_u8, UseWeapon)(b),
+        0x29 => map(le_u8, Move)(b),
+        0x2A => Ok((b, GotoRepeatAttack)),
+        0x2B => map(le_u8, EngineFrame)(b),
+        0x2C => map(le_u8, EngineSet)(b),
+        0x2E => Ok((b, NoBreakCodeStart)),
+        0x2F => Ok((b, NoBreakCodeEnd)),
+        0x30 => Ok((b, IgnoreRest)),
+        0x31 => map(le_u8, AttackShiftProjectile)(b),
+        0x32 => Ok((b, TemporaryRemoveGraphicStart)),
+        0x33 => Ok((b, TemporaryRemoveGraphicEnd)),
+        0x34 => map(le_u8, SetFlingyDirection)(b),
+        0x35 => map(le_u16, Call)(b),
+        0x36 => Ok((b, Return)),
+        0x37 => map(le_u16, SetFlingySpeed)(b),
+        0x38 => map(le_u8, CreateGasOverlays)(b),
+        0x39 => map(le_u16, PowerUpJump)(b),
+        0x3A => map(tuple((le_u16, le_u16)), |(distance, target)| {
+            TargetRangeJump(distance, target)
+        })(b),
+        0x3B => map(tuple((le_u16, le_u16, le_u16)), |(angle, arc, target)| {
+            TargetArcJump(angle, arc, target)
+        })(b),
+        0x3C => map(tuple((le_u16, le_u16, le_u16)), |(angle, arc, target)| {
+            CurrentDirectionJump(angle, arc, target)
+        })(b),
+        0x3D => map(tuple((le_i8, le_i8)), |(x, y)| ImageUnderlayNextId(x, y))(b),
+        0x3F => map(le_u16, LiftOffJump)(b),
+        0x40 => map(le_u16, WarpOverlay)(b),
+        0x41 => map(le_u8, OrderDone)(b),
+        0x44 => Ok((b, DoGroundDamage)),
+        0x0C | 0x2D | 0x3E | 0x43 => Ok((b, Unknown(opcode))),
+        _ => Err(nom::Err::Error(nom::error::Error::new(
+            b,
+            nom::error::ErrorKind::Switch,
+        ))),
+    }
+}
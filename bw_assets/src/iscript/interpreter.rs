@@ -0,0 +1,379 @@
+use snafu::OptionExt;
+
+use super::{
+    instruction::{Instruction, Layer, Offset},
+    script::{Animation, Iscript, ScriptId},
+    InfiniteLoop, InvalidOffset, MissingAnimation, MissingScript, Result,
+};
+
+/// Instructions that a single frame may run before the script is considered
+/// to be stuck in a loop without a wait.
+const MAX_INSTRUCTIONS_PER_FRAME: usize = 1000;
+
+/// Game state that the conditional jumps of a script depend on. Every
+/// condition is false unless it is implemented.
+pub trait Conditions {
+    /// Whether the unit carries a power-up such as a mineral chunk.
+    fn has_power_up(&self) -> bool {
+        false
+    }
+
+    /// Whether the target is within the distance in pixels.
+    fn target_in_range(&self, _distance: u16) -> bool {
+        false
+    }
+
+    /// Whether the target is within the arc around the angle.
+    fn target_in_arc(&self, _angle: u16, _arc: u16) -> bool {
+        false
+    }
+
+    /// Whether the image faces within the arc around the angle.
+    fn faces(&self, _angle: u16, _arc: u16) -> bool {
+        false
+    }
+
+    /// Whether the building is lifted off.
+    fn is_lifted_off(&self) -> bool {
+        false
+    }
+}
+
+impl Conditions for () {}
+
+/// Something that a script does which the game has to act on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// Shows a frame set of the GRP.
+    PlayFrame(u16),
+
+    /// Shows a frame set plus the index of the tileset.
+    PlayFrameTile(u16),
+
+    /// The image moved relative to its sprite.
+    SetPosition(i8, i8),
+
+    /// The script waits for the number of frames.
+    Wait(u8),
+
+    SetFlipState(bool),
+
+    CreateOverlay {
+        image: u16,
+        x: i8,
+        y: i8,
+        layer: Layer,
+        use_lo: bool,
+    },
+
+    CreateSprite {
+        sprite: u16,
+        x: i8,
+        y: i8,
+        layer: Layer,
+        use_lo: bool,
+    },
+
+    PlaySound(u16),
+
+    /// Attacks with the weapon for the target.
+    Attack,
+
+    /// Attacks with the ground (1) or air (2) weapon.
+    AttackWith(u8),
+
+    /// Attacks with a weapon id.
+    UseWeapon(u8),
+
+    /// Deals melee damage to the target.
+    AttackMelee,
+
+    /// Deals the damage of a missile or of a ground weapon.
+    Damage,
+
+    CastSpell,
+
+    /// Turns by the number of directions, counterclockwise when negative.
+    Turn(i8),
+
+    /// Moves forward by the speed.
+    Move(u8),
+
+    /// The image is removed.
+    End,
+
+    /// Any other instruction, which the interpreter leaves to the game.
+    Instruction(Instruction),
+}
+
+/// Runs the animations of a single image frame by frame.
+///
+/// Randomness comes from the same linear congruential generator the game
+/// uses, so two interpreters with the same seed run the same way.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Interpreter {
+    script: ScriptId,
+    offset: Option<Offset>,
+    return_offset: Option<Offset>,
+    wait: u8,
+    frame: u16,
+    position: (i8, i8),
+    is_flipped: bool,
+    is_interruptible: bool,
+    seed: u32,
+}
+
+impl Interpreter {
+    /// Starts the `Init` animation of a script.
+    pub fn new(iscript: &Iscript, script: ScriptId, seed: u32) -> Result<Interpreter> {
+        let mut interpreter = Interpreter {
+            script,
+            offset: None,
+            return_offset: None,
+            wait: 0,
+            frame: 0,
+            position: (0, 0),
+            is_flipped: false,
+            is_interruptible: true,
+            seed,
+        };
+        interpreter.play(iscript, Animation::Init)?;
+
+        Ok(interpreter)
+    }
+
+    /// Switches to an animation. It starts on the next [`step`].
+    ///
+    /// [`step`]: Interpreter::step
+    pub fn play(&mut self, iscript: &Iscript, animation: Animation) -> Result<()> {
+        let offset = iscript
+            .script(self.script)
+            .context(MissingScript { id: self.script })?
+            .animation(animation)
+            .context(MissingAnimation {
+                id: self.script,
+                animation,
+            })?;
+
+        self.offset = Some(offset);
+        self.return_offset = None;
+        self.wait = 0;
+        self.is_interruptible = true;
+
+        Ok(())
+    }
+
+    /// Frame set that was played last.
+    pub fn frame(&self) -> u16 {
+        self.frame
+    }
+
+    /// Position of the image relative to its sprite.
+    pub fn position(&self) -> (i8, i8) {
+        self.position
+    }
+
+    pub fn is_flipped(&self) -> bool {
+        self.is_flipped
+    }
+
+    /// Whether orders may switch to another animation, which scripts prevent
+    /// between `nobrkcodestart` and `nobrkcodeend`.
+    pub fn is_interruptible(&self) -> bool {
+        self.is_interruptible
+    }
+
+    /// Whether the script reached `end`.
+    pub fn is_finished(&self) -> bool {
+        self.offset.is_none()
+    }
+
+    /// Next value of the generator of the game, from 0 to `0x7FFF`.
+    fn random(&mut self) -> u16 {
+        self.seed = self.seed.wrapping_mul(0x015A_4E35).wrapping_add(1);
+
+        ((self.seed >> 16) & 0x7FFF) as u16
+    }
+
+    fn random_sound(&mut self, sounds: &[u16]) -> Option<u16> {
+        if sounds.is_empty() {
+            None
+        } else {
+            let i = self.random() as usize % sounds.len();
+            Some(sounds[i])
+        }
+    }
+
+    fn jump_if(&mut self, condition: bool, target: Offset) {
+        if condition {
+            self.offset = Some(target);
+        }
+    }
+
+    /// Runs a single frame of the animation and returns what the script did
+    /// in the order it did it.
+    pub fn step(&mut self, iscript: &Iscript, conditions: &impl Conditions) -> Result<Vec<Event>> {
+        use Instruction::*;
+
+        let mut events = vec![];
+
+        if self.wait > 0 {
+            self.wait -= 1;
+            return Ok(events);
+        }
+
+        let mut executed = 0;
+        while let Some(offset) = self.offset {
+            executed += 1;
+            if executed > MAX_INSTRUCTIONS_PER_FRAME {
+                return InfiniteLoop { offset }.fail();
+            }
+
+            let (instruction, next) = iscript
+                .instruction(offset)
+                .context(InvalidOffset { offset })?;
+            self.offset = Some(next);
+
+            match instruction {
+                PlayFrame(frame) => {
+                    self.frame = *frame;
+                    events.push(Event::PlayFrame(*frame));
+                }
+                PlayFrameTile(frame) => {
+                    self.frame = *frame;
+                    events.push(Event::PlayFrameTile(*frame));
+                }
+                SetHorizontalPosition(x) => {
+                    self.position.0 = *x;
+                    events.push(Event::SetPosition(self.position.0, self.position.1));
+                }
+                SetVerticalPosition(y) => {
+                    self.position.1 = *y;
+                    events.push(Event::SetPosition(self.position.0, self.position.1));
+                }
+                SetPosition(x, y) => {
+                    self.position = (*x, *y);
+                    events.push(Event::SetPosition(*x, *y));
+                }
+                Wait(frames) => {
+                    self.wait = frames.saturating_sub(1);
+                    events.push(Event::Wait(*frames));
+                    break;
+                }
+                WaitRandom(min, max) => {
+                    let range = max.saturating_sub(*min) as u16 + 1;
+                    let frames = min + (self.random() % range) as u8;
+                    self.wait = frames.saturating_sub(1);
+                    events.push(Event::Wait(frames));
+                    break;
+                }
+                Goto(target) => self.offset = Some(*target),
+                Call(target) => {
+                    self.return_offset = Some(next);
+                    self.offset = Some(*target);
+                }
+                Return => self.offset = self.return_offset.take(),
+                RandomJump(chance, target) => {
+                    let roll = (self.random() & 0xFF) as u8;
+                    self.jump_if(roll < *chance, *target);
+                }
+                PowerUpJump(target) => self.jump_if(conditions.has_power_up(), *target),
+                TargetRangeJump(distance, target) => {
+                    self.jump_if(conditions.target_in_range(*distance), *target)
+                }
+                TargetArcJump(angle, arc, target) => {
+                    self.jump_if(conditions.target_in_arc(*angle, *arc), *target)
+                }
+                CurrentDirectionJump(angle, arc, target) => {
+                    self.jump_if(conditions.faces(*angle, *arc), *target)
+                }
+                LiftOffJump(target) => self.jump_if(conditions.is_lifted_off(), *target),
+                End => {
+                    self.offset = None;
+                    events.push(Event::End);
+                }
+                IgnoreRest => {
+                    // Stays on the instruction until another animation is
+                    // played.
+                    self.offset = Some(offset);
+                    break;
+                }
+                NoBreakCodeStart => self.is_interruptible = false,
+                NoBreakCodeEnd => self.is_interruptible = true,
+                SetFlipState(is_flipped) => {
+                    self.is_flipped = *is_flipped;
+                    events.push(Event::SetFlipState(*is_flipped));
+                }
+                ImageOverlay {
+                    image,
+                    x,
+                    y,
+                    layer,
+                    use_lo,
+                } => events.push(Event::CreateOverlay {
+                    image: *image,
+                    x: *x,
+                    y: *y,
+                    layer: *layer,
+                    use_lo: *use_lo,
+                }),
+                ImageOverlayOrigin(image) => events.push(Event::CreateOverlay {
+                    image: *image,
+                    x: 0,
+                    y: 0,
+                    layer: Layer::Above,
+                    use_lo: false,
+                }),
+                SpriteOverlay {
+                    sprite,
+                    x,
+                    y,
+                    layer,
+                    use_lo,
+                } => events.push(Event::CreateSprite {
+                    sprite: *sprite,
+                    x: *x,
+                    y: *y,
+                    layer: *layer,
+                    use_lo: *use_lo,
+                }),
+                PlaySound(sound) => events.push(Event::PlaySound(*sound)),
+                PlaySoundRandom(sounds) => {
+                    if let Some(sound) = self.random_sound(sounds) {
+                        events.push(Event::PlaySound(sound));
+                    }
+                }
+                PlaySoundBetween(min, max) => {
+                    let range = max.saturating_sub(*min) as u32 + 1;
+                    let sound = *min as u32 + self.random() as u32 % range;
+                    events.push(Event::PlaySound(sound as u16));
+                }
+                AttackMelee(sounds) => {
+                    events.push(Event::AttackMelee);
+                    if let Some(sound) = self.random_sound(sounds) {
+                        events.push(Event::PlaySound(sound));
+                    }
+                }
+                DoMissileDamage | DoGroundDamage => events.push(Event::Damage),
+                Attack => events.push(Event::Attack),
+                AttackWith(weapon) => events.push(Event::AttackWith(*weapon)),
+                UseWeapon(weapon) => events.push(Event::UseWeapon(*weapon)),
+                CastSpell => events.push(Event::CastSpell),
+                Turn(directions) => events.push(Event::Turn(*directions)),
+                TurnRandom(directions) => {
+                    let directions = *directions as i8;
+                    let turn = if self.random() % 4 == 1 {
+                        directions.wrapping_neg()
+                    } else {
+                        directions
+                    };
+                    events.push(Event::Turn(turn));
+                }
+                Move(speed) => events.push(Event::Move(*speed)),
+                other => events.push(Event::Instruction(other.clone())),
+            }
+        }
+
+        Ok(events)
+    }
+}
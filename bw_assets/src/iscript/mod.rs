@@ -0,0 +1,217 @@
+//! Types, Parser and Interpreter for iscript.bin
+//!
+//! `scripts\iscript.bin` holds the scripts that animate every image: which
+//! frame is shown, how long it is shown for, when an attack hits and which
+//! overlays, sprites and sounds are created. Images reference their script
+//! by the `iscript_id` column of images.dat.
+//!
+//! A script is a header with an entry point for each of its animations, e.g.
+//! `Walking` or `GroundAttackRepeat`. The entry points lead into bytecode
+//! that scripts share with each other, so the instructions are decoded by
+//! following every entry point and jump rather than by reading the file from
+//! start to end.
+//!
+//! [`Interpreter`] runs the animations of a single image without any
+//! rendering and reports what the game has to do as [`Event`]s.
+//!
+//! see: http://www.staredit.net/wiki/index.php?title=Iscript.bin
+
+mod instruction;
+mod interpreter;
+mod script;
+
+pub use instruction::{parse_instruction, Instruction, Layer, Offset};
+pub use interpreter::{Conditions, Event, Interpreter};
+pub use script::{
+    parse_iscript, Animation, Iscript, IscriptAsset, IscriptFormat, IscriptHandle, Script, ScriptId,
+};
+
+use snafu::Snafu;
+
+#[derive(Debug, Snafu)]
+#[snafu(visibility = "pub")]
+pub enum Error {
+    #[snafu(display("script {} does not exist", id))]
+    MissingScript { id: ScriptId },
+
+    #[snafu(display("script {} has no {:?} animation", id, animation))]
+    MissingAnimation { id: ScriptId, animation: Animation },
+
+    #[snafu(display("no instruction was decoded at offset {}", offset))]
+    InvalidOffset { offset: Offset },
+
+    #[snafu(display("script loops without waiting at offset {}", offset))]
+    InfiniteLoop { offset: Offset },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use byteorder::{LittleEndian, WriteBytesExt};
+    use nom::Finish;
+    use spectral::prelude::*;
+
+    /// Offset of the first byte of code in [`create_iscript`].
+    const CODE: u16 = 2;
+
+    /// iscript.bin with a single script 7 of type 0 whose `Init` and
+    /// `Death` animations start at the offsets.
+    fn create_iscript(code: &[u8], init: u16, death: u16) -> Vec<u8> {
+        let header_offset = CODE as usize + code.len();
+        let table_offset = header_offset + 12;
+
+        let mut b: Vec<u8> = vec![];
+        b.write_u16::<LittleEndian>(table_offset as u16).unwrap();
+        b.extend(code);
+
+        b.extend(b"SCPE");
+        b.extend(&[0, 0, 0, 0]);
+        b.write_u16::<LittleEndian>(init).unwrap();
+        b.write_u16::<LittleEndian>(death).unwrap();
+
+        for value in &[7, header_offset as u16, 0xFFFF, 0] {
+            b.write_u16::<LittleEndian>(*value).unwrap();
+        }
+
+        b
+    }
+
+    /// Shows a frame, waits, attacks and loops. Dies at offset 23.
+    fn attack_loop() -> Vec<u8> {
+        let code = [
+            0x00, 0x11, 0x00, // playfram 0x11
+            0x05, 0x02, // wait 2
+            0x00, 0x22, 0x00, // playfram 0x22
+            0x25, 0x01, // attackwith 1
+            0x18, 0x05, 0x00, // playsnd 5
+            0x08, 0x64, 0x00, 0x01, 0xFF, // imgol 100 1 -1
+            0x07, 0x05, 0x00, // goto 5
+            0x16, // end
+            0x16, // end, never reached
+        ];
+
+        create_iscript(&code, CODE, CODE + 21)
+    }
+
+    #[test]
+    fn it_decodes_the_reachable_instructions() {
+        let b = attack_loop();
+
+        let (_, iscript) = parse_iscript(&b).finish().unwrap();
+        let script = iscript.script(7).unwrap();
+
+        assert_that(&script.animation(Animation::Init)).is_equal_to(Some(CODE));
+        assert_that(&script.animation(Animation::Death)).is_equal_to(Some(CODE + 21));
+        assert_that(&script.animation(Animation::Walking)).is_none();
+        assert_that(&iscript.instructions().count()).is_equal_to(8);
+        assert_that(&iscript.instruction(CODE + 8))
+            .is_equal_to(Some((&Instruction::AttackWith(1), CODE + 10)));
+        assert_that(&iscript.instruction(CODE + 22)).is_none();
+    }
+
+    #[test]
+    fn it_steps_animations_frame_by_frame() {
+        let b = attack_loop();
+        let (_, iscript) = parse_iscript(&b).finish().unwrap();
+        let mut interpreter = Interpreter::new(&iscript, 7, 0).unwrap();
+
+        assert_that(&interpreter.step(&iscript, &()).unwrap())
+            .is_equal_to(vec![Event::PlayFrame(0x11), Event::Wait(2)]);
+        assert_that(&interpreter.step(&iscript, &()).unwrap()).is_empty();
+        assert_that(&interpreter.step(&iscript, &()).unwrap()).is_equal_to(vec![
+            Event::PlayFrame(0x22),
+            Event::AttackWith(1),
+            Event::PlaySound(5),
+            Event::CreateOverlay {
+                image: 100,
+                x: 1,
+                y: -1,
+                layer: Layer::Above,
+                use_lo: false,
+            },
+            Event::Wait(2),
+        ]);
+        assert_that(&interpreter.frame()).is_equal_to(0x22);
+
+        interpreter.play(&iscript, Animation::Death).unwrap();
+        assert_that(&interpreter.step(&iscript, &()).unwrap()).is_equal_to(vec![Event::End]);
+        assert_that(&interpreter.is_finished()).is_true();
+        assert_that(&interpreter.play(&iscript, Animation::Walking).is_err()).is_true();
+    }
+
+    struct InRange;
+
+    impl Conditions for InRange {
+        fn target_in_range(&self, distance: u16) -> bool {
+            distance >= 32
+        }
+    }
+
+    #[test]
+    fn it_jumps_on_conditions_and_random_rolls() {
+        let code = [
+            0x3A, 0x20, 0x00, 0x0B, 0x00, // trgtrangecondjmp 32 11
+            0x18, 0x01, 0x00, // playsnd 1
+            0x30, // ignorerest
+            0x1E, 0x80, 0x13, 0x00, // randcondjmp 128 19
+            0x18, 0x02, 0x00, // playsnd 2
+            0x30, // ignorerest
+            0x18, 0x03, 0x00, // playsnd 3
+            0x30, // ignorerest
+        ];
+        let b = create_iscript(&code, CODE, CODE);
+        let (_, iscript) = parse_iscript(&b).finish().unwrap();
+
+        let mut out_of_range = Interpreter::new(&iscript, 7, 0).unwrap();
+        assert_that(&out_of_range.step(&iscript, &()).unwrap()[0]).is_equal_to(Event::PlaySound(1));
+
+        // The same seed rolls the same way.
+        let run = |seed| {
+            let mut interpreter = Interpreter::new(&iscript, 7, seed).unwrap();
+            let events = interpreter.step(&iscript, &InRange).unwrap();
+            assert_that(&interpreter.step(&iscript, &InRange).unwrap()).is_empty();
+            events
+        };
+        assert_that(&run(42)).is_equal_to(run(42));
+        assert_that(&(0..16).map(run).collect::<Vec<_>>()).contains(vec![Event::PlaySound(3)]);
+    }
+
+    #[test]
+    fn it_turns_half_a_turn_either_way() {
+        let code = [
+            0x1F, 0x80, // turnccwise 128
+            0x20, 0x80, // turncwise 128
+            0x22, 0x80, // turnrand 128
+            0x30, // ignorerest
+        ];
+        let b = create_iscript(&code, CODE, CODE);
+        let (_, iscript) = parse_iscript(&b).finish().unwrap();
+
+        assert_that(&iscript.instruction(CODE))
+            .is_equal_to(Some((&Instruction::Turn(-128), CODE + 2)));
+        for seed in 0..16 {
+            let mut interpreter = Interpreter::new(&iscript, 7, seed).unwrap();
+            let events = interpreter.step(&iscript, &()).unwrap();
+
+            assert_that(&events).is_equal_to(vec![Event::Turn(-128); 3]);
+        }
+    }
+
+    #[test]
+    fn it_stops_scripts_that_loop_without_waiting() {
+        let b = create_iscript(&[0x07, CODE as u8, 0x00], CODE, CODE);
+        let (_, iscript) = parse_iscript(&b).finish().unwrap();
+        let mut interpreter = Interpreter::new(&iscript, 7, 0).unwrap();
+
+        assert_that(&interpreter.step(&iscript, &()).is_err()).is_true();
+    }
+
+    #[test]
+    fn it_rejects_unknown_opcodes() {
+        let b = create_iscript(&[0x50], CODE, CODE);
+
+        assert_that(&parse_iscript(&b).is_err()).is_true();
+    }
+}
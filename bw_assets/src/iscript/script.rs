@@ -0,0 +1,251 @@
+use amethyst::{
+    assets::{Asset, Format, Handle},
+    ecs::DenseVecStorage,
+};
+use nom::{
+    bytes::complete::{tag, take},
+    combinator::{map, verify},
+    multi::count,
+    number::complete::{le_u8, le_u16},
+    sequence::{preceded, terminated, tuple},
+    Finish, IResult,
+};
+use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
+use std::collections::{BTreeMap, HashMap};
+
+use super::instruction::{parse_instruction, Instruction, Offset};
+
+/// Id of a script in the header table, as referenced by images.dat.
+pub type ScriptId = u16;
+
+/// Animations that a script may have an entry point for.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, FromPrimitive)]
+pub enum Animation {
+    Init = 0,
+    Death = 1,
+    GroundAttackInit = 2,
+    AirAttackInit = 3,
+    Unused1 = 4,
+    GroundAttackRepeat = 5,
+    AirAttackRepeat = 6,
+    CastSpell = 7,
+    GroundAttackToIdle = 8,
+    AirAttackToIdle = 9,
+    Unused2 = 10,
+    Walking = 11,
+    WalkingToIdle = 12,
+    SpecialState1 = 13,
+    SpecialState2 = 14,
+    AlmostBuilt = 15,
+    Built = 16,
+    Landing = 17,
+    LiftOff = 18,
+    IsWorking = 19,
+    WorkingToIdle = 20,
+    WarpIn = 21,
+    Unused3 = 22,
+    StarEditInit = 23,
+    Disable = 24,
+    Burrow = 25,
+    UnBurrow = 26,
+    Enable = 27,
+}
+
+/// Entry points of a script, one for each animation that its type has.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Script {
+    kind: u8,
+    animations: Vec<Offset>,
+}
+
+impl Script {
+    /// Type of the script that decides how many animations it has.
+    pub fn kind(&self) -> u8 {
+        self.kind
+    }
+
+    /// Offset of the first instruction of an animation, or `None` when the
+    /// script doesn't have it.
+    pub fn animation(&self, animation: Animation) -> Option<Offset> {
+        self.animations
+            .get(animation as usize)
+            .copied()
+            .filter(|&offset| offset != 0)
+    }
+
+    /// Every animation that the script has with its offset.
+    pub fn animations(&self) -> impl Iterator<Item = (Animation, Offset)> + '_ {
+        self.animations
+            .iter()
+            .enumerate()
+            .filter(|(_, &offset)| offset != 0)
+            .filter_map(|(i, &offset)| Animation::from_usize(i).map(|a| (a, offset)))
+    }
+}
+
+/// Number of animations of each type of script.
+fn animation_count(kind: u8) -> Option<usize> {
+    match kind {
+        0 | 1 => Some(2),
+        2 => Some(4),
+        12 | 13 => Some(14),
+        14 | 15 => Some(16),
+        20 | 21 => Some(22),
+        23 => Some(24),
+        24 => Some(26),
+        26..=29 => Some(28),
+        _ => None,
+    }
+}
+
+/// An instruction together with the offset of the instruction after it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Decoded {
+    instruction: Instruction,
+    next: Offset,
+}
+
+/// Every script of iscript.bin and the instructions they can reach.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Iscript {
+    scripts: HashMap<ScriptId, Script>,
+    code: BTreeMap<Offset, Decoded>,
+}
+
+impl Iscript {
+    pub fn script(&self, id: ScriptId) -> Option<&Script> {
+        self.scripts.get(&id)
+    }
+
+    pub fn scripts(&self) -> impl Iterator<Item = (ScriptId, &Script)> {
+        self.scripts.iter().map(|(&id, script)| (id, script))
+    }
+
+    /// Instruction at an offset and the offset of the instruction after it.
+    pub fn instruction(&self, offset: Offset) -> Option<(&Instruction, Offset)> {
+        self.code
+            .get(&offset)
+            .map(|decoded| (&decoded.instruction, decoded.next))
+    }
+
+    /// Every instruction that a script can reach, in the order of the file.
+    pub fn instructions(&self) -> impl Iterator<Item = (Offset, &Instruction)> {
+        self.code
+            .iter()
+            .map(|(&offset, decoded)| (offset, &decoded.instruction))
+    }
+}
+
+fn parse_script(b: &[u8]) -> IResult<&[u8], Script> {
+    let (remaining, kind) = preceded(
+        tag(&b"SCPE"[..]),
+        terminated(
+            verify(le_u8, |&kind| animation_count(kind).is_some()),
+            take(3u8),
+        ),
+    )(b)?;
+    let n = animation_count(kind).unwrap_or_default();
+
+    map(count(le_u16, n), move |animations| Script {
+        kind,
+        animations,
+    })(remaining)
+}
+
+/// Decodes every instruction that can be reached from the entry points,
+/// following jumps and calls.
+fn decode_code(
+    b: &[u8],
+    entry_points: impl Iterator<Item = Offset>,
+) -> IResult<&[u8], BTreeMap<Offset, Decoded>> {
+    let mut code = BTreeMap::new();
+    let mut queue: Vec<Offset> = entry_points.collect();
+
+    while let Some(offset) = queue.pop() {
+        if code.contains_key(&offset) {
+            continue;
+        }
+
+        let (input, _) = take(offset)(b)?;
+        let (remaining, instruction) = parse_instruction(input)?;
+        let next = (b.len() - remaining.len()) as Offset;
+
+        queue.extend(instruction.jump_targets());
+        if !instruction.is_terminator() {
+            queue.push(next);
+        }
+        code.insert(offset, Decoded { instruction, next });
+    }
+
+    Ok((b, code))
+}
+
+/// Parses iscript.bin.
+///
+/// The file starts with the offset of the header table. Every entry of the
+/// table is a script id and the offset of its header, up to the id `0xFFFF`.
+pub fn parse_iscript(b: &[u8]) -> IResult<&[u8], Iscript> {
+    let (_, table_offset) = le_u16(b)?;
+    let (mut table, _) = take(table_offset)(b)?;
+
+    let mut scripts = HashMap::new();
+    loop {
+        let (remaining, (id, offset)) = tuple((le_u16, le_u16))(table)?;
+        table = remaining;
+        if id == 0xFFFF {
+            break;
+        }
+
+        let (header, _) = take(offset)(b)?;
+        let (_, script) = parse_script(header)?;
+        scripts.insert(id, script);
+    }
+
+    let entry_points = scripts
+        .values()
+        .flat_map(|script| script.animations().map(|(_, offset)| offset))
+        .collect::<Vec<_>>();
+    let (_, code) = decode_code(b, entry_points.into_iter())?;
+
+    let (remaining, _) = take(b.len())(b)?;
+
+    Ok((remaining, Iscript { scripts, code }))
+}
+
+pub struct IscriptAsset(Option<Iscript>);
+
+impl IscriptAsset {
+    pub fn take(&mut self) -> Option<Iscript> {
+        self.0.take()
+    }
+}
+
+pub type IscriptHandle = Handle<IscriptAsset>;
+
+impl Asset for IscriptAsset {
+    const NAME: &'static str = "bw_assets::iscript::IscriptAsset";
+    type Data = Self;
+    type HandleStorage = DenseVecStorage<IscriptHandle>;
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IscriptFormat;
+
+impl Format<IscriptAsset> for IscriptFormat {
+    fn name(&self) -> &'static str {
+        "IscriptFormat"
+    }
+
+    fn import_simple(&self, b: Vec<u8>) -> amethyst::Result<IscriptAsset> {
+        let (_, iscript) = parse_iscript(&b).finish().map_err(|err| {
+            amethyst::error::format_err!(
+                "failed to load iscript.bin asset: {} at position {}",
+                err.code.description(),
+                b.len() - err.input.len()
+            )
+        })?;
+
+        Ok(IscriptAsset(Some(iscript)))
+    }
+}
@@ -11,6 +11,14 @@ extern crate derive_builder;
 extern crate maplit;
 
 pub mod dat;
+pub mod grp;
+pub mod iscript;
 pub mod map;
+pub mod minimap;
 pub mod mpq;
+pub mod pcx;
+pub mod render;
+pub mod tbl;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
 pub mod tileset;
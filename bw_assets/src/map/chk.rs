@@ -31,6 +31,8 @@ pub enum Chunk {
     MegaTiles(Vec<MegaTile>),
     Units(Vec<Unit>),
     StringData(StringData),
+    Locations(Vec<Location>),
+    Doodads(Vec<PlacedDoodad>),
     Unknown,
 }
 
@@ -67,6 +69,16 @@ pub fn parse_chunk(b: &[u8]) -> nom::IResult<&[u8], Chunk> {
             map(count(parse_placed_unit, size), Chunk::Units)(remaining)
         }
         ChunkName::StringData => map(parse_string_data, Chunk::StringData)(remaining),
+        ChunkName::Locations => {
+            const LOCATION_BYTE_SIZE: usize = 20;
+            let size = header.size as usize / LOCATION_BYTE_SIZE;
+            map(count(parse_location, size), Chunk::Locations)(remaining)
+        }
+        ChunkName::Doodads => {
+            const DOODAD_BYTE_SIZE: usize = 8;
+            let size = header.size as usize / DOODAD_BYTE_SIZE;
+            map(count(parse_placed_doodad, size), Chunk::Doodads)(remaining)
+        }
         _ => map(take(header.size), |_| Chunk::Unknown)(remaining),
     }
 }
@@ -82,6 +94,8 @@ pub enum ChunkName {
     MegaTiles,
     StringData,
     Unit,
+    Locations,
+    Doodads,
     Unknown,
 }
 
@@ -103,6 +117,8 @@ impl ChunkName {
             ChunkName::MegaTiles => "MTXM".as_bytes(),
             ChunkName::StringData => "STR ".as_bytes(),
             ChunkName::Unit => "UNIT".as_bytes(),
+            ChunkName::Locations => "MRGN".as_bytes(),
+            ChunkName::Doodads => "DD2 ".as_bytes(),
             ChunkName::Unknown => "????".as_bytes(),
         }
     }
@@ -142,6 +158,10 @@ pub fn parse_header(b: &[u8]) -> nom::IResult<&[u8], Header> {
             ChunkName::StringData
         }),
         map(tag(ChunkName::Unit.as_bytes()), |_| ChunkName::Unit),
+        map(tag(ChunkName::Locations.as_bytes()), |_| {
+            ChunkName::Locations
+        }),
+        map(tag(ChunkName::Doodads.as_bytes()), |_| ChunkName::Doodads),
         map(take(HEADER_NAME_BYTE_SIZE), |_| ChunkName::Unknown),
     ))(b)?;
 
@@ -325,6 +345,57 @@ pub fn parse_placed_unit(b: &[u8]) -> nom::IResult<&[u8], Unit> {
     )(b)
 }
 
+/// Rectangle in pixels that triggers refer to.
+///
+/// http://www.staredit.net/wiki/index.php?title=Scenario.chk#.22MRGN.22_-_Locations
+#[derive(Debug, Clone, Struple, Eq, PartialEq)]
+pub struct Location {
+    pub left: u32,
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+
+    /// Index of the name in the string data, starting at 1.
+    pub string_index: u16,
+    pub elevation_flags: u16,
+}
+
+impl Location {
+    /// Every map has 255 location slots, most of which are all zeroes.
+    pub fn is_unused(&self) -> bool {
+        self.left == 0 && self.top == 0 && self.right == 0 && self.bottom == 0
+    }
+}
+
+pub fn parse_location(b: &[u8]) -> nom::IResult<&[u8], Location> {
+    map(
+        tuple((le_u32, le_u32, le_u32, le_u32, le_u16, le_u16)),
+        Location::from_tuple,
+    )(b)
+}
+
+/// Doodad placed on the map, on top of the megatiles it is drawn with.
+///
+/// http://www.staredit.net/wiki/index.php?title=Scenario.chk#.22DD2_.22_-_StarEdit_Sprites_.28Doodads.29
+#[derive(Debug, Clone, Struple, Eq, PartialEq)]
+pub struct PlacedDoodad {
+    /// Index into dddata.dat, which the CV5 doodad of the same index sizes.
+    pub id: u16,
+
+    /// Center of the doodad in pixels.
+    pub x: u16,
+    pub y: u16,
+    pub owner: u8,
+    pub flags: u8,
+}
+
+pub fn parse_placed_doodad(b: &[u8]) -> nom::IResult<&[u8], PlacedDoodad> {
+    map(
+        tuple((le_u16, le_u16, le_u16, le_u8, le_u8)),
+        PlacedDoodad::from_tuple,
+    )(b)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -358,6 +429,8 @@ mod tests {
         test_header!(ChunkName::Side);
         test_header!(ChunkName::MegaTiles);
         test_header!(ChunkName::StringData);
+        test_header!(ChunkName::Locations);
+        test_header!(ChunkName::Doodads);
     }
 
     macro_rules! test_scenario_type {
@@ -530,4 +603,47 @@ mod tests {
             .is_ok()
             .is_equal_to(expected);
     }
+
+    #[test]
+    fn it_parses_location() {
+        let mut b: Vec<u8> = vec![];
+        for side in &[64, 32, 192, 96] {
+            b.write_u32::<LittleEndian>(*side).unwrap();
+        }
+        b.write_u16::<LittleEndian>(3).unwrap();
+        b.write_u16::<LittleEndian>(0).unwrap();
+
+        assert_that(&parse_location(&b))
+            .is_ok()
+            .map(|(_, location)| location)
+            .is_equal_to(Location {
+                left: 64,
+                top: 32,
+                right: 192,
+                bottom: 96,
+                string_index: 3,
+                elevation_flags: 0,
+            });
+    }
+
+    #[test]
+    fn it_parses_placed_doodad() {
+        let mut b: Vec<u8> = vec![];
+        b.write_u16::<LittleEndian>(42).unwrap();
+        b.write_u16::<LittleEndian>(320).unwrap();
+        b.write_u16::<LittleEndian>(640).unwrap();
+        b.write_u8(11).unwrap();
+        b.write_u8(0).unwrap();
+
+        assert_that(&parse_placed_doodad(&b))
+            .is_ok()
+            .map(|(_, doodad)| doodad)
+            .is_equal_to(PlacedDoodad {
+                id: 42,
+                x: 320,
+                y: 640,
+                owner: 11,
+                flags: 0,
+            });
+    }
 }
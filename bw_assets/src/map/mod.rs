@@ -9,7 +9,8 @@ use nom::Finish;
 mod chk;
 
 pub use self::chk::{
-    Dimensions, FileFormatVersion, MegaTile, ScenarioType, Side, StringData, Tileset,
+    Dimensions, FileFormatVersion, Location, MegaTile, PlacedDoodad, ScenarioType, Side,
+    StringData, Tileset,
 };
 
 /// Every Starcraft map will have this file.
@@ -37,6 +38,10 @@ pub struct Map {
     pub megatiles: Vec<MegaTile>,
     pub placed_units: Vec<Unit>,
     pub string_data: StringData,
+    #[builder(default)]
+    pub locations: Vec<Location>,
+    #[builder(default)]
+    pub doodads: Vec<PlacedDoodad>,
 }
 
 impl Map {
@@ -124,6 +129,12 @@ impl Format<Map> for MapFormat {
             chk::Chunk::StringData(string_data) => {
                 map_builder.string_data(string_data);
             }
+            chk::Chunk::Locations(locations) => {
+                map_builder.locations(locations);
+            }
+            chk::Chunk::Doodads(doodads) => {
+                map_builder.doodads(doodads);
+            }
             _ => {}
         });
 
@@ -0,0 +1,285 @@
+//! Dots of units on the minimap and the layout of the minimap
+//!
+//! Units are drawn on top of the minimap terrain as rectangles the size of
+//! their footprint, scaled down to the minimap but never smaller than a pixel.
+//! Nothing here depends on a window so that the dots can be computed every
+//! frame by the game and tested on their own.
+
+use crate::{
+    pcx::Pcx,
+    render::{GEYSER_COLOR, MINERAL_COLOR, NEUTRAL_COLOR},
+};
+use bw_core::{Unit, UnitId};
+
+/// Number of players with a colour in `game\tminimap.pcx`.
+const PLAYER_COUNT: usize = 12;
+
+/// Footprint of units that aren't a resource or a start location in pixels.
+const UNIT_SIZE: (u16, u16) = (32, 32);
+
+/// What the minimap needs to know about a unit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MinimapUnit {
+    pub unit_id: UnitId,
+    pub owner: u8,
+
+    /// Center of the unit in pixels from the top left of the map.
+    pub position: (u16, u16),
+}
+
+impl MinimapUnit {
+    /// Minimap unit of a unit placed on the map, or `None` if its unit id is
+    /// unknown.
+    pub fn from_placed(unit: &Unit) -> Option<MinimapUnit> {
+        Some(MinimapUnit {
+            unit_id: unit.unit_id()?,
+            owner: usize::from(unit.owner()) as u8,
+            position: unit.position(),
+        })
+    }
+
+    /// Width and height of the footprint of the unit in pixels.
+    pub fn size(&self) -> (u16, u16) {
+        if self.unit_id == UnitId::SpecialStartLocation {
+            (128, 96)
+        } else if self.unit_id.is_mineral_field() {
+            (64, 32)
+        } else if self.unit_id.is_resource() {
+            (128, 64)
+        } else {
+            UNIT_SIZE
+        }
+    }
+
+    /// Whether the unit is drawn below the other units, since it never moves.
+    fn is_background(&self) -> bool {
+        self.unit_id == UnitId::SpecialStartLocation || self.unit_id.is_resource()
+    }
+}
+
+/// Colours of the dots, in rgb.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MinimapColors {
+    /// Colours of players 1 to 12, the last of which is neutral.
+    pub players: Vec<[u8; 3]>,
+    pub neutral: [u8; 3],
+    pub mineral: [u8; 3],
+    pub geyser: [u8; 3],
+}
+
+impl MinimapColors {
+    /// Looks up the palette index of the minimap colour of every player in
+    /// `game\tminimap.pcx` within a palette, e.g. the WPE of the tileset.
+    pub fn new(tminimap: &Pcx, palette: &[[u8; 3]]) -> MinimapColors {
+        let players = (0..PLAYER_COUNT as u8)
+            .map(|player| {
+                tminimap
+                    .minimap_color(player)
+                    .and_then(|index| palette.get(index as usize))
+                    .copied()
+                    .unwrap_or(NEUTRAL_COLOR.0)
+            })
+            .collect();
+
+        MinimapColors {
+            players,
+            ..MinimapColors::default()
+        }
+    }
+
+    /// Colour of the dot of a unit.
+    pub fn color(&self, unit: &MinimapUnit) -> [u8; 3] {
+        if unit.unit_id.is_mineral_field() {
+            self.mineral
+        } else if unit.unit_id.is_resource() {
+            self.geyser
+        } else {
+            self.players
+                .get(unit.owner as usize)
+                .copied()
+                .unwrap_or(self.neutral)
+        }
+    }
+}
+
+impl Default for MinimapColors {
+    fn default() -> MinimapColors {
+        MinimapColors {
+            players: Vec::new(),
+            neutral: NEUTRAL_COLOR.0,
+            mineral: MINERAL_COLOR.0,
+            geyser: GEYSER_COLOR.0,
+        }
+    }
+}
+
+/// Rectangle drawn on the minimap for a unit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MinimapDot {
+    /// Left of the dot in minimap pixels.
+    pub x: f32,
+
+    /// Top of the dot in minimap pixels.
+    pub y: f32,
+
+    pub width: f32,
+    pub height: f32,
+    pub color: [u8; 3],
+}
+
+/// Dots of units on a minimap of `minimap_size` pixels that shows a map of
+/// `map_size` pixels.
+///
+/// Start locations and resources come first so that units drawn in order are
+/// on top of them.
+pub fn minimap_dots(
+    units: &[MinimapUnit],
+    map_size: (u32, u32),
+    minimap_size: (f32, f32),
+    colors: &MinimapColors,
+) -> Vec<MinimapDot> {
+    let scale_x = minimap_size.0 / map_size.0.max(1) as f32;
+    let scale_y = minimap_size.1 / map_size.1.max(1) as f32;
+
+    let (background, foreground): (Vec<&MinimapUnit>, Vec<&MinimapUnit>) =
+        units.iter().partition(|unit| unit.is_background());
+
+    background
+        .into_iter()
+        .chain(foreground)
+        .map(|unit| {
+            let (width, height) = unit.size();
+            let width = (width as f32 * scale_x).max(1.0);
+            let height = (height as f32 * scale_y).max(1.0);
+
+            MinimapDot {
+                x: unit.position.0 as f32 * scale_x - width / 2.0,
+                y: unit.position.1 as f32 * scale_y - height / 2.0,
+                width,
+                height,
+                color: colors.color(unit),
+            }
+        })
+        .collect()
+}
+
+/// Size of a minimap that fits in a container while keeping the aspect ratio
+/// of a map of `map_size` pixels, e.g. 128x96 in a 128x128 container for a
+/// 128x96 map. The rest of the container is left as bars on two sides.
+pub fn letterbox(map_size: (u32, u32), container_size: (f32, f32)) -> (f32, f32) {
+    let (map_width, map_height) = (map_size.0.max(1) as f32, map_size.1.max(1) as f32);
+    let scale = (container_size.0 / map_width).min(container_size.1 / map_height);
+
+    (map_width * scale, map_height * scale)
+}
+
+/// Position on a map of `map_size` pixels, from its top left, that a point on
+/// the minimap points at. The point is a fraction of the width and height of
+/// the minimap from its top left and is clamped to the minimap.
+///
+/// A point on a minimap without a size is NaN, which is clamped to the top
+/// left rather than passed on to the camera.
+pub fn map_position(point: (f32, f32), map_size: (u32, u32)) -> (f32, f32) {
+    (
+        point.0.max(0.0).min(1.0) * map_size.0 as f32,
+        point.1.max(0.0).min(1.0) * map_size.1 as f32,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{pcx::parse_pcx, test_utils::create_pcx};
+    use spectral::prelude::*;
+
+    fn unit(unit_id: UnitId, owner: u8, position: (u16, u16)) -> MinimapUnit {
+        MinimapUnit {
+            unit_id,
+            owner,
+            position,
+        }
+    }
+
+    #[test]
+    fn it_scales_dots_to_the_minimap() {
+        let colors = MinimapColors {
+            players: vec![[255, 0, 0], [0, 0, 255]],
+            ..MinimapColors::default()
+        };
+        let units = [
+            unit(UnitId::TerranMarine, 1, (64, 64)),
+            unit(UnitId::ResourceMineralField, 11, (256, 128)),
+            unit(UnitId::SpecialStartLocation, 0, (512, 512)),
+        ];
+
+        let dots = minimap_dots(&units, (1024, 1024), (128.0, 128.0), &colors);
+
+        assert_that(&dots).is_equal_to(vec![
+            MinimapDot {
+                x: 28.0,
+                y: 14.0,
+                width: 8.0,
+                height: 4.0,
+                color: MINERAL_COLOR.0,
+            },
+            MinimapDot {
+                x: 56.0,
+                y: 58.0,
+                width: 16.0,
+                height: 12.0,
+                color: [255, 0, 0],
+            },
+            MinimapDot {
+                x: 6.0,
+                y: 6.0,
+                width: 4.0,
+                height: 4.0,
+                color: [0, 0, 255],
+            },
+        ]);
+    }
+
+    #[test]
+    fn it_draws_dots_of_at_least_a_pixel() {
+        let units = [unit(UnitId::TerranMarine, 12, (4096, 4096))];
+
+        let dots = minimap_dots(
+            &units,
+            (8192, 8192),
+            (128.0, 128.0),
+            &MinimapColors::default(),
+        );
+
+        assert_that(&dots[0].width).is_equal_to(1.0);
+        assert_that(&dots[0].height).is_equal_to(1.0);
+        assert_that(&dots[0].color).is_equal_to(NEUTRAL_COLOR.0);
+    }
+
+    #[test]
+    fn it_looks_up_minimap_colors_in_the_palette() {
+        // Palette indices 1 and 2 for the first two players.
+        let b = create_pcx(2, 1, 2, &[1, 2]);
+        let (_, tminimap) = parse_pcx(&b).unwrap();
+        let palette = [[0, 0, 0], [10, 20, 30], [40, 50, 60]];
+
+        let colors = MinimapColors::new(&tminimap, &palette);
+
+        assert_that(&colors.players[0]).is_equal_to([10, 20, 30]);
+        assert_that(&colors.players[1]).is_equal_to([40, 50, 60]);
+        assert_that(&colors.players[2]).is_equal_to(NEUTRAL_COLOR.0);
+    }
+
+    #[test]
+    fn it_letterboxes_maps_that_are_not_square() {
+        assert_that(&letterbox((4096, 3072), (128.0, 128.0))).is_equal_to((128.0, 96.0));
+        assert_that(&letterbox((3072, 4096), (128.0, 128.0))).is_equal_to((96.0, 128.0));
+        assert_that(&letterbox((4096, 4096), (200.0, 100.0))).is_equal_to((100.0, 100.0));
+    }
+
+    #[test]
+    fn it_clamps_map_positions_to_the_minimap() {
+        assert_that(&map_position((0.5, 0.25), (4096, 3072))).is_equal_to((2048.0, 768.0));
+        assert_that(&map_position((-0.5, 1.5), (4096, 3072))).is_equal_to((0.0, 3072.0));
+        assert_that(&map_position((f32::NAN, f32::NAN), (4096, 3072))).is_equal_to((0.0, 0.0));
+    }
+}
@@ -0,0 +1,415 @@
+//! Types and Parsers for the PCX Image Format
+//!
+//! The game keeps its palette-indexed UI graphics and the tables that remap
+//! palette indices in PCX files, e.g. `game\tunit.pcx` with the colours of
+//! every player or `tileset\badlands\ofire.pcx` with the colours of flames
+//! over every colour of the tileset.
+//!
+//! Only the 8 bits per pixel variant with a single colour plane is supported,
+//! which is the only one the game uses. The file starts with a 128 byte
+//! header, followed by the run-length encoded rows and the 256-colour palette
+//! at the end of the file. A byte of the rows is either a palette index or
+//! `0xC0 | n` for `n` times the next byte.
+//!
+//! see: http://www.staredit.net/wiki/index.php?title=PCX
+
+use amethyst::{
+    assets::{Asset, Format, Handle},
+    ecs::DenseVecStorage,
+};
+use nom::{
+    bytes::complete::{tag, take},
+    combinator::{map, verify},
+    multi::count,
+    number::complete::{le_u8, le_u16},
+    sequence::{preceded, tuple},
+    Finish, IResult,
+};
+
+pub(crate) const HEADER_SIZE: usize = 128;
+
+/// Size of the palette at the end of the file including its marker.
+const PALETTE_SIZE: usize = 1 + 256 * 3;
+
+/// Colours per player in `game\tunit.pcx`.
+const PLAYER_COLOR_COUNT: usize = 8;
+
+/// First palette index that is replaced with the colours of the owner.
+const FIRST_PLAYER_COLOR: usize = 8;
+
+/// Row of `dark.pcx` that shadows darken what is below them with.
+const SHADOW_LEVEL: usize = 17;
+
+/// Table that replaces every palette index with another one.
+pub type Remap = [u8; 256];
+
+/// Remap that leaves every palette index as it is.
+pub fn identity_remap() -> Remap {
+    let mut remap = [0; 256];
+    for (i, index) in remap.iter_mut().enumerate() {
+        *index = i as u8;
+    }
+
+    remap
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pcx {
+    width: u16,
+    height: u16,
+    pixels: Vec<u8>,
+    palette: Vec<[u8; 3]>,
+}
+
+impl Pcx {
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    /// Palette index of every pixel row by row.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn pixel(&self, x: u16, y: u16) -> Option<u8> {
+        if x < self.width && y < self.height {
+            self.pixels
+                .get(y as usize * self.width as usize + x as usize)
+                .copied()
+        } else {
+            None
+        }
+    }
+
+    /// Raw rgb values of the 256 colours of the image.
+    pub fn palette(&self) -> &[[u8; 3]] {
+        &self.palette
+    }
+
+    /// Remap that draws palette indices 8-15 in the colours of a player, as
+    /// read from `game\tunit.pcx`.
+    pub fn player_colors(&self, player: u8) -> Option<Remap> {
+        let start = player as usize * PLAYER_COLOR_COUNT;
+        let colors = self.pixels.get(start..start + PLAYER_COLOR_COUNT)?;
+
+        let mut remap = identity_remap();
+        remap[FIRST_PLAYER_COLOR..FIRST_PLAYER_COLOR + PLAYER_COLOR_COUNT].copy_from_slice(colors);
+
+        Some(remap)
+    }
+
+    /// Palette index of the minimap dots of a player, as read from
+    /// `game\tminimap.pcx`.
+    pub fn minimap_color(&self, player: u8) -> Option<u8> {
+        self.pixels.get(player as usize).copied()
+    }
+}
+
+/// Remap tables of a tileset that blend a pixel with the pixel below it.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum RemapTable {
+    /// Orange flames, e.g. of burning Terran buildings.
+    OFire,
+
+    /// Green flames, e.g. of Zerg blood.
+    GFire,
+
+    /// Blue flames, e.g. of burning Protoss buildings.
+    BFire,
+
+    /// Blue explosions.
+    BExpl,
+
+    /// Darker levels of every colour, used for shadows.
+    Dark,
+
+    /// Lighter levels of every colour, used for glow effects.
+    Light,
+}
+
+impl RemapTable {
+    /// Table of the `remapping` column of images.dat.
+    pub fn from_remapping(remapping: u8) -> Option<RemapTable> {
+        match remapping {
+            1 => Some(RemapTable::OFire),
+            2 => Some(RemapTable::GFire),
+            3 => Some(RemapTable::BFire),
+            4 => Some(RemapTable::BExpl),
+            _ => None,
+        }
+    }
+
+    /// Path of the table in the game archives for a tileset, e.g.
+    /// `tileset\badlands\ofire.pcx`.
+    pub fn path(self, tileset_file_name: &str) -> String {
+        let file_name = match self {
+            RemapTable::OFire => "ofire",
+            RemapTable::GFire => "gfire",
+            RemapTable::BFire => "bfire",
+            RemapTable::BExpl => "bexpl",
+            RemapTable::Dark => "dark",
+            RemapTable::Light => "light",
+        };
+
+        format!("tileset\\{}\\{}.pcx", tileset_file_name, file_name)
+    }
+}
+
+/// Rows of remaps that map the palette index below a pixel to the colour
+/// it is drawn with, read from a PCX that is 256 pixels wide.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Remaps(Vec<Remap>);
+
+impl Remaps {
+    /// `None` when the image is not 256 pixels wide.
+    pub fn from_pcx(pcx: &Pcx) -> Option<Remaps> {
+        if pcx.width != 256 {
+            return None;
+        }
+
+        let remaps = pcx
+            .pixels
+            .chunks_exact(256)
+            .map(|row| {
+                let mut remap = [0; 256];
+                remap.copy_from_slice(row);
+                remap
+            })
+            .collect();
+
+        Some(Remaps(remaps))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Remap of a single row, e.g. a level of `dark.pcx` or `light.pcx`.
+    pub fn get(&self, row: usize) -> Option<&Remap> {
+        self.0.get(row)
+    }
+
+    /// Colour of a pixel of a sprite over a background colour for the
+    /// blending tables such as `ofire.pcx`. The table starts at palette
+    /// index 1 since 0 is transparent.
+    pub fn blend(&self, index: u8, background: u8) -> Option<u8> {
+        (index as usize)
+            .checked_sub(1)
+            .and_then(|row| self.get(row))
+            .map(|remap| remap[background as usize])
+    }
+
+    /// Remap that shadows darken the colours below them with, read from
+    /// `dark.pcx`.
+    pub fn shadow(&self) -> Option<&Remap> {
+        self.get(SHADOW_LEVEL)
+    }
+}
+
+struct Header {
+    x_min: u16,
+    y_min: u16,
+    x_max: u16,
+    y_max: u16,
+    bytes_per_line: u16,
+}
+
+fn parse_header(b: &[u8]) -> IResult<&[u8], Header> {
+    let (remaining, _) = tuple((
+        tag(&[0x0A][..]),
+        le_u8,
+        // only run-length encoding
+        tag(&[0x01][..]),
+        // only 8 bits per pixel
+        tag(&[0x08][..]),
+    ))(b)?;
+    let (remaining, (x_min, y_min, x_max, y_max)) =
+        tuple((le_u16, le_u16, le_u16, le_u16))(remaining)?;
+
+    // dpi, the 16-colour palette and a reserved byte
+    let (remaining, _) = take(4 + 48 + 1usize)(remaining)?;
+
+    // only a single colour plane
+    let (remaining, _) = tag(&[0x01][..])(remaining)?;
+    let (remaining, bytes_per_line) = le_u16(remaining)?;
+    let (remaining, _) = take(HEADER_SIZE - 68)(remaining)?;
+
+    Ok((
+        remaining,
+        Header {
+            x_min,
+            y_min,
+            x_max,
+            y_max,
+            bytes_per_line,
+        },
+    ))
+}
+
+/// Decodes `len` bytes of run-length encoded rows. Runs may go on into the
+/// next row.
+fn parse_rows(len: usize) -> impl Fn(&[u8]) -> IResult<&[u8], Vec<u8>> {
+    move |b| {
+        let mut pixels = Vec::with_capacity(len);
+        let mut remaining = b;
+
+        while pixels.len() < len {
+            let (r, c) = le_u8(remaining)?;
+            if c & 0xC0 == 0xC0 {
+                let (r, index) = le_u8(r)?;
+                pixels.resize(pixels.len() + (c & 0x3F) as usize, index);
+                remaining = r;
+            } else {
+                pixels.push(c);
+                remaining = r;
+            }
+        }
+        pixels.truncate(len);
+
+        Ok((remaining, pixels))
+    }
+}
+
+fn parse_color(b: &[u8]) -> IResult<&[u8], [u8; 3]> {
+    map(tuple((le_u8, le_u8, le_u8)), |(r, g, b)| [r, g, b])(b)
+}
+
+fn parse_palette(b: &[u8]) -> IResult<&[u8], Vec<[u8; 3]>> {
+    preceded(tag(&[0x0C][..]), count(parse_color, 256))(b)
+}
+
+pub fn parse_pcx(b: &[u8]) -> IResult<&[u8], Pcx> {
+    let (remaining, header) = verify(parse_header, |header: &Header| {
+        header.x_max >= header.x_min && header.y_max >= header.y_min
+    })(b)?;
+
+    let width = header.x_max - header.x_min + 1;
+    let height = header.y_max - header.y_min + 1;
+    let bytes_per_line = (header.bytes_per_line as usize).max(width as usize);
+
+    let (_, rows) = parse_rows(bytes_per_line * height as usize)(remaining)?;
+    let pixels = rows
+        .chunks_exact(bytes_per_line)
+        .flat_map(|row| &row[..width as usize])
+        .copied()
+        .collect();
+
+    let (palette_start, _) = take(b.len().saturating_sub(PALETTE_SIZE))(b)?;
+    let (remaining, palette) = parse_palette(palette_start)?;
+
+    Ok((
+        remaining,
+        Pcx {
+            width,
+            height,
+            pixels,
+            palette,
+        },
+    ))
+}
+
+pub struct PcxAsset(Option<Pcx>);
+
+impl PcxAsset {
+    pub fn take(&mut self) -> Option<Pcx> {
+        self.0.take()
+    }
+}
+
+pub type PcxHandle = Handle<PcxAsset>;
+
+impl Asset for PcxAsset {
+    const NAME: &'static str = "bw_assets::pcx::PcxAsset";
+    type Data = Self;
+    type HandleStorage = DenseVecStorage<PcxHandle>;
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PcxFormat;
+
+impl Format<PcxAsset> for PcxFormat {
+    fn name(&self) -> &'static str {
+        "PcxFormat"
+    }
+
+    fn import_simple(&self, b: Vec<u8>) -> amethyst::Result<PcxAsset> {
+        let (_, pcx) = parse_pcx(&b).finish().map_err(|err| {
+            amethyst::error::format_err!(
+                "failed to load pcx asset: {} at position {}",
+                err.code.description(),
+                b.len() - err.input.len()
+            )
+        })?;
+
+        Ok(PcxAsset(Some(pcx)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::create_pcx;
+    use spectral::prelude::*;
+
+    #[test]
+    fn it_decodes_run_length_encoded_rows() {
+        // The second row is padded to an even length.
+        let b = create_pcx(3, 2, 4, &[0xC4, 7, 0x01, 0xC2, 0xC1, 0]);
+
+        let (_, pcx) = parse_pcx(&b).unwrap();
+
+        assert_that(&(pcx.width(), pcx.height())).is_equal_to((3, 2));
+        assert_that(&pcx.pixels()).is_equal_to(&[7, 7, 7, 1, 0xC1, 0xC1][..]);
+        assert_that(&pcx.pixel(2, 1)).is_equal_to(Some(0xC1));
+        assert_that(&pcx.palette()[0x42]).is_equal_to([0x42, 0x42, 0x42]);
+    }
+
+    #[test]
+    fn it_builds_the_colours_of_players() {
+        let tunit: Vec<u8> = (0..16).collect();
+        let b = create_pcx(16, 1, 16, &tunit);
+        let (_, pcx) = parse_pcx(&b).unwrap();
+
+        let remap = pcx.player_colors(1).unwrap();
+
+        assert_that(&remap).is_equal_to(identity_remap());
+        assert_that(&pcx.player_colors(2)).is_none();
+        assert_that(&pcx.minimap_color(3)).is_equal_to(Some(3));
+    }
+
+    #[test]
+    fn it_reads_rows_of_remaps() {
+        let mut rows = vec![];
+        for row in 0..2u8 {
+            for _ in 0..4 {
+                rows.extend(&[0xC0 | 63, row + 1, 0xC1, row + 1]);
+            }
+        }
+        let b = create_pcx(256, 2, 256, &rows);
+        let (_, pcx) = parse_pcx(&b).unwrap();
+
+        let remaps = Remaps::from_pcx(&pcx).unwrap();
+
+        assert_that(&remaps.len()).is_equal_to(2);
+        assert_that(&remaps.blend(2, 100)).is_equal_to(Some(2));
+        assert_that(&remaps.blend(0, 100)).is_none();
+        assert_that(&RemapTable::from_remapping(1).map(|table| table.path("badlands")))
+            .is_equal_to(Some("tileset\\badlands\\ofire.pcx".to_string()));
+    }
+
+    #[test]
+    fn it_rejects_other_variants() {
+        let mut b = create_pcx(1, 1, 2, &[1, 0]);
+        b[3] = 4;
+
+        assert_that(&parse_pcx(&b).is_err()).is_true();
+    }
+}
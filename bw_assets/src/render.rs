@@ -0,0 +1,286 @@
+//! Renders maps to images on the CPU
+//!
+//! The terrain is composited the same way the minimap does it, through CV5,
+//! VX4, VR4 and WPE, but without a window or a graphics device so that maps
+//! can be drawn from the command line or in tests. Placed units can be drawn
+//! on top of the terrain as plain rectangles.
+
+use crate::{
+    map::{Map, MegaTile, MEGATILE_PX_SIDE_LEN, MEGATILE_SIDE_LEN, MINITILE_PX_SIDE_LEN},
+    tileset::{CV5s, VR4s, VX4s, WPEs},
+};
+use bw_core::{Unit, UnitId};
+use image::{Rgb, RgbImage};
+use rayon::prelude::*;
+
+/// Colours of the markers of players 1 to 8.
+pub const PLAYER_COLORS: [Rgb<u8>; 8] = [
+    Rgb([244, 4, 4]),
+    Rgb([12, 72, 204]),
+    Rgb([44, 180, 148]),
+    Rgb([136, 64, 156]),
+    Rgb([248, 140, 20]),
+    Rgb([112, 48, 20]),
+    Rgb([204, 224, 208]),
+    Rgb([252, 252, 56]),
+];
+
+/// Colour of the markers of units that no player owns.
+pub const NEUTRAL_COLOR: Rgb<u8> = Rgb([128, 128, 128]);
+
+pub const MINERAL_COLOR: Rgb<u8> = Rgb([0, 228, 252]);
+
+pub const GEYSER_COLOR: Rgb<u8> = Rgb([0, 200, 0]);
+
+/// Side of the marker of a unit in pixels.
+const UNIT_MARKER_SIDE: u32 = 16;
+
+/// Which placed units are drawn on top of the terrain.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Markers {
+    pub units: bool,
+    pub start_locations: bool,
+    pub resources: bool,
+}
+
+impl Markers {
+    pub fn all() -> Markers {
+        Markers {
+            units: true,
+            start_locations: true,
+            resources: true,
+        }
+    }
+}
+
+/// Rectangle that stands in for a placed unit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Marker {
+    /// Center of the marker in pixels.
+    pub position: (u16, u16),
+    pub width: u32,
+    pub height: u32,
+    pub color: Rgb<u8>,
+
+    /// Whether the whole rectangle is drawn or only its outline.
+    pub filled: bool,
+}
+
+impl Marker {
+    /// Marker of a placed unit, or `None` if its kind isn't drawn.
+    ///
+    /// Start locations are outlined with the size of a town hall in the
+    /// colour of their player, resources are filled with the size of their
+    /// footprint and every other unit is a small square in the colour of its
+    /// owner.
+    pub fn for_unit(unit: &Unit, markers: Markers) -> Option<Marker> {
+        let unit_id = unit.unit_id()?;
+        let player_color = PLAYER_COLORS
+            .get(usize::from(unit.owner()))
+            .copied()
+            .unwrap_or(NEUTRAL_COLOR);
+
+        let (width, height, color, filled) = if unit_id == UnitId::SpecialStartLocation {
+            if !markers.start_locations {
+                return None;
+            }
+            (128, 96, player_color, false)
+        } else if unit_id.is_resource() {
+            if !markers.resources {
+                return None;
+            }
+            if unit_id.is_mineral_field() {
+                (64, 32, MINERAL_COLOR, true)
+            } else {
+                (128, 64, GEYSER_COLOR, true)
+            }
+        } else {
+            if !markers.units {
+                return None;
+            }
+            (UNIT_MARKER_SIDE, UNIT_MARKER_SIDE, player_color, true)
+        };
+
+        Some(Marker {
+            position: unit.position(),
+            width,
+            height,
+            color,
+            filled,
+        })
+    }
+
+    /// Draws the marker, leaving out whatever is outside of the image.
+    pub fn draw(&self, image: &mut RgbImage) {
+        let left = self.position.0 as i64 - self.width as i64 / 2;
+        let top = self.position.1 as i64 - self.height as i64 / 2;
+        let right = left + self.width as i64 - 1;
+        let bottom = top + self.height as i64 - 1;
+
+        for y in top.max(0)..=bottom.min(image.height() as i64 - 1) {
+            for x in left.max(0)..=right.min(image.width() as i64 - 1) {
+                let is_outline = x == left || x == right || y == top || y == bottom;
+
+                if self.filled || is_outline {
+                    image.put_pixel(x as u32, y as u32, self.color);
+                }
+            }
+        }
+    }
+}
+
+/// The tileset of a map, which is everything needed to draw its terrain.
+pub struct Renderer<'a> {
+    pub cv5s: &'a CV5s,
+    pub vx4s: &'a VX4s,
+    pub vr4s: &'a VR4s,
+    pub wpes: &'a WPEs,
+}
+
+impl Renderer<'_> {
+    /// The terrain of a map at full resolution with markers on top of it.
+    pub fn render(&self, map: &Map, markers: Markers) -> RgbImage {
+        let mut image = self.render_terrain(&map.megatiles, map.tile_width());
+
+        for marker in map
+            .placed_units
+            .iter()
+            .filter_map(|unit| Marker::for_unit(unit, markers))
+        {
+            marker.draw(&mut image);
+        }
+
+        image
+    }
+
+    /// Terrain of megatiles given row by row, with `width` megatiles in a row.
+    pub fn render_terrain(&self, megatiles: &[MegaTile], width: u32) -> RgbImage {
+        let height = megatiles.len() as u32 / width.max(1);
+        let px_width = width * MEGATILE_PX_SIDE_LEN;
+        let px_height = height * MEGATILE_PX_SIDE_LEN;
+
+        let mut pixels = vec![0; (px_width * px_height * 3) as usize];
+        pixels
+            .par_chunks_mut(px_width.max(1) as usize * 3)
+            .enumerate()
+            .for_each(|(y, row)| {
+                let y = y as u32;
+                let yi = y / MEGATILE_PX_SIDE_LEN;
+                let yj = y % MEGATILE_PX_SIDE_LEN / MINITILE_PX_SIDE_LEN;
+                let yk = y % MINITILE_PX_SIDE_LEN;
+
+                for (x, pixel) in row.chunks_mut(3).enumerate() {
+                    let x = x as u32;
+                    let xi = x / MEGATILE_PX_SIDE_LEN;
+                    let xj = x % MEGATILE_PX_SIDE_LEN / MINITILE_PX_SIDE_LEN;
+                    let xk = x % MINITILE_PX_SIDE_LEN;
+
+                    let megatile = &megatiles[(xi + yi * width) as usize];
+                    let minitiles = &self.vx4s[&self.cv5s[megatile][megatile]];
+                    let minitile = &minitiles[(xj + yj * MEGATILE_SIDE_LEN) as usize];
+
+                    let xk = if minitile.is_horizontally_flipped() {
+                        MINITILE_PX_SIDE_LEN - 1 - xk
+                    } else {
+                        xk
+                    };
+                    let vr4 = &self.vr4s[minitile][(xk + yk * MINITILE_PX_SIDE_LEN) as usize];
+
+                    pixel.copy_from_slice(&self.wpes[vr4].rgb());
+                }
+            });
+
+        RgbImage::from_raw(px_width, px_height, pixels).expect("buffer fits the image")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tileset::{parse_cv5s, parse_vr4s, parse_vx4s, parse_wpes};
+    use bw_core::UnitOwner;
+    use spectral::prelude::*;
+
+    fn placed_unit(unit_id: UnitId, position: (u16, u16), owner: u8) -> Unit {
+        Unit::from_tuple((
+            0,
+            position.0,
+            position.1,
+            Some(unit_id),
+            0,
+            0,
+            0,
+            UnitOwner::new(owner),
+            100,
+            100,
+            100,
+            0,
+            0,
+            0,
+            0,
+        ))
+    }
+
+    #[test]
+    fn it_renders_minitiles_with_flipping() {
+        // A single megatile whose minitiles alternate between a minitile and
+        // its mirror image.
+        let cv5 = vec![0; 52];
+        let vx4: Vec<u8> = (0..16u16)
+            .flat_map(|i| (i % 2).to_le_bytes().to_vec())
+            .collect();
+        // Every row of the minitile has the colours 0 to 7 from left to right.
+        let vr4: Vec<u8> = (0..64).map(|i| i % 8).collect();
+        let wpe: Vec<u8> = (0..8).flat_map(|i| vec![i * 10, 0, 255, 0]).collect();
+
+        let (_, cv5s) = parse_cv5s(&cv5).unwrap();
+        let (_, vx4s) = parse_vx4s(&vx4).unwrap();
+        let (_, vr4s) = parse_vr4s(&vr4).unwrap();
+        let (_, wpes) = parse_wpes(&wpe).unwrap();
+        let renderer = Renderer {
+            cv5s: &cv5s,
+            vx4s: &vx4s,
+            vr4s: &vr4s,
+            wpes: &wpes,
+        };
+
+        let image = renderer.render_terrain(&[MegaTile::from(0), MegaTile::from(0)], 2);
+        assert_that(&image.dimensions()).is_equal_to((64, 32));
+        assert_that(image.get_pixel(1, 0)).is_equal_to(Rgb([10, 0, 255]));
+        assert_that(image.get_pixel(9, 5)).is_equal_to(Rgb([60, 0, 255]));
+        assert_that(image.get_pixel(33, 31)).is_equal_to(Rgb([10, 0, 255]));
+    }
+
+    #[test]
+    fn it_draws_markers_of_placed_units() {
+        let start_location = placed_unit(UnitId::SpecialStartLocation, (64, 48), 1);
+        let mineral_field = placed_unit(UnitId::ResourceMineralField, (0, 0), 11);
+        let marine = placed_unit(UnitId::TerranMarine, (8, 8), 0);
+
+        let markers = Markers {
+            resources: false,
+            ..Markers::all()
+        };
+        assert_that(&Marker::for_unit(&mineral_field, markers)).is_none();
+        assert_that(&Marker::for_unit(&mineral_field, Markers::all()))
+            .is_some()
+            .map(|marker| &marker.color)
+            .is_equal_to(MINERAL_COLOR);
+
+        let mut image = RgbImage::new(128, 128);
+        for unit in &[start_location, mineral_field, marine] {
+            Marker::for_unit(unit, Markers::all())
+                .unwrap()
+                .draw(&mut image);
+        }
+
+        // The mineral field is clipped to the image and the start location is
+        // only outlined.
+        assert_that(image.get_pixel(20, 12)).is_equal_to(MINERAL_COLOR);
+        assert_that(image.get_pixel(127, 95)).is_equal_to(PLAYER_COLORS[1]);
+        assert_that(image.get_pixel(100, 50)).is_equal_to(Rgb([0, 0, 0]));
+        // The marine is drawn over the mineral field it was placed after.
+        assert_that(image.get_pixel(8, 8)).is_equal_to(PLAYER_COLORS[0]);
+        assert_that(image.get_pixel(40, 8)).is_equal_to(Rgb([0, 0, 0]));
+    }
+}
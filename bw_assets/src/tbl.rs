@@ -0,0 +1,374 @@
+//! Types and Parsers for the TBL String Table Format
+//!
+//! TBL files such as `rez\stat_txt.tbl` contain every user facing string in
+//! the game. DAT files reference these strings by index instead of storing
+//! text themselves.
+//!
+//! A TBL file starts with the number of strings followed by an offset for
+//! each string. Strings are null terminated but an entry may hold multiple
+//! parts separated by null bytes, e.g. unit names are stored as
+//! `Name<0>Sub Label<0>StarEdit Category<0>`. The text may also contain
+//! control codes that change the colour or alignment of the text.
+//!
+//! see: http://www.staredit.net/wiki/index.php?title=String_Table
+
+use amethyst::{
+    assets::{Asset, Format, Handle},
+    ecs::DenseVecStorage,
+};
+use nom::{
+    bytes::complete::take, multi::count, number::complete::le_u16, sequence::preceded, Finish,
+    IResult,
+};
+use std::ops::Index;
+
+/// Formatting code embedded inside of TBL text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlCode {
+    /// Changes the colour of the text that follows. The value is the raw
+    /// byte of the code so it can be mapped to the game's text palette.
+    Colour(u8),
+
+    /// Aligns the text to the right.
+    RightAlign,
+
+    /// Centers the text.
+    Center,
+
+    /// The text that follows is not drawn.
+    Invisible,
+
+    /// A code with no known meaning.
+    Unknown(u8),
+}
+
+impl ControlCode {
+    fn from_byte(b: u8) -> Option<ControlCode> {
+        match b {
+            // tabs and new lines are regular text
+            0x09 | 0x0A => None,
+            0x01..=0x08 | 0x0E..=0x11 | 0x15..=0x1F => Some(ControlCode::Colour(b)),
+            0x12 => Some(ControlCode::RightAlign),
+            0x13 => Some(ControlCode::Center),
+            0x0B | 0x14 => Some(ControlCode::Invisible),
+            0x0C | 0x0D => Some(ControlCode::Unknown(b)),
+            _ => None,
+        }
+    }
+}
+
+/// A piece of TBL text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Span {
+    Text(String),
+    Code(ControlCode),
+}
+
+/// Characters of the bytes 0x80 to 0x9F in Windows-1252, e.g. `…` and `’`.
+/// Bytes that Windows-1252 leaves undefined keep their Latin-1 control
+/// character.
+const WINDOWS_1252: [char; 32] = [
+    '\u{20AC}', '\u{0081}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{008D}', '\u{017D}', '\u{008F}',
+    '\u{0090}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{009D}', '\u{017E}', '\u{0178}',
+];
+
+/// Strings are encoded in Windows-1252, which is Latin-1 apart from the
+/// bytes 0x80 to 0x9F.
+fn decode(b: &[u8]) -> String {
+    b.iter()
+        .map(|&c| match c {
+            0x80..=0x9F => WINDOWS_1252[usize::from(c - 0x80)],
+            _ => c as char,
+        })
+        .collect()
+}
+
+/// Name of a unit split into its parts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnitName {
+    /// Name of the unit, i.e. "Terran Marine" or "Jim Raynor".
+    pub name: String,
+
+    /// Sub label shown for heroes and special units, i.e. "Marine". It is
+    /// written as `*` in the table when the unit has none.
+    pub sub_label: Option<String>,
+
+    /// StarEdit category of the unit, i.e. "Ground Units" or "Heroes".
+    pub category: Option<String>,
+}
+
+/// Hotkey prefix of a button string.
+///
+/// Button strings start with the key that triggers the button followed by
+/// a code for the kind of button, i.e. `m<1>Train <3>M<1>arine`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hotkey {
+    pub key: char,
+    pub kind: u8,
+}
+
+/// A single entry of a TBL file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TblEntry(Vec<u8>);
+
+impl TblEntry {
+    fn new(b: &[u8]) -> TblEntry {
+        let end = b.iter().rposition(|&c| c != 0).map_or(0, |i| i + 1);
+
+        TblEntry(b[..end].to_vec())
+    }
+
+    /// Raw bytes of the entry including control codes and null separators.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Parts of the entry that are separated by null bytes.
+    pub fn parts(&self) -> impl Iterator<Item = &[u8]> {
+        self.0.split(|&c| c == 0)
+    }
+
+    /// The first part of the entry split into text and control codes.
+    pub fn spans(&self) -> Vec<Span> {
+        let mut spans = vec![];
+        let mut text = vec![];
+
+        for &c in self.parts().next().unwrap_or(&[]) {
+            match ControlCode::from_byte(c) {
+                Some(code) => {
+                    if !text.is_empty() {
+                        spans.push(Span::Text(decode(&text)));
+                        text.clear();
+                    }
+                    spans.push(Span::Code(code));
+                }
+                None => text.push(c),
+            }
+        }
+
+        if !text.is_empty() {
+            spans.push(Span::Text(decode(&text)));
+        }
+
+        spans
+    }
+
+    /// The first part of the entry with every control code removed.
+    pub fn text(&self) -> String {
+        self.spans()
+            .into_iter()
+            .filter_map(|span| match span {
+                Span::Text(text) => Some(text),
+                Span::Code(_) => None,
+            })
+            .collect()
+    }
+
+    /// Reads the entry as a unit name.
+    pub fn unit_name(&self) -> UnitName {
+        let mut parts = self.parts().skip(1).map(|part| TblEntry::new(part).text());
+
+        let sub_label = parts.next().filter(|s| !s.is_empty() && s != "*");
+        let category = parts.next().filter(|s| !s.is_empty());
+
+        UnitName {
+            name: self.text(),
+            sub_label,
+            category,
+        }
+    }
+
+    /// Hotkey of the entry if it is a button string.
+    pub fn hotkey(&self) -> Option<Hotkey> {
+        match self.0.as_slice() {
+            [key, kind, ..] if *key >= 0x20 && *kind < 0x20 && *kind != 0 => Some(Hotkey {
+                key: *key as char,
+                kind: *kind,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Text of a button string without the hotkey prefix and control codes.
+    pub fn button_text(&self) -> String {
+        match self.hotkey() {
+            Some(_) => TblEntry::new(&self.0[2..]).text(),
+            None => self.text(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Tbl(Vec<TblEntry>);
+
+impl Tbl {
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Entry at a 0 based index, i.e. the name of a unit is at the index of
+    /// its unit id.
+    pub fn get(&self, index: usize) -> Option<&TblEntry> {
+        self.0.get(index)
+    }
+
+    /// Entry referenced by a DAT label.
+    ///
+    /// Labels are 1 based and 0 means the entry has no string.
+    pub fn label(&self, label: u16) -> Option<&TblEntry> {
+        (label as usize)
+            .checked_sub(1)
+            .and_then(|index| self.get(index))
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, TblEntry> {
+        self.0.iter()
+    }
+}
+
+impl Index<usize> for Tbl {
+    type Output = TblEntry;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.0[index]
+    }
+}
+
+pub fn parse_tbl(b: &[u8]) -> IResult<&[u8], Tbl> {
+    let (remaining, str_count) = le_u16(b)?;
+    let (_, str_offsets) = count(le_u16, str_count as usize)(remaining)?;
+
+    let mut sorted_offsets = str_offsets.clone();
+    sorted_offsets.sort_unstable();
+    sorted_offsets.dedup();
+
+    let mut entries = vec![];
+    for offset in str_offsets {
+        // An entry runs until the next entry starts or the file ends.
+        let end = sorted_offsets
+            .binary_search(&offset)
+            .ok()
+            .and_then(|i| sorted_offsets.get(i + 1))
+            .map_or(b.len(), |&next| next as usize);
+
+        let (_, s) = preceded(take(offset), take(end.saturating_sub(offset as usize)))(b)?;
+        entries.push(TblEntry::new(s));
+    }
+
+    let (remaining, _) = take(b.len())(b)?;
+
+    Ok((remaining, Tbl(entries)))
+}
+
+pub struct TblAsset(Option<Tbl>);
+
+impl TblAsset {
+    pub fn take(&mut self) -> Option<Tbl> {
+        self.0.take()
+    }
+}
+
+pub type TblHandle = Handle<TblAsset>;
+
+impl Asset for TblAsset {
+    const NAME: &'static str = "bw_assets::tbl::TblAsset";
+    type Data = Self;
+    type HandleStorage = DenseVecStorage<TblHandle>;
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TblFormat;
+
+impl Format<TblAsset> for TblFormat {
+    fn name(&self) -> &'static str {
+        "TblFormat"
+    }
+
+    fn import_simple(&self, b: Vec<u8>) -> amethyst::Result<TblAsset> {
+        let (_, tbl) = parse_tbl(&b).finish().map_err(|err| {
+            amethyst::error::format_err!(
+                "failed to load tbl asset: {} at position {}",
+                err.code.description(),
+                b.len() - err.input.len()
+            )
+        })?;
+
+        Ok(TblAsset(Some(tbl)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::create_tbl;
+    use byteorder::{LittleEndian, WriteBytesExt};
+    use spectral::prelude::*;
+
+    #[test]
+    fn it_parses_entries_by_index() {
+        let b = create_tbl(&[b"Terran Marine\0*\0Ground Units\0", b"Gauss Rifle\0"]);
+
+        let (_, tbl) = parse_tbl(&b).unwrap();
+
+        assert_that(&tbl.len()).is_equal_to(2);
+        assert_that(&tbl[1].text()).is_equal_to("Gauss Rifle".to_string());
+        assert_that(&tbl.label(2).map(TblEntry::text)).is_equal_to(Some("Gauss Rifle".to_string()));
+        assert_that(&tbl.label(0)).is_none();
+    }
+
+    #[test]
+    fn it_splits_unit_names() {
+        let b = create_tbl(&[
+            b"Terran Marine\0*\0Ground Units\0",
+            b"Jim Raynor\0Marine\0Heroes\0",
+        ]);
+
+        let (_, tbl) = parse_tbl(&b).unwrap();
+
+        assert_that(&tbl[0].unit_name()).is_equal_to(UnitName {
+            name: "Terran Marine".to_string(),
+            sub_label: None,
+            category: Some("Ground Units".to_string()),
+        });
+        assert_that(&tbl[1].unit_name()).is_equal_to(UnitName {
+            name: "Jim Raynor".to_string(),
+            sub_label: Some("Marine".to_string()),
+            category: Some("Heroes".to_string()),
+        });
+    }
+
+    #[test]
+    fn it_strips_control_codes() {
+        let b = create_tbl(&[b"m\x01Train \x03M\x01arine\0"]);
+
+        let (_, tbl) = parse_tbl(&b).unwrap();
+
+        assert_that(&tbl[0].hotkey()).is_equal_to(Some(Hotkey { key: 'm', kind: 1 }));
+        assert_that(&tbl[0].button_text()).is_equal_to("Train Marine".to_string());
+        assert_that(&tbl[0].spans()).contains(Span::Code(ControlCode::Colour(0x03)));
+    }
+
+    #[test]
+    fn it_decodes_windows_1252() {
+        let b = create_tbl(&[b"Don\x92t \x93quote\x94 me\x85 \x80\xE9\0"]);
+
+        let (_, tbl) = parse_tbl(&b).unwrap();
+
+        assert_that(&tbl[0].text()).is_equal_to("Don’t “quote” me… €é".to_string());
+    }
+
+    #[test]
+    fn it_rejects_offsets_past_the_end() {
+        let mut b: Vec<u8> = vec![];
+        b.write_u16::<LittleEndian>(1).unwrap();
+        b.write_u16::<LittleEndian>(100).unwrap();
+
+        assert_that(&parse_tbl(&b).is_err()).is_true();
+    }
+}
@@ -0,0 +1,77 @@
+//! Fixtures shared by the tests of this crate and of the crates that use it.
+//!
+//! Enabled for other crates with the `test-utils` feature.
+
+use crate::{
+    mpq::{ArcMPQ, MPQSource},
+    pcx,
+};
+use rand::Rng;
+use std::path::{Path, PathBuf};
+
+/// A DAT file of `size` random bytes.
+pub fn random_dat(size: usize) -> Vec<u8> {
+    let mut rng = rand::thread_rng();
+
+    (0..size).map(|_| rng.gen()).collect()
+}
+
+/// A string table whose entries are `strings`, which include their trailing
+/// `\0`.
+pub fn create_tbl(strings: &[&[u8]]) -> Vec<u8> {
+    let mut b = (strings.len() as u16).to_le_bytes().to_vec();
+
+    let mut offset = 2 + strings.len() * 2;
+    for s in strings {
+        b.extend(&(offset as u16).to_le_bytes());
+        offset += s.len();
+    }
+    for s in strings {
+        b.extend(*s);
+    }
+
+    b
+}
+
+/// A PCX with encoded rows and a palette where colour `i` is `[i, i, i]`.
+pub fn create_pcx(width: u16, height: u16, bytes_per_line: u16, rows: &[u8]) -> Vec<u8> {
+    let mut b: Vec<u8> = vec![0x0A, 0x05, 0x01, 0x08];
+    for value in &[0, 0, width - 1, height - 1, 72, 72] {
+        b.extend(&value.to_le_bytes());
+    }
+    b.extend(&[0; 48]);
+    b.extend(&[0, 0x01]);
+    b.extend(&bytes_per_line.to_le_bytes());
+    b.resize(pcx::HEADER_SIZE, 0);
+
+    b.extend(rows);
+
+    b.push(0x0C);
+    for i in 0..=255 {
+        b.extend(&[i, i, i]);
+    }
+
+    b
+}
+
+/// Source of the files in the stock MPQ archives.
+///
+/// The archives are read from the directory in `BW_ASSETS_DIR`, or from
+/// `bw_game/assets` if it isn't set. They can't be distributed with the
+/// repository, so the tests on stock files are ignored unless they are run
+/// with `--ignored`, and fail if the archives are missing.
+pub fn stock_mpq_source() -> MPQSource {
+    let assets_dir = std::env::var_os("BW_ASSETS_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("../bw_game/assets"));
+
+    let mut mpq_source = MPQSource::new();
+    for archive in &["STARDAT.MPQ", "BROODAT.MPQ", "patch_rt.mpq"] {
+        let path = assets_dir.join(archive);
+        let mpq = ArcMPQ::from_path(&path)
+            .unwrap_or_else(|err| panic!("failed to open {}: {}", path.display(), err));
+        mpq_source.push_front(mpq);
+    }
+
+    mpq_source
+}
@@ -0,0 +1,243 @@
+use super::VR4s;
+
+/// Where every minitile of a tileset goes in a texture atlas of one or more
+/// square pages.
+///
+/// Each minitile gets a slot with a border of padding around it that repeats
+/// its edge, so that sampling at the edge of a tile never bleeds into its
+/// neighbour. Flipped minitiles share the slot of the minitile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AtlasLayout {
+    minitiles: usize,
+    page_side: u32,
+}
+
+/// Slot of a minitile in an [`AtlasLayout`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AtlasSlot {
+    /// Page that the minitile is on.
+    pub page: usize,
+
+    /// Index of the minitile among the minitiles of its page.
+    pub index: usize,
+
+    /// Left of the minitile in pixels, inside of the padding.
+    pub x: u32,
+
+    /// Top of the minitile in pixels, inside of the padding.
+    pub y: u32,
+}
+
+impl AtlasLayout {
+    /// Largest side of a page in pixels, which every graphics backend
+    /// supports.
+    pub const MAX_PAGE_SIDE: u32 = 2048;
+
+    /// Pixels of padding on every side of a minitile.
+    pub const PADDING: u32 = 1;
+
+    /// Side of a slot in pixels.
+    pub const SLOT_SIDE: u32 = VR4s::MINITILE_SIDE_LENGTH as u32 + Self::PADDING * 2;
+
+    /// Layout with pages as small as possible, up to
+    /// [`AtlasLayout::MAX_PAGE_SIDE`], and as many of them as needed.
+    pub fn new(minitiles: usize) -> AtlasLayout {
+        let mut page_side = Self::SLOT_SIDE.next_power_of_two();
+        while page_side < Self::MAX_PAGE_SIDE
+            && ((page_side / Self::SLOT_SIDE) as usize).pow(2) < minitiles
+        {
+            page_side *= 2;
+        }
+
+        AtlasLayout::with_page_side(minitiles, page_side)
+    }
+
+    /// Layout with pages of a given side in pixels.
+    ///
+    /// Panics if a page can't hold a single slot.
+    pub fn with_page_side(minitiles: usize, page_side: u32) -> AtlasLayout {
+        assert!(
+            page_side >= Self::SLOT_SIDE,
+            "a page of {} pixels can't hold a minitile",
+            page_side
+        );
+
+        AtlasLayout {
+            minitiles,
+            page_side,
+        }
+    }
+
+    pub fn for_vr4s(vr4s: &VR4s) -> AtlasLayout {
+        AtlasLayout::new(vr4s.len())
+    }
+
+    /// Side of every page in pixels.
+    pub fn page_side(&self) -> u32 {
+        self.page_side
+    }
+
+    /// Slots in a row of a page.
+    pub fn columns(&self) -> usize {
+        (self.page_side / Self::SLOT_SIDE) as usize
+    }
+
+    pub fn slots_per_page(&self) -> usize {
+        self.columns() * self.columns()
+    }
+
+    pub fn pages(&self) -> usize {
+        (self.minitiles + self.slots_per_page() - 1) / self.slots_per_page()
+    }
+
+    /// Number of minitiles on a page.
+    pub fn minitiles_on_page(&self, page: usize) -> usize {
+        self.minitiles
+            .saturating_sub(page * self.slots_per_page())
+            .min(self.slots_per_page())
+    }
+
+    /// Slot of a VR4 index, or `None` if the tileset doesn't have it.
+    pub fn slot(&self, minitile: usize) -> Option<AtlasSlot> {
+        if minitile >= self.minitiles {
+            return None;
+        }
+
+        let page = minitile / self.slots_per_page();
+        let index = minitile % self.slots_per_page();
+        let (row, column) = (index / self.columns(), index % self.columns());
+
+        Some(AtlasSlot {
+            page,
+            index,
+            x: column as u32 * Self::SLOT_SIDE + Self::PADDING,
+            y: row as u32 * Self::SLOT_SIDE + Self::PADDING,
+        })
+    }
+
+    /// Page and sprite of a minitile for sprite sheets that have a sprite
+    /// for every minitile of their page followed by the flipped sprites.
+    pub fn sprite(&self, minitile: usize, is_flipped: bool) -> Option<(usize, usize)> {
+        let slot = self.slot(minitile)?;
+
+        if is_flipped {
+            Some((slot.page, slot.index + self.minitiles_on_page(slot.page)))
+        } else {
+            Some((slot.page, slot.index))
+        }
+    }
+
+    /// Texture coordinates of a minitile as left, right, top and bottom, with
+    /// left and right swapped when it is flipped.
+    pub fn uv(&self, minitile: usize, is_flipped: bool) -> Option<(usize, [f32; 4])> {
+        let slot = self.slot(minitile)?;
+        let side = self.page_side as f32;
+        let left = slot.x as f32 / side;
+        let right = (slot.x + VR4s::MINITILE_SIDE_LENGTH as u32) as f32 / side;
+        let top = slot.y as f32 / side;
+        let bottom = (slot.y + VR4s::MINITILE_SIDE_LENGTH as u32) as f32 / side;
+
+        if is_flipped {
+            Some((slot.page, [right, left, top, bottom]))
+        } else {
+            Some((slot.page, [left, right, top, bottom]))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+    use std::collections::HashSet;
+
+    /// Every minitile has a slot of its own within the bounds of its page.
+    fn assert_every_minitile_has_a_slot(layout: &AtlasLayout, minitiles: usize) {
+        let mut slots = HashSet::new();
+
+        for minitile in 0..minitiles {
+            let slot = layout.slot(minitile).unwrap();
+
+            assert!(slot.page < layout.pages());
+            assert!(slot.x >= AtlasLayout::PADDING && slot.y >= AtlasLayout::PADDING);
+            assert!(slot.x + 8 + AtlasLayout::PADDING <= layout.page_side());
+            assert!(slot.y + 8 + AtlasLayout::PADDING <= layout.page_side());
+            assert!(slots.insert((slot.page, slot.x, slot.y)));
+        }
+
+        assert_that(&layout.slot(minitiles)).is_none();
+    }
+
+    #[test]
+    fn it_spreads_minitiles_over_pages() {
+        let small = AtlasLayout::new(100);
+        assert_that(&small.page_side()).is_equal_to(128);
+        assert_that(&small.pages()).is_equal_to(1);
+        assert_every_minitile_has_a_slot(&small, 100);
+
+        // More than a single page of 2048 pixels holds.
+        let large = AtlasLayout::new(100_000);
+        assert_that(&large.page_side()).is_equal_to(2048);
+        assert_that(&large.slots_per_page()).is_equal_to(204 * 204);
+        assert_that(&large.pages()).is_equal_to(3);
+        assert_that(&large.minitiles_on_page(2)).is_equal_to(100_000 - 2 * 204 * 204);
+        assert_every_minitile_has_a_slot(&large, 100_000);
+    }
+
+    #[test]
+    fn it_maps_flipped_minitiles_to_sprites_and_uvs() {
+        let layout = AtlasLayout::with_page_side(30, 32);
+        assert_that(&layout.slots_per_page()).is_equal_to(9);
+        assert_that(&layout.pages()).is_equal_to(4);
+
+        assert_that(&layout.sprite(10, false)).is_equal_to(Some((1, 1)));
+        assert_that(&layout.sprite(10, true)).is_equal_to(Some((1, 10)));
+        assert_that(&layout.sprite(28, true)).is_equal_to(Some((3, 4)));
+
+        let (page, [left, right, top, bottom]) = layout.uv(4, true).unwrap();
+        assert_that(&page).is_equal_to(0);
+        assert_that(&left).is_equal_to(19.0 / 32.0);
+        assert_that(&right).is_equal_to(11.0 / 32.0);
+        assert_that(&top).is_equal_to(11.0 / 32.0);
+        assert_that(&bottom).is_equal_to(19.0 / 32.0);
+    }
+
+    #[test]
+    fn it_has_a_slot_for_every_vr4_index() {
+        // VX4 entries refer to VR4s by the 15 bits above the flip bit.
+        let minitiles = 1 << 15;
+        let layout = AtlasLayout::new(minitiles);
+        assert_every_minitile_has_a_slot(&layout, minitiles);
+
+        let mut sprites = HashSet::new();
+        for minitile in 0..minitiles {
+            for &is_flipped in &[false, true] {
+                let (page, sprite) = layout.sprite(minitile, is_flipped).unwrap();
+                assert!(sprite < 2 * layout.minitiles_on_page(page));
+                assert!(sprites.insert((page, sprite)));
+
+                let (_, uv) = layout.uv(minitile, is_flipped).unwrap();
+                assert!(uv.iter().all(|&uv| (0.0..=1.0).contains(&uv)));
+            }
+        }
+    }
+
+    #[test]
+    #[ignore = "needs stock MPQs"]
+    fn it_has_a_slot_for_every_minitile_of_every_tileset() {
+        use crate::{map::Tileset, test_utils::stock_mpq_source, tileset::parse_vr4s};
+        use amethyst::assets::Source;
+        use num_traits::FromPrimitive;
+
+        let mpq_source = stock_mpq_source();
+
+        for tileset in (0..8).filter_map(Tileset::from_u8) {
+            let path = format!("tileset\\{}.vr4", tileset.file_name());
+            let b = mpq_source.load(&path).unwrap();
+            let (_, vr4s) = parse_vr4s(&b).unwrap();
+
+            let layout = AtlasLayout::for_vr4s(&vr4s);
+            assert_every_minitile_has_a_slot(&layout, vr4s.len());
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use crate::map::MegaTile;
+
+/// CV5 group of the megatiles that are covered by creep.
+pub const CREEP_FILL_GROUP: u16 = 1;
+
+/// Number of megatiles in [`CREEP_FILL_GROUP`] that creep picks from so that
+/// it doesn't repeat.
+pub const CREEP_FILL_VARIANTS: u16 = 13;
+
+/// First CV5 group of the edges that creep draws over the megatiles next to
+/// it. The edges continue in the groups after it, 16 megatiles per group.
+pub const CREEP_EDGE_GROUP: u16 = 2;
+
+/// Megatile of creep that covers the whole tile.
+pub fn creep_fill_megatile(variant: u16) -> MegaTile {
+    MegaTile::from((CREEP_FILL_GROUP << 4) | (variant % CREEP_FILL_VARIANTS))
+}
+
+/// Megatile of the edge of creep next to a tile without creep.
+///
+/// `neighbours` has a bit for every tile around it that has creep, clockwise
+/// from the tile above: N, NE, E, SE, S, SW, W, NW. Edges that creep touches
+/// from the sides come first, one for each combination of sides, followed by
+/// the edges that creep only touches at the corners. Returns `None` when no
+/// neighbour has creep.
+pub fn creep_edge_megatile(neighbours: u8) -> Option<MegaTile> {
+    let sides = (0..4).fold(0, |mask, i| mask | ((neighbours >> (i * 2)) & 1) << i) as u16;
+    let corners = (0..4).fold(0, |mask, i| mask | ((neighbours >> (i * 2 + 1)) & 1) << i) as u16;
+
+    let edge = if sides != 0 {
+        sides - 1
+    } else if corners != 0 {
+        15 + corners - 1
+    } else {
+        return None;
+    };
+
+    Some(MegaTile::from(
+        ((CREEP_EDGE_GROUP + edge / 16) << 4) | (edge % 16),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn it_picks_creep_megatiles() {
+        let fill = creep_fill_megatile(14);
+        assert_that(&fill.group_index()).is_equal_to(1);
+        assert_that(&fill.subtile_index()).is_equal_to(1);
+
+        assert_that(&creep_edge_megatile(0)).is_none();
+
+        // Creep above the tile.
+        let edge = creep_edge_megatile(0b0000_0001).unwrap();
+        assert_that(&edge.group_index()).is_equal_to(2);
+        assert_that(&edge.subtile_index()).is_equal_to(0);
+
+        // Creep on every side, the corners don't matter.
+        let edge = creep_edge_megatile(0xFF).unwrap();
+        assert_that(&edge.group_index()).is_equal_to(2);
+        assert_that(&edge.subtile_index()).is_equal_to(14);
+
+        // Creep only at the north west corner.
+        let edge = creep_edge_megatile(0b1000_0000).unwrap();
+        assert_that(&edge.group_index()).is_equal_to(3);
+        assert_that(&edge.subtile_index()).is_equal_to(6);
+    }
+}
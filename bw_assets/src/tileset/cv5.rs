@@ -14,6 +14,7 @@ use nom::{Finish, IResult};
 
 use std::ops::Index;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BuildFlag {
     Buildable,
     Creep,
@@ -224,10 +225,30 @@ pub enum CV5 {
     TileMetadata(TileMetadata),
 }
 
+impl CV5 {
+    pub fn build_flag(&self) -> BuildFlag {
+        match self {
+            CV5::Doodad(doodad) => doodad.build_flag(),
+            CV5::TileMetadata(tile_metadata) => tile_metadata.build_flag(),
+        }
+    }
+}
+
 /// A list of CV5. Each CV5 is referenced by the MXTM field from CHK.
 #[derive(Debug)]
 pub struct CV5s(Vec<CV5>);
 
+impl CV5s {
+    /// Doodad with an index into dddata.dat, which is how placed doodads
+    /// refer to it.
+    pub fn doodad(&self, dddata_bin_idx: u16) -> Option<&Doodad> {
+        self.0.iter().find_map(|cv5| match cv5 {
+            CV5::Doodad(doodad) if doodad.dddata_bin_idx() == dddata_bin_idx => Some(doodad),
+            _ => None,
+        })
+    }
+}
+
 impl Index<MegaTile> for CV5s {
     type Output = CV5;
 
@@ -244,7 +265,7 @@ impl Index<&MegaTile> for CV5s {
     }
 }
 
-fn parse_cv5s(b: &[u8]) -> IResult<&[u8], CV5s> {
+pub fn parse_cv5s(b: &[u8]) -> IResult<&[u8], CV5s> {
     let (remaining, cv5s_data) = all_consuming(many0(parse_cv5))(b)?;
 
     let cv5s = cv5s_data
@@ -0,0 +1,133 @@
+use crate::map::Tileset;
+
+/// Range of palette indices whose colours rotate by one every few frames,
+/// which animates water, lava and the twilight tileset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaletteCycle {
+    /// First index of the range.
+    pub first: u8,
+    /// Last index of the range, inclusive.
+    pub last: u8,
+    /// Game frames between two rotations.
+    pub delay: u8,
+}
+
+impl PaletteCycle {
+    const fn new(first: u8, last: u8, delay: u8) -> PaletteCycle {
+        PaletteCycle { first, last, delay }
+    }
+
+    pub fn contains(&self, index: u8) -> bool {
+        (self.first..=self.last).contains(&index)
+    }
+}
+
+/// Ranges shared by every tileset with water or lava: a slow cycle for the
+/// deep colours and a faster one for the shallow colours.
+const LIQUID_CYCLES: [PaletteCycle; 2] = [PaletteCycle::new(1, 6, 8), PaletteCycle::new(7, 13, 6)];
+
+/// Palette cycles of a tileset. Space platform and installation don't have
+/// any.
+pub fn palette_cycles(tileset: &Tileset) -> &'static [PaletteCycle] {
+    match tileset {
+        Tileset::Badlands
+        | Tileset::Ashworld
+        | Tileset::Jungle
+        | Tileset::Desert
+        | Tileset::Arctic
+        | Tileset::Twilight => &LIQUID_CYCLES,
+        Tileset::SpacePlatform | Tileset::Installation => &[],
+    }
+}
+
+/// Palette of a tileset as it changes from frame to frame.
+///
+/// Terrain drawn with [`PaletteCycler::rgb`] animates when it is redrawn
+/// every time [`PaletteCycler::tick`] returns `true`.
+#[derive(Debug, Clone)]
+pub struct PaletteCycler {
+    palette: Vec<[u8; 3]>,
+    cycles: Vec<(PaletteCycle, u8)>,
+}
+
+impl PaletteCycler {
+    pub fn new(palette: Vec<[u8; 3]>, tileset: &Tileset) -> PaletteCycler {
+        PaletteCycler::with_cycles(palette, palette_cycles(tileset))
+    }
+
+    pub fn with_cycles(palette: Vec<[u8; 3]>, cycles: &[PaletteCycle]) -> PaletteCycler {
+        PaletteCycler {
+            palette,
+            cycles: cycles.iter().map(|&cycle| (cycle, cycle.delay)).collect(),
+        }
+    }
+
+    /// Current colours of the palette.
+    pub fn rgb(&self) -> &[[u8; 3]] {
+        &self.palette
+    }
+
+    /// Whether the colour of the index changes over time.
+    pub fn is_cycled(&self, index: u8) -> bool {
+        self.cycles.iter().any(|(cycle, _)| cycle.contains(index))
+    }
+
+    /// Advances by a game frame and returns whether any colour changed.
+    pub fn tick(&mut self) -> bool {
+        let mut changed = false;
+
+        for (cycle, timer) in &mut self.cycles {
+            *timer = timer.saturating_sub(1);
+            if *timer > 0 {
+                continue;
+            }
+            *timer = cycle.delay;
+
+            let first = cycle.first as usize;
+            let last = (cycle.last as usize).min(self.palette.len().saturating_sub(1));
+            if first < last {
+                self.palette[first..=last].rotate_right(1);
+                changed = true;
+            }
+        }
+
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    fn palette() -> Vec<[u8; 3]> {
+        (0..16).map(|i| [i, i, i]).collect()
+    }
+
+    #[test]
+    fn it_rotates_ranges_after_their_delay() {
+        let mut cycler = PaletteCycler::with_cycles(palette(), &[PaletteCycle::new(2, 4, 2)]);
+
+        assert_that(&cycler.tick()).is_false();
+        assert_that(&cycler.tick()).is_true();
+        assert_that(&cycler.rgb()[1..6].to_vec())
+            .is_equal_to(vec![[1; 3], [4; 3], [2; 3], [3; 3], [5; 3]]);
+
+        cycler.tick();
+        cycler.tick();
+        cycler.tick();
+        cycler.tick();
+        assert_that(&cycler.rgb().to_vec()).is_equal_to(palette());
+    }
+
+    #[test]
+    fn it_only_cycles_tilesets_with_liquids() {
+        let mut cycler = PaletteCycler::new(palette(), &Tileset::Installation);
+        assert_that(&(0..100).any(|_| cycler.tick())).is_false();
+        assert_that(&cycler.is_cycled(3)).is_false();
+
+        let cycler = PaletteCycler::new(palette(), &Tileset::Jungle);
+        assert_that(&cycler.is_cycled(3)).is_true();
+        assert_that(&cycler.is_cycled(14)).is_false();
+    }
+}
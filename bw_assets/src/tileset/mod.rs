@@ -7,18 +7,30 @@
 //! and is also reference VR4. Each VR4 is a reference to 64 WPEs which represent
 //! the color of the pixel. VF4 on the other hand show the gameplay flags such as
 //! walkable, elevation, blocks view, etc...
+//!
+//! The palette isn't static: water, lava and the twilight tileset animate by
+//! rotating ranges of WPEs, see [`PaletteCycler`].
 
+mod atlas;
+mod creep;
 mod cv5;
+mod cycle;
 mod vf4;
 mod vr4;
 mod vx4;
 mod wpe;
 
+pub use self::atlas::{AtlasLayout, AtlasSlot};
+pub use self::creep::{
+    creep_edge_megatile, creep_fill_megatile, CREEP_EDGE_GROUP, CREEP_FILL_GROUP,
+    CREEP_FILL_VARIANTS,
+};
 pub use self::cv5::{
-    BuildFlag, CV5Data, CV5Format, CV5s, CV5sAsset, CV5sHandle, Doodad, MinitileReference,
-    OverlayFlag, TileMetadata, CV5,
+    parse_cv5s, BuildFlag, CV5Data, CV5Format, CV5s, CV5sAsset, CV5sHandle, Doodad,
+    MinitileReference, OverlayFlag, TileMetadata, CV5,
 };
-pub use self::vf4::{VF4Format, VF4s, VF4sAsset, VF4sHandle, VF4};
-pub use self::vr4::{VR4Format, VR4s, VR4sAsset, VR4sHandle, VR4sIterator, VR4};
-pub use self::vx4::{VX4s, VX4sAsset, VX4sAssetFormat, VX4sHandle, VX4};
-pub use self::wpe::{WPEFormat, WPEs, WPEsAsset, WPEsHandle, WPE};
+pub use self::cycle::{palette_cycles, PaletteCycle, PaletteCycler};
+pub use self::vf4::{Elevation, VF4Format, VF4s, VF4sAsset, VF4sHandle, VF4};
+pub use self::vr4::{parse_vr4s, VR4Format, VR4s, VR4sAsset, VR4sHandle, VR4sIterator, VR4};
+pub use self::vx4::{parse_vx4s, VX4s, VX4sAsset, VX4sAssetFormat, VX4sHandle, VX4};
+pub use self::wpe::{parse_wpes, WPEFormat, WPEs, WPEsAsset, WPEsHandle, WPE};
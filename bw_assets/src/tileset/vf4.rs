@@ -16,6 +16,14 @@ use super::MinitileReference;
 #[derive(Debug)]
 pub struct VF4(u16);
 
+/// Height of the ground of a minitile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Elevation {
+    Low,
+    Mid,
+    High,
+}
+
 #[derive(Debug)]
 pub struct VF4s(Vec<Vec<VF4>>);
 
@@ -61,6 +69,16 @@ impl VF4 {
         return self.0 & VF4::LOW == VF4::LOW;
     }
 
+    /// Elevation of the minitile, which is low unless only one of the mid
+    /// and high flags is set.
+    pub fn elevation(&self) -> Elevation {
+        match (self.is_elevation_mid(), self.is_elevation_high()) {
+            (true, false) => Elevation::Mid,
+            (false, true) => Elevation::High,
+            _ => Elevation::Low,
+        }
+    }
+
     pub fn blocks_view(&self) -> bool {
         return self.0 & VF4::BLOCKS_VIEW == VF4::BLOCKS_VIEW;
     }
@@ -82,7 +82,7 @@ impl<'a> Iterator for VR4sIterator<'a> {
     }
 }
 
-fn parse_vr4s(b: &[u8]) -> IResult<&[u8], VR4s> {
+pub fn parse_vr4s(b: &[u8]) -> IResult<&[u8], VR4s> {
     all_consuming(map(many0(count(parse_vr4, VR4s::BLOCK_SIZE)), VR4s))(b)
 }
 
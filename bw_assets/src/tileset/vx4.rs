@@ -64,7 +64,7 @@ impl Index<&MinitileReference> for VX4s {
     }
 }
 
-fn parse_vx4s(b: &[u8]) -> IResult<&[u8], VX4s> {
+pub fn parse_vx4s(b: &[u8]) -> IResult<&[u8], VX4s> {
     all_consuming(map(many0(count(parse_vx4, VX4s::BLOCK_SIZE)), VX4s))(b)
 }
 
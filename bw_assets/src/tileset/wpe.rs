@@ -60,6 +60,13 @@ fn parse_wpe(b: &[u8]) -> IResult<&[u8], WPE> {
 #[derive(Debug)]
 pub struct WPEs(Vec<WPE>);
 
+impl WPEs {
+    /// Raw rgb values of every colour, e.g. to draw GRP frames with.
+    pub fn rgb(&self) -> Vec<[u8; 3]> {
+        self.0.iter().map(WPE::rgb).collect()
+    }
+}
+
 impl Index<VR4> for WPEs {
     type Output = WPE;
 
@@ -76,7 +83,7 @@ impl Index<&VR4> for WPEs {
     }
 }
 
-fn parse_wpes(b: &[u8]) -> IResult<&[u8], WPEs> {
+pub fn parse_wpes(b: &[u8]) -> IResult<&[u8], WPEs> {
     all_consuming(map(many0(parse_wpe), WPEs))(b)
 }
 
@@ -1,5 +1,10 @@
 mod controller;
+mod tech_tree;
 mod unit;
 
 pub use controller::{Controller, Controllers};
-pub use unit::{Unit, UnitId, UnitOwner};
+pub use tech_tree::{Prerequisite, Producer, Requirements, TechId, UpgradeId};
+pub use unit::{
+    ParseUnitIdError, Race, Unit, UnitId, UnitLink, UnitOwner, UnitPropertyFlags,
+    UnitRelationFlags, UnitStateFlags,
+};
@@ -0,0 +1,236 @@
+mod requirements;
+mod tech_id;
+mod upgrade_id;
+
+pub use tech_id::TechId;
+pub use upgrade_id::UpgradeId;
+
+use crate::UnitId;
+
+/// How a unit comes to be, or which unit researches a tech or an upgrade.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Producer {
+    /// Trained, built or researched by a unit that is still there afterwards,
+    /// e.g. Marines by a Barracks or a Barracks by an SCV.
+    Unit(UnitId),
+
+    /// Morphed from a unit that is used up, e.g. Zerglings from a Larva, a
+    /// Spawning Pool from a Drone or a Lair from a Hatchery.
+    Morph(UnitId),
+
+    /// Merged from two units, e.g. an Archon from two High Templar.
+    Merge(UnitId),
+
+    /// Built as an add-on of a building, e.g. a Machine Shop by a Factory.
+    AddOn(UnitId),
+}
+
+impl Producer {
+    pub fn unit_id(self) -> UnitId {
+        match self {
+            Producer::Unit(unit_id)
+            | Producer::Morph(unit_id)
+            | Producer::Merge(unit_id)
+            | Producer::AddOn(unit_id) => unit_id,
+        }
+    }
+}
+
+/// Everything that has to be there before a unit can be made, or a tech or
+/// an upgrade level can be researched.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Requirements {
+    pub producer: Producer,
+
+    /// Units that must exist besides the producer, e.g. an Academy for
+    /// Firebats.
+    pub units: &'static [UnitId],
+
+    /// Add-on that the producer must have, e.g. a Machine Shop for Siege
+    /// Tanks.
+    pub add_on: Option<UnitId>,
+
+    /// Tech that must be researched, e.g. Lurker Aspect for Lurkers.
+    pub tech: Option<TechId>,
+}
+
+/// A node of the tech tree.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Prerequisite {
+    Unit(UnitId),
+    Tech(TechId),
+
+    /// An upgrade at a level starting from 1.
+    Upgrade(UpgradeId, u8),
+}
+
+impl Prerequisite {
+    /// Requirements of the stock game. `None` for nodes that can't be made
+    /// or researched, e.g. heroes or spells that every unit starts with.
+    pub fn requirements(self) -> Option<Requirements> {
+        match self {
+            Prerequisite::Unit(unit_id) => requirements::unit(unit_id),
+            Prerequisite::Tech(tech_id) => requirements::tech(tech_id),
+            Prerequisite::Upgrade(upgrade_id, level) => {
+                if level == 0 || level > upgrade_id.max_level() {
+                    return None;
+                }
+
+                requirements::upgrade(upgrade_id, level)
+            }
+        }
+    }
+
+    /// Nodes that must exist right before this one, e.g. a Probe and a
+    /// Stargate before a Fleet Beacon.
+    pub fn prerequisites(self) -> Vec<Prerequisite> {
+        let requirements = match self.requirements() {
+            Some(requirements) => requirements,
+            None => return vec![],
+        };
+
+        let mut prerequisites = vec![Prerequisite::Unit(requirements.producer.unit_id())];
+        prerequisites.extend(
+            requirements
+                .add_on
+                .iter()
+                .chain(requirements.units)
+                .map(|&unit_id| Prerequisite::Unit(unit_id)),
+        );
+        prerequisites.extend(requirements.tech.map(Prerequisite::Tech));
+        if let Prerequisite::Upgrade(upgrade_id, level) = self {
+            if level > 1 {
+                prerequisites.push(Prerequisite::Upgrade(upgrade_id, level - 1));
+            }
+        }
+
+        prerequisites
+    }
+
+    /// Every node that leads up to this one, each before the nodes that need
+    /// it, e.g. everything from a Drone to Lurker Aspect for Lurkers.
+    pub fn prerequisite_chain(self) -> Vec<Prerequisite> {
+        let mut chain = vec![];
+        self.visit(&mut chain);
+        chain.retain(|&prerequisite| prerequisite != self);
+
+        chain
+    }
+
+    fn visit(self, chain: &mut Vec<Prerequisite>) {
+        if chain.contains(&self) {
+            return;
+        }
+
+        // Guards against the cycles between workers and the buildings that
+        // train them, e.g. an SCV needs a Command Center that needs an SCV.
+        chain.push(self);
+        let position = chain.len() - 1;
+        for prerequisite in self.prerequisites() {
+            prerequisite.visit(chain);
+        }
+
+        let node = chain.remove(position);
+        chain.push(node);
+    }
+}
+
+impl From<UnitId> for Prerequisite {
+    fn from(unit_id: UnitId) -> Self {
+        Prerequisite::Unit(unit_id)
+    }
+}
+
+impl From<TechId> for Prerequisite {
+    fn from(tech_id: TechId) -> Self {
+        Prerequisite::Tech(tech_id)
+    }
+}
+
+impl UnitId {
+    /// Whether a unit of this type counts as a `required` unit, which is the
+    /// case for the unit itself and for the units it morphs into, e.g. a Hive
+    /// counts as a Lair and a Hatchery.
+    pub fn satisfies(self, required: UnitId) -> bool {
+        match (self, required) {
+            (UnitId::ZergLair, UnitId::ZergHatchery)
+            | (UnitId::ZergHive, UnitId::ZergHatchery)
+            | (UnitId::ZergHive, UnitId::ZergLair)
+            | (UnitId::ZergGreaterSpire, UnitId::ZergSpire) => true,
+            _ => self == required,
+        }
+    }
+}
+
+impl UpgradeId {
+    /// Highest level of the upgrade in the stock game.
+    pub fn max_level(self) -> u8 {
+        // The weapon, armor and shield upgrades come first.
+        if self as u8 <= UpgradeId::ProtossPlasmaShields as u8 {
+            3
+        } else {
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    fn units(unit_ids: &[UnitId]) -> Vec<Prerequisite> {
+        unit_ids.iter().map(|&unit_id| unit_id.into()).collect()
+    }
+
+    #[test]
+    fn it_lists_what_must_exist_before_a_unit() {
+        assert_that(&Prerequisite::from(UnitId::ProtossFleetBeacon).prerequisites())
+            .is_equal_to(units(&[UnitId::ProtossProbe, UnitId::ProtossStargate]));
+        assert_that(&Prerequisite::from(UnitId::TerranSiegeTankTankMode).prerequisites())
+            .is_equal_to(units(&[UnitId::TerranFactory, UnitId::TerranMachineShop]));
+        assert_that(&Prerequisite::from(UnitId::HeroSarahKerrigan).prerequisites()).is_empty();
+    }
+
+    #[test]
+    fn it_lists_the_full_chain_dependencies_first() {
+        let chain = Prerequisite::from(UnitId::ZergLurker).prerequisite_chain();
+
+        for required in &[
+            Prerequisite::Unit(UnitId::ZergDrone),
+            Prerequisite::Unit(UnitId::ZergHatchery),
+            Prerequisite::Unit(UnitId::ZergSpawningPool),
+            Prerequisite::Unit(UnitId::ZergLair),
+            Prerequisite::Unit(UnitId::ZergHydraliskDen),
+            Prerequisite::Unit(UnitId::ZergHydralisk),
+            Prerequisite::Tech(TechId::LurkerAspect),
+        ] {
+            assert_that(&chain).contains(required);
+        }
+        assert_that(&chain).does_not_contain(Prerequisite::Unit(UnitId::ZergLurker));
+
+        let position = |prerequisite| chain.iter().position(|&p| p == prerequisite);
+        assert_that(&position(Prerequisite::Unit(UnitId::ZergLair)))
+            .is_less_than(position(Prerequisite::Tech(TechId::LurkerAspect)));
+        assert_that(&position(Prerequisite::Unit(UnitId::ZergSpawningPool)))
+            .is_less_than(position(Prerequisite::Unit(UnitId::ZergLair)));
+    }
+
+    #[test]
+    fn it_requires_the_previous_level_of_an_upgrade() {
+        let prerequisites = Prerequisite::Upgrade(UpgradeId::ZergMissileAttacks, 3).prerequisites();
+
+        assert_that(&prerequisites).is_equal_to(vec![
+            Prerequisite::Unit(UnitId::ZergEvolutionChamber),
+            Prerequisite::Unit(UnitId::ZergHive),
+            Prerequisite::Upgrade(UpgradeId::ZergMissileAttacks, 2),
+        ]);
+        assert_that(&Prerequisite::Upgrade(UpgradeId::MetabolicBoost, 2).requirements()).is_none();
+    }
+
+    #[test]
+    fn it_counts_morphed_buildings_as_their_earlier_forms() {
+        assert_that(&UnitId::ZergHive.satisfies(UnitId::ZergLair)).is_true();
+        assert_that(&UnitId::ZergLair.satisfies(UnitId::ZergHive)).is_false();
+    }
+}
@@ -0,0 +1,231 @@
+//! Requirements of the stock game.
+//!
+//! The game keeps these in the executable rather than in the DAT files, so
+//! they are written out by hand here.
+
+use super::{Producer, Requirements, TechId, UpgradeId};
+use crate::UnitId::{self, *};
+
+const fn requires(producer: Producer, units: &'static [UnitId]) -> Requirements {
+    Requirements {
+        producer,
+        units,
+        add_on: None,
+        tech: None,
+    }
+}
+
+const fn trained_by(unit: UnitId, units: &'static [UnitId]) -> Requirements {
+    requires(Producer::Unit(unit), units)
+}
+
+const fn morphed_from(unit: UnitId, units: &'static [UnitId]) -> Requirements {
+    requires(Producer::Morph(unit), units)
+}
+
+pub(super) fn unit(unit_id: UnitId) -> Option<Requirements> {
+    let requirements = match unit_id {
+        TerranScv => trained_by(TerranCommandCenter, &[]),
+        TerranMarine => trained_by(TerranBarracks, &[]),
+        TerranFirebat | TerranMedic => trained_by(TerranBarracks, &[TerranAcademy]),
+        TerranGhost => trained_by(TerranBarracks, &[TerranAcademy, TerranCovertOps]),
+        TerranVulture => trained_by(TerranFactory, &[]),
+        TerranSiegeTankTankMode => Requirements {
+            add_on: Some(TerranMachineShop),
+            ..trained_by(TerranFactory, &[])
+        },
+        TerranSiegeTankSiegeMode => Requirements {
+            tech: Some(TechId::TankSiegeMode),
+            ..morphed_from(TerranSiegeTankTankMode, &[])
+        },
+        TerranGoliath => trained_by(TerranFactory, &[TerranArmory]),
+        TerranWraith => trained_by(TerranStarport, &[]),
+        TerranDropship => Requirements {
+            add_on: Some(TerranControlTower),
+            ..trained_by(TerranStarport, &[])
+        },
+        TerranScienceVessel => Requirements {
+            add_on: Some(TerranControlTower),
+            ..trained_by(TerranStarport, &[TerranScienceFacility])
+        },
+        TerranBattlecruiser => Requirements {
+            add_on: Some(TerranControlTower),
+            ..trained_by(TerranStarport, &[TerranPhysicsLab])
+        },
+        TerranValkyrie => Requirements {
+            add_on: Some(TerranControlTower),
+            ..trained_by(TerranStarport, &[TerranArmory])
+        },
+        TerranNuclearMissile => trained_by(TerranNuclearSilo, &[]),
+
+        TerranCommandCenter | TerranSupplyDepot | TerranRefinery => trained_by(TerranScv, &[]),
+        TerranBarracks | TerranEngineeringBay => trained_by(TerranScv, &[TerranCommandCenter]),
+        TerranBunker | TerranAcademy | TerranFactory => trained_by(TerranScv, &[TerranBarracks]),
+        TerranMissileTurret => trained_by(TerranScv, &[TerranEngineeringBay]),
+        TerranStarport | TerranArmory => trained_by(TerranScv, &[TerranFactory]),
+        TerranScienceFacility => trained_by(TerranScv, &[TerranStarport]),
+
+        TerranComsatStation => requires(Producer::AddOn(TerranCommandCenter), &[TerranAcademy]),
+        TerranNuclearSilo => requires(Producer::AddOn(TerranCommandCenter), &[TerranCovertOps]),
+        TerranMachineShop => requires(Producer::AddOn(TerranFactory), &[]),
+        TerranControlTower => requires(Producer::AddOn(TerranStarport), &[]),
+        TerranCovertOps | TerranPhysicsLab => requires(Producer::AddOn(TerranScienceFacility), &[]),
+
+        ZergLarva => trained_by(ZergHatchery, &[]),
+        ZergDrone | ZergOverlord => morphed_from(ZergLarva, &[]),
+        ZergZergling => morphed_from(ZergLarva, &[ZergSpawningPool]),
+        ZergHydralisk => morphed_from(ZergLarva, &[ZergHydraliskDen]),
+        ZergMutalisk | ZergScourge => morphed_from(ZergLarva, &[ZergSpire]),
+        ZergQueen => morphed_from(ZergLarva, &[ZergQueensNest]),
+        ZergUltralisk => morphed_from(ZergLarva, &[ZergUltraliskCavern]),
+        ZergDefiler => morphed_from(ZergLarva, &[ZergDefilerMound]),
+        ZergLurker => Requirements {
+            tech: Some(TechId::LurkerAspect),
+            ..morphed_from(ZergHydralisk, &[ZergHydraliskDen])
+        },
+        ZergGuardian | ZergDevourer => morphed_from(ZergMutalisk, &[ZergGreaterSpire]),
+        ZerginfestedTerran => trained_by(ZergInfestedCommandCenter, &[]),
+
+        ZergHatchery | ZergExtractor | ZergCreepColony => morphed_from(ZergDrone, &[]),
+        ZergSpawningPool | ZergEvolutionChamber => morphed_from(ZergDrone, &[ZergHatchery]),
+        ZergHydraliskDen => morphed_from(ZergDrone, &[ZergSpawningPool]),
+        ZergSpire | ZergQueensNest => morphed_from(ZergDrone, &[ZergLair]),
+        ZergNydusCanal | ZergUltraliskCavern | ZergDefilerMound => {
+            morphed_from(ZergDrone, &[ZergHive])
+        }
+        ZergSunkenColony => morphed_from(ZergCreepColony, &[ZergSpawningPool]),
+        ZergSporeColony => morphed_from(ZergCreepColony, &[ZergEvolutionChamber]),
+        ZergLair => morphed_from(ZergHatchery, &[ZergSpawningPool]),
+        ZergHive => morphed_from(ZergLair, &[ZergQueensNest]),
+        ZergGreaterSpire => morphed_from(ZergSpire, &[ZergHive]),
+
+        ProtossProbe => trained_by(ProtossNexus, &[]),
+        ProtossZealot => trained_by(ProtossGateway, &[]),
+        ProtossDragoon => trained_by(ProtossGateway, &[ProtossCyberneticsCore]),
+        ProtossHighTemplar | ProtossDarkTemplar => {
+            trained_by(ProtossGateway, &[ProtossTemplarArchives])
+        }
+        ProtossArchon => requires(Producer::Merge(ProtossHighTemplar), &[]),
+        ProtossDarkArchon => requires(Producer::Merge(ProtossDarkTemplar), &[]),
+        ProtossShuttle => trained_by(ProtossRoboticsFacility, &[]),
+        ProtossReaver => trained_by(ProtossRoboticsFacility, &[ProtossRoboticsSupportBay]),
+        ProtossObserver => trained_by(ProtossRoboticsFacility, &[ProtossObservatory]),
+        ProtossScout | ProtossCorsair => trained_by(ProtossStargate, &[]),
+        ProtossCarrier => trained_by(ProtossStargate, &[ProtossFleetBeacon]),
+        ProtossArbiter => trained_by(ProtossStargate, &[ProtossArbiterTribunal]),
+        ProtossInterceptor => trained_by(ProtossCarrier, &[]),
+        ProtossScarab => trained_by(ProtossReaver, &[]),
+
+        ProtossNexus | ProtossPylon | ProtossAssimilator => trained_by(ProtossProbe, &[]),
+        ProtossGateway | ProtossForge => trained_by(ProtossProbe, &[ProtossNexus]),
+        ProtossShieldBattery | ProtossCyberneticsCore => {
+            trained_by(ProtossProbe, &[ProtossGateway])
+        }
+        ProtossPhotonCannon => trained_by(ProtossProbe, &[ProtossForge]),
+        ProtossRoboticsFacility | ProtossStargate | ProtossCitadelofAdun => {
+            trained_by(ProtossProbe, &[ProtossCyberneticsCore])
+        }
+        ProtossObservatory | ProtossRoboticsSupportBay => {
+            trained_by(ProtossProbe, &[ProtossRoboticsFacility])
+        }
+        ProtossFleetBeacon => trained_by(ProtossProbe, &[ProtossStargate]),
+        ProtossTemplarArchives => trained_by(ProtossProbe, &[ProtossCitadelofAdun]),
+        ProtossArbiterTribunal => {
+            trained_by(ProtossProbe, &[ProtossTemplarArchives, ProtossStargate])
+        }
+
+        _ => return None,
+    };
+
+    Some(requirements)
+}
+
+pub(super) fn tech(tech_id: TechId) -> Option<Requirements> {
+    use TechId::*;
+
+    let requirements = match tech_id {
+        StimPacks | Restoration | OpticalFlare => trained_by(TerranAcademy, &[]),
+        Lockdown | PersonnelCloaking => trained_by(TerranCovertOps, &[]),
+        EmpShockwave | Irradiate => trained_by(TerranScienceFacility, &[]),
+        SpiderMines | TankSiegeMode => trained_by(TerranMachineShop, &[]),
+        YamatoGun => trained_by(TerranPhysicsLab, &[]),
+        CloakingField => trained_by(TerranControlTower, &[]),
+
+        Burrowing => trained_by(ZergHatchery, &[]),
+        SpawnBroodlings | Ensnare => trained_by(ZergQueensNest, &[]),
+        Plague | Consume => trained_by(ZergDefilerMound, &[]),
+        LurkerAspect => trained_by(ZergHydraliskDen, &[ZergLair]),
+
+        PsionicStorm | Hallucination | MindControl | Maelstrom => {
+            trained_by(ProtossTemplarArchives, &[])
+        }
+        Recall | StasisField => trained_by(ProtossArbiterTribunal, &[]),
+        DisruptionWeb => trained_by(ProtossFleetBeacon, &[]),
+
+        _ => return None,
+    };
+
+    Some(requirements)
+}
+
+pub(super) fn upgrade(upgrade_id: UpgradeId, level: u8) -> Option<Requirements> {
+    use UpgradeId::*;
+
+    // Levels after the first of the weapon and armor upgrades need a tier 3
+    // structure, or a Hive for the last level of the Zerg ones.
+    let zerg_tier = match level {
+        1 => &[][..],
+        2 => &[ZergLair][..],
+        _ => &[ZergHive][..],
+    };
+    let tier = |units: &'static [UnitId]| if level > 1 { units } else { &[] };
+
+    let requirements = match upgrade_id {
+        TerranInfantryArmor | TerranInfantryWeapons => {
+            trained_by(TerranEngineeringBay, tier(&[TerranScienceFacility]))
+        }
+        TerranVehiclePlating | TerranVehicleWeapons | TerranShipPlating | TerranShipWeapons => {
+            trained_by(TerranArmory, tier(&[TerranScienceFacility]))
+        }
+        U238Shells | CaduceusReactor => trained_by(TerranAcademy, &[]),
+        IonThrusters => trained_by(TerranMachineShop, &[]),
+        CharonBoosters => trained_by(TerranMachineShop, &[TerranArmory]),
+        TitanReactor => trained_by(TerranScienceFacility, &[]),
+        OcularImplants | MoebiusReactor => trained_by(TerranCovertOps, &[]),
+        ApolloReactor => trained_by(TerranControlTower, &[]),
+        ColossusReactor => trained_by(TerranPhysicsLab, &[]),
+
+        ZergCarapace | ZergMeleeAttacks | ZergMissileAttacks => {
+            trained_by(ZergEvolutionChamber, zerg_tier)
+        }
+        ZergFlyerCarapace | ZergFlyerAttacks => trained_by(ZergSpire, zerg_tier),
+        VentralSacs | Antennae | PneumatizedCarapace => trained_by(ZergLair, &[]),
+        MetabolicBoost => trained_by(ZergSpawningPool, &[]),
+        AdrenalGlands => trained_by(ZergSpawningPool, &[ZergHive]),
+        MuscularAugments | GroovedSpines => trained_by(ZergHydraliskDen, &[ZergLair]),
+        GameteMeiosis => trained_by(ZergQueensNest, &[]),
+        MetasynapticNode => trained_by(ZergDefilerMound, &[]),
+        ChitinousPlating | AnabolicSynthesis => trained_by(ZergUltraliskCavern, &[]),
+
+        ProtossGroundArmor | ProtossGroundWeapons => {
+            trained_by(ProtossForge, tier(&[ProtossTemplarArchives]))
+        }
+        ProtossAirArmor | ProtossAirWeapons => {
+            trained_by(ProtossCyberneticsCore, tier(&[ProtossFleetBeacon]))
+        }
+        ProtossPlasmaShields => trained_by(ProtossForge, tier(&[ProtossCyberneticsCore])),
+        SingularityCharge => trained_by(ProtossCyberneticsCore, &[]),
+        LegEnhancements => trained_by(ProtossCitadelofAdun, &[]),
+        ScarabDamage | ReaverCapacity | GraviticDrive => trained_by(ProtossRoboticsSupportBay, &[]),
+        SensorArray | GraviticBoosters => trained_by(ProtossObservatory, &[]),
+        KhaydarinAmulet | ArgusTalisman => trained_by(ProtossTemplarArchives, &[]),
+        ApialSensors | GraviticThrusters | CarrierCapacity | ArgusJewel => {
+            trained_by(ProtossFleetBeacon, &[])
+        }
+        KhaydarinCore => trained_by(ProtossArbiterTribunal, &[]),
+
+        _ => return None,
+    };
+
+    Some(requirements)
+}
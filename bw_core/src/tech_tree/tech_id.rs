@@ -0,0 +1,54 @@
+use num_derive::FromPrimitive;
+
+/// Unique identifier for every tech, i.e. spells and abilities, in the
+/// Starcraft Universe
+///
+/// see: http://www.staredit.net/wiki/index.php?title=Technology#Tech_IDs
+#[derive(Debug, Clone, Copy, FromPrimitive, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TechId {
+    StimPacks = 0,
+    Lockdown,
+    EmpShockwave,
+    SpiderMines,
+    ScannerSweep,
+    TankSiegeMode,
+    DefensiveMatrix,
+    Irradiate,
+    YamatoGun,
+    CloakingField,
+    PersonnelCloaking,
+    Burrowing,
+    Infestation,
+    SpawnBroodlings,
+    DarkSwarm,
+    Plague,
+    Consume,
+    Ensnare,
+    Parasite,
+    PsionicStorm,
+    Hallucination,
+    Recall,
+    StasisField,
+    ArchonWarp,
+    Restoration,
+    DisruptionWeb,
+    Unused26,
+    MindControl,
+    DarkArchonMeld,
+    Feedback,
+    OpticalFlare,
+    Maelstrom,
+    LurkerAspect,
+    Unused33,
+    Healing,
+    Unused35,
+    Unused36,
+    Unused37,
+    Unused38,
+    Unused39,
+    Unused40,
+    Unused41,
+    Unused42,
+    Unused43,
+}
@@ -0,0 +1,70 @@
+use num_derive::FromPrimitive;
+
+/// Unique identifier for every upgrade in the Starcraft Universe
+///
+/// see: http://www.staredit.net/wiki/index.php?title=Upgrades#Upgrade_IDs
+#[derive(Debug, Clone, Copy, FromPrimitive, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UpgradeId {
+    TerranInfantryArmor = 0,
+    TerranVehiclePlating,
+    TerranShipPlating,
+    ZergCarapace,
+    ZergFlyerCarapace,
+    ProtossGroundArmor,
+    ProtossAirArmor,
+    TerranInfantryWeapons,
+    TerranVehicleWeapons,
+    TerranShipWeapons,
+    ZergMeleeAttacks,
+    ZergMissileAttacks,
+    ZergFlyerAttacks,
+    ProtossGroundWeapons,
+    ProtossAirWeapons,
+    ProtossPlasmaShields,
+    U238Shells,
+    IonThrusters,
+    UnusedBurstLasers,
+    TitanReactor,
+    OcularImplants,
+    MoebiusReactor,
+    ApolloReactor,
+    ColossusReactor,
+    VentralSacs,
+    Antennae,
+    PneumatizedCarapace,
+    MetabolicBoost,
+    AdrenalGlands,
+    MuscularAugments,
+    GroovedSpines,
+    GameteMeiosis,
+    MetasynapticNode,
+    SingularityCharge,
+    LegEnhancements,
+    ScarabDamage,
+    ReaverCapacity,
+    GraviticDrive,
+    SensorArray,
+    GraviticBoosters,
+    KhaydarinAmulet,
+    ApialSensors,
+    GraviticThrusters,
+    CarrierCapacity,
+    KhaydarinCore,
+    Unused45,
+    Unused46,
+    ArgusJewel,
+    Unused48,
+    ArgusTalisman,
+    Unused50,
+    CaduceusReactor,
+    ChitinousPlating,
+    AnabolicSynthesis,
+    CharonBoosters,
+    Unused55,
+    Unused56,
+    Unused57,
+    Unused58,
+    Unused59,
+    Unused60,
+}
@@ -0,0 +1,97 @@
+use std::ops::{BitAnd, BitOr};
+
+/// Declares a set of bit flags over an integer with a constant for every
+/// flag.
+macro_rules! flags {
+    (
+        $(#[$meta:meta])*
+        pub struct $name:ident($ty:ty) {
+            $(
+                $(#[$flag_meta:meta])*
+                const $flag:ident = $bit:expr;
+            )*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+        pub struct $name($ty);
+
+        impl $name {
+            $(
+                $(#[$flag_meta])*
+                pub const $flag: $name = $name(1 << $bit);
+            )*
+
+            /// Every flag that has a meaning.
+            pub const ALL: $name = $name(0 $(| 1 << $bit)*);
+
+            pub fn from_bits(bits: $ty) -> $name {
+                $name(bits)
+            }
+
+            pub fn bits(self) -> $ty {
+                self.0
+            }
+
+            pub fn is_empty(self) -> bool {
+                self.0 == 0
+            }
+
+            /// Whether every flag of `other` is set.
+            pub fn contains(self, other: $name) -> bool {
+                self.0 & other.0 == other.0
+            }
+        }
+
+        impl BitOr for $name {
+            type Output = $name;
+
+            fn bitor(self, other: $name) -> $name {
+                $name(self.0 | other.0)
+            }
+        }
+
+        impl BitAnd for $name {
+            type Output = $name;
+
+            fn bitand(self, other: $name) -> $name {
+                $name(self.0 & other.0)
+            }
+        }
+    };
+}
+
+flags! {
+    /// States that a placed unit starts in. The same bits say which of the
+    /// states a map sets at all.
+    pub struct UnitStateFlags(u16) {
+        const CLOAKED = 0;
+        const BURROWED = 1;
+
+        /// Lifting off for buildings.
+        const IN_TRANSIT = 2;
+        const HALLUCINATED = 3;
+        const INVINCIBLE = 4;
+    }
+}
+
+flags! {
+    /// Properties of a placed unit that the map sets. The game falls back to
+    /// the defaults for the others, e.g. full hit points.
+    pub struct UnitPropertyFlags(u16) {
+        const OWNER = 0;
+        const HIT_POINTS = 1;
+        const SHIELDS = 2;
+        const ENERGY = 3;
+        const RESOURCE_AMOUNT = 4;
+        const HANGAR = 5;
+    }
+}
+
+flags! {
+    /// How a placed unit is linked to the unit of its `class_instance`.
+    pub struct UnitRelationFlags(u16) {
+        const NYDUS_LINK = 9;
+        const ADDON_LINK = 10;
+    }
+}
@@ -1,8 +1,14 @@
 use struple::Struple;
 
+mod flags;
+mod race;
 mod unit_id;
+mod unit_info;
 
+pub use flags::{UnitPropertyFlags, UnitRelationFlags, UnitStateFlags};
+pub use race::Race;
 pub use unit_id::UnitId;
+pub use unit_info::ParseUnitIdError;
 
 /// Owner of a unit.
 ///
@@ -61,25 +67,220 @@ pub struct Unit {
     class_instance: u32,
 }
 
+/// How a placed unit is linked to another one.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum UnitLink {
+    /// An add-on and the building it is attached to.
+    AddOn,
+
+    /// The two ends of a Nydus Canal.
+    Nydus,
+}
+
 impl Unit {
+    /// Class instance of the unit that other units refer to it by.
+    pub fn serial_number(&self) -> u32 {
+        self.serial_number
+    }
+
+    /// Position of the center of the unit in pixels.
+    pub fn position(&self) -> (u16, u16) {
+        (self.x, self.y)
+    }
+
+    pub fn x(&self) -> u16 {
+        self.x
+    }
+
+    pub fn y(&self) -> u16 {
+        self.y
+    }
+
+    /// `None` for ids that aren't in the stock game.
+    pub fn unit_id(&self) -> Option<UnitId> {
+        self.unit_id
+    }
+
+    pub fn owner(&self) -> &UnitOwner {
+        &self.owner
+    }
+
+    pub fn relation_flags(&self) -> UnitRelationFlags {
+        UnitRelationFlags::from_bits(self.relation_flag)
+    }
+
+    /// States that the map sets, whether they are on or off.
+    pub fn valid_states(&self) -> UnitStateFlags {
+        UnitStateFlags::from_bits(self.special_property_flags)
+    }
+
+    /// States that the unit starts in. Bits that aren't valid are left out as
+    /// the game ignores them.
+    pub fn states(&self) -> UnitStateFlags {
+        UnitStateFlags::from_bits(self.unit_state_flags) & self.valid_states()
+    }
+
+    /// Properties that the map sets rather than leaving them at their
+    /// defaults.
+    pub fn valid_properties(&self) -> UnitPropertyFlags {
+        UnitPropertyFlags::from_bits(self.map_maker_flags)
+    }
+
+    pub fn is_cloaked(&self) -> bool {
+        self.states().contains(UnitStateFlags::CLOAKED)
+    }
+
+    pub fn is_burrowed(&self) -> bool {
+        self.states().contains(UnitStateFlags::BURROWED)
+    }
+
+    pub fn is_in_transit(&self) -> bool {
+        self.states().contains(UnitStateFlags::IN_TRANSIT)
+    }
+
+    pub fn is_hallucinated(&self) -> bool {
+        self.states().contains(UnitStateFlags::HALLUCINATED)
+    }
+
+    pub fn is_invincible(&self) -> bool {
+        self.states().contains(UnitStateFlags::INVINCIBLE)
+    }
+
+    fn property<T>(&self, property: UnitPropertyFlags, value: T) -> Option<T> {
+        if self.valid_properties().contains(property) {
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    /// Hit points % (1-100), or `None` when the unit starts with full hit
+    /// points.
+    pub fn hitpoints_percentage(&self) -> Option<u8> {
+        self.property(UnitPropertyFlags::HIT_POINTS, self.hitpoints_percentage)
+    }
+
+    /// Shield points % (1-100), or `None` when the unit starts with full
+    /// shields.
+    pub fn shield_points_percentage(&self) -> Option<u8> {
+        self.property(UnitPropertyFlags::SHIELDS, self.shield_points_percentage)
+    }
+
+    /// Energy points % (1-100), or `None` when the unit starts with the
+    /// default energy.
+    pub fn energy_points_percentage(&self) -> Option<u8> {
+        self.property(UnitPropertyFlags::ENERGY, self.energy_points_percentage)
+    }
+
+    /// Resources of a mineral field or a geyser, or `None` for the default
+    /// amount.
+    pub fn resource_amount(&self) -> Option<u32> {
+        self.property(UnitPropertyFlags::RESOURCE_AMOUNT, self.resource_amount)
+    }
+
+    /// Interceptors or Scarabs in the hangar, or `None` for the default
+    /// amount.
+    pub fn units_in_hangar(&self) -> Option<u16> {
+        self.property(UnitPropertyFlags::HANGAR, self.units_in_hangar)
+    }
+
+    /// How the unit is linked to the unit of its `class_instance`, if at all.
+    pub fn link(&self) -> Option<UnitLink> {
+        let relation = self.relation_flags();
+        if self.class_instance == 0 {
+            None
+        } else if relation.contains(UnitRelationFlags::ADDON_LINK) {
+            Some(UnitLink::AddOn)
+        } else if relation.contains(UnitRelationFlags::NYDUS_LINK) {
+            Some(UnitLink::Nydus)
+        } else {
+            None
+        }
+    }
+
+    /// The add-on or the other end of the Nydus Canal that the unit is
+    /// linked to, found among `units` by its serial number.
+    pub fn linked_unit<'a>(&self, units: &'a [Unit]) -> Option<&'a Unit> {
+        self.link()?;
+
+        units
+            .iter()
+            .find(|unit| unit.serial_number == self.class_instance)
+    }
+
     pub fn is_mineral_field(&self) -> bool {
-        self.unit_id == Some(UnitId::ResourceMineralField)
-            || self.unit_id == Some(UnitId::ResourceMineralFieldType2)
-            || self.unit_id == Some(UnitId::ResourceMineralFieldType3)
+        self.unit_id.map_or(false, UnitId::is_mineral_field)
     }
 
     /// Checks if the unit is a structure that is placed on top of a Vespene Geyser.
     ///
     /// Refinery types are Terran Refinery, Zerg Extractor, and Protoss Assimilator.
     pub fn is_refinery(&self) -> bool {
-        self.unit_id == Some(UnitId::TerranRefinery)
-            || self.unit_id == Some(UnitId::ZergExtractor)
-            || self.unit_id == Some(UnitId::ProtossAssimilator)
+        self.unit_id.map_or(false, UnitId::is_refinery)
     }
 
     pub fn is_worker(&self) -> bool {
-        self.unit_id == Some(UnitId::TerranScv)
-            || self.unit_id == Some(UnitId::ZergDrone)
-            || self.unit_id == Some(UnitId::ProtossProbe)
+        self.unit_id.map_or(false, UnitId::is_worker)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    fn unit(serial_number: u32, unit_id: UnitId) -> Unit {
+        Unit::from_tuple((
+            serial_number,
+            64,
+            96,
+            Some(unit_id),
+            0,
+            0,
+            0,
+            UnitOwner::new(0),
+            100,
+            100,
+            100,
+            0,
+            0,
+            0,
+            0,
+        ))
+    }
+
+    #[test]
+    fn it_ignores_states_that_are_not_valid() {
+        let mut unit = unit(1, UnitId::ZergLurker);
+        unit.unit_state_flags = (UnitStateFlags::BURROWED | UnitStateFlags::CLOAKED).bits();
+        unit.special_property_flags = UnitStateFlags::BURROWED.bits();
+
+        assert_that(&unit.is_burrowed()).is_true();
+        assert_that(&unit.is_cloaked()).is_false();
+        assert_that(&unit.states()).is_equal_to(UnitStateFlags::BURROWED);
+    }
+
+    #[test]
+    fn it_leaves_properties_that_are_not_valid_at_their_defaults() {
+        let mut unit = unit(1, UnitId::TerranMarine);
+        unit.hitpoints_percentage = 50;
+        unit.map_maker_flags = (UnitPropertyFlags::OWNER | UnitPropertyFlags::HIT_POINTS).bits();
+
+        assert_that(&unit.hitpoints_percentage()).is_equal_to(Some(50));
+        assert_that(&unit.shield_points_percentage()).is_none();
+        assert_that(&unit.position()).is_equal_to((64, 96));
+    }
+
+    #[test]
+    fn it_finds_the_linked_add_on() {
+        let mut factory = unit(1, UnitId::TerranFactory);
+        factory.relation_flag = UnitRelationFlags::ADDON_LINK.bits();
+        factory.class_instance = 2;
+        let units = vec![factory.clone(), unit(2, UnitId::TerranMachineShop)];
+
+        assert_that(&factory.link()).is_equal_to(Some(UnitLink::AddOn));
+        assert_that(&factory.linked_unit(&units).and_then(Unit::unit_id))
+            .is_equal_to(Some(UnitId::TerranMachineShop));
+        assert_that(&units[1].linked_unit(&units)).is_none();
     }
 }
@@ -0,0 +1,8 @@
+/// One of the three playable races.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Race {
+    Terran,
+    Zerg,
+    Protoss,
+}
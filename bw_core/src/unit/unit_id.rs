@@ -3,7 +3,8 @@ use num_derive::FromPrimitive;
 /// Unique identifier for every unit in the Starcraft Universe
 ///
 /// see: http://www.staredit.net/wiki/index.php?title=Unit#Unit_IDs
-#[derive(Debug, Clone, FromPrimitive, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, FromPrimitive, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum UnitId {
     TerranMarine = 0,
     TerranGhost,
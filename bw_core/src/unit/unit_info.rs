@@ -0,0 +1,666 @@
+//! What kind of unit a [`UnitId`] stands for.
+//!
+//! The game reads most of this from the special ability and StarEdit group
+//! flags of units.dat. The tables here follow the stock file so that units
+//! can be classified without loading it.
+
+use std::{error::Error, fmt, str::FromStr};
+
+use num_traits::FromPrimitive;
+
+use super::{Race, UnitId};
+
+impl UnitId {
+    /// Race of the unit, or `None` for critters, resources, powerups, spells
+    /// and the neutral buildings of the campaigns.
+    pub fn race(self) -> Option<Race> {
+        use UnitId::*;
+
+        let race = match self {
+            TerranMarine
+            | TerranGhost
+            | TerranVulture
+            | TerranGoliath
+            | TerranGoliathTurret
+            | TerranSiegeTankTankMode
+            | TerranSiegeTankTankModeTurret
+            | TerranScv
+            | TerranWraith
+            | TerranScienceVessel
+            | HeroGuiMontag
+            | TerranDropship
+            | TerranBattlecruiser
+            | TerranVultureSpiderMine
+            | TerranNuclearMissile
+            | TerranCivilian
+            | HeroSarahKerrigan
+            | HeroAlanSchezar
+            | HeroAlanSchezarTurret
+            | HeroJimRaynorVulture
+            | HeroJimRaynorMarine
+            | HeroTomKazansky
+            | HeroMagellan
+            | HeroEdmundDukeTankMode
+            | HeroEdmundDukeTankModeTurret
+            | HeroEdmundDukeSiegeMode
+            | HeroEdmundDukeSiegeModeTurret
+            | HeroArcturusMengsk
+            | HeroHyperion
+            | HeroNoradIi
+            | TerranSiegeTankSiegeMode
+            | TerranSiegeTankSiegeModeTurret
+            | TerranFirebat
+            | TerranMedic
+            | TerranValkyrie
+            | HeroSamirDuran
+            | HeroAlexeiStukov
+            | HeroGerardDuGalle
+            | TerranCommandCenter
+            | TerranComsatStation
+            | TerranNuclearSilo
+            | TerranSupplyDepot
+            | TerranRefinery
+            | TerranBarracks
+            | TerranAcademy
+            | TerranFactory
+            | TerranStarport
+            | TerranControlTower
+            | TerranScienceFacility
+            | TerranCovertOps
+            | TerranPhysicsLab
+            | UnusedTerran1
+            | TerranMachineShop
+            | UnusedTerran2
+            | TerranEngineeringBay
+            | TerranArmory
+            | TerranMissileTurret
+            | TerranBunker
+            | SpecialCrashedNoradII
+            | SpecialIonCannon
+            | UnusedTerranMarker
+            | SpecialTerranBeacon
+            | SpecialTerranFlagBeacon => Race::Terran,
+
+            ZergLarva
+            | ZergEgg
+            | ZergZergling
+            | ZergHydralisk
+            | ZergUltralisk
+            | ZergBroodling
+            | ZergDrone
+            | ZergOverlord
+            | ZergMutalisk
+            | ZergGuardian
+            | ZergQueen
+            | ZergDefiler
+            | ZergScourge
+            | HeroTorrasque
+            | HeroMatriarch
+            | ZerginfestedTerran
+            | HeroInfestedKerrigan
+            | HeroUncleanOne
+            | HeroHunterKiller
+            | HeroDevouringOne
+            | HeroKukulzaMutalisk
+            | HeroKukulzaGuardian
+            | HeroYggdrasill
+            | ZergCocoon
+            | ZergDevourer
+            | ZergLurkerEgg
+            | ZergLurker
+            | HeroInfestedDuran
+            | ZergInfestedCommandCenter
+            | ZergHatchery
+            | ZergLair
+            | ZergHive
+            | ZergNydusCanal
+            | ZergHydraliskDen
+            | ZergDefilerMound
+            | ZergGreaterSpire
+            | ZergQueensNest
+            | ZergEvolutionChamber
+            | ZergUltraliskCavern
+            | ZergSpire
+            | ZergSpawningPool
+            | ZergCreepColony
+            | ZergSporeColony
+            | UnusedZerg1
+            | ZergSunkenColony
+            | SpecialOvermindWithShell
+            | SpecialOvermind
+            | ZergExtractor
+            | SpecialMatureChrysalis
+            | SpecialCerebrate
+            | SpecialCerebrateDaggoth
+            | UnusedZerg2
+            | UnusedZergMarker
+            | SpecialZergBeacon
+            | SpecialZergFlagBeacon
+            | SpecialOvermindCocoon => Race::Zerg,
+
+            ProtossCorsair
+            | ProtossDarkTemplar
+            | ProtossDarkArchon
+            | ProtossProbe
+            | ProtossZealot
+            | ProtossDragoon
+            | ProtossHighTemplar
+            | ProtossArchon
+            | ProtossShuttle
+            | ProtossScout
+            | ProtossArbiter
+            | ProtossCarrier
+            | ProtossInterceptor
+            | HeroDarkTemplar
+            | HeroZeratul
+            | HeroTassadarZeratulArchon
+            | HeroFenixZealot
+            | HeroFenixDragoon
+            | HeroTassadar
+            | HeroMojo
+            | HeroWarbringer
+            | HeroGantrithor
+            | ProtossReaver
+            | ProtossObserver
+            | ProtossScarab
+            | HeroDanimoth
+            | HeroAldaris
+            | HeroArtanis
+            | HeroRaszagal
+            | ProtossNexus
+            | ProtossRoboticsFacility
+            | ProtossPylon
+            | ProtossAssimilator
+            | UnusedProtoss1
+            | ProtossObservatory
+            | ProtossGateway
+            | UnusedProtoss2
+            | ProtossPhotonCannon
+            | ProtossCitadelofAdun
+            | ProtossCyberneticsCore
+            | ProtossTemplarArchives
+            | ProtossForge
+            | ProtossStargate
+            | SpecialStasisCellPrison
+            | ProtossFleetBeacon
+            | ProtossArbiterTribunal
+            | ProtossRoboticsSupportBay
+            | ProtossShieldBattery
+            | SpecialKhaydarinCrystalForm
+            | SpecialProtossTemple
+            | SpecialWarpGate
+            | UnusedProtossMarker
+            | SpecialProtossBeacon
+            | SpecialProtossFlagBeacon => Race::Protoss,
+
+            _ => return None,
+        };
+
+        Some(race)
+    }
+
+    /// Whether the unit is a building, including the buildings of the
+    /// campaigns, resources and traps.
+    pub fn is_building(self) -> bool {
+        use UnitId::*;
+
+        let id = self as u16;
+        (TerranCommandCenter as u16..=SpecialOvermindCocoon as u16).contains(&id)
+            || (SpecialFloorMissileTrap as u16..=SpecialRightWallFlameTrap as u16).contains(&id)
+    }
+
+    /// Whether the unit is built as an add-on of another building, e.g. a
+    /// Machine Shop.
+    pub fn is_addon(self) -> bool {
+        matches!(
+            self,
+            UnitId::TerranComsatStation
+                | UnitId::TerranNuclearSilo
+                | UnitId::TerranControlTower
+                | UnitId::TerranCovertOps
+                | UnitId::TerranPhysicsLab
+                | UnitId::TerranMachineShop
+        )
+    }
+
+    /// Whether the unit is a hero of the campaigns, including the turrets of
+    /// heroes.
+    pub fn is_hero(self) -> bool {
+        use UnitId::*;
+
+        matches!(
+            self,
+            HeroGuiMontag
+                | HeroSarahKerrigan
+                | HeroAlanSchezar
+                | HeroAlanSchezarTurret
+                | HeroJimRaynorVulture
+                | HeroJimRaynorMarine
+                | HeroTomKazansky
+                | HeroMagellan
+                | HeroEdmundDukeTankMode
+                | HeroEdmundDukeTankModeTurret
+                | HeroEdmundDukeSiegeMode
+                | HeroEdmundDukeSiegeModeTurret
+                | HeroArcturusMengsk
+                | HeroHyperion
+                | HeroNoradIi
+                | HeroTorrasque
+                | HeroMatriarch
+                | HeroInfestedKerrigan
+                | HeroUncleanOne
+                | HeroHunterKiller
+                | HeroDevouringOne
+                | HeroKukulzaMutalisk
+                | HeroKukulzaGuardian
+                | HeroYggdrasill
+                | HeroDarkTemplar
+                | HeroZeratul
+                | HeroTassadarZeratulArchon
+                | HeroFenixZealot
+                | HeroFenixDragoon
+                | HeroTassadar
+                | HeroMojo
+                | HeroWarbringer
+                | HeroGantrithor
+                | HeroDanimoth
+                | HeroAldaris
+                | HeroArtanis
+                | HeroRaszagal
+                | HeroSamirDuran
+                | HeroAlexeiStukov
+                | HeroGerardDuGalle
+                | HeroInfestedDuran
+        )
+    }
+
+    /// Whether the unit is the visible effect of a spell, e.g. Dark Swarm.
+    pub fn is_spell(self) -> bool {
+        matches!(
+            self,
+            UnitId::SpellScannerSweep | UnitId::SpellDisruptionWeb | UnitId::SpellDarkSwarm
+        )
+    }
+
+    /// Whether the unit is the turret of another unit.
+    pub fn is_turret(self) -> bool {
+        self.turret_parent().is_some()
+    }
+
+    /// Unit that the turret sits on, e.g. a Goliath for the Goliath Turret.
+    pub fn turret_parent(self) -> Option<UnitId> {
+        use UnitId::*;
+
+        let parent = match self {
+            TerranGoliathTurret => TerranGoliath,
+            TerranSiegeTankTankModeTurret => TerranSiegeTankTankMode,
+            TerranSiegeTankSiegeModeTurret => TerranSiegeTankSiegeMode,
+            HeroAlanSchezarTurret => HeroAlanSchezar,
+            HeroEdmundDukeTankModeTurret => HeroEdmundDukeTankMode,
+            HeroEdmundDukeSiegeModeTurret => HeroEdmundDukeSiegeMode,
+            _ => return None,
+        };
+
+        Some(parent)
+    }
+
+    /// Whether workers gather from the unit, which are mineral fields,
+    /// Vespene Geysers and the refineries built on top of them.
+    pub fn is_resource(self) -> bool {
+        self.is_mineral_field() || self == UnitId::ResourceVespeneGeyser || self.is_refinery()
+    }
+
+    pub fn is_mineral_field(self) -> bool {
+        matches!(
+            self,
+            UnitId::ResourceMineralField
+                | UnitId::ResourceMineralFieldType2
+                | UnitId::ResourceMineralFieldType3
+        )
+    }
+
+    /// Checks if the unit is a structure that is placed on top of a Vespene Geyser.
+    ///
+    /// Refinery types are Terran Refinery, Zerg Extractor, and Protoss Assimilator.
+    pub fn is_refinery(self) -> bool {
+        matches!(
+            self,
+            UnitId::TerranRefinery | UnitId::ZergExtractor | UnitId::ProtossAssimilator
+        )
+    }
+
+    pub fn is_worker(self) -> bool {
+        matches!(
+            self,
+            UnitId::TerranScv | UnitId::ZergDrone | UnitId::ProtossProbe
+        )
+    }
+
+    /// Name of the unit as the stock stat_txt.tbl has it, e.g.
+    /// "Terran Siege Tank (Tank Mode)".
+    pub fn name(self) -> &'static str {
+        NAMES[self as usize]
+    }
+
+    /// Every unit id in order.
+    pub fn all() -> impl Iterator<Item = UnitId> {
+        (0..)
+            .map(UnitId::from_usize)
+            .take_while(Option::is_some)
+            .flatten()
+    }
+}
+
+/// Writes the name of the variant, e.g. `TerranMarine`, which [`FromStr`]
+/// reads back.
+impl fmt::Display for UnitId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// Reads the name of the variant, e.g. `TerranMarine`, or the number of the
+/// unit id.
+impl FromStr for UnitId {
+    type Err = ParseUnitIdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let unit_id = match s.parse::<u16>() {
+            Ok(id) => UnitId::from_u16(id),
+            Err(_) => UnitId::all().find(|unit_id| format!("{:?}", unit_id) == s),
+        };
+
+        unit_id.ok_or_else(|| ParseUnitIdError(s.to_string()))
+    }
+}
+
+/// A string that is neither the name nor the number of a unit id.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ParseUnitIdError(String);
+
+impl fmt::Display for ParseUnitIdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown unit {}", self.0)
+    }
+}
+
+impl Error for ParseUnitIdError {}
+
+const NAMES: [&str; 228] = [
+    "Terran Marine",
+    "Terran Ghost",
+    "Terran Vulture",
+    "Terran Goliath",
+    "Goliath Turret",
+    "Terran Siege Tank (Tank Mode)",
+    "Tank Turret (Tank Mode)",
+    "Terran SCV",
+    "Terran Wraith",
+    "Terran Science Vessel",
+    "Gui Montag (Firebat)",
+    "Terran Dropship",
+    "Terran Battlecruiser",
+    "Vulture Spider Mine",
+    "Nuclear Missile",
+    "Terran Civilian",
+    "Sarah Kerrigan (Ghost)",
+    "Alan Schezar (Goliath)",
+    "Alan Schezar Turret",
+    "Jim Raynor (Vulture)",
+    "Jim Raynor (Marine)",
+    "Tom Kazansky (Wraith)",
+    "Magellan (Science Vessel)",
+    "Edmund Duke (Siege Tank)",
+    "Duke Turret (Tank Mode)",
+    "Edmund Duke (Siege Mode)",
+    "Duke Turret (Siege Mode)",
+    "Arcturus Mengsk (Battlecruiser)",
+    "Hyperion (Battlecruiser)",
+    "Norad II (Battlecruiser)",
+    "Terran Siege Tank (Siege Mode)",
+    "Tank Turret (Siege Mode)",
+    "Terran Firebat",
+    "Scanner Sweep",
+    "Terran Medic",
+    "Zerg Larva",
+    "Zerg Egg",
+    "Zerg Zergling",
+    "Zerg Hydralisk",
+    "Zerg Ultralisk",
+    "Zerg Broodling",
+    "Zerg Drone",
+    "Zerg Overlord",
+    "Zerg Mutalisk",
+    "Zerg Guardian",
+    "Zerg Queen",
+    "Zerg Defiler",
+    "Zerg Scourge",
+    "Torrasque (Ultralisk)",
+    "Matriarch (Queen)",
+    "Infested Terran",
+    "Infested Kerrigan (Infested Terran)",
+    "Unclean One (Defiler)",
+    "Hunter Killer (Hydralisk)",
+    "Devouring One (Zergling)",
+    "Kukulza (Mutalisk)",
+    "Kukulza (Guardian)",
+    "Yggdrasill (Overlord)",
+    "Terran Valkyrie",
+    "Mutalisk Cocoon",
+    "Protoss Corsair",
+    "Protoss Dark Templar",
+    "Zerg Devourer",
+    "Protoss Dark Archon",
+    "Protoss Probe",
+    "Protoss Zealot",
+    "Protoss Dragoon",
+    "Protoss High Templar",
+    "Protoss Archon",
+    "Protoss Shuttle",
+    "Protoss Scout",
+    "Protoss Arbiter",
+    "Protoss Carrier",
+    "Protoss Interceptor",
+    "Dark Templar (Hero)",
+    "Zeratul (Dark Templar)",
+    "Tassadar/Zeratul (Archon)",
+    "Fenix (Zealot)",
+    "Fenix (Dragoon)",
+    "Tassadar (Templar)",
+    "Mojo (Scout)",
+    "Warbringer (Reaver)",
+    "Gantrithor (Carrier)",
+    "Protoss Reaver",
+    "Protoss Observer",
+    "Protoss Scarab",
+    "Danimoth (Arbiter)",
+    "Aldaris (Templar)",
+    "Artanis (Scout)",
+    "Rhynadon (Badlands Critter)",
+    "Bengalaas (Jungle Critter)",
+    "Cargo Ship (Unused)",
+    "Mercenary Gunship (Unused)",
+    "Scantid (Desert Critter)",
+    "Kakaru (Twilight Critter)",
+    "Ragnasaur (Ashworld Critter)",
+    "Ursadon (Ice World Critter)",
+    "Lurker Egg",
+    "Raszagal (Corsair)",
+    "Samir Duran (Ghost)",
+    "Alexei Stukov (Ghost)",
+    "Map Revealer",
+    "Gerard DuGalle (BattleCruiser)",
+    "Zerg Lurker",
+    "Infested Duran (Infested Terran)",
+    "Disruption Web",
+    "Terran Command Center",
+    "Terran Comsat Station",
+    "Terran Nuclear Silo",
+    "Terran Supply Depot",
+    "Terran Refinery",
+    "Terran Barracks",
+    "Terran Academy",
+    "Terran Factory",
+    "Terran Starport",
+    "Terran Control Tower",
+    "Terran Science Facility",
+    "Terran Covert Ops",
+    "Terran Physics Lab",
+    "Starbase (Unused)",
+    "Terran Machine Shop",
+    "Repair Bay (Unused)",
+    "Terran Engineering Bay",
+    "Terran Armory",
+    "Terran Missile Turret",
+    "Terran Bunker",
+    "Norad II (Crashed)",
+    "Ion Cannon",
+    "Uraj Crystal",
+    "Khalis Crystal",
+    "Infested Command Center",
+    "Zerg Hatchery",
+    "Zerg Lair",
+    "Zerg Hive",
+    "Zerg Nydus Canal",
+    "Zerg Hydralisk Den",
+    "Zerg Defiler Mound",
+    "Zerg Greater Spire",
+    "Zerg Queen's Nest",
+    "Zerg Evolution Chamber",
+    "Zerg Ultralisk Cavern",
+    "Zerg Spire",
+    "Zerg Spawning Pool",
+    "Zerg Creep Colony",
+    "Zerg Spore Colony",
+    "Unused Zerg Building",
+    "Zerg Sunken Colony",
+    "Zerg Overmind (With Shell)",
+    "Zerg Overmind",
+    "Zerg Extractor",
+    "Mature Chrysalis",
+    "Zerg Cerebrate",
+    "Zerg Cerebrate Daggoth",
+    "Unused Zerg Building 5",
+    "Protoss Nexus",
+    "Protoss Robotics Facility",
+    "Protoss Pylon",
+    "Protoss Assimilator",
+    "Unused Protoss Building",
+    "Protoss Observatory",
+    "Protoss Gateway",
+    "Unused Protoss Building",
+    "Protoss Photon Cannon",
+    "Protoss Citadel of Adun",
+    "Protoss Cybernetics Core",
+    "Protoss Templar Archives",
+    "Protoss Forge",
+    "Protoss Stargate",
+    "Stasis Cell/Prison",
+    "Protoss Fleet Beacon",
+    "Protoss Arbiter Tribunal",
+    "Protoss Robotics Support Bay",
+    "Protoss Shield Battery",
+    "Khaydarin Crystal Formation",
+    "Protoss Temple",
+    "Xel'Naga Temple",
+    "Mineral Field (Type 1)",
+    "Mineral Field (Type 2)",
+    "Mineral Field (Type 3)",
+    "Cave (Unused)",
+    "Cave-in (Unused)",
+    "Cantina (Unused)",
+    "Mining Platform (Unused)",
+    "Independent Command Center (Unused)",
+    "Independent Starport (Unused)",
+    "Independent Jump Gate (Unused)",
+    "Ruins (Unused)",
+    "Khaydarin Crystal Formation (Unused)",
+    "Vespene Geyser",
+    "Warp Gate",
+    "Psi Disrupter",
+    "Zerg Marker",
+    "Terran Marker",
+    "Protoss Marker",
+    "Zerg Beacon",
+    "Terran Beacon",
+    "Protoss Beacon",
+    "Zerg Flag Beacon",
+    "Terran Flag Beacon",
+    "Protoss Flag Beacon",
+    "Power Generator",
+    "Overmind Cocoon",
+    "Dark Swarm",
+    "Floor Missile Trap",
+    "Floor Hatch (Unused)",
+    "Left Upper Level Door",
+    "Right Upper Level Door",
+    "Left Pit Door",
+    "Right Pit Door",
+    "Floor Gun Trap",
+    "Left Wall Missile Trap",
+    "Left Wall Flame Trap",
+    "Right Wall Missile Trap",
+    "Right Wall Flame Trap",
+    "Start Location",
+    "Flag",
+    "Young Chrysalis",
+    "Psi Emitter",
+    "Data Disk",
+    "Khaydarin Crystal",
+    "Mineral Chunk (Type 1)",
+    "Mineral Chunk (Type 2)",
+    "Vespene Orb (Protoss Type 1)",
+    "Vespene Orb (Protoss Type 2)",
+    "Vespene Sac (Zerg Type 1)",
+    "Vespene Sac (Zerg Type 2)",
+    "Vespene Tank (Terran Type 1)",
+    "Vespene Tank (Terran Type 2)",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn it_reads_back_what_it_writes() {
+        for unit_id in UnitId::all() {
+            assert_that(&unit_id.to_string().parse::<UnitId>()).is_ok_containing(unit_id);
+        }
+
+        assert_that(&"106".parse::<UnitId>()).is_ok_containing(UnitId::TerranCommandCenter);
+        assert_that(&"Marine".parse::<UnitId>()).is_err();
+        assert_that(&"228".parse::<UnitId>()).is_err();
+    }
+
+    #[test]
+    fn it_names_every_unit() {
+        assert_that(&UnitId::all().count()).is_equal_to(NAMES.len());
+        assert_that(&UnitId::TerranSiegeTankTankMode.name())
+            .is_equal_to("Terran Siege Tank (Tank Mode)");
+        assert_that(&UnitId::SpecialPowerGenerator.name()).is_equal_to("Power Generator");
+    }
+
+    #[test]
+    fn it_finds_the_parents_of_turrets() {
+        for unit_id in UnitId::all().filter(|unit_id| unit_id.is_turret()) {
+            let parent = unit_id.turret_parent().unwrap();
+
+            assert_that(&parent.is_turret()).is_false();
+            assert_that(&parent.race()).is_equal_to(unit_id.race());
+            assert_that(&parent.is_hero()).is_equal_to(unit_id.is_hero());
+        }
+    }
+
+    #[test]
+    fn it_classifies_units() {
+        assert_that(&UnitId::TerranMachineShop.is_addon()).is_true();
+        assert_that(&UnitId::TerranMachineShop.is_building()).is_true();
+        assert_that(&UnitId::ZergLurker.race()).is_equal_to(Some(Race::Zerg));
+        assert_that(&UnitId::HeroRaszagal.race()).is_equal_to(Some(Race::Protoss));
+        assert_that(&UnitId::CritterKakaru.race()).is_none();
+        assert_that(&UnitId::ProtossAssimilator.is_resource()).is_true();
+        assert_that(&UnitId::SpellDarkSwarm.is_building()).is_false();
+    }
+}
@@ -4,4 +4,32 @@ use serde::{Deserialize, Serialize};
 pub struct BWConfig {
     pub log_level: String,
     pub map: String,
+    #[serde(default)]
+    pub camera: CameraConfig,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraConfig {
+    /// Speed of scrolling with the keyboard and the edges of the screen, in
+    /// map pixels per second.
+    pub scroll_speed: f32,
+
+    /// Distance from the edges of the window within which the mouse scrolls
+    /// the camera, in screen pixels.
+    pub edge_scroll_margin: f32,
+
+    /// Zoom levels that the mouse wheel steps through, where 2.0 draws the
+    /// map twice as large. The camera starts at 1.0.
+    pub zoom_levels: Vec<f32>,
+}
+
+impl Default for CameraConfig {
+    fn default() -> Self {
+        CameraConfig {
+            scroll_speed: 1500.0,
+            edge_scroll_margin: 8.0,
+            zoom_levels: vec![0.5, 0.75, 1.0, 1.5, 2.0],
+        }
+    }
 }
@@ -1,16 +1,33 @@
+use crate::config::CameraConfig;
 use amethyst::{
     assets::{AssetStorage, Handle},
-    core::math::max,
-    core::Transform,
-    core::{math::min, num::Float},
-    ecs::{Entities, Join, Read, ReadStorage, System, WriteStorage},
+    core::{Time, Transform},
+    ecs::{Entities, Join, Read, ReadExpect, ReadStorage, System, WriteStorage},
     input::{InputHandler, StringBindings},
     renderer::camera::{ActiveCamera, Camera},
+    window::ScreenDimensions,
+    winit::MouseButton,
 };
 use bw_assets::map::Map;
 
-#[derive(Default)]
-pub struct CameraMovementSystem;
+/// Scrolls the camera with the keyboard, with the mouse at the edges of the
+/// window and by dragging with the middle mouse button.
+pub struct CameraMovementSystem {
+    config: CameraConfig,
+
+    /// Position of the mouse on the last frame while the middle mouse button
+    /// is held.
+    drag_origin: Option<(f32, f32)>,
+}
+
+impl CameraMovementSystem {
+    pub fn new(config: CameraConfig) -> Self {
+        CameraMovementSystem {
+            config,
+            drag_origin: None,
+        }
+    }
+}
 
 impl<'s> System<'s> for CameraMovementSystem {
     type SystemData = (
@@ -19,29 +36,173 @@ impl<'s> System<'s> for CameraMovementSystem {
         ReadStorage<'s, Camera>,
         WriteStorage<'s, Transform>,
         Read<'s, InputHandler<StringBindings>>,
+        Read<'s, Time>,
+        ReadExpect<'s, ScreenDimensions>,
     );
 
-    fn run(&mut self, (active_camera, entities, cameras, mut transforms, input): Self::SystemData) {
-        let x_move = input.axis_value("camera_x").unwrap_or(0.0);
-        let y_move = input.axis_value("camera_y").unwrap_or(0.0);
+    fn run(
+        &mut self,
+        (active_camera, entities, cameras, mut transforms, input, time, screen_dimensions): Self::SystemData,
+    ) {
+        let mut camera_join = (&cameras, &mut transforms).join();
+        let (camera, camera_transform) = match active_camera
+            .entity
+            .and_then(|a| camera_join.get(a, &entities))
+            .or_else(|| camera_join.next())
+        {
+            Some(camera) => camera,
+            None => return,
+        };
 
-        if x_move != 0.0 || y_move != 0.0 {
-            let mut camera_join = (&cameras, &mut transforms).join();
-            if let Some((_, camera_transform)) = active_camera
-                .entity
-                .and_then(|a| camera_join.get(a, &entities))
-                .or_else(|| camera_join.next())
+        let mut x_move = input.axis_value("camera_x").unwrap_or(0.0);
+        let mut y_move = input.axis_value("camera_y").unwrap_or(0.0);
+
+        let mouse_position = input.mouse_position();
+        if let Some((mouse_x, mouse_y)) = mouse_position {
+            let margin = self.config.edge_scroll_margin;
+
+            if mouse_x <= margin {
+                x_move -= 1.0;
+            } else if mouse_x >= screen_dimensions.width() - margin {
+                x_move += 1.0;
+            }
+            // The mouse is in screen coordinates with y going down.
+            if mouse_y <= margin {
+                y_move += 1.0;
+            } else if mouse_y >= screen_dimensions.height() - margin {
+                y_move -= 1.0;
+            }
+        }
+
+        let distance = self.config.scroll_speed * time.delta_seconds();
+        camera_transform.prepend_translation_x(x_move.max(-1.0).min(1.0) * distance);
+        camera_transform.prepend_translation_y(y_move.max(-1.0).min(1.0) * distance);
+
+        if input.mouse_button_is_down(MouseButton::Middle) {
+            if let (Some((origin_x, origin_y)), Some((mouse_x, mouse_y))) =
+                (self.drag_origin, mouse_position)
             {
-                camera_transform.prepend_translation_x(x_move * 50.0);
-                camera_transform.prepend_translation_y(y_move * 50.0);
+                // Map pixels per screen pixel at the current zoom.
+                let scale = 2.0 / camera.matrix[(0, 0)] / screen_dimensions.width();
+
+                camera_transform.prepend_translation_x((origin_x - mouse_x) * scale);
+                camera_transform.prepend_translation_y((mouse_y - origin_y) * scale);
             }
+            self.drag_origin = mouse_position;
+        } else {
+            self.drag_origin = None;
+        }
+    }
+}
+
+/// Steps through the zoom levels of the config with the mouse wheel, keeping
+/// the center of the view in place.
+///
+/// Levels that would show more than the whole map are skipped so that
+/// [`CameraTranslationClampSystem`] can keep the view on the map.
+pub struct CameraZoomSystem {
+    zoom_levels: Vec<f32>,
+    level: usize,
+
+    /// Size of the view at a zoom of 1.0, taken from the camera the first
+    /// time it is zoomed.
+    base_size: Option<(f32, f32)>,
+}
+
+impl CameraZoomSystem {
+    pub fn new(config: &CameraConfig) -> Self {
+        let mut zoom_levels = config.zoom_levels.clone();
+        zoom_levels.retain(|&zoom| zoom > 0.0);
+        zoom_levels.sort_by(|a, b| a.partial_cmp(b).expect("zoom level is NaN"));
+        if zoom_levels.is_empty() {
+            zoom_levels.push(1.0);
+        }
+
+        // The camera is created at a zoom of 1.0.
+        let level = zoom_levels
+            .iter()
+            .position(|&zoom| zoom >= 1.0)
+            .unwrap_or(zoom_levels.len() - 1);
+
+        CameraZoomSystem {
+            zoom_levels,
+            level,
+            base_size: None,
+        }
+    }
+}
+
+impl<'s> System<'s> for CameraZoomSystem {
+    type SystemData = (
+        Read<'s, ActiveCamera>,
+        Entities<'s>,
+        WriteStorage<'s, Camera>,
+        WriteStorage<'s, Transform>,
+        Read<'s, InputHandler<StringBindings>>,
+        Read<'s, AssetStorage<Map>>,
+        ReadStorage<'s, Handle<Map>>,
+    );
+
+    fn run(
+        &mut self,
+        (active_camera, entities, mut cameras, mut transforms, input, map_storage, map_handles): Self::SystemData,
+    ) {
+        let wheel = input.mouse_wheel_value(false);
+        let level = if wheel > 0.0 {
+            (self.level + 1).min(self.zoom_levels.len() - 1)
+        } else if wheel < 0.0 {
+            self.level.saturating_sub(1)
+        } else {
+            return;
+        };
+        if level == self.level {
+            return;
+        }
+
+        let mut camera_join = (&mut cameras, &mut transforms, &map_handles).join();
+        let (camera, camera_transform, map) = match active_camera
+            .entity
+            .and_then(|a| camera_join.get(a, &entities))
+            .or_else(|| camera_join.next())
+            .and_then(|(camera, camera_transform, map_handle)| {
+                map_storage
+                    .get(map_handle)
+                    .map(|map| (camera, camera_transform, map))
+            }) {
+            Some(camera) => camera,
+            None => return,
+        };
+
+        let camera_width = 2.0 / camera.matrix[(0, 0)];
+        let camera_height = -2.0 / camera.matrix[(1, 1)];
+        let zoom = self.zoom_levels[self.level];
+        let (base_width, base_height) = *self
+            .base_size
+            .get_or_insert((camera_width * zoom, camera_height * zoom));
+
+        let width = base_width / self.zoom_levels[level];
+        let height = base_height / self.zoom_levels[level];
+        if width > map.pixel_width() as f32 || height > map.pixel_height() as f32 {
+            return;
         }
+
+        // The translation of the camera is the top left of the view.
+        let center_x = camera_transform.translation().x + camera_width / 2.0;
+        let center_y = camera_transform.translation().y - camera_height / 2.0;
+
+        *camera = Camera::orthographic(0.0, width, 0.0, height, 0.0, 20.0);
+        camera_transform.translation_mut().x = center_x - width / 2.0;
+        camera_transform.translation_mut().y = center_y + height / 2.0;
+        self.level = level;
     }
 }
 
+/// Rotates the camera in 3D to debug the rendering of the map.
+#[cfg(feature = "debug-camera")]
 #[derive(Default)]
 pub struct CameraRotationSystem;
 
+#[cfg(feature = "debug-camera")]
 impl<'s> System<'s> for CameraRotationSystem {
     type SystemData = (
         Read<'s, ActiveCamera>,
@@ -103,24 +264,20 @@ impl<'s> System<'s> for CameraTranslationClampSystem {
             let camera_width = 2.0 / active_camera.matrix[(0, 0)];
             let camera_height = -2.0 / active_camera.matrix[(1, 1)];
 
-            let x_clamped = min(
-                max(
-                    camera_transform.translation_mut().x as i32,
-                    -(map.pixel_width() as i32) / 2,
-                ),
-                ((map.pixel_width() as i32) / 2) - Float::ceil(camera_width) as i32,
-            ) as f32;
-
-            let y_clamped = min(
-                max(
-                    camera_transform.translation_mut().y as i32,
-                    (-(map.pixel_height() as i32) / 2) + Float::ceil(camera_height) as i32,
-                ),
-                (map.pixel_height() as i32) / 2,
-            ) as f32;
-
-            camera_transform.translation_mut().x = x_clamped;
-            camera_transform.translation_mut().y = y_clamped;
+            let half_map_width = map.pixel_width() as f32 / 2.0;
+            let half_map_height = map.pixel_height() as f32 / 2.0;
+
+            // Clamped without rounding, so that scrolling by less than a
+            // pixel a frame still adds up.
+            let translation = camera_transform.translation_mut();
+            translation.x = translation
+                .x
+                .max(-half_map_width)
+                .min(half_map_width - camera_width);
+            translation.y = translation
+                .y
+                .max(-half_map_height + camera_height)
+                .min(half_map_height);
         }
     }
 }
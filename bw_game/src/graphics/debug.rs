@@ -0,0 +1,358 @@
+//! Overlays that draw the terrain data of the map with debug lines, to debug
+//! terrain analysis and placement rules.
+//!
+//! Every overlay is toggled by an action in `bindings.ron`.
+
+use std::{collections::HashSet, sync::Arc};
+
+use amethyst::{
+    assets::AssetStorage,
+    core::{math::Point2, Transform},
+    ecs::{Entities, Join, Read, ReadStorage, System, Write},
+    input::{InputHandler, StringBindings},
+    renderer::{
+        camera::{ActiveCamera, Camera},
+        debug_drawing::DebugLines,
+        palette::Srgba,
+    },
+};
+use bw_assets::{
+    map::{Map, MapHandle, MEGATILE_PX_SIDE_LEN, MEGATILE_SIDE_LEN, MINITILE_PX_SIDE_LEN},
+    tileset::{CV5s, Elevation, VF4s},
+};
+use bw_sim::{CreepGrid, Footprint};
+
+/// Depth of the lines, in front of the tilemap.
+const OVERLAY_Z: f32 = 1.0;
+
+/// Terrain data that can be drawn on top of the map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DebugOverlay {
+    /// Walkable and unwalkable minitiles.
+    Walkability,
+
+    /// Low, mid and high ground of every minitile.
+    Elevation,
+
+    /// Minitiles that block the view of units below them.
+    BlocksView,
+
+    /// Build tiles where buildings that require creep can be placed, where
+    /// every other building can, and where none can.
+    Buildability,
+
+    /// Footprints of placed doodads.
+    Doodads,
+
+    /// MRGN locations.
+    Locations,
+}
+
+impl DebugOverlay {
+    pub const ALL: [DebugOverlay; 6] = [
+        DebugOverlay::Walkability,
+        DebugOverlay::Elevation,
+        DebugOverlay::BlocksView,
+        DebugOverlay::Buildability,
+        DebugOverlay::Doodads,
+        DebugOverlay::Locations,
+    ];
+
+    /// Action in `bindings.ron` that toggles the overlay.
+    pub fn action(self) -> &'static str {
+        match self {
+            DebugOverlay::Walkability => "overlay_walkability",
+            DebugOverlay::Elevation => "overlay_elevation",
+            DebugOverlay::BlocksView => "overlay_blocks_view",
+            DebugOverlay::Buildability => "overlay_buildability",
+            DebugOverlay::Doodads => "overlay_doodads",
+            DebugOverlay::Locations => "overlay_locations",
+        }
+    }
+}
+
+/// Overlays that are shown.
+#[derive(Debug, Default)]
+pub struct DebugOverlays(HashSet<DebugOverlay>);
+
+impl DebugOverlays {
+    pub fn is_shown(&self, overlay: DebugOverlay) -> bool {
+        self.0.contains(&overlay)
+    }
+
+    pub fn toggle(&mut self, overlay: DebugOverlay) {
+        if !self.0.remove(&overlay) {
+            self.0.insert(overlay);
+        }
+    }
+}
+
+/// Toggles an overlay when the key of its action is pressed.
+#[derive(Default)]
+pub struct DebugOverlayToggleSystem {
+    /// Overlays whose key was down on the last frame.
+    held: HashSet<DebugOverlay>,
+}
+
+impl<'s> System<'s> for DebugOverlayToggleSystem {
+    type SystemData = (
+        Read<'s, InputHandler<StringBindings>>,
+        Write<'s, DebugOverlays>,
+    );
+
+    fn run(&mut self, (input, mut overlays): Self::SystemData) {
+        for &overlay in DebugOverlay::ALL.iter() {
+            let is_down = input.action_is_down(overlay.action()).unwrap_or(false);
+
+            if is_down && self.held.insert(overlay) {
+                overlays.toggle(overlay);
+            } else if !is_down {
+                self.held.remove(&overlay);
+            }
+        }
+    }
+}
+
+/// Draws the shown overlays over the part of the map that is on screen.
+#[derive(Default)]
+pub struct DebugOverlaySystem;
+
+impl<'s> System<'s> for DebugOverlaySystem {
+    type SystemData = (
+        Read<'s, DebugOverlays>,
+        Write<'s, DebugLines>,
+        Option<Read<'s, MapHandle>>,
+        Read<'s, AssetStorage<Map>>,
+        Option<Read<'s, Arc<CV5s>>>,
+        Option<Read<'s, Arc<VF4s>>>,
+        Option<Read<'s, CreepGrid>>,
+        Read<'s, ActiveCamera>,
+        Entities<'s>,
+        ReadStorage<'s, Camera>,
+        ReadStorage<'s, Transform>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            overlays,
+            mut debug_lines,
+            map_handle,
+            map_storage,
+            cv5s,
+            vf4s,
+            creep,
+            active_camera,
+            entities,
+            cameras,
+            transforms,
+        ): Self::SystemData,
+    ) {
+        if overlays.0.is_empty() {
+            return;
+        }
+
+        let (map, cv5s, vf4s) = match (
+            map_handle.and_then(|map_handle| map_storage.get(&map_handle)),
+            cv5s,
+            vf4s,
+        ) {
+            (Some(map), Some(cv5s), Some(vf4s)) => (map, cv5s, vf4s),
+            _ => return,
+        };
+
+        let mut camera_join = (&cameras, &transforms).join();
+        let view = match active_camera
+            .entity
+            .and_then(|entity| camera_join.get(entity, &entities))
+            .or_else(|| camera_join.next())
+        {
+            Some((camera, camera_transform)) => View::new(map, camera, camera_transform),
+            None => return,
+        };
+        let mut painter = Painter {
+            map,
+            debug_lines: &mut debug_lines,
+        };
+
+        let has_minitile_overlay = [
+            DebugOverlay::Walkability,
+            DebugOverlay::Elevation,
+            DebugOverlay::BlocksView,
+        ]
+        .iter()
+        .any(|&overlay| overlays.is_shown(overlay));
+        let (left, top, right, bottom) = if has_minitile_overlay {
+            view.tiles(MINITILE_PX_SIDE_LEN)
+        } else {
+            (0, 0, 0, 0)
+        };
+        for y in top..bottom {
+            for x in left..right {
+                let megatile = &map.megatiles
+                    [(x / MEGATILE_SIDE_LEN + y / MEGATILE_SIDE_LEN * map.tile_width()) as usize];
+                let minitile_reference = &cv5s[megatile][megatile];
+                let vf4 = &vf4s[minitile_reference]
+                    [(x % MEGATILE_SIDE_LEN + y % MEGATILE_SIDE_LEN * MEGATILE_SIDE_LEN) as usize];
+
+                if overlays.is_shown(DebugOverlay::Walkability) {
+                    let color = if vf4.is_walkable() {
+                        Srgba::new(0.0, 1.0, 0.0, 1.0)
+                    } else {
+                        Srgba::new(1.0, 0.0, 0.0, 1.0)
+                    };
+                    painter.tile(x, y, MINITILE_PX_SIDE_LEN, color);
+                }
+                if overlays.is_shown(DebugOverlay::Elevation) {
+                    let color = match vf4.elevation() {
+                        Elevation::Low => Srgba::new(0.2, 0.4, 1.0, 1.0),
+                        Elevation::Mid => Srgba::new(1.0, 0.8, 0.2, 1.0),
+                        Elevation::High => Srgba::new(1.0, 1.0, 1.0, 1.0),
+                    };
+                    painter.tile(x, y, MINITILE_PX_SIDE_LEN, color);
+                }
+                if overlays.is_shown(DebugOverlay::BlocksView) && vf4.blocks_view() {
+                    painter.tile(x, y, MINITILE_PX_SIDE_LEN, Srgba::new(1.0, 0.0, 1.0, 1.0));
+                }
+            }
+        }
+
+        if let Some(creep) = creep.filter(|_| overlays.is_shown(DebugOverlay::Buildability)) {
+            let (left, top, right, bottom) = view.tiles(MEGATILE_PX_SIDE_LEN);
+            for y in top..bottom {
+                for x in left..right {
+                    let tile = Footprint {
+                        x: x as i32,
+                        y: y as i32,
+                        width: 1,
+                        height: 1,
+                    };
+                    let color = if creep.can_build(&tile, true) {
+                        Srgba::new(0.6, 0.2, 0.8, 1.0)
+                    } else if creep.can_build(&tile, false) {
+                        Srgba::new(0.0, 1.0, 0.0, 1.0)
+                    } else {
+                        Srgba::new(1.0, 0.0, 0.0, 1.0)
+                    };
+                    painter.tile(x, y, MEGATILE_PX_SIDE_LEN, color);
+                }
+            }
+        }
+
+        if overlays.is_shown(DebugOverlay::Doodads) {
+            for placed_doodad in &map.doodads {
+                if let Some(doodad) = cv5s.doodad(placed_doodad.id) {
+                    let width = doodad.width() as i64 * MEGATILE_PX_SIDE_LEN as i64;
+                    let height = doodad.height() as i64 * MEGATILE_PX_SIDE_LEN as i64;
+                    let left = placed_doodad.x as i64 - width / 2;
+                    let top = placed_doodad.y as i64 - height / 2;
+
+                    painter.rectangle(
+                        (left, top, left + width, top + height),
+                        Srgba::new(0.0, 1.0, 1.0, 1.0),
+                    );
+                }
+            }
+        }
+
+        if overlays.is_shown(DebugOverlay::Locations) {
+            for location in map
+                .locations
+                .iter()
+                .filter(|location| !location.is_unused())
+            {
+                painter.rectangle(
+                    (
+                        location.left.min(location.right) as i64,
+                        location.top.min(location.bottom) as i64,
+                        location.left.max(location.right) as i64,
+                        location.top.max(location.bottom) as i64,
+                    ),
+                    Srgba::new(1.0, 1.0, 0.0, 1.0),
+                );
+            }
+        }
+    }
+}
+
+/// Part of the map on screen, in pixels from the top left of the map.
+struct View {
+    left: f32,
+    top: f32,
+    right: f32,
+    bottom: f32,
+    map_width: u32,
+    map_height: u32,
+}
+
+impl View {
+    fn new(map: &Map, camera: &Camera, camera_transform: &Transform) -> View {
+        let camera_width = 2.0 / camera.matrix[(0, 0)];
+        let camera_height = -2.0 / camera.matrix[(1, 1)];
+        let translation = camera_transform.translation();
+
+        // The camera is at the top left of the screen and the map is centered
+        // on the origin with y going up.
+        let left = translation.x + map.pixel_width() as f32 / 2.0;
+        let top = map.pixel_height() as f32 / 2.0 - translation.y;
+
+        View {
+            left,
+            top,
+            right: left + camera_width,
+            bottom: top + camera_height,
+            map_width: map.pixel_width(),
+            map_height: map.pixel_height(),
+        }
+    }
+
+    /// Left, top, right and bottom of the tiles on screen with a side in
+    /// pixels, with right and bottom exclusive.
+    fn tiles(&self, side: u32) -> (u32, u32, u32, u32) {
+        let clamp = |px: f32, max: u32| (px.max(0.0) as u32).min(max) / side;
+        let clamp_up = |px: f32, max: u32| ((px.max(0.0) as u32).min(max) + side - 1) / side;
+
+        (
+            clamp(self.left, self.map_width),
+            clamp(self.top, self.map_height),
+            clamp_up(self.right, self.map_width),
+            clamp_up(self.bottom, self.map_height),
+        )
+    }
+}
+
+/// Draws rectangles given in pixels from the top left of the map.
+struct Painter<'a> {
+    map: &'a Map,
+    debug_lines: &'a mut DebugLines,
+}
+
+impl Painter<'_> {
+    /// Outlines a tile one pixel inside of its edges, so that the outlines of
+    /// neighbouring tiles don't overlap.
+    fn tile(&mut self, x: u32, y: u32, side: u32, color: Srgba) {
+        let (left, top) = ((x * side) as i64, (y * side) as i64);
+
+        self.rectangle(
+            (
+                left + 1,
+                top + 1,
+                left + side as i64 - 1,
+                top + side as i64 - 1,
+            ),
+            color,
+        );
+    }
+
+    fn rectangle(&mut self, (left, top, right, bottom): (i64, i64, i64, i64), color: Srgba) {
+        let half_width = self.map.pixel_width() as f32 / 2.0;
+        let half_height = self.map.pixel_height() as f32 / 2.0;
+
+        self.debug_lines.draw_rectangle(
+            Point2::new(left as f32 - half_width, half_height - bottom as f32),
+            Point2::new(right as f32 - half_width, half_height - top as f32),
+            OVERLAY_Z,
+            color,
+        );
+    }
+}
@@ -7,8 +7,10 @@ use image::ImageBuffer;
 use std::cell::UnsafeCell;
 
 pub mod camera;
+pub mod debug;
 pub mod tile;
 pub mod ui;
+pub mod unit;
 
 pub fn create(params: (&mut World, &Handle<Map>, &mut ProgressCounter)) {
     let (world, map_handle, progress_counter) = params;
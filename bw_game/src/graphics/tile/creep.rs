@@ -0,0 +1,47 @@
+use std::time::Duration;
+
+use super::{SpriteGrid, GAME_FRAME};
+use amethyst::{
+    core::Time,
+    ecs::{Read, System, Write},
+};
+use bw_sim::CreepGrid;
+
+/// Spreads the creep of Zerg buildings every game frame and invalidates the
+/// megatiles of the [`SpriteGrid`] around the new creep.
+#[derive(Default)]
+pub struct CreepSpreadSystem {
+    elapsed: Duration,
+}
+
+impl<'s> System<'s> for CreepSpreadSystem {
+    type SystemData = (
+        Read<'s, Time>,
+        Option<Write<'s, CreepGrid>>,
+        Option<Write<'s, SpriteGrid>>,
+    );
+
+    fn run(&mut self, (time, creep, sprite_grid): Self::SystemData) {
+        let mut creep = match creep {
+            Some(creep) => creep,
+            None => return,
+        };
+
+        self.elapsed += time.delta_time();
+
+        while self.elapsed >= GAME_FRAME {
+            self.elapsed -= GAME_FRAME;
+            creep.tick();
+        }
+
+        let changes = creep.take_changes();
+        if let Some(mut sprite_grid) = sprite_grid {
+            // Edges are drawn on the neighbours of creep.
+            for (x, y) in changes {
+                for (dx, dy) in (-1..=1).flat_map(|dx| (-1..=1).map(move |dy| (dx, dy))) {
+                    sprite_grid.invalidate(x + dx, y + dy);
+                }
+            }
+        }
+    }
+}
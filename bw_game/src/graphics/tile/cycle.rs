@@ -0,0 +1,64 @@
+use std::time::Duration;
+
+use super::{
+    map::{create_map_texture_data, TilemapTexture},
+    GAME_FRAME,
+};
+use amethyst::{
+    assets::{AssetStorage, Loader},
+    core::Time,
+    ecs::{Read, ReadExpect, System, Write},
+    renderer::{SpriteSheet, Texture},
+};
+use bw_assets::tileset::PaletteCycler;
+
+/// Rotates the palette of the tileset every game frame and redraws the
+/// tilemap texture whenever a colour changed, which animates water and lava.
+#[derive(Default)]
+pub struct PaletteCycleSystem {
+    elapsed: Duration,
+}
+
+impl<'s> System<'s> for PaletteCycleSystem {
+    type SystemData = (
+        Read<'s, Time>,
+        ReadExpect<'s, Loader>,
+        Option<Write<'s, PaletteCycler>>,
+        Option<Read<'s, TilemapTexture>>,
+        Read<'s, AssetStorage<Texture>>,
+        Write<'s, AssetStorage<SpriteSheet>>,
+    );
+
+    fn run(
+        &mut self,
+        (time, loader, palette_cycler, tilemap_texture, textures, mut sprite_sheets): Self::SystemData,
+    ) {
+        let (mut palette_cycler, tilemap_texture) = match (palette_cycler, tilemap_texture) {
+            (Some(palette_cycler), Some(tilemap_texture)) => (palette_cycler, tilemap_texture),
+            _ => return,
+        };
+
+        self.elapsed += time.delta_time();
+
+        let mut changed = false;
+        while self.elapsed >= GAME_FRAME {
+            self.elapsed -= GAME_FRAME;
+            changed |= palette_cycler.tick();
+        }
+
+        if !changed {
+            return;
+        }
+
+        for page in &tilemap_texture.pages {
+            if let Some(sprite_sheet) = sprite_sheets.get_mut(&page.sprite_sheet) {
+                let texture_data = create_map_texture_data(
+                    &tilemap_texture.layout,
+                    &page.indices,
+                    palette_cycler.rgb(),
+                );
+                sprite_sheet.texture = loader.load_from_data(texture_data, (), &textures);
+            }
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use std::{collections::BTreeSet, sync::Arc};
+
+use super::map::TilemapTexture;
+use amethyst::{
+    assets::AssetStorage,
+    core::math::Point3,
+    ecs::{Read, System, Write},
+};
+use bw_assets::{
+    map::{Map, MapHandle, MegaTile, MEGATILE_SIDE_LEN},
+    tileset::{creep_edge_megatile, creep_fill_megatile, AtlasLayout, CV5s, VX4s},
+};
+use bw_sim::{CreepGrid, CreepTile};
+
+/// Megatile that creep draws instead of the one of the map, if any.
+fn creep_megatile(x: u32, y: u32, creep: &CreepGrid) -> Option<MegaTile> {
+    match creep.tile(x as i32, y as i32)? {
+        CreepTile::Fill(variant) => Some(creep_fill_megatile(variant)),
+        CreepTile::Edge(neighbours) => creep_edge_megatile(neighbours),
+    }
+}
+
+/// Everything that decides which sprite a minitile of the map has.
+pub struct Terrain<'a> {
+    pub map: &'a Map,
+    pub cv5s: &'a CV5s,
+    pub vx4s: &'a VX4s,
+    pub layout: &'a AtlasLayout,
+    pub creep: Option<&'a CreepGrid>,
+}
+
+/// Page and sprite of every minitile of the map, resolved through CV5, VX4
+/// and the atlas once so that drawing a tile is a single lookup.
+///
+/// Megatiles whose terrain changed, e.g. because of creep, have to be
+/// invalidated and are resolved again by the [`SpriteGridSystem`].
+pub struct SpriteGrid {
+    /// Width in minitiles.
+    width: u32,
+    height: u32,
+    sprites: Vec<Option<(usize, usize)>>,
+    invalidated: BTreeSet<(u32, u32)>,
+}
+
+impl SpriteGrid {
+    pub fn new(terrain: &Terrain<'_>) -> SpriteGrid {
+        let width = terrain.map.tile_width();
+        let height = terrain.map.tile_height();
+
+        let mut sprite_grid = SpriteGrid {
+            width: width * MEGATILE_SIDE_LEN,
+            height: height * MEGATILE_SIDE_LEN,
+            sprites: vec![None; (width * height * MEGATILE_SIDE_LEN * MEGATILE_SIDE_LEN) as usize],
+            invalidated: BTreeSet::new(),
+        };
+        for y in 0..height {
+            for x in 0..width {
+                sprite_grid.resolve(x, y, terrain);
+            }
+        }
+
+        sprite_grid
+    }
+
+    /// Page and sprite of the minitile at the coordinates of the tilemap.
+    pub fn get(&self, coords: &Point3<u32>) -> Option<(usize, usize)> {
+        if coords.x < self.width && coords.y < self.height {
+            self.sprites[(coords.x + coords.y * self.width) as usize]
+        } else {
+            None
+        }
+    }
+
+    /// Marks a megatile to be resolved again. Megatiles outside the map are
+    /// ignored.
+    pub fn invalidate(&mut self, x: i32, y: i32) {
+        let (width, height) = (
+            self.width / MEGATILE_SIDE_LEN,
+            self.height / MEGATILE_SIDE_LEN,
+        );
+
+        if (0..width as i32).contains(&x) && (0..height as i32).contains(&y) {
+            self.invalidated.insert((x as u32, y as u32));
+        }
+    }
+
+    pub fn is_invalidated(&self) -> bool {
+        !self.invalidated.is_empty()
+    }
+
+    /// Resolves every megatile that was invalidated.
+    pub fn refresh(&mut self, terrain: &Terrain<'_>) {
+        for (x, y) in std::mem::take(&mut self.invalidated) {
+            self.resolve(x, y, terrain);
+        }
+    }
+
+    fn resolve(&mut self, x: u32, y: u32, terrain: &Terrain<'_>) {
+        let creep_megatile = terrain.creep.and_then(|creep| creep_megatile(x, y, creep));
+        let megatile = creep_megatile
+            .as_ref()
+            .unwrap_or(&terrain.map.megatiles[(x + y * terrain.map.tile_width()) as usize]);
+
+        let megatile_reference = &terrain.cv5s[megatile][megatile];
+        let minitiles = &terrain.vx4s[megatile_reference];
+
+        for (i, minitile) in minitiles.iter().enumerate() {
+            let xj = x * MEGATILE_SIDE_LEN + i as u32 % MEGATILE_SIDE_LEN;
+            let yj = y * MEGATILE_SIDE_LEN + i as u32 / MEGATILE_SIDE_LEN;
+
+            self.sprites[(xj + yj * self.width) as usize] = terrain
+                .layout
+                .sprite(minitile.index(), minitile.is_horizontally_flipped());
+        }
+    }
+}
+
+/// Resolves the megatiles of the [`SpriteGrid`] that were invalidated.
+#[derive(Default)]
+pub struct SpriteGridSystem;
+
+impl<'s> System<'s> for SpriteGridSystem {
+    type SystemData = (
+        Option<Write<'s, SpriteGrid>>,
+        Option<Read<'s, MapHandle>>,
+        Read<'s, AssetStorage<Map>>,
+        Option<Read<'s, Arc<CV5s>>>,
+        Option<Read<'s, Arc<VX4s>>>,
+        Option<Read<'s, TilemapTexture>>,
+        Option<Read<'s, CreepGrid>>,
+    );
+
+    fn run(
+        &mut self,
+        (sprite_grid, map_handle, map_storage, cv5s, vx4s, tilemap_texture, creep): Self::SystemData,
+    ) {
+        let mut sprite_grid = match sprite_grid {
+            Some(sprite_grid) if sprite_grid.is_invalidated() => sprite_grid,
+            _ => return,
+        };
+
+        if let (Some(map), Some(cv5s), Some(vx4s), Some(tilemap_texture)) = (
+            map_handle.and_then(|map_handle| map_storage.get(&map_handle)),
+            cv5s,
+            vx4s,
+            tilemap_texture,
+        ) {
+            sprite_grid.refresh(&Terrain {
+                map,
+                cv5s: &cv5s,
+                vx4s: &vx4s,
+                layout: &tilemap_texture.layout,
+                creep: creep.as_deref(),
+            });
+        }
+    }
+}
@@ -1,10 +1,12 @@
-use super::super::UnsafeImageBufferCell;
-use super::AmethystTileBridge;
+use super::{AmethystTileBridge, SpriteGrid, Terrain};
 use amethyst::{
     assets::{AssetStorage, Handle, Loader, ProgressCounter},
     core::{
         ecs::World,
-        {math::Vector3, Transform},
+        {
+            math::{Point3, Vector3},
+            Transform,
+        },
     },
     prelude::*,
     renderer::{
@@ -19,228 +21,258 @@ use amethyst::{
     tiles::TileMap,
 };
 use bw_assets::{
+    dat::UnitsDat,
     map::{self, Map},
-    tileset::{VR4s, WPEs},
+    tileset::{AtlasLayout, CV5s, PaletteCycler, VF4s, VR4s, VX4s, WPEs},
 };
-use image::ImageBuffer;
-use std::{cell::UnsafeCell, sync::Arc};
+use bw_sim::CreepGrid;
+use std::sync::Arc;
+
+/// Palette indices of a page of the tilemap texture, kept so that it can be
+/// redrawn whenever the palette cycles.
+pub struct TilemapPage {
+    pub indices: Arc<Vec<u8>>,
+    pub sprite_sheet: Handle<SpriteSheet>,
+}
 
-const TILEMAP_TEXTURE_SIDE_LENGTH: usize = 2048;
-const PADDING: usize = 1;
-const MINITILE_SIDE_LENGTH_WITH_PADDING: usize =
-    (map::MINITILE_PX_SIDE_LEN + (PADDING as u32) * 2) as usize;
+/// Pages of the tilemap texture. Every page has a tilemap of its own that
+/// only draws the minitiles on the page.
+pub struct TilemapTexture {
+    pub layout: AtlasLayout,
+    pub pages: Vec<TilemapPage>,
+}
 
 pub fn create(params: (&mut World, &Handle<Map>, &mut ProgressCounter)) {
     let (world, map_handle, progress_counter) = params;
 
-    let tilemap_texture_handle = load_map_texture(world, progress_counter);
-    let sprite_sheet_handle =
-        load_sprite_sheet_handle(world, progress_counter, tilemap_texture_handle);
-
-    let tilemap = {
+    let vr4s = (*world.try_fetch::<Arc<VR4s>>().expect("vr4s is missing")).clone();
+    let layout = AtlasLayout::for_vr4s(&vr4s);
+    let (palette_cycler, creep, dimensions) = {
         let map_storage = world.read_resource::<AssetStorage<Map>>();
-
         let map = map_storage.get(&map_handle).expect("map is missing");
-        let width = map.dimensions.width as u32 * map::MEGATILE_SIDE_LEN;
-        let height = map.dimensions.height as u32 * map::MEGATILE_SIDE_LEN;
+        let wpes = world.try_fetch::<Arc<WPEs>>().expect("wpes is missing");
+        let units_dat = world.try_fetch::<UnitsDat>().expect("units.dat is missing");
+        let cv5s = world.try_fetch::<Arc<CV5s>>().expect("cv5s is missing");
+        let vf4s = world.try_fetch::<Arc<VF4s>>().expect("vf4s is missing");
 
-        TileMap::<AmethystTileBridge>::new(
-            Vector3::new(width, height, 1),
-            Vector3::new(map::MINITILE_PX_SIDE_LEN, map::MINITILE_PX_SIDE_LEN, 1),
-            Some(sprite_sheet_handle.clone()),
+        (
+            PaletteCycler::new(wpes.rgb(), &map.tileset),
+            CreepGrid::from_map(map, &units_dat, &cv5s, &vf4s),
+            Vector3::new(
+                map.dimensions.width as u32 * map::MEGATILE_SIDE_LEN,
+                map.dimensions.height as u32 * map::MEGATILE_SIDE_LEN,
+                1,
+            ),
         )
     };
 
-    world
-        .create_entity()
-        .with(tilemap)
-        .with(Transform::default())
-        .build();
+    let mut pages = vec![];
+    for page in 0..layout.pages() {
+        let indices = Arc::new(create_map_texture_indices(&vr4s, &layout, page));
+        let texture_handle = load_map_texture(
+            world,
+            progress_counter,
+            layout,
+            indices.clone(),
+            palette_cycler.rgb().to_vec(),
+        );
+        let sprite_sheet =
+            load_sprite_sheet_handle(world, progress_counter, texture_handle, layout, page);
+
+        pages.push(TilemapPage {
+            indices,
+            sprite_sheet,
+        });
+    }
+
+    for (page, tilemap_page) in pages.iter().enumerate() {
+        let mut tilemap = TileMap::<AmethystTileBridge>::new(
+            dimensions,
+            Vector3::new(map::MINITILE_PX_SIDE_LEN, map::MINITILE_PX_SIDE_LEN, 1),
+            Some(tilemap_page.sprite_sheet.clone()),
+        );
+
+        if page > 0 {
+            for y in 0..dimensions.y {
+                for x in 0..dimensions.x {
+                    if let Some(tile) = tilemap.get_mut(&Point3::new(x, y, 0)) {
+                        *tile = AmethystTileBridge::new(page);
+                    }
+                }
+            }
+        }
+
+        world
+            .create_entity()
+            .with(tilemap)
+            .with(Transform::default())
+            .build();
+    }
+
+    let sprite_grid = {
+        let map_storage = world.read_resource::<AssetStorage<Map>>();
+        let map = map_storage.get(&map_handle).expect("map is missing");
+        let cv5s = world.try_fetch::<Arc<CV5s>>().expect("cv5s is missing");
+        let vx4s = world.try_fetch::<Arc<VX4s>>().expect("vx4s is missing");
+
+        SpriteGrid::new(&Terrain {
+            map,
+            cv5s: &cv5s,
+            vx4s: &vx4s,
+            layout: &layout,
+            creep: Some(&creep),
+        })
+    };
+
+    world.insert(palette_cycler);
+    world.insert(creep);
+    world.insert(sprite_grid);
+    world.insert(TilemapTexture { layout, pages });
 }
 
 fn load_sprite_sheet_handle(
     world: &World,
     progress_counter: &mut ProgressCounter,
     tilemap_texture_handle: Handle<Texture>,
+    layout: AtlasLayout,
+    page: usize,
 ) -> Handle<SpriteSheet> {
     let loader = world.read_resource::<Loader>();
 
-    let vr4s = (*world.try_fetch::<Arc<VR4s>>().expect("vr4s is missing")).clone();
-
     loader.load_from_data_async(
-        move || create_tilemap_sprite_sheet(tilemap_texture_handle, vr4s),
+        move || create_tilemap_sprite_sheet(tilemap_texture_handle, &layout, page),
         progress_counter,
         &world.read_resource::<AssetStorage<SpriteSheet>>(),
     )
 }
 
-fn load_map_texture(world: &World, progress_counter: &mut ProgressCounter) -> Handle<Texture> {
+fn load_map_texture(
+    world: &World,
+    progress_counter: &mut ProgressCounter,
+    layout: AtlasLayout,
+    indices: Arc<Vec<u8>>,
+    palette: Vec<[u8; 3]>,
+) -> Handle<Texture> {
     let loader = world.read_resource::<Loader>();
 
-    let vr4s = (*world.try_fetch::<Arc<VR4s>>().expect("vr4s is missing")).clone();
-    let wpes = (*world.try_fetch::<Arc<WPEs>>().expect("wpes is missing")).clone();
-
     loader.load_from_data_async(
-        move || {
-            let pixels = create_map_texture_pixels(vr4s, wpes);
-
-            // https://stackoverflow.com/questions/57691913/how-to-load-a-texture-from-memory-in-amethyst-engine
-            let texture_builder = TextureBuilder::new()
-                .with_kind(Kind::D2(
-                    TILEMAP_TEXTURE_SIDE_LENGTH as u32,
-                    TILEMAP_TEXTURE_SIDE_LENGTH as u32,
-                    1,
-                    1,
-                ))
-                .with_view_kind(ViewKind::D2)
-                .with_data_width(TILEMAP_TEXTURE_SIDE_LENGTH as u32)
-                .with_data_height(TILEMAP_TEXTURE_SIDE_LENGTH as u32)
-                .with_sampler_info(SamplerInfo {
-                    min_filter: Filter::Nearest,
-                    mag_filter: Filter::Nearest,
-                    mip_filter: Filter::Nearest,
-                    wrap_mode: (WrapMode::Clamp, WrapMode::Clamp, WrapMode::Clamp),
-                    lod_bias: 0.0.into(),
-                    lod_range: std::ops::Range {
-                        start: 0.0.into(),
-                        end: 1000.0.into(),
-                    },
-                    comparison: None,
-                    border: PackedColor(0),
-                    normalized: true,
-                    anisotropic: Anisotropic::Off,
-                })
-                .with_raw_data(pixels, renderer::Format::Rgb8Srgb);
-
-            TextureData::from(texture_builder)
-        },
+        move || create_map_texture_data(&layout, &indices, &palette),
         progress_counter,
         &world.read_resource::<AssetStorage<Texture>>(),
     )
 }
 
-fn create_tilemap_sprite_sheet(texture: Handle<Texture>, vr4s: Arc<VR4s>) -> SpriteSheet {
+/// Colours the palette indices of a page of the tilemap into a texture.
+pub fn create_map_texture_data(
+    layout: &AtlasLayout,
+    indices: &[u8],
+    palette: &[[u8; 3]],
+) -> TextureData {
     use rayon::prelude::*;
 
-    let sprite_count = vr4s.len();
-    let mut sprites: Vec<Sprite> = (0..sprite_count)
-        .into_par_iter()
-        .map(|i| {
-            let columns = TILEMAP_TEXTURE_SIDE_LENGTH / MINITILE_SIDE_LENGTH_WITH_PADDING;
-            let row = i / columns;
-            let column = i - (row * columns);
-            let x = column * MINITILE_SIDE_LENGTH_WITH_PADDING;
-            let y = row * MINITILE_SIDE_LENGTH_WITH_PADDING;
-            let sprite = Sprite::from_pixel_values(
-                TILEMAP_TEXTURE_SIDE_LENGTH as u32,
-                TILEMAP_TEXTURE_SIDE_LENGTH as u32,
-                map::MINITILE_PX_SIDE_LEN,
-                map::MINITILE_PX_SIDE_LEN,
-                (x + PADDING) as u32,
-                (y + PADDING) as u32,
-                [0.0; 2],
-                false,
-                false,
-            );
-            sprite
+    let pixels = indices
+        .par_iter()
+        .flat_map_iter(|&index| palette[index as usize].to_vec())
+        .collect::<Vec<u8>>();
+
+    // https://stackoverflow.com/questions/57691913/how-to-load-a-texture-from-memory-in-amethyst-engine
+    let texture_builder = TextureBuilder::new()
+        .with_kind(Kind::D2(layout.page_side(), layout.page_side(), 1, 1))
+        .with_view_kind(ViewKind::D2)
+        .with_data_width(layout.page_side())
+        .with_data_height(layout.page_side())
+        .with_sampler_info(SamplerInfo {
+            min_filter: Filter::Nearest,
+            mag_filter: Filter::Nearest,
+            mip_filter: Filter::Nearest,
+            wrap_mode: (WrapMode::Clamp, WrapMode::Clamp, WrapMode::Clamp),
+            lod_bias: 0.0.into(),
+            lod_range: std::ops::Range {
+                start: 0.0.into(),
+                end: 1000.0.into(),
+            },
+            comparison: None,
+            border: PackedColor(0),
+            normalized: true,
+            anisotropic: Anisotropic::Off,
         })
-        .collect();
+        .with_raw_data(pixels, renderer::Format::Rgb8Srgb);
+
+    TextureData::from(texture_builder)
+}
+
+/// Sprites of the minitiles on a page followed by the same sprites flipped
+/// horizontally, see [`AtlasLayout::sprite`].
+fn create_tilemap_sprite_sheet(
+    texture: Handle<Texture>,
+    layout: &AtlasLayout,
+    page: usize,
+) -> SpriteSheet {
+    use rayon::prelude::*;
+
+    let first = page * layout.slots_per_page();
+    let sprite = |i: usize, is_flipped: bool| {
+        let slot = layout
+            .slot(first + i)
+            .expect("minitile is outside of the atlas");
+
+        Sprite::from_pixel_values(
+            layout.page_side(),
+            layout.page_side(),
+            map::MINITILE_PX_SIDE_LEN,
+            map::MINITILE_PX_SIDE_LEN,
+            slot.x,
+            slot.y,
+            [0.0; 2],
+            is_flipped,
+            false,
+        )
+    };
 
-    let flipped_sprites: Vec<Sprite> = (0..sprite_count)
+    let sprite_count = layout.minitiles_on_page(page);
+    let mut sprites: Vec<Sprite> = (0..sprite_count)
         .into_par_iter()
-        .map(|i| {
-            let columns = TILEMAP_TEXTURE_SIDE_LENGTH / MINITILE_SIDE_LENGTH_WITH_PADDING;
-            let row = i / columns;
-            let column = i - (row * columns);
-            let x = column * MINITILE_SIDE_LENGTH_WITH_PADDING;
-            let y = row * MINITILE_SIDE_LENGTH_WITH_PADDING;
-            let sprite = Sprite::from_pixel_values(
-                TILEMAP_TEXTURE_SIDE_LENGTH as u32,
-                TILEMAP_TEXTURE_SIDE_LENGTH as u32,
-                map::MINITILE_PX_SIDE_LEN,
-                map::MINITILE_PX_SIDE_LEN,
-                (x + PADDING) as u32,
-                (y + PADDING) as u32,
-                [0.0; 2],
-                true,
-                false,
-            );
-            sprite
-        })
+        .map(|i| sprite(i, false))
         .collect();
-
-    sprites.extend(flipped_sprites);
+    sprites.par_extend((0..sprite_count).into_par_iter().map(|i| sprite(i, true)));
 
     SpriteSheet { texture, sprites }
 }
 
-fn create_map_texture_pixels(vr4s: Arc<VR4s>, wpes: Arc<WPEs>) -> Vec<u8> {
+/// Lays the minitiles of a page out in rows with a padding around each of
+/// them that repeats its edge, and returns the palette index of every pixel.
+fn create_map_texture_indices(vr4s: &VR4s, layout: &AtlasLayout, page: usize) -> Vec<u8> {
     use rayon::prelude::*;
 
-    let img_buffer = UnsafeImageBufferCell(UnsafeCell::new(
-        ImageBuffer::<image::Rgb<u8>, Vec<u8>>::new(
-            TILEMAP_TEXTURE_SIDE_LENGTH as u32,
-            TILEMAP_TEXTURE_SIDE_LENGTH as u32,
-        ),
-    ));
-
-    vr4s.par_iter().enumerate().for_each(|(i, minitile)| {
-        let columns = TILEMAP_TEXTURE_SIDE_LENGTH / MINITILE_SIDE_LENGTH_WITH_PADDING;
-        let row = i / columns;
-        let column = i - (row * columns);
-        let xi = column * MINITILE_SIDE_LENGTH_WITH_PADDING;
-        let yi = row * MINITILE_SIDE_LENGTH_WITH_PADDING;
-
-        (0..(map::MINITILE_PX_SIDE_LEN as usize))
-            .into_par_iter()
-            .for_each(|xj| {
-                (0..(map::MINITILE_PX_SIDE_LEN as usize))
-                    .into_par_iter()
-                    .for_each(|yj| {
-                        let vr4 = &minitile[xj + yj * map::MINITILE_PX_SIDE_LEN as usize];
-                        let color = &wpes[vr4];
-
-                        unsafe {
-                            if xj == 0 {
-                                (*img_buffer.get()).put_pixel(
-                                    (xi + xj) as u32,
-                                    (yi + PADDING + yj) as u32,
-                                    image::Rgb(color.rgb()),
-                                )
-                            }
-
-                            if xj == (map::MINITILE_PX_SIDE_LEN - 1) as usize {
-                                (*img_buffer.get()).put_pixel(
-                                    (xi + (PADDING * 2) + xj) as u32,
-                                    (yi + PADDING + yj) as u32,
-                                    image::Rgb(color.rgb()),
-                                )
-                            }
-
-                            if yj == 0 {
-                                (*img_buffer.get()).put_pixel(
-                                    (xi + PADDING + xj) as u32,
-                                    (yi + yj) as u32,
-                                    image::Rgb(color.rgb()),
-                                )
-                            }
-
-                            if yj == (map::MINITILE_PX_SIDE_LEN - 1) as usize {
-                                (*img_buffer.get()).put_pixel(
-                                    (xi + PADDING + xj) as u32,
-                                    (yi + (PADDING * 2) + yj) as u32,
-                                    image::Rgb(color.rgb()),
-                                )
-                            }
-                            (*img_buffer.get()).put_pixel(
-                                (xi + PADDING + xj) as u32,
-                                (yi + PADDING + yj) as u32,
-                                image::Rgb(color.rgb()),
-                            )
-                        }
-                    })
-            });
-    });
-
-    img_buffer.into_inner().into_raw()
+    let page_side = layout.page_side() as usize;
+    let slot_side = AtlasLayout::SLOT_SIDE as usize;
+    let padding = AtlasLayout::PADDING as usize;
+    let side = VR4s::MINITILE_SIDE_LENGTH;
+    let columns = layout.columns();
+    let minitiles = vr4s
+        .iter()
+        .skip(page * layout.slots_per_page())
+        .take(layout.minitiles_on_page(page))
+        .collect::<Vec<_>>();
+
+    let mut indices = vec![0; page_side * page_side];
+    indices
+        .par_chunks_mut(page_side)
+        .enumerate()
+        .for_each(|(y, row)| {
+            let yj = (y % slot_side).saturating_sub(padding).min(side - 1);
+            for (x, index) in row.iter_mut().enumerate() {
+                let column = x / slot_side;
+                if column >= columns {
+                    break;
+                }
+
+                let xj = (x % slot_side).saturating_sub(padding).min(side - 1);
+                if let Some(minitile) = minitiles.get(y / slot_side * columns + column) {
+                    *index = usize::from(&minitile[xj + yj * side]) as u8;
+                }
+            }
+        });
+
+    indices
 }
@@ -1,7 +1,6 @@
-use std::sync::Arc;
+use std::time::Duration;
 
 use amethyst::{
-    assets::AssetStorage,
     core::{
         math::{Point3, Vector3},
         Transform,
@@ -11,53 +10,42 @@ use amethyst::{
     tiles::{self, CoordinateEncoder, DrawTiles2DBounds, Region, Tile, TileMap},
     window::ScreenDimensions,
 };
-use bw_assets::{
-    map::{Map, MapHandle},
-    tileset::{CV5s, VR4s, VX4s},
-};
 
+mod creep;
+mod cycle;
+mod grid;
 pub mod map;
 pub mod resources;
 
+pub use creep::CreepSpreadSystem;
+pub use cycle::PaletteCycleSystem;
+pub use grid::{SpriteGrid, SpriteGridSystem, Terrain};
 pub use resources::TilesetHandles;
 
-#[derive(Debug, Default, Clone)]
-pub struct AmethystTileBridge;
+/// Length of a game frame at the fastest game speed.
+const GAME_FRAME: Duration = Duration::from_millis(42);
 
-fn megatile_index(coords: &Point3<u32>, map: &Map) -> usize {
-    (coords.x / bw_assets::map::MEGATILE_SIDE_LEN) as usize
-        + (coords.y / bw_assets::map::MEGATILE_SIDE_LEN) as usize * map.dimensions.width as usize
+/// Tile of the tilemap of a page of the tilemap texture. It only draws the
+/// minitiles on its page.
+#[derive(Debug, Default, Clone)]
+pub struct AmethystTileBridge {
+    page: usize,
 }
 
-fn minitile_index(coords: &Point3<u32>) -> usize {
-    (coords.x % bw_assets::map::MEGATILE_SIDE_LEN) as usize
-        + (coords.y % bw_assets::map::MEGATILE_SIDE_LEN) as usize
-            * bw_assets::map::MEGATILE_SIDE_LEN as usize
+impl AmethystTileBridge {
+    pub fn new(page: usize) -> AmethystTileBridge {
+        AmethystTileBridge { page }
+    }
 }
 
 impl Tile for AmethystTileBridge {
     fn sprite(&self, coords: Point3<u32>, world: &World) -> Option<usize> {
-        let map_handle = world.try_fetch::<MapHandle>()?;
-        let map_storage = world.try_fetch::<AssetStorage<Map>>()?;
-        let map = map_storage.get(&map_handle)?;
-
-        let megatile = &map.megatiles[megatile_index(&coords, &map)];
-
-        let cv5s = &(*world.try_fetch::<Arc<CV5s>>()?);
-
-        let megatile_reference = &cv5s[megatile][megatile];
-
-        let vx4s = &(*world.try_fetch::<Arc<VX4s>>()?);
-
-        let minitiles = &vx4s[megatile_reference];
-        let minitile = &minitiles[minitile_index(&coords)];
-
-        let vr4s = &(*world.try_fetch::<Arc<VR4s>>()?);
+        let (page, sprite) = world.try_fetch::<SpriteGrid>()?.get(&coords)?;
 
-        if minitile.is_horizontally_flipped() {
-            Some(minitile.index() + vr4s.len())
+        if page == self.page {
+            Some(sprite)
         } else {
-            Some(minitile.index())
+            None
         }
     }
 }
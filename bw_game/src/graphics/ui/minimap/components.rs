@@ -6,7 +6,10 @@ use amethyst::{
     ui::UiFinder,
     ui::{UiImage, UiTransform},
 };
-use bw_assets::map::Map;
+use bw_assets::{
+    map::Map,
+    minimap::{letterbox, MinimapUnit},
+};
 
 #[derive(Default)]
 pub struct MinimapMarker;
@@ -43,15 +46,15 @@ impl Minimap {
             world.exec(|finder: UiFinder<'_>| finder.find("minimap_container")),
             world.exec(|finder: UiFinder<'_>| finder.find("minimap")),
         ) {
-            let (map_width, map_height) = {
+            let map_size = {
                 let map_storage = world.read_resource::<AssetStorage<Map>>();
                 let map = map_storage.get(&map_handle).expect("map is missing");
 
-                (map.dimensions.width, map.dimensions.height)
+                (map.pixel_width(), map.pixel_height())
             };
 
             let mut ui_transform = world.write_component::<UiTransform>();
-            if let (Some((container_width, container_height)), Some(minimap_ui_transform)) = (
+            if let (Some(container_size), Some(minimap_ui_transform)) = (
                 ui_transform
                     .get(minimap_container)
                     .map(|minimap_container_ui_transform| {
@@ -62,10 +65,9 @@ impl Minimap {
                     }),
                 ui_transform.get_mut(minimap),
             ) {
-                minimap_ui_transform.width =
-                    container_width.min(container_width * (map_width as f32 / map_height as f32));
-                minimap_ui_transform.height =
-                    container_height.min(container_height * (map_height as f32 / map_width as f32));
+                let (width, height) = letterbox(map_size, container_size);
+                minimap_ui_transform.width = width;
+                minimap_ui_transform.height = height;
             }
 
             world
@@ -89,3 +91,29 @@ impl Minimap {
 impl Component for Minimap {
     type Storage = NullStorage<Minimap>;
 }
+
+/// Units drawn on the minimap, starting out as the units placed on the map.
+///
+/// Whatever moves units around keeps their positions here up to date so that
+/// the dots on the minimap follow them.
+#[derive(Debug, Default)]
+pub struct MinimapUnits(pub Vec<MinimapUnit>);
+
+impl MinimapUnits {
+    pub fn from_map(map: &Map) -> MinimapUnits {
+        MinimapUnits(
+            map.placed_units
+                .iter()
+                .filter_map(MinimapUnit::from_placed)
+                .collect(),
+        )
+    }
+}
+
+/// Events of the minimap, read from an `EventChannel<MinimapEvent>`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MinimapEvent {
+    /// The minimap was right clicked, e.g. to move the selected units there.
+    /// The target is in pixels from the top left of the map.
+    MoveTarget { x: f32, y: f32 },
+}
@@ -2,6 +2,9 @@ mod components;
 mod systems;
 mod textures;
 
-pub use components::{Minimap, MinimapMarker};
-pub use systems::{MinimapMarkerCameraTrackingSystem, MinimapMouseMovementTrackingSystem};
+pub use components::{Minimap, MinimapEvent, MinimapMarker, MinimapUnits};
+pub use systems::{
+    MinimapDotSystem, MinimapMarkerCameraTrackingSystem, MinimapMouseMovementTrackingSystem,
+    MinimapRightClickSystem,
+};
 pub use textures::load_minimap as load_minimap_texture;
@@ -1,22 +1,27 @@
 use std::ops::Range;
 
-use super::{Minimap, MinimapMarker};
+use super::{Minimap, MinimapEvent, MinimapMarker, MinimapUnits};
 use amethyst::{
     assets::{AssetStorage, Handle},
     core::{
-        ecs::{Read, ReadExpect, ReadStorage, System, WriteStorage},
-        Transform,
+        ecs::{Read, ReadExpect, ReadStorage, System, Write, WriteStorage},
+        Parent, Transform,
     },
     ecs::Entities,
-    ecs::Join,
+    ecs::{Entity, Join},
     input::{InputHandler, StringBindings},
     renderer::ActiveCamera,
     renderer::Camera,
+    shrev::EventChannel,
     ui::Anchor,
-    ui::UiTransform,
+    ui::{UiImage, UiTransform},
     window::ScreenDimensions,
+    winit::MouseButton,
+};
+use bw_assets::{
+    map::Map,
+    minimap::{map_position, minimap_dots, MinimapColors},
 };
-use bw_assets::map::Map;
 
 #[derive(Default)]
 
@@ -59,7 +64,7 @@ impl<'s> System<'s> for MinimapMarkerCameraTrackingSystem {
         if let (
             Some(minimap_ui_transform),
             Some((minimap_marker_ui_transform, map)),
-            Some((_, camera_transform)),
+            Some((camera, camera_transform)),
         ) = (
             minimap_ui_transform_opt,
             minimap_markers_join
@@ -98,9 +103,128 @@ impl<'s> System<'s> for MinimapMarkerCameraTrackingSystem {
 
             minimap_marker_ui_transform.local_x = minimap_x;
             minimap_marker_ui_transform.local_y = minimap_y;
+
+            // The marker outlines the view, which changes with the size of the
+            // window and the zoom.
+            let camera_width = 2.0 / camera.matrix[(0, 0)];
+            let camera_height = -2.0 / camera.matrix[(1, 1)];
+            minimap_marker_ui_transform.width =
+                camera_width / map.pixel_width() as f32 * minimap_ui_transform.width;
+            minimap_marker_ui_transform.height =
+                camera_height / map.pixel_height() as f32 * minimap_ui_transform.height;
         }
     }
 }
+
+/// Draws a dot on the minimap for every unit in `MinimapUnits`.
+///
+/// The dots are recomputed every frame so that they follow units as they
+/// move. Dot entities are children of the minimap and are reused from frame
+/// to frame; only the ones that are no longer needed get deleted.
+#[derive(Default)]
+pub struct MinimapDotSystem {
+    dots: Vec<Entity>,
+}
+
+impl<'s> System<'s> for MinimapDotSystem {
+    type SystemData = (
+        Entities<'s>,
+        Option<Read<'s, MinimapUnits>>,
+        Option<Read<'s, MinimapColors>>,
+        Read<'s, AssetStorage<Map>>,
+        ReadStorage<'s, Handle<Map>>,
+        ReadStorage<'s, Minimap>,
+        WriteStorage<'s, UiTransform>,
+        WriteStorage<'s, UiImage>,
+        WriteStorage<'s, Parent>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            minimap_units,
+            minimap_colors,
+            map_storage,
+            map_handles,
+            minimaps,
+            mut ui_transforms,
+            mut ui_images,
+            mut parents,
+        ): Self::SystemData,
+    ) {
+        let (minimap_units, minimap_colors) = match (minimap_units, minimap_colors) {
+            (Some(minimap_units), Some(minimap_colors)) => (minimap_units, minimap_colors),
+            _ => return,
+        };
+
+        let minimap_opt = (&entities, &minimaps, &ui_transforms, &map_handles)
+            .join()
+            .next()
+            .and_then(|(minimap, _, minimap_ui_transform, map_handle)| {
+                map_storage.get(map_handle).map(|map| {
+                    (
+                        minimap,
+                        (minimap_ui_transform.width, minimap_ui_transform.height),
+                        (map.pixel_width(), map.pixel_height()),
+                    )
+                })
+            });
+        let (minimap, minimap_size, map_size) = match minimap_opt {
+            Some(minimap) => minimap,
+            None => return,
+        };
+
+        let dots = minimap_dots(&minimap_units.0, map_size, minimap_size, &minimap_colors);
+
+        for stale_dot in self.dots.drain(dots.len().min(self.dots.len())..) {
+            entities
+                .delete(stale_dot)
+                .expect("failed to delete minimap dot");
+        }
+
+        for (i, dot) in dots.iter().enumerate() {
+            if i == self.dots.len() {
+                let entity = entities.create();
+                parents
+                    .insert(entity, Parent { entity: minimap })
+                    .expect("failed to add minimap dot to the minimap");
+                self.dots.push(entity);
+            }
+            let entity = self.dots[i];
+
+            if let Some(ui_transform) = ui_transforms.get_mut(entity) {
+                ui_transform.local_x = dot.x;
+                ui_transform.local_y = -dot.y;
+                ui_transform.width = dot.width;
+                ui_transform.height = dot.height;
+            } else {
+                // In front of the minimap, letting clicks through to it.
+                let ui_transform = UiTransform::new(
+                    format!("minimap_dot_{}", i),
+                    Anchor::TopLeft,
+                    Anchor::TopLeft,
+                    dot.x,
+                    -dot.y,
+                    1.0,
+                    dot.width,
+                    dot.height,
+                )
+                .into_transparent();
+                ui_transforms
+                    .insert(entity, ui_transform)
+                    .expect("failed to position minimap dot");
+            }
+
+            let [r, g, b] = dot.color;
+            let color = [r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, 1.0];
+            ui_images
+                .insert(entity, UiImage::SolidColor(color))
+                .expect("failed to color minimap dot");
+        }
+    }
+}
+
 /// System that keeps track of mouse movements on the minimap
 ///
 /// This system does not check whether the player has clicked on the minimap
@@ -163,32 +287,12 @@ impl<'s> System<'s> for MinimapMouseMovementTrackingSystem {
 
             if let Some(mouse_position) = mouse_position_opt {
                 assert_eq!(minimap_ui_transform.anchor, Anchor::Middle);
-                let (local_mouse_position_x, local_mouse_position_y) =
-                    get_mouse_position_relative_to_minimap(mouse_position, &screen_dimensions);
-
-                let minimap_x_offset =
-                    minimap_ui_transform.pixel_x() - (minimap_ui_transform.pixel_width() / 2.0);
-                let minimap_y_offset =
-                    minimap_ui_transform.pixel_y() - (minimap_ui_transform.pixel_height() / 2.0);
-
-                let x_percentage =
-                    (local_mouse_position_x - minimap_x_offset) / minimap_ui_transform.width;
-                let y_percentage =
-                    (local_mouse_position_y - minimap_y_offset) / minimap_ui_transform.height;
-
-                let map_width = map.pixel_width() as f32;
-                let map_height = map.pixel_height() as f32;
-
-                let x = rescale(
-                    x_percentage * map_width,
-                    0.0..map_width,
-                    (-map_width / 2.0)..(map_width / 2.0),
-                );
-                let y = rescale(
-                    y_percentage * map_height,
-                    0.0..map_height,
-                    (-map_height / 2.0)..(map_height / 2.0),
-                );
+                let point =
+                    mouse_on_minimap(mouse_position, &screen_dimensions, minimap_ui_transform);
+                let (map_x, map_y) = map_position(point, (map.pixel_width(), map.pixel_height()));
+
+                let x = map_x - map.pixel_width() as f32 / 2.0;
+                let y = map.pixel_height() as f32 / 2.0 - map_y;
 
                 let camera_width = 2.0 / camera.matrix[(0, 0)];
                 let camera_height = -2.0 / camera.matrix[(1, 1)];
@@ -200,6 +304,88 @@ impl<'s> System<'s> for MinimapMouseMovementTrackingSystem {
     }
 }
 
+/// Emits a [`MinimapEvent::MoveTarget`] when the minimap is right clicked.
+#[derive(Default)]
+pub struct MinimapRightClickSystem {
+    /// Whether the right mouse button was down on the last frame.
+    was_down: bool,
+}
+
+impl<'s> System<'s> for MinimapRightClickSystem {
+    type SystemData = (
+        Read<'s, InputHandler<StringBindings>>,
+        ReadStorage<'s, Minimap>,
+        ReadStorage<'s, UiTransform>,
+        ReadExpect<'s, ScreenDimensions>,
+        Read<'s, AssetStorage<Map>>,
+        ReadStorage<'s, Handle<Map>>,
+        Write<'s, EventChannel<MinimapEvent>>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            input,
+            minimaps,
+            ui_transforms,
+            screen_dimensions,
+            map_storage,
+            map_handles,
+            mut minimap_events,
+        ): Self::SystemData,
+    ) {
+        let is_down = input.mouse_button_is_down(MouseButton::Right);
+        let is_clicked = is_down && !self.was_down;
+        self.was_down = is_down;
+        if !is_clicked {
+            return;
+        }
+
+        let mut minimap_join = (&minimaps, &ui_transforms, &map_handles).join();
+        if let (Some((minimap_ui_transform, map)), Some(mouse_position)) = (
+            minimap_join
+                .next()
+                .and_then(|(_, minimap_ui_transform, map_handle)| {
+                    map_storage
+                        .get(map_handle)
+                        .map(|map| (minimap_ui_transform, map))
+                }),
+            input.mouse_position(),
+        ) {
+            let point = mouse_on_minimap(mouse_position, &screen_dimensions, minimap_ui_transform);
+            let is_on_minimap = (0.0..=1.0).contains(&point.0) && (0.0..=1.0).contains(&point.1);
+
+            if is_on_minimap {
+                let (x, y) = map_position(point, (map.pixel_width(), map.pixel_height()));
+                minimap_events.single_write(MinimapEvent::MoveTarget { x, y });
+            }
+        }
+    }
+}
+
+/// Position of the mouse on the minimap as a fraction of its width and height
+/// from its top left.
+///
+/// Both the mouse and the minimap are in screen pixels, which keeps clicks
+/// working whatever the size of the window or the scale of the UI.
+fn mouse_on_minimap(
+    mouse_position: (f32, f32),
+    screen_dimensions: &ScreenDimensions,
+    minimap_ui_transform: &UiTransform,
+) -> (f32, f32) {
+    let (mouse_x, mouse_y) =
+        get_mouse_position_relative_to_minimap(mouse_position, screen_dimensions);
+
+    // The minimap is positioned by its center, with y going up.
+    let left = minimap_ui_transform.pixel_x() - minimap_ui_transform.pixel_width() / 2.0;
+    let top = minimap_ui_transform.pixel_y() + minimap_ui_transform.pixel_height() / 2.0;
+
+    (
+        (mouse_x - left) / minimap_ui_transform.pixel_width(),
+        (top - mouse_y) / minimap_ui_transform.pixel_height(),
+    )
+}
+
 fn get_mouse_position_relative_to_minimap(
     mouse_position: (f32, f32),
     screen_dimensions: &ScreenDimensions,
@@ -1,5 +1,5 @@
 use amethyst::{
-    assets::{Handle, ProgressCounter},
+    assets::{AssetStorage, Handle, ProgressCounter},
     core::ecs::World,
 };
 use bw_assets::map::Map;
@@ -8,7 +8,8 @@ mod minimap;
 pub mod resources;
 
 pub use self::minimap::{
-    Minimap, MinimapMarker, MinimapMarkerCameraTrackingSystem, MinimapMouseMovementTrackingSystem,
+    Minimap, MinimapDotSystem, MinimapEvent, MinimapMarker, MinimapMarkerCameraTrackingSystem,
+    MinimapMouseMovementTrackingSystem, MinimapRightClickSystem, MinimapUnits,
 };
 
 pub fn create(params: (&mut World, &Handle<Map>, &mut ProgressCounter)) {
@@ -20,5 +21,13 @@ pub fn create(params: (&mut World, &Handle<Map>, &mut ProgressCounter)) {
     Minimap::attach((world, map_handle, &minimap_texture_handle));
     MinimapMarker::attach(world, map_handle);
 
+    let minimap_units = {
+        let map_storage = world.read_resource::<AssetStorage<Map>>();
+        let map = map_storage.get(map_handle).expect("map is missing");
+
+        MinimapUnits::from_map(map)
+    };
+    world.insert(minimap_units);
+
     resources::load_fonts(world, progress_counter)
 }
@@ -5,11 +5,7 @@ use amethyst::{
     prelude::*,
     ui::{FontAsset, TtfFormat},
 };
-use bw_assets::dat::{
-    FlingyDatFormat, FlingyDatHandle, SpritesDatFormat, SpritesDatHandle, TechDataDatFormat,
-    TechDataDatHandle, UnitsDatFormat, UnitsDatHandle, UpgradesDatFormat, UpgradesDatHandle,
-    WeaponsDatFormat, WeaponsDatHandle,
-};
+use bw_assets::{pcx::Pcx, tbl::Tbl};
 
 #[derive(Clone)]
 pub struct Fonts {
@@ -49,73 +45,16 @@ pub fn load_fonts(world: &mut World, progress_counter: &mut ProgressCounter) {
     world.insert(fonts);
 }
 
-#[derive(Debug, Clone)]
-pub struct DatHandles {
-    pub units_dat: UnitsDatHandle,
-    pub flingy_dat: FlingyDatHandle,
-    pub weapons_dat: WeaponsDatHandle,
-    pub sprites_dat: SpritesDatHandle,
-    pub tech_data_dat: TechDataDatHandle,
-    pub upgrades_dat: UpgradesDatHandle,
-}
-
-pub fn load_dats(world: &mut World, progress_counter: &mut ProgressCounter) -> DatHandles {
-    let mut progress_counter_newtype = ProgressCounterMutRef::new(progress_counter);
-
-    let units_dat: UnitsDatHandle = world.read_resource::<Loader>().load_from(
-        "arr\\units.dat",
-        UnitsDatFormat,
-        "bw_assets",
-        &mut progress_counter_newtype,
-        &world.read_resource(),
-    );
+/// The string table that units, weapons, upgrades and techs take their names
+/// from.
+pub struct StatTxt(pub Tbl);
 
-    let flingy_dat = world.read_resource::<Loader>().load_from(
-        "arr\\flingy.dat",
-        FlingyDatFormat,
-        "bw_assets",
-        &mut progress_counter_newtype,
-        &world.read_resource(),
-    );
+/// The string table with the paths of the GRP files that images are drawn
+/// from.
+pub struct ImagesTbl(pub Tbl);
 
-    let weapons_dat = world.read_resource::<Loader>().load_from(
-        "arr\\weapons.dat",
-        WeaponsDatFormat,
-        "bw_assets",
-        &mut progress_counter_newtype,
-        &world.read_resource(),
-    );
+/// `game\tminimap.pcx`, with the colours of the players on the minimap.
+pub struct TMinimap(pub Pcx);
 
-    let sprites_dat = world.read_resource::<Loader>().load_from(
-        "arr\\sprites.dat",
-        SpritesDatFormat,
-        "bw_assets",
-        &mut progress_counter_newtype,
-        &world.read_resource(),
-    );
-
-    let tech_data_dat = world.read_resource::<Loader>().load_from(
-        "arr\\techdata.dat",
-        TechDataDatFormat,
-        "bw_assets",
-        &mut progress_counter_newtype,
-        &world.read_resource(),
-    );
-
-    let upgrades_dat = world.read_resource::<Loader>().load_from(
-        "arr\\upgrades.dat",
-        UpgradesDatFormat,
-        "bw_assets",
-        &mut progress_counter_newtype,
-        &world.read_resource(),
-    );
-
-    DatHandles {
-        units_dat,
-        flingy_dat,
-        weapons_dat,
-        sprites_dat,
-        tech_data_dat,
-        upgrades_dat,
-    }
-}
+/// `game\tunit.pcx`, the palette the player colours of units are read from.
+pub struct TUnit(pub Pcx);
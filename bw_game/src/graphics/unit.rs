@@ -0,0 +1,293 @@
+//! Entities of the units placed on the map.
+//!
+//! Every placed unit is drawn with the first frame of the GRP of its image.
+//! Units whose graphics can't be resolved, e.g. because their unit id is
+//! unknown, are drawn as a placeholder square instead.
+
+use crate::graphics::ui::resources::ImagesTbl;
+use amethyst::{
+    assets::{AssetStorage, Handle, Loader, ProgressCounter},
+    core::{ecs::World, Transform},
+    ecs::{Component, DenseVecStorage},
+    prelude::*,
+    renderer::{
+        rendy::{
+            hal::image::{Filter, SamplerInfo, ViewKind, WrapMode},
+            texture::TextureBuilder,
+        },
+        types::TextureData,
+        Format, Kind, Sprite, SpriteRender, SpriteSheet, Texture, Transparent,
+    },
+};
+use bw_assets::{
+    dat::{FlingyDat, ImagesDat, SpritesDat, UnitGraphics, UnitImage, UnitsDat},
+    grp::{DrawMode, Grp, GrpAsset, GrpFormat, GrpHandle},
+    map::Map,
+    pcx::Pcx,
+    tileset::WPEs,
+};
+use bw_core::UnitId;
+use log::warn;
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    sync::Arc,
+};
+
+/// Depth of units, in front of the tilemap and behind the debug overlays.
+const UNIT_Z: f32 = 0.5;
+
+/// Side of the square that units without graphics are drawn as.
+const PLACEHOLDER_SIDE: u32 = 16;
+
+const PLACEHOLDER_COLOR: [u8; 4] = [0xFF, 0x00, 0xFF, 0xFF];
+
+/// A unit placed on the map.
+#[derive(Debug, Clone, Copy)]
+pub struct PlacedUnit {
+    /// `None` if the unit id in the map is unknown.
+    pub unit_id: Option<UnitId>,
+    pub owner: u8,
+}
+
+impl Component for PlacedUnit {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Images of the units placed on the map and the GRPs they are drawn from.
+#[derive(Default)]
+pub struct PlacedUnitImages {
+    images: HashMap<UnitId, UnitImage>,
+    grps: HashMap<String, GrpHandle>,
+}
+
+impl PlacedUnitImages {
+    /// Resolves the image of every kind of unit on the map and starts loading
+    /// their GRPs.
+    pub fn load(
+        world: &World,
+        map_handle: &Handle<Map>,
+        progress_counter: &mut ProgressCounter,
+    ) -> PlacedUnitImages {
+        let map_storage = world.read_resource::<AssetStorage<Map>>();
+        let map = map_storage.get(map_handle).expect("map is missing");
+        let units_dat = world.read_resource::<UnitsDat>();
+        let flingy_dat = world.read_resource::<FlingyDat>();
+        let sprites_dat = world.read_resource::<SpritesDat>();
+        let images_dat = world.read_resource::<ImagesDat>();
+        let images_tbl = world.read_resource::<ImagesTbl>();
+        let unit_graphics = UnitGraphics {
+            units_dat: &units_dat,
+            flingy_dat: &flingy_dat,
+            sprites_dat: &sprites_dat,
+            images_dat: &images_dat,
+            images_tbl: &images_tbl.0,
+        };
+
+        let loader = world.read_resource::<Loader>();
+        let grp_storage = world.read_resource::<AssetStorage<GrpAsset>>();
+        let mut placed_unit_images = PlacedUnitImages::default();
+        for unit_id in map.placed_units.iter().filter_map(|unit| unit.unit_id()) {
+            if let Entry::Vacant(entry) = placed_unit_images.images.entry(unit_id) {
+                match unit_graphics.image(unit_id) {
+                    Ok(image) => {
+                        placed_unit_images
+                            .grps
+                            .entry(image.grp_path.clone())
+                            .or_insert_with(|| {
+                                loader.load_from(
+                                    image.grp_path.as_str(),
+                                    GrpFormat,
+                                    "bw_assets",
+                                    &mut *progress_counter,
+                                    &grp_storage,
+                                )
+                            });
+                        entry.insert(image);
+                    }
+                    Err(err) => warn!("no graphics for {:?}: {}", unit_id, err),
+                }
+            }
+        }
+
+        placed_unit_images
+    }
+
+    /// Whether every GRP has been loaded.
+    pub fn is_loaded(&self, grp_storage: &AssetStorage<GrpAsset>) -> bool {
+        self.grps
+            .values()
+            .all(|handle| grp_storage.get(handle).is_some())
+    }
+}
+
+/// Creates an entity for every unit placed on the map.
+///
+/// `tunit` is `game\tunit.pcx`, which the player colours of units are read
+/// from.
+pub fn create(
+    world: &mut World,
+    map_handle: &Handle<Map>,
+    placed_unit_images: &PlacedUnitImages,
+    tunit: &Pcx,
+) {
+    let (placed_units, map_width, map_height) = {
+        let map_storage = world.read_resource::<AssetStorage<Map>>();
+        let map = map_storage.get(map_handle).expect("map is missing");
+
+        (
+            map.placed_units.clone(),
+            map.pixel_width() as f32,
+            map.pixel_height() as f32,
+        )
+    };
+    let palette = world
+        .try_fetch::<Arc<WPEs>>()
+        .expect("wpes is missing")
+        .rgb();
+    let grps = {
+        let mut grp_storage = world.write_resource::<AssetStorage<GrpAsset>>();
+
+        placed_unit_images
+            .grps
+            .iter()
+            .filter_map(|(path, handle)| {
+                grp_storage
+                    .get_mut(handle)
+                    .and_then(|asset| asset.take())
+                    .map(|grp| (path.as_str(), grp))
+            })
+            .collect::<HashMap<_, _>>()
+    };
+
+    // Every image is drawn once per player whose colours it is drawn in.
+    let mut sprite_sheets = HashMap::new();
+    let mut placeholder = None;
+    for unit in &placed_units {
+        let owner = usize::from(unit.owner()) as u8;
+        let image = unit
+            .unit_id()
+            .and_then(|unit_id| placed_unit_images.images.get(&unit_id));
+        let grp = image.and_then(|image| grps.get(image.grp_path.as_str()));
+
+        let sprite_sheet = match (image, grp) {
+            (Some(image), Some(grp)) if !grp.is_empty() => {
+                let player = if image.has_player_colors() {
+                    Some(owner)
+                } else {
+                    None
+                };
+
+                sprite_sheets
+                    .entry((image.image, player))
+                    .or_insert_with(|| {
+                        let remap = player.and_then(|player| tunit.player_colors(player));
+                        let mode = remap.as_ref().map_or(DrawMode::Normal, DrawMode::Remap);
+
+                        load_grp_sprite_sheet(world, grp, &palette, mode)
+                    })
+                    .clone()
+            }
+            _ => {
+                match unit.unit_id() {
+                    Some(unit_id) => warn!(
+                        "drawing a placeholder for {:?} {}, whose graphics are missing",
+                        unit_id,
+                        unit.serial_number()
+                    ),
+                    None => warn!(
+                        "drawing a placeholder for unit {}, whose unit id is invalid",
+                        unit.serial_number()
+                    ),
+                }
+
+                placeholder
+                    .get_or_insert_with(|| load_placeholder_sprite_sheet(world))
+                    .clone()
+            }
+        };
+
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(
+            unit.x() as f32 - map_width / 2.0,
+            map_height / 2.0 - unit.y() as f32,
+            UNIT_Z,
+        );
+
+        world
+            .create_entity()
+            .with(PlacedUnit {
+                unit_id: unit.unit_id(),
+                owner,
+            })
+            .with(transform)
+            .with(SpriteRender::new(sprite_sheet, 0))
+            .with(Transparent)
+            .build();
+    }
+}
+
+/// Sprite sheet of the first frame of a GRP, drawn centered on the unit the
+/// way the game does.
+fn load_grp_sprite_sheet(
+    world: &World,
+    grp: &Grp,
+    palette: &[[u8; 3]],
+    mode: DrawMode<'_>,
+) -> Handle<SpriteSheet> {
+    let frame = grp.frame(0).expect("GRP has no frames");
+    let (width, height) = (frame.width().max(1) as u32, frame.height().max(1) as u32);
+    let mut rgba = frame.to_rgba(palette, mode);
+    rgba.resize((width * height * 4) as usize, 0);
+
+    // The frame is offset by its bounding box inside of the box of the GRP,
+    // whose center is the position of the unit.
+    let offset_x = frame.x() as f32 + width as f32 / 2.0 - grp.width() as f32 / 2.0;
+    let offset_y = frame.y() as f32 + height as f32 / 2.0 - grp.height() as f32 / 2.0;
+
+    load_sprite_sheet(world, width, height, rgba, [-offset_x, offset_y])
+}
+
+fn load_placeholder_sprite_sheet(world: &World) -> Handle<SpriteSheet> {
+    let rgba = PLACEHOLDER_COLOR
+        .iter()
+        .copied()
+        .cycle()
+        .take((PLACEHOLDER_SIDE * PLACEHOLDER_SIDE * 4) as usize)
+        .collect();
+
+    load_sprite_sheet(world, PLACEHOLDER_SIDE, PLACEHOLDER_SIDE, rgba, [0.0; 2])
+}
+
+fn load_sprite_sheet(
+    world: &World,
+    width: u32,
+    height: u32,
+    rgba: Vec<u8>,
+    offsets: [f32; 2],
+) -> Handle<SpriteSheet> {
+    let loader = world.read_resource::<Loader>();
+
+    let texture_builder = TextureBuilder::new()
+        .with_kind(Kind::D2(width, height, 1, 1))
+        .with_view_kind(ViewKind::D2)
+        .with_data_width(width)
+        .with_data_height(height)
+        .with_sampler_info(SamplerInfo::new(Filter::Nearest, WrapMode::Clamp))
+        .with_raw_data(rgba, Format::Rgba8Srgb);
+    let texture: Handle<Texture> = loader.load_from_data(
+        TextureData::from(texture_builder),
+        (),
+        &world.read_resource::<AssetStorage<Texture>>(),
+    );
+
+    let sprite =
+        Sprite::from_pixel_values(width, height, width, height, 0, 0, offsets, false, false);
+    loader.load_from_data(
+        SpriteSheet {
+            texture,
+            sprites: vec![sprite],
+        },
+        (),
+        &world.read_resource::<AssetStorage<SpriteSheet>>(),
+    )
+}
@@ -15,17 +15,24 @@ use amethyst::{
 };
 use amethyst::{tiles::MortonEncoder2D, utils::fps_counter::FpsCounterBundle};
 use bw_assets::{
+    dat::ImagesDatAsset,
     dat::SpritesDatAsset,
     dat::TechDataDatAsset,
     dat::UpgradesDatAsset,
     dat::WeaponsDatAsset,
     dat::{FlingyDatAsset, UnitsDatAsset},
+    grp::GrpAsset,
+    iscript::IscriptAsset,
     map::Map,
     mpq::ArcMPQ,
+    pcx::PcxAsset,
+    tbl::TblAsset,
     tileset::{CV5sAsset, VF4sAsset, VR4sAsset, VX4sAsset, WPEsAsset},
 };
 use fern::colors::{Color, ColoredLevelConfig};
-use graphics::camera::{CameraMovementSystem, CameraRotationSystem};
+#[cfg(feature = "debug-camera")]
+use graphics::camera::CameraRotationSystem;
+use graphics::camera::{CameraMovementSystem, CameraZoomSystem};
 use ron;
 use std::{fs::File, str::FromStr};
 
@@ -79,10 +86,6 @@ fn main() -> amethyst::Result<()> {
         "amethyst_utils::fps_counter".to_string(),
         amethyst::LogLevelFilter::Off,
     ));
-    logger_config.module_levels.push((
-        "incremental_topo".to_string(),
-        amethyst::LogLevelFilter::Off,
-    ));
     let logger = setup_logger(logger_config)?;
     logger.start();
 
@@ -148,15 +151,30 @@ fn main() -> amethyst::Result<()> {
             &[],
         )
         .with(
-            CameraMovementSystem,
+            Processor::<ImagesDatAsset>::new(),
+            "images_dat_processor",
+            &[],
+        )
+        .with(Processor::<TblAsset>::new(), "tbl_processor", &[])
+        .with(Processor::<GrpAsset>::new(), "grp_processor", &[])
+        .with(Processor::<PcxAsset>::new(), "pcx_processor", &[])
+        .with(Processor::<IscriptAsset>::new(), "iscript_processor", &[])
+        .with(
+            CameraMovementSystem::new(bw_config.camera.clone()),
             "camera_movement_system",
             &["input_system"],
         )
         .with(
-            CameraRotationSystem,
-            "camera_rotation_system",
-            &["input_system"],
+            CameraZoomSystem::new(&bw_config.camera),
+            "camera_zoom_system",
+            &["camera_movement_system"],
         );
+    #[cfg(feature = "debug-camera")]
+    let game_data = game_data.with(
+        CameraRotationSystem,
+        "camera_rotation_system",
+        &["input_system"],
+    );
 
     let state = state::MatchLoadingState::new(app_root.join("assets"), bw_config);
 
@@ -0,0 +1,369 @@
+//! Declarative registry of the assets of a match.
+//!
+//! Every asset is registered once with the names of the assets it depends on
+//! and a function that loads it. The registry is turned into a dependency
+//! graph, which is checked for unknown dependencies and cycles up front so
+//! that a mistake in the registry is reported when the game starts rather
+//! than by a load that never finishes.
+
+use amethyst::prelude::*;
+use std::{
+    collections::HashSet,
+    fmt::{Display, Formatter},
+};
+
+/// Starts or continues loading an asset and returns whether it is loaded.
+///
+/// It is called every frame once the dependencies of the asset are loaded,
+/// until it returns `true`. It owns whatever it needs between the frames, such
+/// as the handle of the asset it is waiting for.
+pub type LoadFn<C> = Box<dyn FnMut(&mut C, &mut World) -> bool>;
+
+/// An asset, the assets it depends on and how it is loaded.
+pub struct AssetRegistration<C> {
+    pub name: &'static str,
+    pub dependencies: &'static [&'static str],
+    pub load: LoadFn<C>,
+}
+
+/// The registrations of every asset, in the order they are listed in.
+pub struct AssetRegistry<C> {
+    registrations: Vec<AssetRegistration<C>>,
+}
+
+impl<C> Default for AssetRegistry<C> {
+    fn default() -> AssetRegistry<C> {
+        AssetRegistry {
+            registrations: Vec::new(),
+        }
+    }
+}
+
+impl<C> AssetRegistry<C> {
+    /// Registers an asset that is loaded by `load` once every asset in
+    /// `dependencies` has been loaded.
+    pub fn register<L>(
+        mut self,
+        name: &'static str,
+        dependencies: &'static [&'static str],
+        load: L,
+    ) -> AssetRegistry<C>
+    where
+        L: FnMut(&mut C, &mut World) -> bool + 'static,
+    {
+        self.registrations.push(AssetRegistration {
+            name,
+            dependencies,
+            load: Box::new(load),
+        });
+
+        self
+    }
+
+    /// Dependency graph of the registered assets, or why there is none.
+    pub fn build(self) -> Result<AssetGraph<C>, RegistryError> {
+        let mut names = HashSet::new();
+        for registration in &self.registrations {
+            if !names.insert(registration.name) {
+                return Err(RegistryError::Duplicate {
+                    name: registration.name,
+                });
+            }
+        }
+
+        for registration in &self.registrations {
+            for &dependency in registration.dependencies {
+                if !names.contains(dependency) {
+                    return Err(RegistryError::UnknownDependency {
+                        name: registration.name,
+                        dependency,
+                    });
+                }
+            }
+        }
+
+        let mut order: Vec<Node<C>> = Vec::with_capacity(self.registrations.len());
+        let mut remaining = self
+            .registrations
+            .into_iter()
+            .map(|registration| Node {
+                name: registration.name,
+                dependencies: registration.dependencies,
+                load: registration.load,
+                loaded: false,
+            })
+            .collect::<Vec<_>>();
+        while !remaining.is_empty() {
+            let next = remaining.iter().position(|node| {
+                node.dependencies
+                    .iter()
+                    .all(|&dependency| order.iter().any(|ordered| ordered.name == dependency))
+            });
+            match next {
+                Some(next) => order.push(remaining.remove(next)),
+                None => {
+                    return Err(RegistryError::Cycle {
+                        names: find_cycle(&remaining),
+                    })
+                }
+            }
+        }
+
+        Ok(AssetGraph { nodes: order })
+    }
+}
+
+/// Follows the dependencies of `nodes`, none of which can be ordered, until
+/// it gets back to an asset it has already been at.
+fn find_cycle<C>(nodes: &[Node<C>]) -> Vec<&'static str> {
+    let mut path: Vec<&'static str> = Vec::new();
+    let mut name = nodes[0].name;
+    while !path.contains(&name) {
+        path.push(name);
+        let node = nodes
+            .iter()
+            .find(|node| node.name == name)
+            .expect("node is missing");
+        // An asset can't be ordered because one of its dependencies can't.
+        name = node
+            .dependencies
+            .iter()
+            .copied()
+            .find(|&dependency| nodes.iter().any(|other| other.name == dependency))
+            .expect("node without unordered dependencies");
+    }
+
+    let start = path
+        .iter()
+        .position(|&visited| visited == name)
+        .expect("cycle is missing");
+    path.split_off(start)
+}
+
+/// Why the registered assets can't be loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistryError {
+    Duplicate {
+        name: &'static str,
+    },
+    UnknownDependency {
+        name: &'static str,
+        dependency: &'static str,
+    },
+    /// Every asset depends on the next one and the last one on the first.
+    Cycle {
+        names: Vec<&'static str>,
+    },
+}
+
+impl Display for RegistryError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegistryError::Duplicate { name } => write!(f, "{} is registered twice", name),
+            RegistryError::UnknownDependency { name, dependency } => write!(
+                f,
+                "{} depends on {}, which isn't registered",
+                name, dependency
+            ),
+            RegistryError::Cycle { names } => {
+                write!(f, "assets depend on each other: ")?;
+                for name in names {
+                    write!(f, "{} → ", name)?;
+                }
+                write!(f, "{}", names[0])
+            }
+        }
+    }
+}
+
+/// An asset in the dependency graph.
+pub struct Node<C> {
+    pub name: &'static str,
+    dependencies: &'static [&'static str],
+    load: LoadFn<C>,
+    loaded: bool,
+}
+
+impl<C> Node<C> {
+    pub fn is_loaded(&self) -> bool {
+        self.loaded
+    }
+}
+
+/// State of an asset, shown on the loading screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeState {
+    /// Some of its dependencies aren't loaded yet.
+    Waiting,
+    Loading,
+    Loaded,
+}
+
+impl Display for NodeState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NodeState::Waiting => write!(f, "waiting"),
+            NodeState::Loading => write!(f, "loading"),
+            NodeState::Loaded => write!(f, "loaded"),
+        }
+    }
+}
+
+/// Dependency graph of the registered assets.
+pub struct AssetGraph<C> {
+    /// Nodes in dependency order, otherwise in the order they were registered.
+    nodes: Vec<Node<C>>,
+}
+
+impl<C> Default for AssetGraph<C> {
+    fn default() -> AssetGraph<C> {
+        AssetGraph { nodes: Vec::new() }
+    }
+}
+
+impl<C> AssetGraph<C> {
+    /// Nodes in dependency order, otherwise in the order they were registered.
+    pub fn nodes(&self) -> &[Node<C>] {
+        &self.nodes
+    }
+
+    pub fn state(&self, node: &Node<C>) -> NodeState {
+        if node.is_loaded() {
+            NodeState::Loaded
+        } else if node
+            .dependencies
+            .iter()
+            .filter_map(|&dependency| self.nodes.iter().find(|other| other.name == dependency))
+            .all(|dependency| dependency.is_loaded())
+        {
+            NodeState::Loading
+        } else {
+            NodeState::Waiting
+        }
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.nodes.iter().all(|node| node.is_loaded())
+    }
+
+    /// Loads every asset whose dependencies are loaded.
+    ///
+    /// Assets that don't depend on each other are started in the same frame,
+    /// so the loader reads their files on its worker threads in parallel.
+    /// Assets are loaded in dependency order, so an asset that finishes
+    /// loading this frame lets the assets that depend on it start right away.
+    pub fn load(&mut self, context: &mut C, world: &mut World) {
+        for i in 0..self.nodes.len() {
+            if self.state(&self.nodes[i]) == NodeState::Loading {
+                let node = &mut self.nodes[i];
+                node.loaded = (node.load)(context, world);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    /// The assets that were asked to load, in order, and whether `a` is
+    /// loaded when it is asked.
+    #[derive(Default)]
+    struct Context {
+        loads: Vec<&'static str>,
+        a_is_ready: bool,
+    }
+
+    fn load_a(context: &mut Context, _: &mut World) -> bool {
+        context.loads.push("a");
+        context.a_is_ready
+    }
+
+    fn load_b(context: &mut Context, _: &mut World) -> bool {
+        context.loads.push("b");
+        true
+    }
+
+    fn names(graph: &AssetGraph<Context>) -> Vec<&'static str> {
+        graph.nodes().iter().map(|node| node.name).collect()
+    }
+
+    #[test]
+    fn it_rejects_an_asset_that_is_registered_twice() {
+        let registry = AssetRegistry::<Context>::default()
+            .register("a", &[], load_a)
+            .register("a", &[], load_b);
+
+        assert_that(&registry.build().err())
+            .is_equal_to(Some(RegistryError::Duplicate { name: "a" }));
+    }
+
+    #[test]
+    fn it_rejects_a_dependency_that_isnt_registered() {
+        let registry = AssetRegistry::<Context>::default()
+            .register("a", &[], load_a)
+            .register("b", &["a", "c"], load_b);
+
+        assert_that(&registry.build().err()).is_equal_to(Some(RegistryError::UnknownDependency {
+            name: "b",
+            dependency: "c",
+        }));
+    }
+
+    #[test]
+    fn it_reports_every_asset_of_a_cycle() {
+        let registry = AssetRegistry::<Context>::default()
+            .register("d", &[], load_b)
+            .register("a", &["d", "b"], load_a)
+            .register("b", &["c"], load_b)
+            .register("c", &["a"], load_b);
+
+        let err = registry.build().err();
+
+        assert_that(&err).is_equal_to(Some(RegistryError::Cycle {
+            names: vec!["a", "b", "c"],
+        }));
+        assert_that(&err.unwrap().to_string())
+            .is_equal_to("assets depend on each other: a → b → c → a".to_string());
+    }
+
+    #[test]
+    fn it_orders_assets_after_their_dependencies() {
+        let graph = AssetRegistry::<Context>::default()
+            .register("c", &[], load_b)
+            .register("b", &["a"], load_b)
+            .register("a", &[], load_a)
+            .build()
+            .unwrap_or_else(|err| panic!("{}", err));
+
+        assert_that(&names(&graph)).is_equal_to(vec!["c", "a", "b"]);
+    }
+
+    #[test]
+    fn it_loads_an_asset_once_its_dependencies_are_loaded() {
+        let mut graph = AssetRegistry::<Context>::default()
+            .register("b", &["a"], load_b)
+            .register("a", &[], load_a)
+            .build()
+            .unwrap_or_else(|err| panic!("{}", err));
+        let mut context = Context::default();
+        let mut world = World::new();
+
+        graph.load(&mut context, &mut world);
+
+        assert_that(&context.loads).is_equal_to(vec!["a"]);
+        assert_that(&graph.state(&graph.nodes()[1])).is_equal_to(NodeState::Waiting);
+        assert_that(&graph.is_loaded()).is_false();
+
+        context.a_is_ready = true;
+        graph.load(&mut context, &mut world);
+
+        assert_that(&context.loads).is_equal_to(vec!["a", "a", "b"]);
+        assert_that(&graph.is_loaded()).is_true();
+
+        graph.load(&mut context, &mut world);
+
+        assert_that(&context.loads).has_length(3);
+    }
+}
@@ -11,7 +11,12 @@ use log::info;
 
 use crate::graphics::{
     camera::CameraTranslationClampSystem,
-    ui::{MinimapMarkerCameraTrackingSystem, MinimapMouseMovementTrackingSystem},
+    debug::{DebugOverlaySystem, DebugOverlayToggleSystem},
+    tile::{CreepSpreadSystem, PaletteCycleSystem, SpriteGridSystem},
+    ui::{
+        MinimapDotSystem, MinimapMarkerCameraTrackingSystem, MinimapMouseMovementTrackingSystem,
+        MinimapRightClickSystem,
+    },
 };
 
 #[derive(PartialEq)]
@@ -57,6 +62,32 @@ impl<'a, 'b> SimpleState for GameplayState<'a, 'b> {
             "minimap_camera_tracking_system",
             &["camera_translation_clamp_system"],
         );
+        dispatcher_builder.add(
+            MinimapRightClickSystem::default(),
+            "minimap_right_click_system",
+            &[],
+        );
+        dispatcher_builder.add(MinimapDotSystem::default(), "minimap_dot_system", &[]);
+        dispatcher_builder.add(PaletteCycleSystem::default(), "palette_cycle_system", &[]);
+        dispatcher_builder.add(CreepSpreadSystem::default(), "creep_spread_system", &[]);
+        dispatcher_builder.add(
+            SpriteGridSystem::default(),
+            "sprite_grid_system",
+            &["creep_spread_system"],
+        );
+        dispatcher_builder.add(
+            DebugOverlayToggleSystem::default(),
+            "debug_overlay_toggle_system",
+            &[],
+        );
+        dispatcher_builder.add(
+            DebugOverlaySystem::default(),
+            "debug_overlay_system",
+            &[
+                "debug_overlay_toggle_system",
+                "camera_translation_clamp_system",
+            ],
+        );
 
         let mut dispatcher = dispatcher_builder
             .with_pool((*world.read_resource::<ArcThreadPool>()).clone())
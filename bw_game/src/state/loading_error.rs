@@ -0,0 +1,153 @@
+use crate::config::BWConfig;
+use amethyst::{
+    assets::AssetErrorMeta,
+    ecs::Entity,
+    input::is_close_requested,
+    prelude::*,
+    ui::{UiCreator, UiEventType, UiFinder, UiText},
+    SimpleState, SimpleTrans,
+};
+use log::info;
+use std::{
+    fmt::{Display, Formatter},
+    path::PathBuf,
+};
+
+/// An asset that failed to load and why.
+#[derive(Debug, Clone)]
+pub struct LoadingError {
+    /// Name of the asset, e.g. `STARDAT.MPQ` or `maps/(2)Destination.scx`.
+    pub asset: String,
+    pub reason: String,
+}
+
+impl From<AssetErrorMeta> for LoadingError {
+    fn from(meta: AssetErrorMeta) -> LoadingError {
+        LoadingError {
+            asset: meta.asset_name,
+            reason: meta
+                .error
+                .causes()
+                .map(|cause| cause.to_string())
+                .collect::<Vec<_>>()
+                .join(": "),
+        }
+    }
+}
+
+impl Display for LoadingError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.asset, self.reason)
+    }
+}
+
+/// State that shows why loading a match failed and offers to retry or quit.
+pub struct LoadingErrorState {
+    assets_dir: PathBuf,
+    config: BWConfig,
+    errors: Vec<LoadingError>,
+
+    /// Nodes of the asset dependency graph that were loading when it failed.
+    loading: Vec<String>,
+
+    ui: Option<Entity>,
+    error_text: Option<Entity>,
+    retry: Option<Entity>,
+    quit: Option<Entity>,
+}
+
+impl LoadingErrorState {
+    pub fn new(
+        assets_dir: PathBuf,
+        config: BWConfig,
+        errors: Vec<LoadingError>,
+        loading: Vec<String>,
+    ) -> LoadingErrorState {
+        LoadingErrorState {
+            assets_dir,
+            config,
+            errors,
+            loading,
+            ui: None,
+            error_text: None,
+            retry: None,
+            quit: None,
+        }
+    }
+
+    fn text(&self) -> String {
+        let mut text = self
+            .errors
+            .iter()
+            .map(|error| error.to_string())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        if !self.loading.is_empty() {
+            text.push_str(&format!("\n\nWhile loading {}", self.loading.join(", ")));
+        }
+
+        text
+    }
+}
+
+impl SimpleState for LoadingErrorState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        info!("LoadingErrorState started");
+
+        let StateData { world, .. } = data;
+
+        self.ui = Some(
+            world.exec(|mut creator: UiCreator<'_>| creator.create("ui/loading_error.ron", ())),
+        );
+    }
+
+    fn on_stop(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let StateData { world, .. } = data;
+
+        if let Some(ui) = self.ui.take() {
+            super::delete_ui(world, ui);
+        }
+    }
+
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        let StateData { world, .. } = data;
+
+        if self.error_text.is_none() {
+            world.exec(|finder: UiFinder<'_>| {
+                self.error_text = finder.find("loading_error");
+                self.retry = finder.find("loading_error_retry");
+                self.quit = finder.find("loading_error_quit");
+            });
+
+            let mut ui_text = world.write_storage::<UiText>();
+            if let Some(error_text) = self.error_text.and_then(|entity| ui_text.get_mut(entity)) {
+                error_text.text = self.text();
+            }
+        }
+
+        Trans::None
+    }
+
+    fn handle_event(
+        &mut self,
+        _: StateData<'_, GameData<'_, '_>>,
+        event: StateEvent,
+    ) -> SimpleTrans {
+        match &event {
+            StateEvent::Window(event) if is_close_requested(&event) => Trans::Quit,
+            StateEvent::Ui(ui_event) if ui_event.event_type == UiEventType::Click => {
+                if self.retry == Some(ui_event.target) {
+                    Trans::Switch(Box::new(super::MatchLoadingState::new(
+                        self.assets_dir.clone(),
+                        self.config.clone(),
+                    )))
+                } else if self.quit == Some(ui_event.target) {
+                    Trans::Quit
+                } else {
+                    Trans::None
+                }
+            }
+            _ => Trans::None,
+        }
+    }
+}
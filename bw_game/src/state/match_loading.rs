@@ -2,95 +2,55 @@ use crate::{
     config::BWConfig,
     graphics::{
         tile::TilesetHandles,
-        ui::{resources::load_dats, resources::DatHandles, Minimap, MinimapMarker},
+        ui::{
+            resources::ImagesTbl, resources::StatTxt, resources::TMinimap, resources::TUnit,
+            Minimap, MinimapMarker,
+        },
+        unit::{PlacedUnit, PlacedUnitImages},
     },
 };
 
+use super::{
+    asset_registry::{AssetGraph, AssetRegistry, NodeState},
+    LoadingError, LoadingErrorState,
+};
 use crate::graphics::{self};
 use amethyst::{
-    assets::{AssetStorage, Completion, Handle, Loader, ProgressCounter},
+    assets::{Asset, AssetStorage, Completion, Format, Handle, Loader, ProgressCounter},
     core::Transform,
     ecs::storage::MaskedStorage,
-    ecs::Entity,
+    ecs::{Entity, Join},
+    input::is_close_requested,
     prelude::*,
     renderer::ActiveCamera,
     renderer::Camera,
-    ui::UiCreator,
+    ui::{UiCreator, UiFinder, UiText, UiTransform},
     SimpleState, SimpleTrans,
 };
 use bw_assets::{
-    dat::UnitsDat,
     dat::{
-        FlingyDat, FlingyDatAsset, SpritesDat, SpritesDatAsset, TechDataDat, TechDataDatAsset,
-        UnitsDatAsset, UpgradesDat, UpgradesDatAsset, WeaponsDat, WeaponsDatAsset,
+        FlingyDatAsset, FlingyDatFormat, ImagesDatAsset, ImagesDatFormat, SpritesDatAsset,
+        SpritesDatFormat, TechDataDatAsset, TechDataDatFormat, UnitsDatAsset, UnitsDatFormat,
+        UpgradesDatAsset, UpgradesDatFormat, WeaponsDatAsset, WeaponsDatFormat,
     },
+    grp::GrpAsset,
     map::{Map, MapFormat, MapHandle},
+    minimap::MinimapColors,
     mpq::MPQHandle,
     mpq::{self, ArcMPQ},
-    tileset::CV5s,
-    tileset::VR4sAsset,
-    tileset::{CV5sAsset, VF4s, VF4sAsset, VR4s, VX4s, WPEs, WPEsAsset},
+    pcx::{PcxAsset, PcxFormat},
+    tbl::{TblAsset, TblFormat},
+    tileset::{CV5sAsset, VF4sAsset, VR4sAsset, WPEs, WPEsAsset},
 };
 use bw_assets::{mpq::MPQSource, tileset::VX4sAsset};
-use incremental_topo::IncrementalTopo;
 use log::{error, info};
-use std::{
-    cell::Cell,
-    fmt::{Display, Formatter},
-    hash::{Hash, Hasher},
-    path::PathBuf,
-    rc::Rc,
-    sync::Arc,
-};
+use std::{collections::HashSet, path::PathBuf, sync::Arc};
 
-#[derive(Debug, Clone, Hash, Eq, PartialEq)]
-enum AssetType {
-    MPQHandles,
-    MPQSource,
-    UIHud,
-    Map,
-    DatHandles,
-    UnitsDat,
-    FlingyDat,
-    WeaponsDat,
-    SpritesDat,
-    TechDataDat,
-    UpgradesDat,
-    Camera,
-    TilesetHandles,
-    CV5s,
-    VF4s,
-    VR4s,
-    VX4s,
-    WPEs,
-    Graphics,
-}
+/// Labels of ui/loading.ron that list the states of the assets, side by side.
+const ASSET_COLUMNS: [&str; 2] = ["loading_assets_left", "loading_assets_right"];
 
-impl Display for AssetType {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            AssetType::MPQHandles => write!(f, "mpq_handles"),
-            AssetType::MPQSource => write!(f, "mpq_source"),
-            AssetType::UIHud => write!(f, "ui_hud"),
-            AssetType::Map => write!(f, "map"),
-            AssetType::DatHandles => write!(f, "dat_handles"),
-            AssetType::UnitsDat => write!(f, "units.dat"),
-            AssetType::FlingyDat => write!(f, "flingy.dat"),
-            AssetType::WeaponsDat => write!(f, "weapons.dat"),
-            AssetType::SpritesDat => write!(f, "sprites.dat"),
-            AssetType::TechDataDat => write!(f, "techdata.dat"),
-            AssetType::UpgradesDat => write!(f, "upgrades.dat"),
-            AssetType::Camera => write!(f, "camera"),
-            AssetType::TilesetHandles => write!(f, "tileset_handles"),
-            AssetType::CV5s => write!(f, "cv5s"),
-            AssetType::VF4s => write!(f, "vf4s"),
-            AssetType::VR4s => write!(f, "vr4s"),
-            AssetType::VX4s => write!(f, "vx4s"),
-            AssetType::WPEs => write!(f, "wpes"),
-            AssetType::Graphics => write!(f, "graphics"),
-        }
-    }
-}
+/// Height in pixels of a line of the 12 point asset list.
+const ASSET_LINE_HEIGHT: f32 = 15.0;
 
 struct MPQHandles {
     stardat: MPQHandle,
@@ -98,310 +58,514 @@ struct MPQHandles {
     patchrt: MPQHandle,
 }
 
-#[derive(Clone)]
-struct Node {
-    name: AssetType,
-    loaded: Rc<Cell<bool>>,
+/// What the loads of the assets of a match share with each other.
+struct LoadContext {
+    assets_dir: PathBuf,
+    config: BWConfig,
+    mpq_handles: Option<MPQHandles>,
+    tileset_handles: Option<TilesetHandles>,
+    map_handle: Option<MapHandle>,
+    placed_unit_images: Option<PlacedUnitImages>,
+    ui: Option<Entity>,
+    progress_counter: ProgressCounter,
+
+    /// Errors of assets that aren't loaded through the progress counter.
+    errors: Vec<LoadingError>,
 }
 
-impl Node {
-    fn new(name: AssetType) -> Node {
-        Node {
-            name,
-            loaded: Rc::from(Cell::from(false)),
+impl LoadContext {
+    /// Opens an MPQ, or records why it can't be opened.
+    ///
+    /// The archive is opened right away rather than on a worker thread, where
+    /// a missing or broken archive could only panic.
+    fn load_mpq(&mut self, world: &mut World, path: &str) -> Option<MPQHandle> {
+        let mpq_asset_path = self.assets_dir.join(path);
+
+        match mpq::ArcMPQ::from_path(&mpq_asset_path.as_path()) {
+            Ok(mpq) => Some(world.read_resource::<Loader>().load_from_data(
+                mpq,
+                &mut self.progress_counter,
+                &world.read_resource::<AssetStorage<ArcMPQ>>(),
+            )),
+            Err(err) => {
+                self.errors.push(LoadingError {
+                    asset: path.to_string(),
+                    reason: err.to_string(),
+                });
+                None
+            }
         }
     }
 
-    fn is_loaded(&self) -> bool {
-        self.loaded.get()
+    fn tileset_handles(&self) -> &TilesetHandles {
+        self.tileset_handles
+            .as_ref()
+            .expect("tileset handles not loaded")
     }
-}
 
-impl Hash for Node {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.name.hash(state)
+    fn map_handle(&self) -> MapHandle {
+        self.map_handle.clone().expect("map handle is missing")
     }
 }
 
-impl PartialEq for Node {
-    fn eq(&self, other: &Self) -> bool {
-        self.name == other.name
-    }
+/// Moves an asset out of its storage once it has been loaded.
+fn take_asset<A: Asset, T>(
+    world: &World,
+    handle: &Handle<A>,
+    take: fn(&mut A) -> Option<T>,
+) -> Option<T> {
+    world
+        .write_resource::<AssetStorage<A>>()
+        .get_mut(handle)
+        .and_then(take)
 }
 
-impl Display for Node {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "({} {})", self.name, self.is_loaded())
+/// Loads an asset from the MPQs and moves it into the world with `insert`.
+///
+/// The load owns the handle of the asset, so an asset of the MPQs is added
+/// by registering it alone.
+fn load_asset<A, F, T, I>(
+    path: &'static str,
+    format: F,
+    take: fn(&mut A) -> Option<T>,
+    insert: I,
+) -> impl FnMut(&mut LoadContext, &mut World) -> bool
+where
+    A: Asset,
+    F: Format<A::Data> + Clone,
+    T: 'static,
+    I: Fn(&mut World, T) + 'static,
+{
+    let mut handle: Option<Handle<A>> = None;
+    move |context: &mut LoadContext, world: &mut World| {
+        let handle = handle.get_or_insert_with(|| {
+            world.read_resource::<Loader>().load_from(
+                path,
+                format.clone(),
+                "bw_assets",
+                &mut context.progress_counter,
+                &world.read_resource::<AssetStorage<A>>(),
+            )
+        });
+        take_asset(world, handle, take)
+            .map(|asset| insert(world, asset))
+            .is_some()
     }
 }
 
-impl Eq for Node {}
-
-fn build_asset_dependency_graph() -> IncrementalTopo<Node> {
-    let mut dag = IncrementalTopo::new();
-
-    dag.add_node(Node::new(AssetType::MPQHandles));
-    dag.add_node(Node::new(AssetType::MPQSource));
-
-    dag.add_node(Node::new(AssetType::UIHud));
-    dag.add_node(Node::new(AssetType::Map));
-
-    dag.add_node(Node::new(AssetType::DatHandles));
-    dag.add_node(Node::new(AssetType::UnitsDat));
-    dag.add_node(Node::new(AssetType::FlingyDat));
-    dag.add_node(Node::new(AssetType::WeaponsDat));
-    dag.add_node(Node::new(AssetType::SpritesDat));
-    dag.add_node(Node::new(AssetType::TechDataDat));
-    dag.add_node(Node::new(AssetType::UpgradesDat));
-
-    dag.add_node(Node::new(AssetType::Camera));
-    dag.add_node(Node::new(AssetType::TilesetHandles));
-
-    dag.add_node(Node::new(AssetType::CV5s));
-    dag.add_node(Node::new(AssetType::VF4s));
-    dag.add_node(Node::new(AssetType::VR4s));
-    dag.add_node(Node::new(AssetType::VX4s));
-    dag.add_node(Node::new(AssetType::WPEs));
-
-    dag.add_node(Node::new(AssetType::Graphics));
-
-    dag.add_dependency(
-        &Node::new(AssetType::MPQHandles),
-        &Node::new(AssetType::MPQSource),
-    )
-    .expect(&format!(
-        "add {} <- {} dependency",
-        AssetType::MPQSource,
-        AssetType::MPQHandles
-    ));
-
-    dag.add_dependency(
-        &Node::new(AssetType::MPQSource),
-        &Node::new(AssetType::DatHandles),
-    )
-    .expect(&format!(
-        "add {} <- {} dependency",
-        AssetType::DatHandles,
-        AssetType::MPQSource
-    ));
-    dag.add_dependency(
-        &Node::new(AssetType::DatHandles),
-        &Node::new(AssetType::UnitsDat),
-    )
-    .expect(&format!(
-        "add {} <- {} dependency",
-        AssetType::UnitsDat,
-        AssetType::DatHandles
-    ));
-    dag.add_dependency(
-        &Node::new(AssetType::DatHandles),
-        &Node::new(AssetType::FlingyDat),
-    )
-    .expect(&format!(
-        "add {} <- {} dependency",
-        AssetType::FlingyDat,
-        AssetType::DatHandles
-    ));
-    dag.add_dependency(
-        &Node::new(AssetType::DatHandles),
-        &Node::new(AssetType::WeaponsDat),
-    )
-    .expect(&format!(
-        "add {} <- {} dependency",
-        AssetType::WeaponsDat,
-        AssetType::DatHandles
-    ));
-    dag.add_dependency(
-        &Node::new(AssetType::DatHandles),
-        &Node::new(AssetType::SpritesDat),
-    )
-    .expect(&format!(
-        "add {} <- {} dependency",
-        AssetType::SpritesDat,
-        AssetType::DatHandles
-    ));
-    dag.add_dependency(
-        &Node::new(AssetType::DatHandles),
-        &Node::new(AssetType::TechDataDat),
-    )
-    .expect(&format!(
-        "add {} <- {} dependency",
-        AssetType::TechDataDat,
-        AssetType::DatHandles
-    ));
-    dag.add_dependency(
-        &Node::new(AssetType::DatHandles),
-        &Node::new(AssetType::UpgradesDat),
-    )
-    .expect(&format!(
-        "add {} <- {} dependency",
-        AssetType::UpgradesDat,
-        AssetType::DatHandles
-    ));
-
-    dag.add_dependency(
-        &Node::new(AssetType::MPQSource),
-        &Node::new(AssetType::TilesetHandles),
-    )
-    .expect(&format!(
-        "add {} <- {} dependency",
-        AssetType::TilesetHandles,
-        AssetType::MPQSource
-    ));
-    dag.add_dependency(
-        &Node::new(AssetType::Map),
-        &Node::new(AssetType::TilesetHandles),
-    )
-    .expect(&format!(
-        "add {} <- {} dependency",
-        AssetType::TilesetHandles,
-        AssetType::Map
-    ));
-
-    dag.add_dependency(
-        &Node::new(AssetType::TilesetHandles),
-        &Node::new(AssetType::CV5s),
-    )
-    .expect(&format!(
-        "add {} <- {} dependency",
-        AssetType::CV5s,
-        AssetType::TilesetHandles
-    ));
-
-    dag.add_dependency(
-        &Node::new(AssetType::TilesetHandles),
-        &Node::new(AssetType::VF4s),
-    )
-    .expect(&format!(
-        "add {} <- {} dependency",
-        AssetType::VF4s,
-        AssetType::TilesetHandles
-    ));
-
-    dag.add_dependency(
-        &Node::new(AssetType::TilesetHandles),
-        &Node::new(AssetType::VR4s),
-    )
-    .expect(&format!(
-        "add {} <- {} dependency",
-        AssetType::VR4s,
-        AssetType::TilesetHandles
-    ));
-
-    dag.add_dependency(
-        &Node::new(AssetType::TilesetHandles),
-        &Node::new(AssetType::VX4s),
-    )
-    .expect(&format!(
-        "add {} <- {} dependency",
-        AssetType::VX4s,
-        AssetType::TilesetHandles
-    ));
-
-    dag.add_dependency(
-        &Node::new(AssetType::TilesetHandles),
-        &Node::new(AssetType::WPEs),
-    )
-    .expect(&format!(
-        "add {} <- {} dependency",
-        AssetType::WPEs,
-        AssetType::TilesetHandles
-    ));
-
-    dag.add_dependency(&Node::new(AssetType::Map), &Node::new(AssetType::Camera))
-        .expect(&format!(
-            "add {} <- {} dependency",
-            AssetType::Camera,
-            AssetType::Map
-        ));
-
-    dag.add_dependency(&Node::new(AssetType::CV5s), &Node::new(AssetType::Graphics))
-        .expect(&format!(
-            "add {} <- {} dependency",
-            AssetType::CV5s,
-            AssetType::Graphics
-        ));
-    dag.add_dependency(&Node::new(AssetType::VF4s), &Node::new(AssetType::Graphics))
-        .expect(&format!(
-            "add {} <- {} dependency",
-            AssetType::VF4s,
-            AssetType::Graphics
-        ));
-    dag.add_dependency(&Node::new(AssetType::VR4s), &Node::new(AssetType::Graphics))
-        .expect(&format!(
-            "add {} <- {} dependency",
-            AssetType::VR4s,
-            AssetType::Graphics
-        ));
-    dag.add_dependency(&Node::new(AssetType::VX4s), &Node::new(AssetType::Graphics))
-        .expect(&format!(
-            "add {} <- {} dependency",
-            AssetType::VX4s,
-            AssetType::Graphics
-        ));
-    dag.add_dependency(&Node::new(AssetType::WPEs), &Node::new(AssetType::Graphics))
-        .expect(&format!(
-            "add {} <- {} dependency",
-            AssetType::WPEs,
-            AssetType::Graphics
-        ));
+/// Every asset of a match, the assets it needs and how it is loaded.
+fn asset_registry() -> AssetRegistry<LoadContext> {
+    AssetRegistry::<LoadContext>::default()
+        .register("mpq_handles", &[], |context, world| {
+            if let (Some(stardat), Some(broodat), Some(patchrt)) = (
+                context.load_mpq(world, "STARDAT.MPQ"),
+                context.load_mpq(world, "BROODAT.MPQ"),
+                context.load_mpq(world, "patch_rt.mpq"),
+            ) {
+                context.mpq_handles = Some(MPQHandles {
+                    stardat,
+                    broodat,
+                    patchrt,
+                });
+                true
+            } else {
+                false
+            }
+        })
+        .register("mpq_source", &["mpq_handles"], |context, world| {
+            let mpq_handles = context
+                .mpq_handles
+                .as_ref()
+                .expect("mpq handles are missing");
+            let mpq_storage = world.read_resource::<AssetStorage<ArcMPQ>>();
+            if let (Some(stardat), Some(broodat), Some(patchrt)) = (
+                mpq_storage.get(&mpq_handles.stardat),
+                mpq_storage.get(&mpq_handles.broodat),
+                mpq_storage.get(&mpq_handles.patchrt),
+            ) {
+                let mut mpq_source = MPQSource::new();
+                mpq_source.push_front(stardat.clone());
+                mpq_source.push_front(broodat.clone());
+                mpq_source.push_front(patchrt.clone());
+                let mut loader = world.write_resource::<Loader>();
+                loader.add_source("bw_assets", mpq_source);
+                true
+            } else {
+                false
+            }
+        })
+        .register("ui_hud", &[], |context, world| {
+            let progress_counter = &mut context.progress_counter;
+            context.ui =
+                Some(world.exec(|mut creator: UiCreator<'_>| {
+                    creator.create("ui/hud.ron", progress_counter)
+                }));
+            true
+        })
+        .register("map", &[], |context, world| {
+            let map_handle = world.read_resource::<Loader>().load(
+                format!("maps/{}", context.config.map),
+                MapFormat,
+                &mut context.progress_counter,
+                &world.read_resource::<AssetStorage<Map>>(),
+            );
+            world.insert(map_handle.clone());
+            context.map_handle = Some(map_handle);
+            true
+        })
+        .register(
+            "units.dat",
+            &["mpq_source"],
+            load_asset(
+                "arr\\units.dat",
+                UnitsDatFormat,
+                UnitsDatAsset::take,
+                |world, units_dat| world.insert(units_dat),
+            ),
+        )
+        .register(
+            "flingy.dat",
+            &["mpq_source"],
+            load_asset(
+                "arr\\flingy.dat",
+                FlingyDatFormat,
+                FlingyDatAsset::take,
+                |world, flingy_dat| world.insert(flingy_dat),
+            ),
+        )
+        .register(
+            "weapons.dat",
+            &["mpq_source"],
+            load_asset(
+                "arr\\weapons.dat",
+                WeaponsDatFormat,
+                WeaponsDatAsset::take,
+                |world, weapons_dat| world.insert(weapons_dat),
+            ),
+        )
+        .register(
+            "sprites.dat",
+            &["mpq_source"],
+            load_asset(
+                "arr\\sprites.dat",
+                SpritesDatFormat,
+                SpritesDatAsset::take,
+                |world, sprites_dat| world.insert(sprites_dat),
+            ),
+        )
+        .register(
+            "images.dat",
+            &["mpq_source"],
+            load_asset(
+                "arr\\images.dat",
+                ImagesDatFormat,
+                ImagesDatAsset::take,
+                |world, images_dat| world.insert(images_dat),
+            ),
+        )
+        .register(
+            "techdata.dat",
+            &["mpq_source"],
+            load_asset(
+                "arr\\techdata.dat",
+                TechDataDatFormat,
+                TechDataDatAsset::take,
+                |world, tech_data_dat| world.insert(tech_data_dat),
+            ),
+        )
+        .register(
+            "upgrades.dat",
+            &["mpq_source"],
+            load_asset(
+                "arr\\upgrades.dat",
+                UpgradesDatFormat,
+                UpgradesDatAsset::take,
+                |world, upgrades_dat| world.insert(upgrades_dat),
+            ),
+        )
+        .register(
+            "stat_txt.tbl",
+            &["mpq_source"],
+            load_asset(
+                "rez\\stat_txt.tbl",
+                TblFormat,
+                TblAsset::take,
+                |world, stat_txt| world.insert(StatTxt(stat_txt)),
+            ),
+        )
+        .register(
+            "images.tbl",
+            &["mpq_source"],
+            load_asset(
+                "arr\\images.tbl",
+                TblFormat,
+                TblAsset::take,
+                |world, images_tbl| world.insert(ImagesTbl(images_tbl)),
+            ),
+        )
+        .register(
+            "tminimap.pcx",
+            &["mpq_source"],
+            load_asset(
+                "game\\tminimap.pcx",
+                PcxFormat,
+                PcxAsset::take,
+                |world, tminimap| world.insert(TMinimap(tminimap)),
+            ),
+        )
+        .register(
+            "tunit.pcx",
+            &["mpq_source"],
+            load_asset(
+                "game\\tunit.pcx",
+                PcxFormat,
+                PcxAsset::take,
+                |world, tunit| world.insert(TUnit(tunit)),
+            ),
+        )
+        .register("camera", &["map"], |context, world| {
+            let camera_width = 640.0;
+            let camera_height = 500.0;
+
+            let camera = Camera::orthographic(0.0, camera_width, 0.0, camera_height, 0.0, 20.0);
+            let mut camera_transform = Transform::default();
+            camera_transform.set_translation_xyz(-(camera_width / 2.0), camera_height / 2.0, 10.0);
+
+            let camera_entity = world
+                .create_entity()
+                .with(camera)
+                .with(camera_transform)
+                .with(context.map_handle())
+                .build();
+            world.fetch_mut::<ActiveCamera>().entity = Some(camera_entity);
+            true
+        })
+        .register(
+            "tileset_handles",
+            &["mpq_source", "map"],
+            |context, world| {
+                context.tileset_handles = graphics::tile::resources::load(
+                    world,
+                    context.map_handle(),
+                    &mut context.progress_counter,
+                );
+                // The tileset is only known once the map has been loaded.
+                context.tileset_handles.is_some()
+            },
+        )
+        .register("cv5s", &["tileset_handles"], |context, world| {
+            let cv5s = take_asset(world, &context.tileset_handles().cv5s, CV5sAsset::take);
+            cv5s.map(|cv5s| world.insert(Arc::new(cv5s))).is_some()
+        })
+        .register("vf4s", &["tileset_handles"], |context, world| {
+            let vf4s = take_asset(world, &context.tileset_handles().vf4s, VF4sAsset::take);
+            vf4s.map(|vf4s| world.insert(Arc::new(vf4s))).is_some()
+        })
+        .register("vr4s", &["tileset_handles"], |context, world| {
+            let vr4s = take_asset(world, &context.tileset_handles().vr4s, VR4sAsset::take);
+            vr4s.map(|vr4s| world.insert(Arc::new(vr4s))).is_some()
+        })
+        .register("vx4s", &["tileset_handles"], |context, world| {
+            let vx4s = take_asset(world, &context.tileset_handles().vx4s, VX4sAsset::take);
+            vx4s.map(|vx4s| world.insert(Arc::new(vx4s))).is_some()
+        })
+        .register("wpes", &["tileset_handles"], |context, world| {
+            let wpes = take_asset(world, &context.tileset_handles().wpes, WPEsAsset::take);
+            wpes.map(|wpes| world.insert(Arc::new(wpes))).is_some()
+        })
+        .register("minimap_colors", &["tminimap.pcx", "wpes"], |_, world| {
+            let minimap_colors = MinimapColors::new(
+                &world.read_resource::<TMinimap>().0,
+                &world.read_resource::<Arc<WPEs>>().rgb(),
+            );
+            world.insert(minimap_colors);
+            true
+        })
+        .register(
+            "graphics",
+            &[
+                "cv5s",
+                "vf4s",
+                "vr4s",
+                "vx4s",
+                "wpes",
+                "minimap_colors",
+                "units.dat",
+            ],
+            |context, world| {
+                let map_handle = context.map_handle();
+                graphics::create((world, &map_handle, &mut context.progress_counter));
+                true
+            },
+        )
+        .register(
+            "placed_unit_images",
+            &[
+                "map",
+                "units.dat",
+                "flingy.dat",
+                "sprites.dat",
+                "images.dat",
+                "images.tbl",
+            ],
+            |context, world| {
+                let map_handle = context.map_handle();
+                context.placed_unit_images = Some(PlacedUnitImages::load(
+                    world,
+                    &map_handle,
+                    &mut context.progress_counter,
+                ));
+                true
+            },
+        )
+        .register(
+            "placed_units",
+            &["placed_unit_images", "tunit.pcx", "wpes", "graphics"],
+            |context, world| {
+                let placed_unit_images = context
+                    .placed_unit_images
+                    .as_ref()
+                    .expect("placed unit images are missing");
+                if !placed_unit_images.is_loaded(&world.read_resource::<AssetStorage<GrpAsset>>()) {
+                    return false;
+                }
 
-    dag
+                let TUnit(tunit) = world.remove::<TUnit>().expect("tunit.pcx is missing");
+                graphics::unit::create(world, &context.map_handle(), placed_unit_images, &tunit);
+                true
+            },
+        )
 }
 
 pub struct MatchLoadingState {
-    assets_dir: PathBuf,
-    mpq_handles: Option<MPQHandles>,
-    tileset_handles: Option<TilesetHandles>,
-    dat_handles: Option<DatHandles>,
-    map_handle: Option<MapHandle>,
-    ui: Option<Entity>,
-    progress_counter: ProgressCounter,
-    asset_dependency_graph: IncrementalTopo<Node>,
-    config: BWConfig,
+    context: LoadContext,
+    assets: AssetGraph<LoadContext>,
+
+    /// Entities that were there before the state started, which the match
+    /// doesn't own.
+    existing_entities: HashSet<Entity>,
+
+    loading_screen: Option<Entity>,
+    loading_assets: Vec<Entity>,
+    loading_progress: Option<Entity>,
+    loading_progress_bar: Option<Entity>,
 }
 
 impl MatchLoadingState {
     pub fn new(assets_dir: PathBuf, config: BWConfig) -> MatchLoadingState {
+        // The registry is checked by the tests, but a mistake in it is still
+        // shown like any other asset that fails to load.
+        let (assets, errors) = match asset_registry().build() {
+            Ok(assets) => (assets, Vec::new()),
+            Err(err) => (
+                AssetGraph::default(),
+                vec![LoadingError {
+                    asset: "asset registry".to_string(),
+                    reason: err.to_string(),
+                }],
+            ),
+        };
+
         MatchLoadingState {
-            assets_dir,
-            config,
-            mpq_handles: None,
-            tileset_handles: None,
-            asset_dependency_graph: build_asset_dependency_graph(),
-            dat_handles: None,
-            map_handle: None,
-            ui: None,
-            progress_counter: ProgressCounter::default(),
+            context: LoadContext {
+                assets_dir,
+                config,
+                mpq_handles: None,
+                tileset_handles: None,
+                map_handle: None,
+                placed_unit_images: None,
+                ui: None,
+                progress_counter: ProgressCounter::default(),
+                errors,
+            },
+            assets,
+            existing_entities: HashSet::new(),
+            loading_screen: None,
+            loading_assets: Vec::new(),
+            loading_progress: None,
+            loading_progress_bar: None,
         }
     }
 }
 
 impl MatchLoadingState {
-    fn load_mpq(&mut self, world: &mut World, path: &str) -> MPQHandle {
-        let mpq_asset_path = self.assets_dir.join(path);
-        let loader = world.read_resource::<Loader>();
-
-        loader.load_from_data_async(
-            move || {
-                mpq::ArcMPQ::from_path(&mpq_asset_path.as_path()).expect(&format!(
-                    "failed to load MPQ: {}",
-                    mpq_asset_path.as_path().display()
-                ))
-            },
-            &mut self.progress_counter,
-            &world.read_resource::<AssetStorage<ArcMPQ>>(),
-        )
+    /// Fraction of the nodes and the assets they wait for that are loaded.
+    fn progress(&self) -> f32 {
+        let nodes = self.assets.nodes();
+        let progress_counter = &self.context.progress_counter;
+        let loaded =
+            nodes.iter().filter(|node| node.is_loaded()).count() + progress_counter.num_finished();
+        let total = nodes.len() + progress_counter.num_assets();
+
+        loaded as f32 / total.max(1) as f32
+    }
+
+    fn update_loading_screen(&mut self, world: &mut World) {
+        if self.loading_assets.is_empty() {
+            world.exec(|finder: UiFinder<'_>| {
+                self.loading_assets = ASSET_COLUMNS
+                    .iter()
+                    .filter_map(|id| finder.find(id))
+                    .collect();
+                self.loading_progress = finder.find("loading_progress");
+                self.loading_progress_bar = finder.find("loading_progress_bar");
+            });
+        }
+
+        let mut lines = self
+            .assets
+            .nodes()
+            .iter()
+            .map(|node| format!("{}: {}", node.name, self.assets.state(node)))
+            .collect::<Vec<_>>();
+        lines.push(format!(
+            "{} of {} files",
+            self.context.progress_counter.num_finished(),
+            self.context.progress_counter.num_assets()
+        ));
+
+        // The columns grow downwards with the number of assets, which are
+        // spread evenly over them.
+        let mut ui_text = world.write_storage::<UiText>();
+        let mut ui_transforms = world.write_storage::<UiTransform>();
+        let lines_per_column = (lines.len() + ASSET_COLUMNS.len() - 1) / ASSET_COLUMNS.len();
+        for (i, &column) in self.loading_assets.iter().enumerate() {
+            let column_lines = lines
+                .iter()
+                .skip(i * lines_per_column)
+                .take(lines_per_column)
+                .cloned()
+                .collect::<Vec<_>>();
+
+            if let Some(text) = ui_text.get_mut(column) {
+                text.text = column_lines.join("\n");
+            }
+            if let Some(ui_transform) = ui_transforms.get_mut(column) {
+                ui_transform.height = lines_per_column as f32 * ASSET_LINE_HEIGHT;
+            }
+        }
+
+        let progress_width = self
+            .loading_progress
+            .and_then(|entity| ui_transforms.get(entity))
+            .map(|ui_transform| ui_transform.width);
+        if let (Some(progress_width), Some(progress_bar)) = (
+            progress_width,
+            self.loading_progress_bar
+                .and_then(|entity| ui_transforms.get_mut(entity)),
+        ) {
+            progress_bar.width = progress_width * self.progress();
+        }
+    }
+
+    fn delete_loading_screen(&mut self, world: &mut World) {
+        if let Some(loading_screen) = self.loading_screen.take() {
+            super::delete_ui(world, loading_screen);
+        }
     }
 
     fn is_complete(&self) -> bool {
-        self.asset_dependency_graph
-            .iter_unsorted()
-            .filter(|(_, node)| !node.loaded.get())
-            .collect::<Vec<_>>()
-            .len()
-            == 0
-            && self.progress_counter.is_complete()
+        self.assets.is_loaded() && self.context.progress_counter.is_complete()
     }
 }
 
@@ -411,278 +575,100 @@ impl SimpleState for MatchLoadingState {
 
         let StateData { world, .. } = data;
 
+        self.existing_entities = (&*world.entities()).join().collect();
+
         world.insert(MaskedStorage::<Handle<Map>>::default());
         world.insert(MaskedStorage::<Minimap>::default());
         world.insert(MaskedStorage::<MinimapMarker>::default());
+        world.insert(MaskedStorage::<PlacedUnit>::default());
+
+        self.loading_screen =
+            Some(world.exec(|mut creator: UiCreator<'_>| creator.create("ui/loading.ron", ())));
     }
 
-    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+    /// Deletes the loading screen and whatever of the match was created
+    /// before loading it failed, so that a retry starts over.
+    fn on_stop(&mut self, data: StateData<'_, GameData<'_, '_>>) {
         let StateData { world, .. } = data;
 
-        let dependencies = self
-            .asset_dependency_graph
-            .iter_unsorted()
-            .map(|(i, node)| (i, node.clone()))
+        let created = (&*world.entities())
+            .join()
+            .filter(|entity| !self.existing_entities.contains(entity))
             .collect::<Vec<_>>();
-        for (_, node) in dependencies {
-            if node.is_loaded() {
-                continue;
-            }
-            let are_dependencies_satisfied = self
-                .asset_dependency_graph
-                .iter_unsorted()
-                .filter(|(_, other)| {
-                    self.asset_dependency_graph
-                        .contains_dependency(other, &node)
-                })
-                .filter(|(_, dependency)| !dependency.is_loaded())
-                .count()
-                == 0;
-            if !are_dependencies_satisfied {
-                continue;
-            }
-
-            match node.name {
-                AssetType::MPQHandles => {
-                    self.mpq_handles = Some(MPQHandles {
-                        stardat: self.load_mpq(world, "STARDAT.MPQ"),
-                        broodat: self.load_mpq(world, "BROODAT.MPQ"),
-                        patchrt: self.load_mpq(world, "patch_rt.mpq"),
-                    });
-                    node.loaded.set(true);
-                }
-                AssetType::MPQSource => {
-                    let mpq_handles = self.mpq_handles.as_ref().expect("mpq handles are missing");
-                    let mpq_storage = world.read_resource::<AssetStorage<ArcMPQ>>();
-                    if let (Some(stardat), Some(broodat), Some(patchrt)) = (
-                        mpq_storage.get(&mpq_handles.stardat),
-                        mpq_storage.get(&mpq_handles.broodat),
-                        mpq_storage.get(&mpq_handles.patchrt),
-                    ) {
-                        let mut mpq_source = MPQSource::new();
-                        mpq_source.push_front(stardat.clone());
-                        mpq_source.push_front(broodat.clone());
-                        mpq_source.push_front(patchrt.clone());
-                        let mut loader = world.write_resource::<Loader>();
-                        loader.add_source("bw_assets", mpq_source);
-                        node.loaded.set(true);
-                    }
-                }
-                AssetType::UIHud => {
-                    let progress_counter = &mut self.progress_counter;
-                    self.ui = Some(world.exec(|mut creator: UiCreator<'_>| {
-                        creator.create("ui/hud.ron", progress_counter)
-                    }));
-                    node.loaded.set(true);
-                }
-                AssetType::Map => {
-                    let map_handle = world.read_resource::<Loader>().load(
-                        format!("maps/{}", self.config.map),
-                        MapFormat,
-                        &mut self.progress_counter,
-                        &world.read_resource::<AssetStorage<Map>>(),
-                    );
-                    world.insert(map_handle.clone());
-                    self.map_handle = Some(map_handle);
-                    node.loaded.set(true);
-                }
-                AssetType::DatHandles => {
-                    self.dat_handles = Some(load_dats(world, &mut self.progress_counter));
-                    node.loaded.set(true);
-                }
-                AssetType::UnitsDat => {
-                    let dat_handles = self.dat_handles.as_ref().expect("dat handles are missing");
-                    let units_dat_opt = world
-                        .write_resource::<AssetStorage<UnitsDatAsset>>()
-                        .get_mut(&dat_handles.units_dat)
-                        .and_then(|asset| asset.take());
-                    if let Some(units_dat) = units_dat_opt {
-                        world.insert::<UnitsDat>(units_dat);
-                        node.loaded.set(true);
-                    }
-                }
-                AssetType::FlingyDat => {
-                    let dat_handles = self.dat_handles.as_ref().expect("dat handles are missing");
-                    let flingy_dat_opt = world
-                        .write_resource::<AssetStorage<FlingyDatAsset>>()
-                        .get_mut(&dat_handles.flingy_dat)
-                        .and_then(|asset| asset.take());
-                    if let Some(flingy_dat) = flingy_dat_opt {
-                        world.insert::<FlingyDat>(flingy_dat);
-                        node.loaded.set(true);
-                    }
-                }
-                AssetType::WeaponsDat => {
-                    let dat_handles = self.dat_handles.as_ref().expect("dat handles are missing");
-                    let weapons_dat_opt = world
-                        .write_resource::<AssetStorage<WeaponsDatAsset>>()
-                        .get_mut(&dat_handles.weapons_dat)
-                        .and_then(|asset| asset.take());
-                    if let Some(weapons_dat) = weapons_dat_opt {
-                        world.insert::<WeaponsDat>(weapons_dat);
-                        node.loaded.set(true);
-                    }
-                }
-                AssetType::SpritesDat => {
-                    let dat_handles = self.dat_handles.as_ref().expect("dat handles are missing");
-                    let sprites_dat_opt = world
-                        .write_resource::<AssetStorage<SpritesDatAsset>>()
-                        .get_mut(&dat_handles.sprites_dat)
-                        .and_then(|asset| asset.take());
-                    if let Some(sprites_dat) = sprites_dat_opt {
-                        world.insert::<SpritesDat>(sprites_dat);
-                        node.loaded.set(true);
-                    }
-                }
-                AssetType::TechDataDat => {
-                    let dat_handles = self.dat_handles.as_ref().expect("dat handles are missing");
-                    let tech_data_dat_opt = world
-                        .write_resource::<AssetStorage<TechDataDatAsset>>()
-                        .get_mut(&dat_handles.tech_data_dat)
-                        .and_then(|asset| asset.take());
-                    if let Some(tech_data_dat) = tech_data_dat_opt {
-                        world.insert::<TechDataDat>(tech_data_dat);
-                        node.loaded.set(true);
-                    }
-                }
-                AssetType::UpgradesDat => {
-                    let dat_handles = self.dat_handles.as_ref().expect("dat handles are missing");
-                    let upgrades_dat_opt = world
-                        .write_resource::<AssetStorage<UpgradesDatAsset>>()
-                        .get_mut(&dat_handles.upgrades_dat)
-                        .and_then(|asset| asset.take());
-                    if let Some(upgrades_dat) = upgrades_dat_opt {
-                        world.insert::<UpgradesDat>(upgrades_dat);
-                        node.loaded.set(true);
-                    }
-                }
+        world
+            .delete_entities(&created)
+            .expect("failed to delete the entities of the match");
+        self.loading_screen = None;
+    }
 
-                AssetType::Camera => {
-                    let map_handle = self.map_handle.as_ref().expect("map handle is missing");
-                    let camera_width = 640.0;
-                    let camera_height = 500.0;
-
-                    let camera =
-                        Camera::orthographic(0.0, camera_width, 0.0, camera_height, 0.0, 20.0);
-                    let mut camera_transform = Transform::default();
-                    camera_transform.set_translation_xyz(
-                        -(camera_width / 2.0),
-                        camera_height / 2.0,
-                        10.0,
-                    );
-
-                    let camera_entity = world
-                        .create_entity()
-                        .with(camera)
-                        .with(camera_transform)
-                        .with(map_handle.clone())
-                        .build();
-                    world.fetch_mut::<ActiveCamera>().entity = Some(camera_entity);
-                    node.loaded.set(true);
-                }
-                AssetType::TilesetHandles => {
-                    let map_handle = self.map_handle.as_ref().expect("map handle is missing");
-                    self.tileset_handles = graphics::tile::resources::load(
-                        world,
-                        map_handle.clone(),
-                        &mut self.progress_counter,
-                    );
-                    node.loaded.set(true);
-                }
-                AssetType::CV5s => {
-                    let tileset_handles = self
-                        .tileset_handles
-                        .as_ref()
-                        .expect("tileset handles not loaded");
-                    let cv5s_opt = world
-                        .write_resource::<AssetStorage<CV5sAsset>>()
-                        .get_mut(&tileset_handles.cv5s)
-                        .and_then(|asset| asset.take())
-                        .map(Arc::new);
-                    if let Some(cv5s) = cv5s_opt {
-                        world.insert::<Arc<CV5s>>(cv5s);
-                        node.loaded.set(true);
-                    }
-                }
-                AssetType::VF4s => {
-                    let tileset_handles = self
-                        .tileset_handles
-                        .as_ref()
-                        .expect("tileset handles not loaded");
-                    let vf4s_opt = world
-                        .write_resource::<AssetStorage<VF4sAsset>>()
-                        .get_mut(&tileset_handles.vf4s)
-                        .and_then(|asset| asset.take())
-                        .map(Arc::new);
-                    if let Some(vf4s) = vf4s_opt {
-                        world.insert::<Arc<VF4s>>(vf4s);
-                        node.loaded.set(true);
-                    }
-                }
-                AssetType::VR4s => {
-                    let tileset_handles = self
-                        .tileset_handles
-                        .as_ref()
-                        .expect("tileset handles not loaded");
-                    let vr4s_opt = world
-                        .write_resource::<AssetStorage<VR4sAsset>>()
-                        .get_mut(&tileset_handles.vr4s)
-                        .and_then(|asset| asset.take())
-                        .map(Arc::new);
-                    if let Some(vr4s) = vr4s_opt {
-                        world.insert::<Arc<VR4s>>(vr4s);
-                        node.loaded.set(true);
-                    }
-                }
-                AssetType::VX4s => {
-                    let tileset_handles = self
-                        .tileset_handles
-                        .as_ref()
-                        .expect("tileset handles not loaded");
-                    let vx4s_opt = world
-                        .write_resource::<AssetStorage<VX4sAsset>>()
-                        .get_mut(&tileset_handles.vx4s)
-                        .and_then(|asset| asset.take())
-                        .map(Arc::new);
-                    if let Some(vx4s) = vx4s_opt {
-                        world.insert::<Arc<VX4s>>(vx4s);
-                        node.loaded.set(true);
-                    }
-                }
-                AssetType::WPEs => {
-                    let tileset_handles = self
-                        .tileset_handles
-                        .as_ref()
-                        .expect("tileset handles not loaded");
-                    let wpes_opts = world
-                        .write_resource::<AssetStorage<WPEsAsset>>()
-                        .get_mut(&tileset_handles.wpes)
-                        .and_then(|asset| asset.take())
-                        .map(Arc::new);
-                    if let Some(wpes) = wpes_opts {
-                        world.insert::<Arc<WPEs>>(wpes);
-                        node.loaded.set(true);
-                    }
-                }
-                AssetType::Graphics => {
-                    let map_handle = self.map_handle.as_ref().expect("map handle is missing");
-                    graphics::create((world, map_handle, &mut self.progress_counter));
-                    node.loaded.set(true);
-                }
-            }
-        }
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        let StateData { world, .. } = data;
 
-        if Completion::Failed == self.progress_counter.complete() {
+        self.assets.load(&mut self.context, world);
+
+        if Completion::Failed == self.context.progress_counter.complete()
+            || !self.context.errors.is_empty()
+        {
+            let mut errors = std::mem::take(&mut self.context.errors);
+            errors.extend(
+                self.context
+                    .progress_counter
+                    .errors()
+                    .into_iter()
+                    .map(LoadingError::from),
+            );
             error!(
                 "Failed to initialize game due to {} error(s) loading assets",
-                self.progress_counter.errors().len()
+                errors.len()
             );
+            for err in &errors {
+                error!("{}", err);
+            }
 
-            Trans::Quit
+            let loading = self
+                .assets
+                .nodes()
+                .iter()
+                .filter(|node| self.assets.state(node) == NodeState::Loading)
+                .map(|node| node.name.to_string())
+                .collect();
+
+            Trans::Switch(Box::new(LoadingErrorState::new(
+                self.context.assets_dir.clone(),
+                self.context.config.clone(),
+                errors,
+                loading,
+            )))
         } else if self.is_complete() {
+            self.delete_loading_screen(world);
+
             Trans::Push(Box::new(super::GameplayState::default()))
         } else {
+            self.update_loading_screen(world);
+
             Trans::None
         }
     }
+
+    fn handle_event(
+        &mut self,
+        _: StateData<'_, GameData<'_, '_>>,
+        event: StateEvent,
+    ) -> SimpleTrans {
+        match &event {
+            StateEvent::Window(event) if is_close_requested(&event) => Trans::Quit,
+            _ => Trans::None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn it_registers_every_dependency_of_the_assets_of_a_match() {
+        assert_that(&asset_registry().build().err()).is_none();
+    }
 }
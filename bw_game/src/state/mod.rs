@@ -1,5 +1,22 @@
+mod asset_registry;
 mod gameplay;
+mod loading_error;
 mod match_loading;
 
 pub use gameplay::GameplayState;
+pub use loading_error::{LoadingError, LoadingErrorState};
 pub use match_loading::MatchLoadingState;
+
+use amethyst::{core::ParentHierarchy, ecs::Entity, prelude::*};
+
+/// Deletes a UI created from a `.ron` file together with its children.
+fn delete_ui(world: &mut World, ui: Entity) {
+    let children = world
+        .read_resource::<ParentHierarchy>()
+        .all_children_iter(ui)
+        .collect::<Vec<_>>();
+    world
+        .delete_entities(&children)
+        .expect("failed to delete the children of a UI");
+    world.delete_entity(ui).expect("failed to delete a UI");
+}
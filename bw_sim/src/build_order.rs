@@ -0,0 +1,914 @@
+//! Build orders played out frame by frame.
+//!
+//! Only the economy is simulated: workers mine at a fixed rate without
+//! walking anywhere, buildings are placed right away and nothing is ever
+//! attacked. Steps start in order as soon as their requirements, resources
+//! and supply allow, like a player that follows the build order to the
+//! letter.
+
+use bw_assets::dat::{TechDataDat, UnitsDat, UpgradesDat};
+use bw_core::{Prerequisite, Producer, TechId, UnitId, UpgradeId};
+use num_traits::FromPrimitive;
+use snafu::{OptionExt, Snafu};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Frames it takes a Hatchery to spawn another larva.
+const LARVA_FRAMES: u32 = 342;
+
+/// Larva that a Hatchery spawns before it stops.
+const MAX_LARVA: u8 = 3;
+
+/// Supply cap of 200, in halves.
+const MAX_SUPPLY: u16 = 400;
+
+/// Gas workers that a refinery takes.
+const WORKERS_PER_GEYSER: usize = 3;
+
+/// Mineral workers per mineral field past which no more are mined.
+const WORKERS_PER_FIELD: usize = 2;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("step {} ({:?}) can't be made or researched", index + 1, step))]
+    Unavailable { index: usize, step: Step },
+
+    #[snafu(display("step {} ({:?}) is already researched", index + 1, step))]
+    AlreadyResearched { index: usize, step: Step },
+
+    #[snafu(display(
+        "step {} ({:?}) still can't start after {} frames",
+        index + 1,
+        step,
+        frame
+    ))]
+    Stalled {
+        index: usize,
+        step: Step,
+        frame: u32,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// What it takes to make a unit or to research a tech or an upgrade level.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Cost {
+    pub minerals: u16,
+    pub gas: u16,
+
+    /// Frames it takes at the Fastest speed.
+    pub time: u16,
+
+    /// Supply taken up, in halves.
+    pub supply_required: u8,
+
+    /// Supply provided once done, in halves.
+    pub supply_provided: u8,
+}
+
+/// Cost of the first level of an upgrade and what every further level costs
+/// on top of the previous one.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct UpgradeCost {
+    pub base: Cost,
+    pub factor: Cost,
+}
+
+impl UpgradeCost {
+    /// Cost of researching `level`, starting from 1.
+    pub fn level(&self, level: u8) -> Cost {
+        let n = u16::from(level.saturating_sub(1));
+        let scale = |base: u16, factor: u16| base.saturating_add(factor.saturating_mul(n));
+
+        Cost {
+            minerals: scale(self.base.minerals, self.factor.minerals),
+            gas: scale(self.base.gas, self.factor.gas),
+            time: scale(self.base.time, self.factor.time),
+            ..Cost::default()
+        }
+    }
+}
+
+/// Costs of everything that a build order can contain.
+#[derive(Clone, Debug, Default)]
+pub struct Costs {
+    pub units: HashMap<UnitId, Cost>,
+    pub tech: HashMap<TechId, Cost>,
+    pub upgrades: HashMap<UpgradeId, UpgradeCost>,
+}
+
+impl Costs {
+    /// Costs of every unit, tech and upgrade of the stock game as given in
+    /// the DAT files.
+    pub fn new(
+        units_dat: &UnitsDat,
+        tech_data_dat: &TechDataDat,
+        upgrades_dat: &UpgradesDat,
+    ) -> Costs {
+        let units = (0..)
+            .map(UnitId::from_usize)
+            .take_while(Option::is_some)
+            .flatten()
+            .filter_map(|unit_id| {
+                let unit = units_dat.get(unit_id as usize)?;
+                let cost = Cost {
+                    minerals: unit.mineral_cost(),
+                    gas: unit.vespene_cost(),
+                    time: unit.build_time(),
+                    supply_required: unit.supply_required(),
+                    supply_provided: unit.supply_provided(),
+                };
+
+                Some((unit_id, cost))
+            })
+            .collect();
+
+        let tech = (0..)
+            .map(TechId::from_usize)
+            .take_while(Option::is_some)
+            .flatten()
+            .filter_map(|tech_id| {
+                let tech_data = tech_data_dat.get(tech_id as usize)?;
+                let cost = Cost {
+                    minerals: tech_data.mineral_cost(),
+                    gas: tech_data.vespene_cost(),
+                    time: tech_data.research_time(),
+                    ..Cost::default()
+                };
+
+                Some((tech_id, cost))
+            })
+            .collect();
+
+        let upgrades = (0..)
+            .map(UpgradeId::from_usize)
+            .take_while(Option::is_some)
+            .flatten()
+            .filter_map(|upgrade_id| {
+                let upgrade = upgrades_dat.get(upgrade_id as usize)?;
+                let cost = UpgradeCost {
+                    base: Cost {
+                        minerals: upgrade.mineral_cost(),
+                        gas: upgrade.vespene_cost(),
+                        time: upgrade.time_cost(),
+                        ..Cost::default()
+                    },
+                    factor: Cost {
+                        minerals: upgrade.mineral_factor(),
+                        gas: upgrade.vespene_factor(),
+                        time: upgrade.time_factor(),
+                        ..Cost::default()
+                    },
+                };
+
+                Some((upgrade_id, cost))
+            })
+            .collect();
+
+        Costs {
+            units,
+            tech,
+            upgrades,
+        }
+    }
+}
+
+/// A single step of a build order.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Step {
+    /// Trains, builds or morphs a unit.
+    Make(UnitId),
+
+    Research(TechId),
+
+    /// Researches the next level of an upgrade.
+    Upgrade(UpgradeId),
+
+    /// Moves workers between minerals and gas until this many mine gas, as
+    /// far as the finished refineries allow.
+    GasWorkers(u8),
+}
+
+/// How fast workers mine and how long to simulate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Settings {
+    /// Minerals that a worker mines per frame.
+    pub minerals_per_worker: f64,
+
+    /// Gas that a worker mines per frame.
+    pub gas_per_worker: f64,
+
+    /// Mineral fields at the base.
+    pub mineral_fields: u8,
+
+    /// Minerals at the start.
+    pub minerals: u16,
+
+    /// Frames to give up after when a step still can't start.
+    pub max_frames: u32,
+
+    /// Frames between two samples of the resources.
+    pub sample_interval: u32,
+}
+
+impl Default for Settings {
+    /// Close base of a melee game, where a worker mines about 65 minerals or
+    /// 55 gas a minute.
+    fn default() -> Self {
+        Settings {
+            minerals_per_worker: 0.045,
+            gas_per_worker: 0.038,
+            mineral_fields: 8,
+            minerals: 50,
+            max_frames: 30 * 60 * 24,
+            sample_interval: 24,
+        }
+    }
+}
+
+/// Units that a player starts a melee game with given their town hall, i.e.
+/// four workers and an Overlord for Zerg.
+pub fn starting_units(town_hall: UnitId) -> Vec<UnitId> {
+    let (worker, overlord) = match town_hall {
+        UnitId::TerranCommandCenter => (UnitId::TerranScv, None),
+        UnitId::ZergHatchery => (UnitId::ZergDrone, Some(UnitId::ZergOverlord)),
+        UnitId::ProtossNexus => (UnitId::ProtossProbe, None),
+        _ => return vec![town_hall],
+    };
+
+    std::iter::once(town_hall)
+        .chain(overlord)
+        .chain(std::iter::repeat(worker).take(4))
+        .collect()
+}
+
+/// A scripted build order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BuildOrder {
+    /// Units at the start, e.g. from [`starting_units`].
+    pub start: Vec<UnitId>,
+
+    pub steps: Vec<Step>,
+}
+
+impl BuildOrder {
+    /// Plays out the build order until every step is done.
+    pub fn simulate(&self, costs: &Costs, settings: &Settings) -> Result<Timeline> {
+        let mut simulation = Simulation::new(&self.start, costs, settings);
+        let mut timeline = Timeline::default();
+        let mut next = 0;
+
+        loop {
+            simulation.complete();
+            simulation.spawn_larva();
+
+            while let Some(&step) = self.steps.get(next) {
+                match simulation.start(next, step)? {
+                    Some(finish) => timeline.steps.push(StepTiming {
+                        step,
+                        start: simulation.frame,
+                        finish,
+                    }),
+                    None => break,
+                }
+                next += 1;
+            }
+
+            let done = next == self.steps.len() && simulation.pending.is_empty();
+            if simulation.frame % settings.sample_interval.max(1) == 0 || done {
+                timeline.samples.push(simulation.sample());
+            }
+            if done {
+                return Ok(timeline);
+            }
+
+            if simulation.frame >= settings.max_frames {
+                return Stalled {
+                    index: next,
+                    step: self.steps[next],
+                    frame: simulation.frame,
+                }
+                .fail();
+            }
+
+            simulation.mine();
+            simulation.frame += 1;
+        }
+    }
+}
+
+/// When each step of a build order started and finished, and the resources
+/// over time.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Timeline {
+    pub steps: Vec<StepTiming>,
+    pub samples: Vec<Sample>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct StepTiming {
+    pub step: Step,
+
+    /// Frame the step started at.
+    pub start: u32,
+
+    /// Frame the unit, tech or upgrade is done at.
+    pub finish: u32,
+}
+
+/// Resources of the player at some frame.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Sample {
+    pub frame: u32,
+    pub minerals: u32,
+    pub gas: u32,
+
+    /// Supply taken up, in halves.
+    pub supply_used: u16,
+
+    /// Supply provided up to the cap of 200, in halves.
+    pub supply_provided: u16,
+
+    pub workers: u16,
+    pub gas_workers: u16,
+}
+
+/// A finished unit of the player.
+struct Owned {
+    unit_id: UnitId,
+
+    /// Frame from which the unit can start on something else.
+    busy_until: u32,
+
+    add_on: Option<UnitId>,
+
+    /// Larva of a Hatchery.
+    larva: u8,
+
+    /// Frame at which a Hatchery spawns its next larva.
+    next_larva: u32,
+}
+
+impl Owned {
+    fn new(unit_id: UnitId, frame: u32) -> Owned {
+        Owned {
+            unit_id,
+            busy_until: frame,
+            add_on: None,
+            larva: 0,
+            next_larva: frame + LARVA_FRAMES,
+        }
+    }
+
+    fn is_idle(&self, frame: u32) -> bool {
+        self.busy_until <= frame
+    }
+}
+
+enum Done {
+    Units(UnitId, u8),
+    AddOn { owner: usize, add_on: UnitId },
+    Morph { owner: usize, into: UnitId },
+    Tech(TechId),
+    Upgrade(UpgradeId),
+}
+
+struct Pending {
+    finish: u32,
+    done: Done,
+}
+
+struct Simulation<'a> {
+    costs: &'a Costs,
+    settings: &'a Settings,
+    frame: u32,
+    minerals: f64,
+    gas: f64,
+    gas_workers: u8,
+    supply_used: u16,
+    supply_provided: u16,
+    owned: BTreeMap<usize, Owned>,
+    next_key: usize,
+    pending: Vec<Pending>,
+    researched: HashSet<TechId>,
+    researching: HashSet<TechId>,
+    upgrade_levels: HashMap<UpgradeId, u8>,
+    upgrading: HashMap<UpgradeId, u8>,
+}
+
+impl<'a> Simulation<'a> {
+    fn new(start: &[UnitId], costs: &'a Costs, settings: &'a Settings) -> Simulation<'a> {
+        let mut simulation = Simulation {
+            costs,
+            settings,
+            frame: 0,
+            minerals: f64::from(settings.minerals),
+            gas: 0.0,
+            gas_workers: 0,
+            supply_used: 0,
+            supply_provided: 0,
+            owned: BTreeMap::new(),
+            next_key: 0,
+            pending: vec![],
+            researched: HashSet::new(),
+            researching: HashSet::new(),
+            upgrade_levels: HashMap::new(),
+            upgrading: HashMap::new(),
+        };
+
+        for &unit_id in start {
+            let cost = simulation.cost(unit_id);
+            simulation.supply_used += u16::from(cost.supply_required);
+            let key = simulation.add(unit_id);
+            if unit_id.satisfies(UnitId::ZergHatchery) {
+                simulation
+                    .owned
+                    .get_mut(&key)
+                    .expect("unit was added")
+                    .larva = MAX_LARVA;
+            }
+        }
+
+        simulation
+    }
+
+    fn cost(&self, unit_id: UnitId) -> Cost {
+        self.costs.units.get(&unit_id).copied().unwrap_or_default()
+    }
+
+    fn add(&mut self, unit_id: UnitId) -> usize {
+        let key = self.next_key;
+        self.next_key += 1;
+        self.owned.insert(key, Owned::new(unit_id, self.frame));
+        self.supply_provided += u16::from(self.cost(unit_id).supply_provided);
+
+        key
+    }
+
+    fn remove(&mut self, key: usize) {
+        if let Some(owned) = self.owned.remove(&key) {
+            let cost = self.cost(owned.unit_id);
+            self.supply_used -= u16::from(cost.supply_required);
+            self.supply_provided -= u16::from(cost.supply_provided);
+        }
+    }
+
+    fn has(&self, unit_id: UnitId) -> bool {
+        self.owned
+            .values()
+            .any(|owned| owned.unit_id.satisfies(unit_id))
+    }
+
+    /// Finishes everything that is done by the current frame.
+    fn complete(&mut self) {
+        let frame = self.frame;
+        let (done, pending) = self
+            .pending
+            .drain(..)
+            .partition::<Vec<_>, _>(|pending| pending.finish <= frame);
+        self.pending = pending;
+
+        for Pending { done, .. } in done {
+            match done {
+                Done::Units(unit_id, count) => {
+                    for _ in 0..count {
+                        self.add(unit_id);
+                    }
+                }
+                Done::AddOn { owner, add_on } => {
+                    if let Some(owned) = self.owned.get_mut(&owner) {
+                        owned.add_on = Some(add_on);
+                    }
+                    self.add(add_on);
+                }
+                Done::Morph { owner, into } => {
+                    let from = match self.owned.get_mut(&owner) {
+                        Some(owned) => std::mem::replace(&mut owned.unit_id, into),
+                        None => continue,
+                    };
+                    self.supply_provided -= u16::from(self.cost(from).supply_provided);
+                    self.supply_provided += u16::from(self.cost(into).supply_provided);
+                }
+                Done::Tech(tech_id) => {
+                    self.researched.insert(tech_id);
+                }
+                Done::Upgrade(upgrade_id) => {
+                    *self.upgrade_levels.entry(upgrade_id).or_default() += 1;
+                }
+            }
+        }
+    }
+
+    fn spawn_larva(&mut self) {
+        let frame = self.frame;
+        for owned in self.owned.values_mut() {
+            if !owned.unit_id.satisfies(UnitId::ZergHatchery) || owned.larva >= MAX_LARVA {
+                continue;
+            }
+
+            if owned.next_larva <= frame {
+                owned.larva += 1;
+                owned.next_larva = frame + LARVA_FRAMES;
+            }
+        }
+    }
+
+    /// Starts a step if everything it needs is there, returning the frame
+    /// at which it is done.
+    fn start(&mut self, index: usize, step: Step) -> Result<Option<u32>> {
+        let (prerequisite, cost) = match step {
+            Step::GasWorkers(count) => {
+                self.gas_workers = count;
+                return Ok(Some(self.frame));
+            }
+            Step::Make(unit_id) => (
+                Prerequisite::Unit(unit_id),
+                self.costs.units.get(&unit_id).copied(),
+            ),
+            Step::Research(tech_id) => {
+                if self.researched.contains(&tech_id) || self.researching.contains(&tech_id) {
+                    return AlreadyResearched { index, step }.fail();
+                }
+
+                (
+                    Prerequisite::Tech(tech_id),
+                    self.costs.tech.get(&tech_id).copied(),
+                )
+            }
+            Step::Upgrade(upgrade_id) => {
+                let level = self.upgrading.get(&upgrade_id).copied().unwrap_or(0) + 1;
+
+                // Only one level of an upgrade is researched at a time.
+                let researched = self.upgrade_levels.get(&upgrade_id).copied().unwrap_or(0);
+                if researched + 1 < level {
+                    return Ok(None);
+                }
+
+                (
+                    Prerequisite::Upgrade(upgrade_id, level),
+                    self.costs
+                        .upgrades
+                        .get(&upgrade_id)
+                        .map(|cost| cost.level(level)),
+                )
+            }
+        };
+        let requirements = prerequisite
+            .requirements()
+            .context(Unavailable { index, step })?;
+        let cost = cost.context(Unavailable { index, step })?;
+
+        let requirements_met = requirements.units.iter().all(|&unit_id| self.has(unit_id))
+            && requirements
+                .tech
+                .map_or(true, |tech_id| self.researched.contains(&tech_id));
+        let affordable =
+            self.minerals >= f64::from(cost.minerals) && self.gas >= f64::from(cost.gas);
+        if !requirements_met || !affordable {
+            return Ok(None);
+        }
+
+        let producers = match self.producers(requirements.producer, requirements.add_on) {
+            Some(producers) => producers,
+            None => return Ok(None),
+        };
+
+        let count = match (requirements.producer, step) {
+            (Producer::Morph(UnitId::ZergLarva), Step::Make(UnitId::ZergZergling))
+            | (Producer::Morph(UnitId::ZergLarva), Step::Make(UnitId::ZergScourge)) => 2,
+            _ => 1,
+        };
+        let morph_in_place = match (requirements.producer, step) {
+            (Producer::Morph(from), Step::Make(into)) => into.satisfies(from),
+            _ => false,
+        };
+        let consumed = match requirements.producer {
+            Producer::Morph(UnitId::ZergLarva) => 0,
+            Producer::Morph(from) | Producer::Merge(from) if !morph_in_place => {
+                producers.len() as u16 * u16::from(self.cost(from).supply_required)
+            }
+            _ => 0,
+        };
+        let supply = u16::from(cost.supply_required) * count;
+        if supply > consumed
+            && self.supply_used + supply - consumed > self.supply_provided.min(MAX_SUPPLY)
+        {
+            return Ok(None);
+        }
+
+        self.minerals -= f64::from(cost.minerals);
+        self.gas -= f64::from(cost.gas);
+        self.supply_used += supply;
+        let finish = self.frame + u32::from(cost.time);
+
+        let done = match (requirements.producer, step) {
+            (_, Step::Research(tech_id)) => {
+                self.researching.insert(tech_id);
+                Done::Tech(tech_id)
+            }
+            (_, Step::Upgrade(upgrade_id)) => {
+                *self.upgrading.entry(upgrade_id).or_default() += 1;
+                Done::Upgrade(upgrade_id)
+            }
+            (Producer::AddOn(_), Step::Make(add_on)) => Done::AddOn {
+                owner: producers[0],
+                add_on,
+            },
+            (Producer::Morph(UnitId::ZergLarva), Step::Make(unit_id)) => {
+                let hatchery = self.owned.get_mut(&producers[0]).expect("producer exists");
+                if hatchery.larva == MAX_LARVA {
+                    hatchery.next_larva = self.frame + LARVA_FRAMES;
+                }
+                hatchery.larva -= 1;
+
+                Done::Units(unit_id, count as u8)
+            }
+            (Producer::Morph(_), Step::Make(into)) if morph_in_place => Done::Morph {
+                owner: producers[0],
+                into,
+            },
+            (Producer::Morph(_), Step::Make(unit_id))
+            | (Producer::Merge(_), Step::Make(unit_id)) => {
+                for &key in &producers {
+                    self.remove(key);
+                }
+
+                Done::Units(unit_id, 1)
+            }
+            (_, Step::Make(unit_id)) => Done::Units(unit_id, count as u8),
+            (_, Step::GasWorkers(_)) => unreachable!("gas workers are set right away"),
+        };
+
+        // A Probe only starts the warp-in of a building and goes back to
+        // mining, while anything else is busy until it is done.
+        let keeps_producer = !matches!(requirements.producer, Producer::Unit(UnitId::ProtossProbe))
+            && !matches!(requirements.producer, Producer::Morph(UnitId::ZergLarva));
+        if keeps_producer {
+            for key in &producers {
+                if let Some(owned) = self.owned.get_mut(key) {
+                    owned.busy_until = finish;
+                }
+            }
+        }
+
+        self.pending.push(Pending { finish, done });
+
+        Ok(Some(finish))
+    }
+
+    /// Units that are free to produce something, or `None` when there are
+    /// not enough of them yet.
+    fn producers(&self, producer: Producer, add_on: Option<UnitId>) -> Option<Vec<usize>> {
+        let frame = self.frame;
+        let mut idle = self
+            .owned
+            .iter()
+            .filter(|(_, owned)| owned.is_idle(frame))
+            .map(|(&key, owned)| (key, owned));
+
+        let producers: Vec<usize> = match producer {
+            Producer::Unit(unit_id) => idle
+                .find(|(_, owned)| {
+                    owned.unit_id.satisfies(unit_id)
+                        && add_on.map_or(true, |add_on| owned.add_on == Some(add_on))
+                })
+                .map(|(key, _)| key)
+                .into_iter()
+                .collect(),
+            Producer::AddOn(unit_id) => idle
+                .find(|(_, owned)| owned.unit_id.satisfies(unit_id) && owned.add_on.is_none())
+                .map(|(key, _)| key)
+                .into_iter()
+                .collect(),
+            // Larva are taken from busy Hatcheries as well.
+            Producer::Morph(UnitId::ZergLarva) => self
+                .owned
+                .iter()
+                .find(|(_, owned)| owned.larva > 0)
+                .map(|(&key, _)| key)
+                .into_iter()
+                .collect(),
+            Producer::Morph(unit_id) => idle
+                .find(|(_, owned)| owned.unit_id == unit_id)
+                .map(|(key, _)| key)
+                .into_iter()
+                .collect(),
+            Producer::Merge(unit_id) => idle
+                .filter(|(_, owned)| owned.unit_id == unit_id)
+                .map(|(key, _)| key)
+                .take(2)
+                .collect(),
+        };
+
+        let needed = match producer {
+            Producer::Merge(_) => 2,
+            _ => 1,
+        };
+
+        Some(producers).filter(|producers| producers.len() == needed)
+    }
+
+    /// Workers that are free to mine, and how many of them mine gas.
+    fn workers(&self) -> (usize, usize) {
+        let frame = self.frame;
+        let workers = self
+            .owned
+            .values()
+            .filter(|owned| owned.unit_id.is_worker() && owned.is_idle(frame))
+            .count();
+        let geysers = self
+            .owned
+            .values()
+            .filter(|owned| owned.unit_id.is_refinery())
+            .count();
+        let gas_workers = usize::from(self.gas_workers)
+            .min(geysers * WORKERS_PER_GEYSER)
+            .min(workers);
+
+        (workers, gas_workers)
+    }
+
+    fn mine(&mut self) {
+        let (workers, gas_workers) = self.workers();
+        let mineral_workers = (workers - gas_workers)
+            .min(usize::from(self.settings.mineral_fields) * WORKERS_PER_FIELD);
+
+        self.minerals += mineral_workers as f64 * self.settings.minerals_per_worker;
+        self.gas += gas_workers as f64 * self.settings.gas_per_worker;
+    }
+
+    fn sample(&self) -> Sample {
+        let (_, gas_workers) = self.workers();
+
+        Sample {
+            frame: self.frame,
+            minerals: self.minerals as u32,
+            gas: self.gas as u32,
+            supply_used: self.supply_used,
+            supply_provided: self.supply_provided.min(MAX_SUPPLY),
+            workers: self
+                .owned
+                .values()
+                .filter(|owned| owned.unit_id.is_worker())
+                .count() as u16,
+            gas_workers: gas_workers as u16,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    fn cost(minerals: u16, time: u16, supply_required: u8, supply_provided: u8) -> Cost {
+        Cost {
+            minerals,
+            gas: 0,
+            time,
+            supply_required,
+            supply_provided,
+        }
+    }
+
+    fn costs() -> Costs {
+        let mut costs = Costs::default();
+        costs.units.extend(vec![
+            (UnitId::TerranScv, cost(50, 300, 2, 0)),
+            (UnitId::TerranMarine, cost(50, 360, 2, 0)),
+            (UnitId::TerranCommandCenter, cost(400, 1800, 0, 20)),
+            (UnitId::TerranSupplyDepot, cost(100, 600, 0, 16)),
+            (UnitId::TerranBarracks, cost(150, 1200, 0, 0)),
+            (UnitId::ZergLarva, cost(1, 1, 0, 0)),
+            (UnitId::ZergDrone, cost(50, 300, 2, 0)),
+            (UnitId::ZergZergling, cost(50, 420, 1, 0)),
+            (UnitId::ZergOverlord, cost(100, 600, 0, 16)),
+            (UnitId::ZergHatchery, cost(300, 1800, 0, 2)),
+            (UnitId::ZergSpawningPool, cost(200, 1200, 0, 0)),
+        ]);
+
+        costs
+    }
+
+    /// Four workers mine a mineral a frame.
+    fn settings() -> Settings {
+        Settings {
+            minerals_per_worker: 0.25,
+            ..Settings::default()
+        }
+    }
+
+    fn simulate(town_hall: UnitId, steps: Vec<Step>) -> Result<Timeline> {
+        BuildOrder {
+            start: starting_units(town_hall),
+            steps,
+        }
+        .simulate(&costs(), &settings())
+    }
+
+    fn starts(timeline: &Timeline) -> Vec<u32> {
+        timeline.steps.iter().map(|step| step.start).collect()
+    }
+
+    #[test]
+    fn it_starts_steps_once_they_are_affordable() {
+        let timeline = simulate(
+            UnitId::TerranCommandCenter,
+            vec![
+                Step::Make(UnitId::TerranScv),
+                Step::Make(UnitId::TerranSupplyDepot),
+            ],
+        )
+        .unwrap();
+
+        assert_that(&starts(&timeline)).is_equal_to(vec![0, 100]);
+        assert_that(&timeline.steps[1].finish).is_equal_to(700);
+        assert_that(&timeline.samples.last().unwrap().workers).is_equal_to(5);
+        assert_that(&timeline.samples.last().unwrap().supply_provided).is_equal_to(36);
+    }
+
+    #[test]
+    fn it_waits_for_the_producer() {
+        let timeline = simulate(
+            UnitId::TerranCommandCenter,
+            vec![Step::Make(UnitId::TerranScv), Step::Make(UnitId::TerranScv)],
+        )
+        .unwrap();
+
+        assert_that(&starts(&timeline)).is_equal_to(vec![0, 300]);
+    }
+
+    #[test]
+    fn it_waits_for_required_buildings() {
+        let timeline = simulate(
+            UnitId::TerranCommandCenter,
+            vec![
+                Step::Make(UnitId::TerranBarracks),
+                Step::Make(UnitId::TerranMarine),
+            ],
+        )
+        .unwrap();
+
+        assert_that(&starts(&timeline)).is_equal_to(vec![100, 1300]);
+    }
+
+    #[test]
+    fn it_stalls_without_supply() {
+        let err = simulate(
+            UnitId::TerranCommandCenter,
+            vec![Step::Make(UnitId::TerranScv); 7],
+        )
+        .unwrap_err();
+
+        assert!(matches!(err, Error::Stalled { index: 6, .. }));
+    }
+
+    #[test]
+    fn it_morphs_larva_into_pairs_of_zerglings() {
+        let timeline = simulate(
+            UnitId::ZergHatchery,
+            vec![
+                Step::Make(UnitId::ZergSpawningPool),
+                Step::Make(UnitId::ZergZergling),
+                Step::Make(UnitId::ZergZergling),
+                Step::Make(UnitId::ZergZergling),
+                Step::Make(UnitId::ZergZergling),
+            ],
+        )
+        .unwrap();
+        let last = timeline.samples.last().unwrap();
+
+        // The Drone that became the Spawning Pool mines no more.
+        assert_that(&last.workers).is_equal_to(3);
+        assert_that(&last.supply_used).is_equal_to(6 + 8);
+
+        // Three larva are there at the start and the fourth spawns later.
+        let starts = starts(&timeline);
+        assert_that(&starts[0]).is_equal_to(150);
+        assert_that(&starts[1]).is_equal_to(1350);
+        assert_that(&starts[4]).is_greater_than(starts[3]);
+    }
+
+    #[test]
+    fn it_rejects_what_cant_be_made() {
+        let err = simulate(
+            UnitId::TerranCommandCenter,
+            vec![Step::Make(UnitId::HeroSarahKerrigan)],
+        )
+        .unwrap_err();
+
+        assert!(matches!(err, Error::Unavailable { index: 0, .. }));
+    }
+
+    #[test]
+    fn it_scales_the_cost_of_upgrade_levels() {
+        let cost = UpgradeCost {
+            base: cost(100, 4000, 0, 0),
+            factor: cost(75, 480, 0, 0),
+        };
+
+        assert_that(&cost.level(1).minerals).is_equal_to(100);
+        assert_that(&cost.level(3).minerals).is_equal_to(250);
+        assert_that(&cost.level(3).time).is_equal_to(4960);
+    }
+}
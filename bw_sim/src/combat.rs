@@ -0,0 +1,394 @@
+//! Damage of weapons against units.
+//!
+//! Damage, shields and hit points are calculated in 24.8 fixed point like the
+//! game does, so the smallest hit of 0.5 damage is exact.
+
+use bw_assets::dat::{Unit, Weapon};
+use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
+
+/// Game frames per second at the Fastest game speed.
+pub const FRAMES_PER_SECOND: f32 = 1000.0 / 42.0;
+
+/// Smallest damage that a hit deals, 0.5 in fixed point.
+const MIN_DAMAGE: i64 = 128;
+
+fn fixed(value: impl Into<i64>) -> i64 {
+    value.into() << 8
+}
+
+/// Damage type of a weapon.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, FromPrimitive)]
+pub enum DamageType {
+    Independent = 0,
+    Explosive = 1,
+    Concussive = 2,
+    Normal = 3,
+    IgnoreArmor = 4,
+}
+
+/// Size of a unit that decides how much damage explosive and concussive
+/// weapons deal.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, FromPrimitive)]
+pub enum UnitSize {
+    /// Units that take 0.5 damage from every hit.
+    Independent = 0,
+    Small = 1,
+    Medium = 2,
+    Large = 3,
+}
+
+impl DamageType {
+    /// Share of the damage out of 256 that units of `size` take.
+    fn modifier(self, size: UnitSize) -> i64 {
+        match (self, size) {
+            (DamageType::Explosive, UnitSize::Small) => 128,
+            (DamageType::Explosive, UnitSize::Medium) => 192,
+            (DamageType::Concussive, UnitSize::Medium) => 128,
+            (DamageType::Concussive, UnitSize::Large) => 64,
+            _ => 256,
+        }
+    }
+}
+
+/// Attack of a weapon at some upgrade level.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Attack {
+    /// Damage of a single hit including upgrades.
+    pub damage: u16,
+
+    /// Number of hits of every attack. Armor is subtracted from every hit.
+    pub hits: u8,
+
+    pub damage_type: DamageType,
+
+    /// Frames between two attacks.
+    pub cooldown: u8,
+
+    /// Inner, medium and outer splash radii in pixels. Weapons without splash
+    /// have no radii.
+    pub splash_radii: (u16, u16, u16),
+}
+
+impl Attack {
+    /// Attack of `weapon` with `upgrades` levels of its damage upgrade.
+    pub fn new(weapon: &Weapon, upgrades: u8) -> Attack {
+        Attack {
+            damage: weapon
+                .damage_amount()
+                .saturating_add(weapon.damage_bonus().saturating_mul(upgrades.into())),
+            hits: weapon.damage_factor().max(1),
+            damage_type: DamageType::from_u8(weapon.weapon_type()).unwrap_or(DamageType::Normal),
+            cooldown: weapon.weapon_cooldown(),
+            splash_radii: weapon.splash_radii(),
+        }
+    }
+
+    /// Share of the damage out of 256 that a unit `distance` pixels away
+    /// from where the attack hits takes.
+    fn splash_modifier(&self, distance: u16) -> i64 {
+        let (inner, medium, outer) = self.splash_radii;
+
+        match distance {
+            _ if outer == 0 => 256,
+            d if d <= inner => 256,
+            d if d <= medium => 128,
+            d if d <= outer => 64,
+            _ => 0,
+        }
+    }
+
+    /// Damage that a single hit deals to `target` while it has `shields`
+    /// shield points left, `distance` pixels away from where the attack hits.
+    ///
+    /// Shields take the damage minus the shield armor. Whatever they can't
+    /// absorb goes on to the hit points, where the armor is subtracted before
+    /// the size modifier is applied. Neither takes less than 0.5 damage from
+    /// a hit.
+    pub fn hit(&self, target: &Target, shields: f32, distance: u16) -> HitDamage {
+        self.hit_fixed(target, (shields * 256.0) as i64, distance)
+    }
+
+    fn hit_fixed(&self, target: &Target, shields: i64, distance: u16) -> HitDamage {
+        let mut damage = (fixed(self.damage) * self.splash_modifier(distance)) >> 8;
+        if damage == 0 {
+            return HitDamage::default();
+        }
+
+        let mut shield_damage = 0;
+        if shields > 0 {
+            let damage_to_shields = (damage - fixed(target.shield_armour)).max(MIN_DAMAGE);
+            shield_damage = damage_to_shields.min(shields);
+            damage = damage_to_shields - shield_damage;
+
+            if damage == 0 {
+                return HitDamage {
+                    shields: shield_damage,
+                    hit_points: 0,
+                };
+            }
+        }
+
+        let hit_point_damage = if target.size == UnitSize::Independent {
+            MIN_DAMAGE
+        } else {
+            let armour = match self.damage_type {
+                DamageType::IgnoreArmor => 0,
+                _ => fixed(target.armour),
+            };
+            let damage = (damage - armour).max(MIN_DAMAGE);
+
+            ((damage * self.damage_type.modifier(target.size)) >> 8).max(MIN_DAMAGE)
+        };
+
+        HitDamage {
+            shields: shield_damage,
+            hit_points: hit_point_damage,
+        }
+    }
+
+    /// Attacks needed to kill `target` from full shields and hit points, with
+    /// every attack hitting `distance` pixels away from it. `None` when the
+    /// target is outside of the splash or the attacks deal no damage to it.
+    pub fn kill(&self, target: &Target, distance: u16) -> Option<Kill> {
+        if self.splash_modifier(distance) == 0 {
+            return None;
+        }
+
+        let mut shields = fixed(target.shields);
+        let mut hit_points = i64::from(target.hit_points);
+        let mut kill = Kill::default();
+
+        while hit_points > 0 {
+            kill.attacks += 1;
+
+            for _ in 0..self.hits {
+                let damage = self.hit_fixed(target, shields, distance);
+                // A weapon without damage would attack forever.
+                if damage == HitDamage::default() {
+                    return None;
+                }
+
+                shields -= damage.shields;
+                hit_points -= damage.hit_points;
+                kill.hits += 1;
+
+                if hit_points <= 0 {
+                    break;
+                }
+            }
+        }
+
+        // The first attack is fired right away.
+        kill.frames = kill.attacks.saturating_sub(1) * u32::from(self.cooldown);
+
+        Some(kill)
+    }
+}
+
+/// Defenses of a unit at some upgrade level.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Target {
+    /// Hit points as 24.8 fixed point.
+    pub hit_points: u32,
+
+    pub shields: u16,
+
+    /// Armor including upgrades.
+    pub armour: u8,
+
+    /// Armor of the shields, i.e. the level of Plasma Shields.
+    pub shield_armour: u8,
+
+    pub size: UnitSize,
+}
+
+impl Target {
+    /// Defenses of `unit` with `armour_upgrades` levels of its armor upgrade
+    /// and `shield_upgrades` levels of Plasma Shields.
+    pub fn new(unit: &Unit, armour_upgrades: u8, shield_upgrades: u8) -> Target {
+        Target {
+            hit_points: unit.raw_hit_points(),
+            shields: unit.shields().unwrap_or(0),
+            armour: unit.armour().saturating_add(armour_upgrades),
+            shield_armour: shield_upgrades,
+            size: UnitSize::from_u8(unit.unit_size()).unwrap_or(UnitSize::Independent),
+        }
+    }
+}
+
+/// Damage of a single hit.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct HitDamage {
+    shields: i64,
+    hit_points: i64,
+}
+
+impl HitDamage {
+    /// Damage absorbed by the shields.
+    pub fn shields(&self) -> f32 {
+        self.shields as f32 / 256.0
+    }
+
+    /// Damage dealt to the hit points.
+    pub fn hit_points(&self) -> f32 {
+        self.hit_points as f32 / 256.0
+    }
+}
+
+/// What it takes to kill a unit.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Kill {
+    pub attacks: u32,
+    pub hits: u32,
+
+    /// Frames from the first to the last attack.
+    pub frames: u32,
+}
+
+impl Kill {
+    /// Time from the first to the last attack at the Fastest game speed.
+    pub fn seconds(&self) -> f32 {
+        self.frames as f32 / FRAMES_PER_SECOND
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    const GAUSS_RIFLE: Attack = Attack {
+        damage: 6,
+        hits: 1,
+        damage_type: DamageType::Normal,
+        cooldown: 15,
+        splash_radii: (0, 0, 0),
+    };
+
+    const PSI_BLADES: Attack = Attack {
+        damage: 8,
+        hits: 2,
+        damage_type: DamageType::Normal,
+        cooldown: 22,
+        splash_radii: (0, 0, 0),
+    };
+
+    const ARCLITE_SHOCK_CANNON: Attack = Attack {
+        damage: 70,
+        hits: 1,
+        damage_type: DamageType::Explosive,
+        cooldown: 75,
+        splash_radii: (10, 25, 40),
+    };
+
+    fn marine() -> Target {
+        Target {
+            hit_points: 40 << 8,
+            shields: 0,
+            armour: 0,
+            shield_armour: 0,
+            size: UnitSize::Small,
+        }
+    }
+
+    fn zealot() -> Target {
+        Target {
+            hit_points: 100 << 8,
+            shields: 60,
+            armour: 1,
+            shield_armour: 0,
+            size: UnitSize::Small,
+        }
+    }
+
+    #[test]
+    fn it_drains_shields_before_hit_points() {
+        let kill = GAUSS_RIFLE.kill(&zealot(), 0).unwrap();
+
+        // 10 hits of 6 on the shields and 20 hits of 6 - 1 on the hit points.
+        assert_that(&kill.hits).is_equal_to(30);
+        assert_that(&kill.frames).is_equal_to(29 * 15);
+        assert_that(&GAUSS_RIFLE.hit(&zealot(), 60.0, 0).shields()).is_equal_to(6.0);
+        assert_that(&GAUSS_RIFLE.hit(&zealot(), 0.0, 0).hit_points()).is_equal_to(5.0);
+    }
+
+    #[test]
+    fn it_carries_damage_over_from_depleted_shields() {
+        let damage = PSI_BLADES.hit(&zealot(), 3.0, 0);
+
+        assert_that(&damage.shields()).is_equal_to(3.0);
+        assert_that(&damage.hit_points()).is_equal_to(4.0);
+    }
+
+    #[test]
+    fn it_subtracts_armor_from_every_hit() {
+        let mut marine = marine();
+        let kill = PSI_BLADES.kill(&marine, 0).unwrap();
+        assert_that(&kill.attacks).is_equal_to(3);
+        assert_that(&kill.hits).is_equal_to(5);
+
+        marine.armour = 3;
+        let kill = PSI_BLADES.kill(&marine, 0).unwrap();
+        assert_that(&kill.attacks).is_equal_to(4);
+        assert_that(&kill.hits).is_equal_to(8);
+    }
+
+    #[test]
+    fn it_subtracts_armor_before_the_size_modifier() {
+        let attack = Attack {
+            damage: 20,
+            damage_type: DamageType::Explosive,
+            ..GAUSS_RIFLE
+        };
+        let zergling = Target {
+            hit_points: 35 << 8,
+            armour: 1,
+            ..marine()
+        };
+
+        // (20 - 1) * 50%, where the size modifier first would deal 20 * 50% - 1.
+        assert_that(&attack.hit(&zergling, 0.0, 0).hit_points()).is_equal_to(9.5);
+        assert_that(&attack.kill(&zergling, 0).unwrap().hits).is_equal_to(4);
+    }
+
+    #[test]
+    fn it_deals_at_least_half_a_point_of_damage() {
+        let ultralisk = Target {
+            hit_points: 400 << 8,
+            armour: 10,
+            size: UnitSize::Large,
+            ..marine()
+        };
+
+        assert_that(&GAUSS_RIFLE.hit(&ultralisk, 0.0, 0).hit_points()).is_equal_to(0.5);
+
+        let attack = Attack {
+            damage_type: DamageType::IgnoreArmor,
+            ..GAUSS_RIFLE
+        };
+        assert_that(&attack.hit(&ultralisk, 0.0, 0).hit_points()).is_equal_to(6.0);
+    }
+
+    #[test]
+    fn it_reduces_splash_damage_with_distance() {
+        let marine = marine();
+
+        assert_that(&ARCLITE_SHOCK_CANNON.hit(&marine, 0.0, 0).hit_points()).is_equal_to(35.0);
+        assert_that(&ARCLITE_SHOCK_CANNON.hit(&marine, 0.0, 20).hit_points()).is_equal_to(17.5);
+        assert_that(&ARCLITE_SHOCK_CANNON.hit(&marine, 0.0, 40).hit_points()).is_equal_to(8.75);
+        assert_that(&ARCLITE_SHOCK_CANNON.kill(&marine, 20).unwrap().hits).is_equal_to(3);
+        assert_that(&ARCLITE_SHOCK_CANNON.kill(&marine, 41)).is_none();
+    }
+
+    #[test]
+    fn it_cant_kill_with_a_weapon_without_damage() {
+        let attack = Attack {
+            damage: 0,
+            ..GAUSS_RIFLE
+        };
+
+        assert_that(&attack.kill(&marine(), 0)).is_none();
+        assert_that(&attack.kill(&zealot(), 0)).is_none();
+    }
+}
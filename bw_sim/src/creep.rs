@@ -0,0 +1,483 @@
+//! Creep under and around Zerg buildings.
+//!
+//! Creep is tracked per megatile. Every Zerg building keeps creep under its
+//! footprint, and hatcheries and colonies spread it a tile at a time until
+//! it fills an ellipse around them or reaches terrain that can't be built
+//! on. Creep that a map starts with is spread completely before the first
+//! frame.
+
+use bw_assets::{
+    dat::{Unit, UnitsDat},
+    map::Map,
+    tileset::{BuildFlag, CV5s, VF4s, VF4},
+};
+use bw_core::{Race, UnitId};
+
+/// Frames between two tiles of creep that a building spreads.
+pub const CREEP_SPREAD_FRAMES: u8 = 15;
+
+/// Tiles by which creep reaches past the footprint of a building,
+/// horizontally and vertically.
+const CREEP_RANGE: (i64, i64) = (8, 5);
+
+/// Side of a megatile in pixels.
+const TILE: i32 = 32;
+
+/// Tiles around a tile, clockwise from the tile above.
+const NEIGHBOURS: [(i32, i32); 8] = [
+    (0, -1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+];
+
+/// Tiles that a building covers.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Footprint {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Footprint {
+    /// Footprint of a building at a position in pixels, which is its center
+    /// like the position of placed units, with a placement box in pixels.
+    pub fn new(position: (u16, u16), placement_box: (u16, u16)) -> Footprint {
+        let (width, height) = (i32::from(placement_box.0), i32::from(placement_box.1));
+
+        Footprint {
+            x: (i32::from(position.0) - width / 2).div_euclid(TILE),
+            y: (i32::from(position.1) - height / 2).div_euclid(TILE),
+            width: (width + TILE - 1) / TILE,
+            height: (height + TILE - 1) / TILE,
+        }
+    }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        (self.x..self.x + self.width).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
+
+    fn tiles(&self) -> impl Iterator<Item = (i32, i32)> {
+        let Footprint {
+            x,
+            y,
+            width,
+            height,
+        } = *self;
+
+        (y..y + height).flat_map(move |ty| (x..x + width).map(move |tx| (tx, ty)))
+    }
+
+    /// Distance of a tile from the center relative to the creep range, where
+    /// values up to 1.0 are in range. Calculated in half tiles so that it
+    /// stays exact.
+    fn creep_distance(&self, x: i32, y: i32) -> (i64, i64) {
+        let dx = i64::from(2 * x + 1 - (2 * self.x + self.width));
+        let dy = i64::from(2 * y + 1 - (2 * self.y + self.height));
+        let rx = i64::from(self.width) + 2 * CREEP_RANGE.0;
+        let ry = i64::from(self.height) + 2 * CREEP_RANGE.1;
+
+        (dx * dx * ry * ry + dy * dy * rx * rx, rx * rx * ry * ry)
+    }
+
+    fn is_in_creep_range(&self, x: i32, y: i32) -> bool {
+        let (distance, range) = self.creep_distance(x, y);
+
+        self.contains(x, y) || distance <= range
+    }
+}
+
+/// How a tile is drawn because of creep.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CreepTile {
+    /// The tile has creep. The variant keeps creep from repeating.
+    Fill(u16),
+
+    /// The tile has no creep but its neighbours do, with a bit for every
+    /// neighbour clockwise from the tile above.
+    Edge(u8),
+}
+
+/// Whether Zerg buildings of the type have creep under them.
+fn has_creep(unit_id: UnitId) -> bool {
+    unit_id.race() == Some(Race::Zerg) && unit_id.is_building() && !unit_id.is_refinery()
+}
+
+/// Whether Zerg buildings of the type spread creep around them.
+fn spreads_creep(unit_id: UnitId) -> bool {
+    use UnitId::*;
+
+    matches!(
+        unit_id,
+        ZergHatchery | ZergLair | ZergHive | ZergCreepColony | ZergSunkenColony | ZergSporeColony
+    )
+}
+
+/// Whether buildings can be placed on every megatile of a map as far as the
+/// terrain goes: the CV5 group of the megatile doesn't forbid it and all of
+/// its minitiles are walkable.
+pub fn buildable_tiles(map: &Map, cv5s: &CV5s, vf4s: &VF4s) -> Vec<bool> {
+    let tiles = usize::from(map.dimensions.width) * usize::from(map.dimensions.height);
+
+    (0..tiles)
+        .map(|i| {
+            map.megatiles.get(i).map_or(false, |megatile| {
+                cv5s[megatile].build_flag() != BuildFlag::Unbuildable
+                    && vf4s[&cv5s[megatile][megatile]].iter().all(VF4::is_walkable)
+            })
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug)]
+struct Source {
+    footprint: Footprint,
+    timer: u8,
+    is_complete: bool,
+}
+
+/// Creep on every megatile of a map.
+#[derive(Clone, Debug)]
+pub struct CreepGrid {
+    width: i32,
+    height: i32,
+    tiles: Vec<bool>,
+    buildable: Vec<bool>,
+    sources: Vec<Source>,
+    changes: Vec<(i32, i32)>,
+}
+
+impl CreepGrid {
+    /// Grid of a map in megatiles without any creep, where every tile can be
+    /// built on.
+    pub fn new(width: u16, height: u16) -> CreepGrid {
+        let tiles = usize::from(width) * usize::from(height);
+
+        CreepGrid::with_buildable(width, height, vec![true; tiles])
+    }
+
+    /// Grid of a map in megatiles without any creep, where creep only
+    /// spreads over the tiles that `buildable` has, row by row, e.g. from
+    /// [`buildable_tiles`].
+    pub fn with_buildable(width: u16, height: u16, buildable: Vec<bool>) -> CreepGrid {
+        let tiles = usize::from(width) * usize::from(height);
+        assert_eq!(buildable.len(), tiles, "a tile is missing from the mask");
+
+        CreepGrid {
+            width: i32::from(width),
+            height: i32::from(height),
+            tiles: vec![false; tiles],
+            buildable,
+            sources: vec![],
+            changes: vec![],
+        }
+    }
+
+    /// Creep of the Zerg buildings that are placed on a map, spread
+    /// completely over the terrain of its tileset that can be built on.
+    pub fn from_map(map: &Map, units_dat: &UnitsDat, cv5s: &CV5s, vf4s: &VF4s) -> CreepGrid {
+        let mut grid = CreepGrid::with_buildable(
+            map.dimensions.width,
+            map.dimensions.height,
+            buildable_tiles(map, cv5s, vf4s),
+        );
+
+        for placed_unit in &map.placed_units {
+            let unit_id = match placed_unit.unit_id() {
+                Some(unit_id) => unit_id,
+                None => continue,
+            };
+
+            if let Some(unit) = units_dat.get(unit_id as usize) {
+                let footprint = Footprint::new(placed_unit.position(), unit.placement_box());
+                grid.add_building(unit_id, footprint);
+            }
+        }
+        grid.spread_fully();
+        grid.changes.clear();
+
+        grid
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width as u16
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height as u16
+    }
+
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        if (0..self.width).contains(&x) && (0..self.height).contains(&y) {
+            Some((x + y * self.width) as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Whether the megatile has creep. Tiles outside the map don't.
+    pub fn has_creep(&self, x: i32, y: i32) -> bool {
+        matches!(self.index(x, y), Some(i) if self.tiles[i])
+    }
+
+    /// Whether the terrain of the megatile can be built on. Tiles outside the
+    /// map can't.
+    pub fn is_buildable(&self, x: i32, y: i32) -> bool {
+        matches!(self.index(x, y), Some(i) if self.buildable[i])
+    }
+
+    fn set_creep(&mut self, x: i32, y: i32) -> bool {
+        match self.index(x, y) {
+            Some(i) if !self.tiles[i] => {
+                self.tiles[i] = true;
+                self.changes.push((x, y));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Tiles that got creep since the last call, e.g. to redraw them and
+    /// their neighbours.
+    pub fn take_changes(&mut self) -> Vec<(i32, i32)> {
+        std::mem::take(&mut self.changes)
+    }
+
+    /// Adds the creep of a building: all of its footprint at once, and the
+    /// area around it over time if it spreads creep.
+    pub fn add_building(&mut self, unit_id: UnitId, footprint: Footprint) {
+        if !has_creep(unit_id) {
+            return;
+        }
+
+        for (x, y) in footprint.tiles() {
+            self.set_creep(x, y);
+        }
+
+        if spreads_creep(unit_id) {
+            self.sources.push(Source {
+                footprint,
+                timer: CREEP_SPREAD_FRAMES,
+                is_complete: false,
+            });
+        }
+    }
+
+    /// Advances by a game frame and returns whether any tile got creep.
+    pub fn tick(&mut self) -> bool {
+        let mut changed = false;
+
+        for i in 0..self.sources.len() {
+            let source = &mut self.sources[i];
+            if source.is_complete {
+                continue;
+            }
+
+            source.timer -= 1;
+            if source.timer == 0 {
+                source.timer = CREEP_SPREAD_FRAMES;
+                changed |= self.spread(i);
+            }
+        }
+
+        changed
+    }
+
+    /// Spreads creep until every building filled its range.
+    pub fn spread_fully(&mut self) {
+        for i in 0..self.sources.len() {
+            while self.spread(i) {}
+        }
+    }
+
+    /// Adds creep to the buildable tile next to existing creep that is
+    /// closest to the building. Returns `false` once the range is full.
+    fn spread(&mut self, i: usize) -> bool {
+        let footprint = self.sources[i].footprint;
+        let (rx, ry) = (CREEP_RANGE.0 as i32, CREEP_RANGE.1 as i32);
+
+        let next = (footprint.y - ry..footprint.y + footprint.height + ry)
+            .flat_map(|y| {
+                (footprint.x - rx..footprint.x + footprint.width + rx).map(move |x| (x, y))
+            })
+            .filter(|&(x, y)| {
+                self.is_buildable(x, y)
+                    && !self.has_creep(x, y)
+                    && footprint.is_in_creep_range(x, y)
+                    && NEIGHBOURS
+                        .iter()
+                        .step_by(2)
+                        .any(|(dx, dy)| self.has_creep(x + dx, y + dy))
+            })
+            .min_by_key(|&(x, y)| {
+                let (distance, range) = footprint.creep_distance(x, y);
+                (distance * 1024 / range, y, x)
+            });
+
+        match next {
+            Some((x, y)) => self.set_creep(x, y),
+            None => {
+                self.sources[i].is_complete = true;
+                false
+            }
+        }
+    }
+
+    /// How a megatile is drawn, or `None` when creep doesn't touch it.
+    pub fn tile(&self, x: i32, y: i32) -> Option<CreepTile> {
+        if self.has_creep(x, y) {
+            let variant = (x as u32).wrapping_mul(0x9E37) ^ (y as u32).wrapping_mul(0x79B9);
+            return Some(CreepTile::Fill((variant >> 3) as u16));
+        }
+
+        let neighbours = NEIGHBOURS
+            .iter()
+            .enumerate()
+            .filter(|(_, (dx, dy))| self.has_creep(x + dx, y + dy))
+            .fold(0, |mask, (i, _)| mask | 1 << i);
+
+        if neighbours != 0 {
+            Some(CreepTile::Edge(neighbours))
+        } else {
+            None
+        }
+    }
+
+    /// Whether terrain and creep allow a building on the footprint. Every
+    /// tile has to be buildable, and buildings that require creep need it on
+    /// every tile while every other building can't have it on any tile.
+    pub fn can_build(&self, footprint: &Footprint, requires_creep: bool) -> bool {
+        footprint
+            .tiles()
+            .all(|(x, y)| self.is_buildable(x, y) && self.has_creep(x, y) == requires_creep)
+    }
+
+    /// Whether a building of the type can be placed with its center at a
+    /// position in pixels.
+    pub fn can_place(&self, unit: &Unit, position: (u16, u16)) -> bool {
+        let footprint = Footprint::new(position, unit.placement_box());
+
+        self.can_build(&footprint, unit.requires_creep())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    /// Hatchery in the middle of a 64x64 map.
+    fn hatchery() -> Footprint {
+        Footprint::new((1024, 1024), (128, 96))
+    }
+
+    #[test]
+    fn it_covers_the_footprint_of_zerg_buildings() {
+        let mut grid = CreepGrid::new(64, 64);
+        grid.add_building(UnitId::ZergSpawningPool, Footprint::new((48, 32), (96, 64)));
+        grid.add_building(
+            UnitId::TerranBarracks,
+            Footprint::new((400, 400), (128, 96)),
+        );
+
+        let footprint = Footprint::new((48, 32), (96, 64));
+        assert_that(&footprint).is_equal_to(Footprint {
+            x: 0,
+            y: 0,
+            width: 3,
+            height: 2,
+        });
+        assert_that(&grid.has_creep(2, 1)).is_true();
+        assert_that(&grid.has_creep(3, 1)).is_false();
+        assert_that(&grid.has_creep(12, 12)).is_false();
+
+        // Spawning pools don't spread creep.
+        assert_that(&(0..100).any(|_| grid.tick())).is_false();
+    }
+
+    #[test]
+    fn it_spreads_creep_a_tile_at_a_time() {
+        let mut grid = CreepGrid::new(64, 64);
+        grid.add_building(UnitId::ZergHatchery, hatchery());
+        let creep = |grid: &CreepGrid| grid.tiles.iter().filter(|&&tile| tile).count();
+        assert_that(&creep(&grid)).is_equal_to(12);
+        assert_that(&grid.take_changes()).has_length(12);
+
+        for _ in 0..CREEP_SPREAD_FRAMES - 1 {
+            assert_that(&grid.tick()).is_false();
+        }
+        assert_that(&grid.tick()).is_true();
+        assert_that(&creep(&grid)).is_equal_to(13);
+        assert_that(&grid.take_changes()).has_length(1);
+
+        let mut spread = grid.clone();
+        spread.spread_fully();
+        let full = creep(&spread);
+        assert_that(&full).is_greater_than(100);
+
+        // Wider than it is tall.
+        assert_that(&spread.has_creep(30 - 8, 31)).is_true();
+        assert_that(&spread.has_creep(31, 30 - 8)).is_false();
+
+        while grid.tick() || creep(&grid) < full {}
+        assert_that(&grid.tiles).is_equal_to(&spread.tiles);
+    }
+
+    #[test]
+    fn it_draws_edges_around_creep() {
+        let mut grid = CreepGrid::new(8, 8);
+        grid.add_building(UnitId::ZergCreepColony, Footprint::new((64, 64), (64, 64)));
+
+        assert_that(&grid.tile(1, 1)).matches(|tile| matches!(tile, Some(CreepTile::Fill(_))));
+        assert_that(&grid.tile(0, 1)).is_equal_to(Some(CreepTile::Edge(0b0000_1100)));
+        assert_that(&grid.tile(0, 0)).is_equal_to(Some(CreepTile::Edge(0b0000_1000)));
+        assert_that(&grid.tile(5, 5)).is_none();
+    }
+
+    #[test]
+    fn it_only_allows_zerg_buildings_on_creep() {
+        let mut grid = CreepGrid::new(64, 64);
+        grid.add_building(UnitId::ZergHatchery, hatchery());
+        grid.spread_fully();
+
+        let pool = Footprint::new((1024, 1200), (96, 64));
+        let far = Footprint::new((100, 100), (96, 64));
+        assert_that(&grid.can_build(&pool, true)).is_true();
+        assert_that(&grid.can_build(&pool, false)).is_false();
+        assert_that(&grid.can_build(&far, true)).is_false();
+        assert_that(&grid.can_build(&far, false)).is_true();
+    }
+
+    #[test]
+    fn it_stops_creep_at_unbuildable_tiles() {
+        // A cliff along column 34, east of the hatchery.
+        let buildable = (0..64 * 64).map(|i| i % 64 != 34).collect::<Vec<_>>();
+        let mut grid = CreepGrid::with_buildable(64, 64, buildable);
+        grid.add_building(UnitId::ZergHatchery, hatchery());
+        grid.spread_fully();
+
+        let mut open = CreepGrid::new(64, 64);
+        open.add_building(UnitId::ZergHatchery, hatchery());
+        open.spread_fully();
+
+        assert_that(&grid.has_creep(33, 31)).is_true();
+        assert_that(&grid.is_buildable(34, 31)).is_false();
+        assert_that(&open.has_creep(35, 31)).is_true();
+        assert_that(&(0..64).any(|y| (34..64).any(|x| grid.has_creep(x, y)))).is_false();
+
+        let across = Footprint::new((1104, 1008), (64, 64));
+        assert_that(&across).is_equal_to(Footprint {
+            x: 33,
+            y: 30,
+            width: 2,
+            height: 2,
+        });
+        assert_that(&grid.can_build(&across, true)).is_false();
+        assert_that(&grid.can_build(&across, false)).is_false();
+    }
+}
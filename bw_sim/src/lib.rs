@@ -0,0 +1,15 @@
+//! Game Rules of Brood War
+//!
+//! Calculations that follow the rules of the game on top of the DAT files,
+//! without running a match.
+
+mod build_order;
+mod combat;
+mod creep;
+
+pub use build_order::{
+    starting_units, BuildOrder, Cost, Costs, Error as BuildOrderError, Sample, Settings, Step,
+    StepTiming, Timeline, UpgradeCost,
+};
+pub use combat::{Attack, DamageType, HitDamage, Kill, Target, UnitSize, FRAMES_PER_SECOND};
+pub use creep::{CreepGrid, CreepTile, Footprint, CREEP_SPREAD_FRAMES};
@@ -0,0 +1,275 @@
+//! Plays out a build order and reports when every step happens.
+//!
+//! ```text
+//! bw-build 9pool.txt --race zerg
+//! bw-build bio.txt --race terran --samples bio.csv --mpq STARDAT.MPQ --mpq BROODAT.MPQ
+//! ```
+//!
+//! A build order has a step on every line, with `#` starting a comment:
+//!
+//! ```text
+//! 4 ZergDrone
+//! ZergOverlord
+//! ZergSpawningPool
+//! gas 3
+//! research Burrowing
+//! upgrade MetabolicBoost
+//! ```
+
+use bw_core::UnitId;
+use bw_sim::{
+    starting_units, BuildOrder, BuildOrderError, Costs, Sample, Settings, Step, FRAMES_PER_SECOND,
+};
+use bw_tools::{open_source, GameData, DEFAULT_ARCHIVES, IO};
+use clap::{App, Arg, ArgMatches};
+use serde::de::{value, DeserializeOwned, IntoDeserializer};
+use snafu::{ResultExt, Snafu};
+use std::fs;
+
+#[derive(Debug, Snafu)]
+enum Error {
+    #[snafu(display("{}", source))]
+    Tools { source: bw_tools::Error },
+
+    #[snafu(display("unknown build order step on line {}: {}", line, text))]
+    UnknownStep { line: usize, text: String },
+
+    #[snafu(display("{}", source))]
+    Simulation { source: BuildOrderError },
+}
+
+impl From<bw_tools::Error> for Error {
+    fn from(source: bw_tools::Error) -> Self {
+        Error::Tools { source }
+    }
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Width of the longest bar of the resource graphs.
+const GRAPH_WIDTH: u32 = 40;
+
+/// Seconds between two rows of the resource graphs.
+const GRAPH_INTERVAL: u32 = 30;
+
+fn main() {
+    let samples_help = format!(
+        "Writes the resources every {} frames and at the end to a CSV file",
+        Settings::default().sample_interval
+    );
+    let matches = App::new("bw-build")
+        .about("Plays out a build order and reports when every step happens")
+        .arg(
+            Arg::with_name("build-order")
+                .required(true)
+                .help("File with a step on every line"),
+        )
+        .arg(
+            Arg::with_name("race")
+                .long("race")
+                .takes_value(true)
+                .required(true)
+                .possible_values(&["terran", "zerg", "protoss"])
+                .help("Race to start with"),
+        )
+        .arg(
+            Arg::with_name("samples")
+                .long("samples")
+                .takes_value(true)
+                .help(&samples_help),
+        )
+        .arg(
+            Arg::with_name("mpq")
+                .long("mpq")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help(
+                    "Archives or a directory to read the DAT files from. Archives given later \
+                     take precedence [default: STARDAT.MPQ BROODAT.MPQ patch_rt.mpq]",
+                ),
+        )
+        .get_matches();
+
+    if let Err(err) = build(&matches) {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+fn build(matches: &ArgMatches) -> Result<()> {
+    let path = matches.value_of("build-order").unwrap_or_default();
+    let text = fs::read_to_string(path).context(IO {})?;
+    let steps = parse_steps(&text)?;
+
+    let paths = matches
+        .values_of("mpq")
+        .map_or_else(|| DEFAULT_ARCHIVES.to_vec(), Iterator::collect);
+    let game_data = GameData::load(&open_source(&paths)?)?;
+    let costs = Costs::new(&game_data.units, &game_data.tech_data, &game_data.upgrades);
+
+    let town_hall = match matches.value_of("race") {
+        Some("zerg") => UnitId::ZergHatchery,
+        Some("protoss") => UnitId::ProtossNexus,
+        _ => UnitId::TerranCommandCenter,
+    };
+    let build_order = BuildOrder {
+        start: starting_units(town_hall),
+        steps,
+    };
+    let timeline = build_order
+        .simulate(&costs, &Settings::default())
+        .context(Simulation {})?;
+
+    println!("{:>6}  {:<32}{:>6}", "start", "step", "done");
+    for step in &timeline.steps {
+        println!(
+            "{:>6}  {:<32}{:>6}",
+            clock(step.start),
+            describe(step.step),
+            clock(step.finish)
+        );
+    }
+
+    println!();
+    print_graphs(&timeline.samples);
+
+    if let Some(path) = matches.value_of("samples") {
+        fs::write(path, samples_csv(&timeline.samples)).context(IO {})?;
+    }
+
+    Ok(())
+}
+
+/// Reads a step from every line that isn't blank or a comment.
+fn parse_steps(text: &str) -> Result<Vec<Step>> {
+    let mut steps = vec![];
+
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let unknown = || Error::UnknownStep {
+            line: i + 1,
+            text: line.to_string(),
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let (count, words) = match words.split_first() {
+            Some((count, rest)) if !rest.is_empty() && count.parse::<usize>().is_ok() => {
+                (count.parse().unwrap_or(1), rest)
+            }
+            _ => (1, &words[..]),
+        };
+
+        let step = match words {
+            ["research", name] => from_name(name).map(Step::Research),
+            ["upgrade", name] => from_name(name).map(Step::Upgrade),
+            ["gas", workers] => workers.parse().ok().map(Step::GasWorkers),
+            [name] => name.parse().ok().map(Step::Make),
+            _ => None,
+        }
+        .ok_or_else(unknown)?;
+
+        steps.extend(std::iter::repeat(step).take(count));
+    }
+
+    Ok(steps)
+}
+
+/// Value of an enum like [`UnitId`] by the name of its variant.
+fn from_name<T: DeserializeOwned>(name: &str) -> Option<T> {
+    let deserializer: value::StrDeserializer<value::Error> = name.into_deserializer();
+
+    T::deserialize(deserializer).ok()
+}
+
+fn describe(step: Step) -> String {
+    match step {
+        Step::Make(unit_id) => format!("{:?}", unit_id),
+        Step::Research(tech_id) => format!("research {:?}", tech_id),
+        Step::Upgrade(upgrade_id) => format!("upgrade {:?}", upgrade_id),
+        Step::GasWorkers(workers) => format!("gas {}", workers),
+    }
+}
+
+fn seconds(frame: u32) -> u32 {
+    (frame as f32 / FRAMES_PER_SECOND) as u32
+}
+
+/// Game time as minutes and seconds at the Fastest speed.
+fn clock(frame: u32) -> String {
+    let seconds = seconds(frame);
+
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Minerals and gas as bars and supply as numbers, every few seconds of
+/// game time.
+fn print_graphs(samples: &[Sample]) {
+    let mut rows: Vec<&Sample> = vec![];
+    for sample in samples {
+        let due = rows.last().map_or(true, |row| {
+            seconds(sample.frame) >= seconds(row.frame) + GRAPH_INTERVAL
+        });
+        if due {
+            rows.push(sample);
+        }
+    }
+    if let (Some(last), Some(row)) = (samples.last(), rows.last()) {
+        if last != *row {
+            rows.push(last);
+        }
+    }
+
+    let max = rows
+        .iter()
+        .map(|row| row.minerals.max(row.gas))
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let bar = |amount: u32, c: &str| c.repeat((amount * GRAPH_WIDTH / max) as usize);
+
+    println!(
+        "{:>6}  {:<w$}  {:<w$}  {:>7}  {:>7}",
+        "time",
+        "minerals",
+        "gas",
+        "supply",
+        "workers",
+        w = GRAPH_WIDTH as usize + 6
+    );
+    for row in rows {
+        println!(
+            "{:>6}  {:<w$}  {:<w$}  {:>7}  {:>7}",
+            clock(row.frame),
+            format!("{} {}", bar(row.minerals, "#"), row.minerals),
+            format!("{} {}", bar(row.gas, "="), row.gas),
+            format!("{}/{}", row.supply_used / 2, row.supply_provided / 2),
+            row.workers,
+            w = GRAPH_WIDTH as usize + 6
+        );
+    }
+}
+
+fn samples_csv(samples: &[Sample]) -> String {
+    let mut csv = String::from(
+        "frame,seconds,minerals,gas,supply_used,supply_provided,workers,gas_workers\n",
+    );
+    for sample in samples {
+        csv.push_str(&format!(
+            "{},{:.1},{},{},{},{},{},{}\n",
+            sample.frame,
+            sample.frame as f32 / FRAMES_PER_SECOND,
+            sample.minerals,
+            sample.gas,
+            f32::from(sample.supply_used) / 2.0,
+            f32::from(sample.supply_provided) / 2.0,
+            sample.workers,
+            sample.gas_workers
+        ));
+    }
+
+    csv
+}
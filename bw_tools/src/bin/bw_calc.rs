@@ -0,0 +1,227 @@
+//! Answers how many hits it takes one unit to kill another.
+//!
+//! ```text
+//! bw-calc TerranMarine ProtossZealot --attack-upgrades 1 --armour-upgrades 1
+//! bw-calc ZergHydralisk TerranWraith --mpq STARDAT.MPQ --mpq BROODAT.MPQ --mpq mod.mpq
+//! ```
+
+use bw_assets::{
+    dat::{UnitRowId, Weapon},
+    tbl::TblEntry,
+};
+use bw_sim::{Attack, Target};
+use bw_tools::{open_source, GameData, DEFAULT_ARCHIVES};
+use clap::{value_t, App, Arg, ArgMatches};
+use serde::{
+    de::{value, IntoDeserializer},
+    Deserialize,
+};
+use snafu::Snafu;
+
+#[derive(Debug, Snafu)]
+enum Error {
+    #[snafu(display("{}", source))]
+    Tools { source: bw_tools::Error },
+
+    #[snafu(display("unknown unit {}", name))]
+    UnknownUnit { name: String },
+
+    #[snafu(display("{} has no weapon against {}", unit, target))]
+    NoWeapon { unit: String, target: String },
+}
+
+impl From<bw_tools::Error> for Error {
+    fn from(source: bw_tools::Error) -> Self {
+        Error::Tools { source }
+    }
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
+fn main() {
+    let upgrade_arg = |name: &'static str, help: &'static str| {
+        Arg::with_name(name)
+            .long(name)
+            .takes_value(true)
+            .default_value("0")
+            .help(help)
+    };
+
+    let matches = App::new("bw-calc")
+        .about("Calculates how many hits it takes one unit to kill another")
+        .arg(
+            Arg::with_name("attacker")
+                .required(true)
+                .help("Attacking unit by name, e.g. TerranMarine, or by id"),
+        )
+        .arg(
+            Arg::with_name("target")
+                .required(true)
+                .help("Attacked unit by name, e.g. ProtossZealot, or by id"),
+        )
+        .arg(upgrade_arg(
+            "attack-upgrades",
+            "Levels of the damage upgrade of the attacker's weapon",
+        ))
+        .arg(upgrade_arg(
+            "armour-upgrades",
+            "Levels of the armor upgrade of the target",
+        ))
+        .arg(upgrade_arg(
+            "shield-upgrades",
+            "Levels of Plasma Shields of the target",
+        ))
+        .arg(
+            Arg::with_name("distance")
+                .long("distance")
+                .takes_value(true)
+                .default_value("0")
+                .help("Distance in pixels from where splash attacks hit"),
+        )
+        .arg(
+            Arg::with_name("weapon")
+                .long("weapon")
+                .takes_value(true)
+                .possible_values(&["ground", "air"])
+                .help("Weapon to attack with [default: air against flyers, otherwise ground]"),
+        )
+        .arg(
+            Arg::with_name("mpq")
+                .long("mpq")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help(
+                    "Archives or a directory to read the DAT files from. Archives given later \
+                     take precedence [default: STARDAT.MPQ BROODAT.MPQ patch_rt.mpq]",
+                ),
+        )
+        .get_matches();
+
+    if let Err(err) = calc(&matches) {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+fn calc(matches: &ArgMatches) -> Result<()> {
+    let paths = matches
+        .values_of("mpq")
+        .map_or_else(|| DEFAULT_ARCHIVES.to_vec(), Iterator::collect);
+    let game_data = GameData::load(&open_source(&paths)?)?;
+
+    let attacker_id = unit_id(&game_data, matches.value_of("attacker").unwrap_or_default())?;
+    let target_id = unit_id(&game_data, matches.value_of("target").unwrap_or_default())?;
+    let unit_name = |id: usize| {
+        game_data
+            .stat_txt
+            .get(id)
+            .map(|entry| entry.unit_name().name)
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| format!("unit #{}", id))
+    };
+
+    let upgrades = |name| value_t!(matches, name, u8).unwrap_or_else(|err| err.exit());
+    let distance = value_t!(matches, "distance", u16).unwrap_or_else(|err| err.exit());
+
+    let target_unit = game_data.units.get(target_id).expect("unit id is checked");
+    let air = match matches.value_of("weapon") {
+        Some(weapon) => weapon == "air",
+        None => target_unit.is_flyer(),
+    };
+
+    let weapon = weapon(&game_data, attacker_id, air).ok_or_else(|| Error::NoWeapon {
+        unit: unit_name(attacker_id),
+        target: unit_name(target_id),
+    })?;
+    let attacker = match game_data.stat_txt.label(weapon.label()).map(TblEntry::text) {
+        Some(weapon_name) if !weapon_name.is_empty() => {
+            format!("{} ({})", unit_name(attacker_id), weapon_name)
+        }
+        _ => unit_name(attacker_id),
+    };
+
+    let attack = Attack::new(weapon, upgrades("attack-upgrades"));
+    let target = Target::new(
+        target_unit,
+        upgrades("armour-upgrades"),
+        upgrades("shield-upgrades"),
+    );
+
+    println!("{} against {}", attacker, unit_name(target_id));
+
+    let shields = f32::from(target.shields);
+    let mut damage = vec![];
+    if shields > 0.0 {
+        damage.push(format!(
+            "{} to shields",
+            attack.hit(&target, shields, distance).shields()
+        ));
+    }
+    damage.push(format!(
+        "{} to hit points",
+        attack.hit(&target, 0.0, distance).hit_points()
+    ));
+    if attack.hits > 1 {
+        damage.push(format!("{} hits per attack", attack.hits));
+    }
+    println!("  damage per hit: {}", damage.join(", "));
+
+    match attack.kill(&target, distance) {
+        Some(kill) => {
+            println!(
+                "  hits to kill:   {} in {} attacks",
+                kill.hits, kill.attacks
+            );
+            println!(
+                "  time to kill:   {} frames ({:.1} s at Fastest)",
+                kill.frames,
+                kill.seconds()
+            );
+        }
+        None => println!("  hits to kill:   never, no damage at this distance"),
+    }
+
+    Ok(())
+}
+
+/// Unit by the name of its `UnitId` or by its number.
+fn unit_id(game_data: &GameData, name: &str) -> Result<usize> {
+    let deserializer: value::StrDeserializer<value::Error> = name.into_deserializer();
+
+    UnitRowId::deserialize(deserializer)
+        .ok()
+        .map(|id| usize::from(id.0))
+        .filter(|&id| game_data.units.get(id).is_some())
+        .ok_or_else(|| Error::UnknownUnit {
+            name: name.to_string(),
+        })
+}
+
+/// Weapon that a unit attacks with, which is the weapon of its turret for
+/// units like Siege Tanks.
+fn weapon(game_data: &GameData, unit_id: usize, air: bool) -> Option<&Weapon> {
+    // Fields that refer to no entry hold the entry count of the table, which
+    // extended DAT files raise.
+    let no_unit = game_data.units.len();
+    let no_weapon = game_data.weapons.len();
+
+    let unit = game_data.units.get(unit_id)?;
+    let turret = match usize::from(unit.sub_unit_1()) {
+        sub_unit if sub_unit == no_unit => None,
+        sub_unit => game_data.units.get(sub_unit),
+    };
+
+    std::iter::once(unit)
+        .chain(turret)
+        .map(|unit| {
+            if air {
+                unit.air_weapon()
+            } else {
+                unit.ground_weapon()
+            }
+        })
+        .map(usize::from)
+        .find(|&weapon_id| weapon_id != no_weapon)
+        .and_then(|weapon_id| game_data.weapons.get(weapon_id))
+}
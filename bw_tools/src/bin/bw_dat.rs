@@ -0,0 +1,228 @@
+//! Converts DAT files to CSV, JSON, or RON rows and back.
+//!
+//! ```text
+//! bw-dat export units --format csv --output units.csv
+//! bw-dat export weapons --file mod/weapons.dat
+//! bw-dat import units units.csv --output units.dat
+//! bw-dat diff --old STARDAT.MPQ --new STARDAT.MPQ --new mod.mpq
+//! ```
+
+use bw_assets::dat::TableFormat;
+use bw_tools::{
+    diff, open_archives, open_source, AssetSource, DatTable, GameData, Json, ReadFile, Result,
+    DEFAULT_ARCHIVES, IO,
+};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use snafu::ResultExt;
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+const FORMATS: &[&str] = &["csv", "json", "ron"];
+
+fn main() {
+    let table_names = DatTable::ALL
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    let table_names = table_names.iter().map(String::as_str).collect::<Vec<_>>();
+    let table_arg = Arg::with_name("table")
+        .help("DAT file to convert")
+        .required(true)
+        .possible_values(&table_names);
+    let format_arg = Arg::with_name("format")
+        .long("format")
+        .takes_value(true)
+        .possible_values(FORMATS)
+        .help("Format of the rows [default: from the file extension, otherwise csv]");
+
+    let matches = App::new("bw-dat")
+        .about("Converts DAT files to CSV, JSON, or RON rows and back")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            SubCommand::with_name("export")
+                .about("Writes a DAT file as rows")
+                .arg(table_arg.clone())
+                .arg(
+                    Arg::with_name("mpq")
+                        .long("mpq")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .conflicts_with("file")
+                        .help(
+                            "Archive to read the DAT file from. Archives given later take \
+                             precedence [default: STARDAT.MPQ BROODAT.MPQ patch_rt.mpq]",
+                        ),
+                )
+                .arg(
+                    Arg::with_name("file")
+                        .long("file")
+                        .takes_value(true)
+                        .help("Loose DAT file to read instead of the archives"),
+                )
+                .arg(format_arg.clone())
+                .arg(
+                    Arg::with_name("output")
+                        .long("output")
+                        .short("o")
+                        .takes_value(true)
+                        .help("File to write the rows to [default: stdout]"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("import")
+                .about("Writes rows back to a DAT file")
+                .arg(table_arg)
+                .arg(
+                    Arg::with_name("input")
+                        .required(true)
+                        .help("File with the rows"),
+                )
+                .arg(format_arg)
+                .arg(
+                    Arg::with_name("output")
+                        .long("output")
+                        .short("o")
+                        .takes_value(true)
+                        .required(true)
+                        .help("DAT file to write"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("diff")
+                .about("Lists the fields that differ between two sets of DAT files")
+                .arg(
+                    Arg::with_name("old")
+                        .long("old")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .required(true)
+                        .help("Archives or a directory with the original DAT files"),
+                )
+                .arg(
+                    Arg::with_name("new")
+                        .long("new")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .required(true)
+                        .help("Archives or a directory with the changed DAT files"),
+                )
+                .arg(
+                    Arg::with_name("json")
+                        .long("json")
+                        .help("Writes the changes as JSON instead of text"),
+                )
+                .arg(
+                    Arg::with_name("exit-code")
+                        .long("exit-code")
+                        .help("Exits with 1 when there are changes"),
+                ),
+        )
+        .get_matches();
+
+    let result = match matches.subcommand() {
+        ("export", Some(matches)) => export(matches),
+        ("import", Some(matches)) => import(matches),
+        ("diff", Some(matches)) => diff_dats(matches),
+        _ => unreachable!("a subcommand is required"),
+    };
+
+    if let Err(err) = result {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+fn table(matches: &ArgMatches) -> Result<DatTable> {
+    matches.value_of("table").unwrap_or_default().parse()
+}
+
+/// Format given on the command line, falling back to the extension of
+/// `path` and then CSV.
+fn format(matches: &ArgMatches, path: Option<&Path>) -> TableFormat {
+    matches
+        .value_of("format")
+        .and_then(|format| format.parse().ok())
+        .or_else(|| path.and_then(TableFormat::from_path))
+        .unwrap_or(TableFormat::Csv)
+}
+
+fn export(matches: &ArgMatches) -> Result<()> {
+    let table = table(matches)?;
+
+    let b = match matches.value_of("file") {
+        Some(file) => std::fs::read(file).context(ReadFile { path: file })?,
+        None => {
+            let archives = matches
+                .values_of("mpq")
+                .map_or_else(|| DEFAULT_ARCHIVES.to_vec(), Iterator::collect);
+            AssetSource::Archives(open_archives(&archives)?).read(table.file_name())?
+        }
+    };
+
+    let output = matches.value_of("output").map(Path::new);
+    let format = format(matches, output);
+
+    match output {
+        Some(path) => {
+            let mut w = BufWriter::new(File::create(path).context(IO {})?);
+            table.export(&b, &mut w, format)?;
+            w.flush().context(IO {})
+        }
+        None => {
+            let stdout = io::stdout();
+            let mut w = stdout.lock();
+            table.export(&b, &mut w, format)?;
+
+            // CSV rows already end with a new line.
+            if format != TableFormat::Csv {
+                writeln!(w).context(IO {})?;
+            }
+
+            Ok(())
+        }
+    }
+}
+
+fn import(matches: &ArgMatches) -> Result<()> {
+    let table = table(matches)?;
+
+    let input = Path::new(matches.value_of("input").unwrap_or_default());
+    let format = format(matches, Some(input));
+    let r = BufReader::new(File::open(input).context(IO {})?);
+
+    let b = table.import(r, format)?;
+
+    std::fs::write(matches.value_of("output").unwrap_or_default(), b).context(IO {})
+}
+
+fn diff_dats(matches: &ArgMatches) -> Result<()> {
+    let load = |name: &str| {
+        let paths = matches.values_of(name).into_iter().flatten().collect::<Vec<_>>();
+        GameData::load(&open_source(&paths)?)
+    };
+
+    let changes = diff(&load("old")?, &load("new")?)?;
+
+    let stdout = io::stdout();
+    let mut w = stdout.lock();
+    if matches.is_present("json") {
+        serde_json::to_writer_pretty(&mut w, &changes).context(Json {})?;
+        writeln!(w).context(IO {})?;
+    } else {
+        for change in &changes {
+            writeln!(w, "{}", change).context(IO {})?;
+        }
+    }
+
+    if matches.is_present("exit-code") && !changes.is_empty() {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
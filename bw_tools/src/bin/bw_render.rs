@@ -0,0 +1,171 @@
+//! Renders the terrain of a map to a PNG without opening a window.
+//!
+//! ```text
+//! bw-render "(2)Destination.scx"
+//! bw-render "(4)Fighting Spirit.scx" --output fs.png --markers
+//! bw-render "(4)Python.scx" --start-locations --resources --width 256
+//! ```
+
+use amethyst::assets::Format;
+use bw_assets::{
+    map::{Map, MapFormat},
+    render::{Markers, Renderer},
+    tileset::{parse_cv5s, parse_vr4s, parse_vx4s, parse_wpes},
+};
+use bw_tools::{open_source, parse, DEFAULT_ARCHIVES, IO};
+use clap::{value_t, App, Arg, ArgMatches};
+use image::imageops::{self, FilterType};
+use snafu::{ResultExt, Snafu};
+use std::{fs, path::Path};
+
+#[derive(Debug, Snafu)]
+enum Error {
+    #[snafu(display("{}", source))]
+    Tools { source: bw_tools::Error },
+
+    #[snafu(display("failed to load map {}: {}", path, message))]
+    LoadMap { path: String, message: String },
+
+    #[snafu(display("image error: {}", source))]
+    Image { source: image::ImageError },
+}
+
+impl From<bw_tools::Error> for Error {
+    fn from(source: bw_tools::Error) -> Self {
+        Error::Tools { source }
+    }
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
+fn main() {
+    let matches = App::new("bw-render")
+        .about("Renders the terrain of a map to a PNG without opening a window")
+        .arg(
+            Arg::with_name("map")
+                .required(true)
+                .help("SCM or SCX map to render"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .short("o")
+                .takes_value(true)
+                .help("PNG to write [default: the map with a png extension]"),
+        )
+        .arg(
+            Arg::with_name("markers")
+                .long("markers")
+                .help("Draws markers for every placed unit, start location and resource"),
+        )
+        .arg(
+            Arg::with_name("units")
+                .long("units")
+                .help("Draws a marker in the colour of its owner for every placed unit"),
+        )
+        .arg(
+            Arg::with_name("start-locations")
+                .long("start-locations")
+                .help("Outlines every start location"),
+        )
+        .arg(
+            Arg::with_name("resources")
+                .long("resources")
+                .help("Draws mineral fields and vespene geysers"),
+        )
+        .arg(
+            Arg::with_name("width")
+                .long("width")
+                .takes_value(true)
+                .validator(|width| match width.parse::<u32>() {
+                    Ok(width) if width > 0 => Ok(()),
+                    _ => Err(format!("{} isn't a positive number of pixels", width)),
+                })
+                .help(
+                    "Scales the image down to a width in pixels, e.g. for thumbnails. Images \
+                     that are narrower already keep their size",
+                ),
+        )
+        .arg(
+            Arg::with_name("mpq")
+                .long("mpq")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help(
+                    "Archives or a directory to read the tileset from. Archives given later \
+                     take precedence [default: STARDAT.MPQ BROODAT.MPQ patch_rt.mpq]",
+                ),
+        )
+        .get_matches();
+
+    if let Err(err) = render(&matches) {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+fn render(matches: &ArgMatches) -> Result<()> {
+    let map_path = Path::new(matches.value_of("map").unwrap_or_default());
+    let map = load_map(map_path)?;
+
+    let paths = matches
+        .values_of("mpq")
+        .map_or_else(|| DEFAULT_ARCHIVES.to_vec(), Iterator::collect);
+    let source = open_source(&paths)?;
+
+    let tileset_file =
+        |extension: &str| format!("tileset\\{}.{}", map.tileset.file_name(), extension);
+    let read = |file_name: &str| source.read(file_name);
+
+    let cv5 = tileset_file("cv5");
+    let cv5s = parse(&cv5, &read(&cv5)?, parse_cv5s)?;
+    let vx4 = tileset_file("vx4");
+    let vx4s = parse(&vx4, &read(&vx4)?, parse_vx4s)?;
+    let vr4 = tileset_file("vr4");
+    let vr4s = parse(&vr4, &read(&vr4)?, parse_vr4s)?;
+    let wpe = tileset_file("wpe");
+    let wpes = parse(&wpe, &read(&wpe)?, parse_wpes)?;
+
+    let markers = if matches.is_present("markers") {
+        Markers::all()
+    } else {
+        Markers {
+            units: matches.is_present("units"),
+            start_locations: matches.is_present("start-locations"),
+            resources: matches.is_present("resources"),
+        }
+    };
+
+    let renderer = Renderer {
+        cv5s: &cv5s,
+        vx4s: &vx4s,
+        vr4s: &vr4s,
+        wpes: &wpes,
+    };
+    let mut image = renderer.render(&map, markers);
+
+    if matches.is_present("width") {
+        let width = value_t!(matches, "width", u32)
+            .unwrap_or_else(|err| err.exit())
+            .min(image.width());
+        let height = (image.height() as u64 * width as u64 / image.width().max(1) as u64) as u32;
+        image = imageops::resize(&image, width, height.max(1), FilterType::Triangle);
+    }
+
+    let output = matches
+        .value_of("output")
+        .map_or_else(|| map_path.with_extension("png"), Into::into);
+    image.save(&output).context(Image {})?;
+
+    Ok(())
+}
+
+fn load_map(path: &Path) -> Result<Map> {
+    let b = fs::read(path).context(IO {})?;
+
+    MapFormat.import_simple(b).map_err(|err| Error::LoadMap {
+        path: path.display().to_string(),
+        message: err.to_string(),
+    })
+}
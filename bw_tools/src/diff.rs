@@ -0,0 +1,325 @@
+//! Field level differences between two sets of DAT files.
+
+use bw_assets::tbl::TblEntry;
+use serde::Serialize;
+use serde_json::Value;
+use snafu::ResultExt;
+use std::fmt;
+
+use crate::{DatTable, GameData, Json, Result};
+
+/// How an entry differs between two sets of DAT files.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    /// A field of an entry that exists on both sides changed.
+    Changed,
+
+    /// The entry only exists in the new files, e.g. in the extended layout of
+    /// a mod.
+    Added,
+
+    /// The entry only exists in the old files.
+    Removed,
+}
+
+/// A single field of an entry that differs between two sets of DAT files, or
+/// an entry that only exists on one side.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Change {
+    pub kind: ChangeKind,
+    pub table: String,
+    pub id: usize,
+
+    /// Name of the entry resolved through stat_txt.tbl, or the table and id
+    /// when the entry has no name.
+    pub name: String,
+
+    /// Column name of the field as written by `bw-dat export`, or `None` for
+    /// added and removed entries.
+    pub field: Option<String>,
+
+    /// Old value of the field, or every field of a removed entry.
+    pub old: Value,
+
+    /// New value of the field, or every field of an added entry.
+    pub new: Value,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.kind, &self.field) {
+            (ChangeKind::Added, _) => write!(f, "{} added", self.name),
+            (ChangeKind::Removed, _) => write!(f, "{} removed", self.name),
+            (ChangeKind::Changed, field) => write!(
+                f,
+                "{}.{} {} → {}",
+                self.name,
+                field.as_deref().unwrap_or_default(),
+                display_value(&self.old),
+                display_value(&self.new)
+            ),
+        }
+    }
+}
+
+/// Whole numbers are shown without a fraction so `40.0` hit points reads as
+/// `40`.
+fn display_value(value: &Value) -> String {
+    match value {
+        Value::Null => "-".to_string(),
+        Value::String(s) if s.is_empty() => "-".to_string(),
+        Value::String(s) => s.clone(),
+        Value::Number(n) => match n.as_f64() {
+            Some(x) if n.is_f64() && x.fract() == 0.0 => format!("{}", x as i64),
+            _ => n.to_string(),
+        },
+        _ => value.to_string(),
+    }
+}
+
+/// Compares every DAT file of `old` and `new` field by field.
+///
+/// Names are resolved with the strings of `old`, so a mod that renames a unit
+/// still shows the name that the unit had before.
+pub fn diff(old: &GameData, new: &GameData) -> Result<Vec<Change>> {
+    let stat_txt = &old.stat_txt;
+    let label = |label: u16| stat_txt.label(label).map(TblEntry::text);
+
+    let weapons = old.weapons.rows();
+    let tech_data = old.tech_data.rows();
+    let upgrades = old.upgrades.rows();
+
+    let mut changes = vec![];
+    changes.extend(diff_rows(
+        DatTable::Units,
+        &old.units.rows(),
+        &new.units.rows(),
+        |id| stat_txt.get(id).map(|entry| entry.unit_name().name),
+    )?);
+    changes.extend(diff_rows(
+        DatTable::Weapons,
+        &weapons,
+        &new.weapons.rows(),
+        |id| weapons.get(id).and_then(|weapon| label(weapon.label)),
+    )?);
+    changes.extend(diff_rows(
+        DatTable::Flingy,
+        &old.flingy.rows(),
+        &new.flingy.rows(),
+        |_| None,
+    )?);
+    changes.extend(diff_rows(
+        DatTable::Sprites,
+        &old.sprites.rows(),
+        &new.sprites.rows(),
+        |_| None,
+    )?);
+    changes.extend(diff_rows(
+        DatTable::TechData,
+        &tech_data,
+        &new.tech_data.rows(),
+        |id| tech_data.get(id).and_then(|tech| label(tech.label)),
+    )?);
+    changes.extend(diff_rows(
+        DatTable::Upgrades,
+        &upgrades,
+        &new.upgrades.rows(),
+        |id| upgrades.get(id).and_then(|upgrade| label(upgrade.label)),
+    )?);
+
+    Ok(changes)
+}
+
+fn diff_rows<R, F>(table: DatTable, old: &[R], new: &[R], name: F) -> Result<Vec<Change>>
+where
+    R: Serialize,
+    F: Fn(usize) -> Option<String>,
+{
+    let mut changes = vec![];
+
+    for id in 0..old.len().max(new.len()) {
+        let change = |kind, field, old, new| Change {
+            kind,
+            table: table.to_string(),
+            id,
+            name: name(id)
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| format!("{} #{}", table, id)),
+            field,
+            old,
+            new,
+        };
+
+        let (old, new) = match (old.get(id), new.get(id)) {
+            (Some(old), Some(new)) => (old, new),
+            (Some(old), None) => {
+                let old = serde_json::to_value(old).context(Json {})?;
+                changes.push(change(ChangeKind::Removed, None, old, Value::Null));
+                continue;
+            }
+            (None, Some(new)) => {
+                let new = serde_json::to_value(new).context(Json {})?;
+                changes.push(change(ChangeKind::Added, None, Value::Null, new));
+                continue;
+            }
+            (None, None) => continue,
+        };
+
+        let old = serde_json::to_value(old).context(Json {})?;
+        let new = serde_json::to_value(new).context(Json {})?;
+
+        let (old, new) = match (old, new) {
+            (Value::Object(old), Value::Object(new)) => (old, new),
+            _ => continue,
+        };
+
+        for (field, old_value) in old {
+            let new_value = new.get(&field).cloned().unwrap_or(Value::Null);
+            if old_value == new_value {
+                continue;
+            }
+
+            changes.push(change(
+                ChangeKind::Changed,
+                Some(field),
+                old_value,
+                new_value,
+            ));
+        }
+    }
+
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bw_assets::{
+        dat::{
+            parse_flingy_dat, parse_sprites_dat, parse_tech_dat_dat, parse_unit_dat,
+            parse_upgrades_dat, parse_weapons_dat, write_unit_dat, write_weapons_dat, UnitsDat,
+            WeaponsDat,
+        },
+        tbl::parse_tbl,
+        test_utils::{create_tbl, random_dat},
+    };
+    use nom::Finish;
+    use spectral::prelude::*;
+
+    fn game_data(units: &[u8], weapons: &[u8], stat_txt: &[u8]) -> GameData {
+        GameData {
+            units: parse_unit_dat(units).finish().unwrap().1,
+            weapons: parse_weapons_dat(weapons).finish().unwrap().1,
+            flingy: parse_flingy_dat(&[0; 3135]).finish().unwrap().1,
+            sprites: parse_sprites_dat(&[0; 3229]).finish().unwrap().1,
+            tech_data: parse_tech_dat_dat(&[0; 836]).finish().unwrap().1,
+            upgrades: parse_upgrades_dat(&[0; 1281]).finish().unwrap().1,
+            stat_txt: parse_tbl(stat_txt).finish().unwrap().1,
+        }
+    }
+
+    #[test]
+    fn it_reports_changed_fields_by_name() {
+        let old_units = random_dat(19876);
+        let mut unit_rows = parse_unit_dat(&old_units).finish().unwrap().1.rows();
+        let old_hit_points = display_value(&serde_json::json!(unit_rows[0].hit_points));
+        unit_rows[0].hit_points = 45.0;
+        let new_units = write_unit_dat(&UnitsDat::from_rows(unit_rows).unwrap());
+
+        let mut weapon_rows = parse_weapons_dat(&[0; 5460]).finish().unwrap().1.rows();
+        weapon_rows[3].label = 2;
+        weapon_rows[3].damage_amount = 20;
+        let old_weapons = write_weapons_dat(&WeaponsDat::from_rows(weapon_rows.clone()).unwrap());
+        weapon_rows[3].damage_amount = 18;
+        let new_weapons = write_weapons_dat(&WeaponsDat::from_rows(weapon_rows).unwrap());
+
+        let stat_txt = create_tbl(&[b"Marine\0*\0Ground Units\0", b"Fragmentation Grenade\0"]);
+
+        let changes = diff(
+            &game_data(&old_units, &old_weapons, &stat_txt),
+            &game_data(&new_units, &new_weapons, &stat_txt),
+        )
+        .unwrap();
+
+        assert_that(&changes.iter().map(ToString::to_string).collect::<Vec<_>>()).is_equal_to(
+            vec![
+                format!("Marine.hit_points {} → 45", old_hit_points),
+                "Fragmentation Grenade.damage_amount 20 → 18".to_string(),
+            ],
+        );
+        assert_that(&changes[1].table).is_equal_to("weapons".to_string());
+        assert_that(&changes[1].id).is_equal_to(3);
+    }
+
+    #[test]
+    fn it_reports_entries_that_only_exist_on_one_side() {
+        let stock_units = vec![0; 19876];
+        let extended_units = vec![0; 300 * 96];
+        let weapons = vec![0; 5460];
+        let extended_weapons = vec![0; 400 * 42];
+        let stat_txt = create_tbl(&[]);
+
+        let changes = diff(
+            &game_data(&stock_units, &weapons, &stat_txt),
+            &game_data(&extended_units, &extended_weapons, &stat_txt),
+        )
+        .unwrap();
+        let added = |table: &str| {
+            changes
+                .iter()
+                .filter(|change| change.table == table && change.kind == ChangeKind::Added)
+                .map(|change| change.id)
+                .collect::<Vec<_>>()
+        };
+
+        assert_that(&added("units")).is_equal_to((228..300).collect::<Vec<_>>());
+        assert_that(&added("weapons")).is_equal_to((130..400).collect::<Vec<_>>());
+
+        let unit = changes
+            .iter()
+            .find(|change| change.table == "units" && change.id == 228)
+            .unwrap();
+        assert_that(&unit.to_string()).is_equal_to("units #228 added".to_string());
+        assert_that(&unit.field).is_none();
+        assert_that(&unit.new["hit_points"]).is_equal_to(serde_json::json!(0.0));
+
+        let json = serde_json::to_value(unit).unwrap();
+        assert_that(&json["kind"]).is_equal_to(serde_json::json!("added"));
+
+        let changes = diff(
+            &game_data(&extended_units, &weapons, &stat_txt),
+            &game_data(&stock_units, &weapons, &stat_txt),
+        )
+        .unwrap();
+        let removed = changes
+            .iter()
+            .filter(|change| change.kind == ChangeKind::Removed)
+            .count();
+
+        assert_that(&removed).is_equal_to(72);
+        assert_that(&changes.last().unwrap().to_string())
+            .is_equal_to("units #299 removed".to_string());
+    }
+
+    #[test]
+    fn it_names_entries_without_a_string_by_id() {
+        let old_units = vec![0; 19876];
+        let mut unit_rows = parse_unit_dat(&old_units).finish().unwrap().1.rows();
+        unit_rows[5].special_ability_flags = "building|flyer".to_string();
+        let new_units = write_unit_dat(&UnitsDat::from_rows(unit_rows).unwrap());
+
+        let weapons = vec![0; 5460];
+        let stat_txt = create_tbl(&[]);
+
+        let changes = diff(
+            &game_data(&old_units, &weapons, &stat_txt),
+            &game_data(&new_units, &weapons, &stat_txt),
+        )
+        .unwrap();
+
+        assert_that(&changes.iter().map(ToString::to_string).collect::<Vec<_>>()).is_equal_to(
+            vec!["units #5.special_ability_flags - → building|flyer".to_string()],
+        );
+    }
+}
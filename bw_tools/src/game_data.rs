@@ -0,0 +1,66 @@
+use bw_assets::{
+    dat::{
+        parse_flingy_dat, parse_sprites_dat, parse_tech_dat_dat, parse_unit_dat,
+        parse_upgrades_dat, parse_weapons_dat, FlingyDat, SpritesDat, TechDataDat, UnitsDat,
+        UpgradesDat, WeaponsDat,
+    },
+    tbl::{parse_tbl, Tbl},
+};
+
+use crate::{parse, AssetSource, DatTable, Result};
+
+/// Every DAT file of a game or mod together with the strings that name their
+/// entries.
+pub struct GameData {
+    pub units: UnitsDat,
+    pub weapons: WeaponsDat,
+    pub flingy: FlingyDat,
+    pub sprites: SpritesDat,
+    pub tech_data: TechDataDat,
+    pub upgrades: UpgradesDat,
+    pub stat_txt: Tbl,
+}
+
+impl GameData {
+    pub fn load(source: &AssetSource) -> Result<GameData> {
+        let load = |table: DatTable| source.read(table.file_name());
+
+        Ok(GameData {
+            units: parse(
+                DatTable::Units.file_name(),
+                &load(DatTable::Units)?,
+                parse_unit_dat,
+            )?,
+            weapons: parse(
+                DatTable::Weapons.file_name(),
+                &load(DatTable::Weapons)?,
+                parse_weapons_dat,
+            )?,
+            flingy: parse(
+                DatTable::Flingy.file_name(),
+                &load(DatTable::Flingy)?,
+                parse_flingy_dat,
+            )?,
+            sprites: parse(
+                DatTable::Sprites.file_name(),
+                &load(DatTable::Sprites)?,
+                parse_sprites_dat,
+            )?,
+            tech_data: parse(
+                DatTable::TechData.file_name(),
+                &load(DatTable::TechData)?,
+                parse_tech_dat_dat,
+            )?,
+            upgrades: parse(
+                DatTable::Upgrades.file_name(),
+                &load(DatTable::Upgrades)?,
+                parse_upgrades_dat,
+            )?,
+            stat_txt: parse(
+                "rez\\stat_txt.tbl",
+                &source.read("rez\\stat_txt.tbl")?,
+                parse_tbl,
+            )?,
+        })
+    }
+}
@@ -0,0 +1,263 @@
+//! Command Line Tools for Brood War Assets
+//!
+//! Shared helpers for the binaries in this crate. Assets are read either from
+//! a loose file on disk or from a queue of MPQ archives in the same order the
+//! game loads them.
+
+mod diff;
+mod game_data;
+
+pub use diff::{diff, Change, ChangeKind};
+pub use game_data::GameData;
+
+use amethyst::assets::Source;
+use bw_assets::{
+    dat::{
+        parse_flingy_dat, parse_sprites_dat, parse_tech_dat_dat, parse_unit_dat,
+        parse_upgrades_dat, parse_weapons_dat, read_rows, write_flingy_dat, write_rows,
+        write_sprites_dat, write_tech_data_dat, write_unit_dat, write_upgrades_dat,
+        write_weapons_dat, FlingyDat, SpritesDat, TableError, TableFormat, TechDataDat, UnitsDat,
+        UpgradesDat, WeaponsDat,
+    },
+    mpq::{self, ArcMPQ, MPQSource},
+};
+use nom::{Finish, IResult};
+use snafu::{ResultExt, Snafu};
+use std::{
+    fmt,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("failed to read file at path {}: {}", path, source))]
+    #[snafu(visibility = "pub")]
+    ReadFile {
+        source: std::io::Error,
+        path: String,
+    },
+
+    #[snafu(display("failed to open archive {}: {}", path, source))]
+    OpenArchive { source: mpq::Error, path: String },
+
+    #[snafu(display("{} is not in any archive: {}", file_name, message))]
+    MissingFile { file_name: String, message: String },
+
+    #[snafu(display(
+        "failed to parse {}: {} at position {}",
+        file_name,
+        description,
+        position
+    ))]
+    Parse {
+        file_name: String,
+        description: String,
+        position: usize,
+    },
+
+    #[snafu(display("unknown dat table {}", name))]
+    UnknownTable { name: String },
+
+    #[snafu(display("{}", source))]
+    Table { source: TableError },
+
+    #[snafu(display("json error: {}", source))]
+    #[snafu(visibility = "pub")]
+    Json { source: serde_json::Error },
+
+    #[snafu(display("io error: {}", source))]
+    #[snafu(visibility = "pub")]
+    IO { source: std::io::Error },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Archives that are loaded when no archive is given, from lowest to highest
+/// precedence.
+pub const DEFAULT_ARCHIVES: &[&str] = &["STARDAT.MPQ", "BROODAT.MPQ", "patch_rt.mpq"];
+
+/// Opens archives into a queue where later archives take precedence over
+/// earlier ones, i.e. `patch_rt.mpq` is given last.
+pub fn open_archives<P: AsRef<Path>>(paths: &[P]) -> Result<MPQSource> {
+    let mut mpq_source = MPQSource::new();
+    for path in paths {
+        let path = path.as_ref();
+        let mpq = ArcMPQ::from_path(path).context(OpenArchive {
+            path: path.display().to_string(),
+        })?;
+        mpq_source.push_front(mpq);
+    }
+
+    Ok(mpq_source)
+}
+
+/// Opens a set of assets given on the command line. A single directory is
+/// read as loose files, anything else as a queue of archives.
+pub fn open_source<P: AsRef<Path>>(paths: &[P]) -> Result<AssetSource> {
+    match paths {
+        [path] if path.as_ref().is_dir() => Ok(AssetSource::Directory(path.as_ref().to_owned())),
+        _ => open_archives(paths).map(AssetSource::Archives),
+    }
+}
+
+/// Where assets are read from.
+pub enum AssetSource {
+    /// A directory with the same layout as the archives, i.e. `arr\units.dat`
+    /// is read from `<dir>/arr/units.dat`.
+    Directory(PathBuf),
+
+    /// A queue of MPQ archives.
+    Archives(MPQSource),
+}
+
+impl AssetSource {
+    pub fn read(&self, file_name: &str) -> Result<Vec<u8>> {
+        match self {
+            AssetSource::Directory(dir) => {
+                let path = file_name
+                    .split('\\')
+                    .fold(dir.clone(), |path, part| path.join(part));
+                std::fs::read(&path).context(ReadFile {
+                    path: path.display().to_string(),
+                })
+            }
+            AssetSource::Archives(mpq_source) => {
+                mpq_source
+                    .load(file_name)
+                    .map_err(|err| Error::MissingFile {
+                        file_name: file_name.to_string(),
+                        message: err.to_string(),
+                    })
+            }
+        }
+    }
+}
+
+/// Runs a DAT parser over a whole file.
+pub fn parse<'a, O, F>(file_name: &str, b: &'a [u8], parser: F) -> Result<O>
+where
+    F: Fn(&'a [u8]) -> IResult<&'a [u8], O>,
+{
+    parser(b)
+        .finish()
+        .map(|(_, o)| o)
+        .map_err(|err| Error::Parse {
+            file_name: file_name.to_string(),
+            description: err.code.description().to_string(),
+            position: b.len() - err.input.len(),
+        })
+}
+
+/// A DAT file that can be converted to rows.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DatTable {
+    Units,
+    Weapons,
+    Flingy,
+    Sprites,
+    TechData,
+    Upgrades,
+}
+
+impl DatTable {
+    pub const ALL: &'static [DatTable] = &[
+        DatTable::Units,
+        DatTable::Weapons,
+        DatTable::Flingy,
+        DatTable::Sprites,
+        DatTable::TechData,
+        DatTable::Upgrades,
+    ];
+
+    /// Path of the DAT file inside of the game archives.
+    pub fn file_name(self) -> &'static str {
+        match self {
+            DatTable::Units => "arr\\units.dat",
+            DatTable::Weapons => "arr\\weapons.dat",
+            DatTable::Flingy => "arr\\flingy.dat",
+            DatTable::Sprites => "arr\\sprites.dat",
+            DatTable::TechData => "arr\\techdata.dat",
+            DatTable::Upgrades => "arr\\upgrades.dat",
+        }
+    }
+
+    /// Converts a DAT file to rows in the given format.
+    pub fn export<W: Write>(self, b: &[u8], w: W, format: TableFormat) -> Result<()> {
+        let file_name = self.file_name();
+
+        match self {
+            DatTable::Units => write_rows(w, &parse(file_name, b, parse_unit_dat)?.rows(), format),
+            DatTable::Weapons => {
+                write_rows(w, &parse(file_name, b, parse_weapons_dat)?.rows(), format)
+            }
+            DatTable::Flingy => {
+                write_rows(w, &parse(file_name, b, parse_flingy_dat)?.rows(), format)
+            }
+            DatTable::Sprites => {
+                write_rows(w, &parse(file_name, b, parse_sprites_dat)?.rows(), format)
+            }
+            DatTable::TechData => {
+                write_rows(w, &parse(file_name, b, parse_tech_dat_dat)?.rows(), format)
+            }
+            DatTable::Upgrades => {
+                write_rows(w, &parse(file_name, b, parse_upgrades_dat)?.rows(), format)
+            }
+        }
+        .context(Table {})
+    }
+
+    /// Converts rows in the given format back to a DAT file.
+    pub fn import<R: Read>(self, r: R, format: TableFormat) -> Result<Vec<u8>> {
+        let b = match self {
+            DatTable::Units => write_unit_dat(&UnitsDat::from_rows(read_rows(r, format)?)?),
+            DatTable::Weapons => write_weapons_dat(&WeaponsDat::from_rows(read_rows(r, format)?)?),
+            DatTable::Flingy => write_flingy_dat(&FlingyDat::from_rows(read_rows(r, format)?)?),
+            DatTable::Sprites => write_sprites_dat(&SpritesDat::from_rows(read_rows(r, format)?)?),
+            DatTable::TechData => {
+                write_tech_data_dat(&TechDataDat::from_rows(read_rows(r, format)?)?)
+            }
+            DatTable::Upgrades => {
+                write_upgrades_dat(&UpgradesDat::from_rows(read_rows(r, format)?)?)
+            }
+        };
+
+        Ok(b)
+    }
+}
+
+impl From<TableError> for Error {
+    fn from(source: TableError) -> Self {
+        Error::Table { source }
+    }
+}
+
+impl FromStr for DatTable {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        DatTable::ALL
+            .iter()
+            .copied()
+            .find(|table| table.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| Error::UnknownTable {
+                name: s.to_string(),
+            })
+    }
+}
+
+impl fmt::Display for DatTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DatTable::Units => "units",
+            DatTable::Weapons => "weapons",
+            DatTable::Flingy => "flingy",
+            DatTable::Sprites => "sprites",
+            DatTable::TechData => "techdata",
+            DatTable::Upgrades => "upgrades",
+        };
+
+        write!(f, "{}", name)
+    }
+}